@@ -0,0 +1,166 @@
+use crate::{
+    Result,
+    options::{Config, expand_home},
+};
+use grawlix::error::GrawlixIOError;
+use log::{debug, info, warn};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// Value of `backup_dir` moving files to the trash of the OS
+const TRASH: &str = "trash";
+
+/// Current date as year-month-day
+fn today() -> String {
+    humantime::format_rfc3339_seconds(SystemTime::now()).to_string()[..10].to_string()
+}
+
+/// Move existing comic at `path` to the backup directory or trash before it is overwritten.
+/// Nothing is done if `backup_dir` is not set or there is no file at `path`. The comic should
+/// not be written if this fails.
+pub fn backup(path: &str, config: &Config) -> Result<()> {
+    let source = Path::new(path);
+    let backup_dir = match &config.backup_dir {
+        Some(dir) if source.exists() => dir,
+        _ => return Ok(()),
+    };
+    if backup_dir == TRASH {
+        debug!("Moving {} to trash", path);
+        return move_to_trash(source);
+    }
+    // Backups are grouped by date so old backups can be removed, and keep their place in the
+    // library so comics with the same file name do not replace each other
+    let library = PathBuf::from(config.library_dir());
+    let relative = source.strip_prefix(&library)
+        .ok()
+        .or_else(|| Some(source).filter(|x| x.is_relative() && library == Path::new(".")))
+        .or_else(|| source.file_name().map(Path::new))
+        .ok_or_else(|| GrawlixIOError::InvalidLocation(path.to_string()))?;
+    let target = unused_path(PathBuf::from(expand_home(backup_dir)).join(today()).join(relative));
+    debug!("Moving {} to {}", path, target.display());
+    move_path(source, &target)?;
+    Ok(())
+}
+
+/// `path` or `path` with a number added if it already exists
+fn unused_path(path: PathBuf) -> PathBuf {
+    let stem = path.file_stem().map(|x| x.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = path.extension().map(|x| format!(".{}", x.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|n| if n == 1 { path.clone() } else { path.with_file_name(format!("{} ({}){}", stem, n, extension)) })
+        .find(|x| !x.exists())
+        .unwrap()
+}
+
+/// Move file or directory. Files are copied if they can not be renamed, for example because the
+/// target is on another file system.
+fn move_path(source: &Path, target: &Path) -> std::result::Result<(), GrawlixIOError> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::rename(source, target).is_ok() {
+        return Ok(());
+    }
+    copy_path(source, target)?;
+    if source.is_dir() {
+        std::fs::remove_dir_all(source)?;
+    } else {
+        std::fs::remove_file(source)?;
+    }
+    Ok(())
+}
+
+fn copy_path(source: &Path, target: &Path) -> std::io::Result<()> {
+    if source.is_dir() {
+        std::fs::create_dir_all(target)?;
+        for entry in std::fs::read_dir(source)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &target.join(entry.file_name()))?;
+        }
+    } else {
+        std::fs::copy(source, target)?;
+    }
+    Ok(())
+}
+
+/// Move `path` to the trash of the desktop following the freedesktop.org trash specification
+#[cfg(all(unix, not(target_os = "macos")))]
+fn move_to_trash(path: &Path) -> Result<()> {
+    let trash = dirs::data_dir()
+        .map(|x| x.join("Trash"))
+        .ok_or_else(|| GrawlixIOError::InvalidLocation(TRASH.to_string()))?;
+    let absolute = std::fs::canonicalize(path).map_err(GrawlixIOError::from)?;
+    let name = absolute.file_name()
+        .ok_or_else(|| GrawlixIOError::InvalidLocation(path.display().to_string()))?;
+    let target = unused_path(trash.join("files").join(name));
+    let target_name = target.file_name().unwrap().to_string_lossy().to_string();
+    let info_dir = trash.join("info");
+    std::fs::create_dir_all(&info_dir).map_err(GrawlixIOError::from)?;
+    let info = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encode_path(&absolute.to_string_lossy()),
+        &humantime::format_rfc3339_seconds(SystemTime::now()).to_string()[..19]
+    );
+    std::fs::write(info_dir.join(format!("{}.trashinfo", target_name)), info)
+        .map_err(GrawlixIOError::from)?;
+    move_path(&absolute, &target)?;
+    Ok(())
+}
+
+/// Move `path` to the trash of the user
+#[cfg(target_os = "macos")]
+fn move_to_trash(path: &Path) -> Result<()> {
+    let trash = dirs::home_dir()
+        .map(|x| x.join(".Trash"))
+        .ok_or_else(|| GrawlixIOError::InvalidLocation(TRASH.to_string()))?;
+    let name = path.file_name()
+        .ok_or_else(|| GrawlixIOError::InvalidLocation(path.display().to_string()))?;
+    move_path(path, &unused_path(trash.join(name)))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn move_to_trash(_path: &Path) -> Result<()> {
+    Err(GrawlixIOError::InvalidLocation("trash (Not supported on this OS, use a directory)".to_string()).into())
+}
+
+/// Percent encode path for trash info files
+#[cfg(all(unix, not(target_os = "macos")))]
+fn encode_path(path: &str) -> String {
+    path.bytes()
+        .map(|x| match x {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => (x as char).to_string(),
+            _ => format!("%{:02X}", x),
+        })
+        .collect()
+}
+
+/// Delete backups older than `backup_days` from the backup directory. The trash of the OS is
+/// left to the OS.
+pub fn remove_old_backups(config: &Config) {
+    let (dir, days) = match (&config.backup_dir, config.backup_days) {
+        (Some(dir), Some(days)) if dir != TRASH => (PathBuf::from(expand_home(dir)), days),
+        _ => return,
+    };
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    let oldest = SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60);
+    for entry in entries.filter_map(|x| x.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        // Only directories named by date are created by grawlix
+        let date = match humantime::parse_rfc3339(&format!("{}T00:00:00Z", name)) {
+            Ok(date) => date,
+            Err(_) => continue,
+        };
+        if date < oldest {
+            info!("Removing backups from {}", name);
+            if let Err(e) = std::fs::remove_dir_all(entry.path()) {
+                warn!("Could not remove {}: {}", entry.path().display(), e);
+            }
+        }
+    }
+}
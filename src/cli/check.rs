@@ -0,0 +1,65 @@
+use crate::{
+    CliError, utils,
+    options::Config,
+};
+use grawlix::{
+    comic::Comic,
+    metadata::{check_issue_numbers, format_ranges},
+    source::{ComicId, ResolveMode, comic_from_comicid_with_mode},
+};
+use futures::{StreamExt, stream};
+use log::{info, warn};
+
+/// Analyse downloaded issues of series for gaps and duplicates
+pub async fn check_series(input: &str, download: bool, config: &Config) -> Result<(), CliError> {
+    info!("Searching for comics");
+    let (source, client) = utils::get_source_from_url(input, config).await?;
+    let seriesid = source.id_from_url(input)?;
+    if !matches!(seriesid, ComicId::Series(_)) {
+        return Err(crate::update::UpdateError::NotASeries(input.to_string()).into());
+    }
+    let comicids = utils::resolve_ids(&*source, &client, seriesid, config).await?;
+    info!("Retrieving metadata for {} comics", comicids.len());
+    let comics: Vec<(ComicId, Comic)> = stream::iter(comicids)
+        .map(|comicid| {
+            let source = &*source;
+            let client = &client;
+            async move {
+                let comic = comic_from_comicid_with_mode(source, client, comicid.clone(), ResolveMode::MetadataOnly).await?;
+                Ok::<_, CliError>((comicid, comic))
+            }
+        })
+        .buffered(config.concurrency)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<_, _>>()?;
+    // Splitting comics into downloaded and missing based on output template
+    let mut downloaded = Vec::new();
+    let mut missing = Vec::new();
+    let options = config.download_options();
+    for (comicid, comic) in comics {
+        let path = options.format_path(&comic)?;
+        if std::path::Path::new(&path).exists() {
+            downloaded.push(comic);
+        } else {
+            missing.push(comicid);
+        }
+    }
+    let report = check_issue_numbers(downloaded.iter().map(|x| x.metadata.issue_number));
+    info!("Found {} downloaded and {} missing comics", downloaded.len(), missing.len());
+    if !report.gaps.is_empty() {
+        warn!("Gaps in issue numbers: {}", format_ranges(&report.gaps));
+    }
+    if !report.duplicates.is_empty() {
+        warn!("Duplicate issue numbers: {}", format_ranges(&report.duplicates));
+    }
+    if report.unnumbered > 0 {
+        warn!("{} downloaded comics have no issue number", report.unnumbered);
+    }
+    if download && !missing.is_empty() {
+        info!("Downloading {} missing comics", missing.len());
+        utils::download_and_write_comics(&*source, &client, &missing, Some(input), config).await;
+    }
+    Ok(())
+}
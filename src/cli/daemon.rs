@@ -0,0 +1,86 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set when grawlix should stop after the current comic
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+/// Returns true if grawlix has been asked to stop
+pub fn shutdown_requested() -> bool {
+    SHUTDOWN.load(Ordering::Relaxed)
+}
+
+/// Listen for SIGTERM and ctrl-c. The returned future requests shutdown when one of them is
+/// received. Signals are handled from when this is called, so systemd can be told grawlix is
+/// ready right after.
+#[cfg(unix)]
+pub fn shutdown_signal() -> std::io::Result<impl std::future::Future<Output = ()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    Ok(async move {
+        tokio::select! {
+            _ = terminate.recv() => (),
+            _ = interrupt.recv() => (),
+        }
+        request_shutdown();
+    })
+}
+
+#[cfg(not(unix))]
+pub fn shutdown_signal() -> std::io::Result<impl std::future::Future<Output = ()>> {
+    Ok(async {
+        let _ = tokio::signal::ctrl_c().await;
+        request_shutdown();
+    })
+}
+
+fn request_shutdown() {
+    log::info!("Stopping after current comic");
+    SHUTDOWN.store(true, Ordering::Relaxed);
+    notify("STOPPING=1");
+}
+
+/// Send `state` to systemd when running as a service with `Type=notify`
+#[cfg(unix)]
+pub fn notify(state: &str) {
+    use std::os::unix::net::UnixDatagram;
+    let path = match std::env::var("NOTIFY_SOCKET") {
+        Ok(path) => path,
+        Err(_) => return,
+    };
+    let result = UnixDatagram::unbound().and_then(|socket| {
+        // Sockets starting with @ are in the abstract namespace
+        #[cfg(target_os = "linux")]
+        if let Some(name) = path.strip_prefix('@') {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            return socket.send_to_addr(state.as_bytes(), &addr);
+        }
+        socket.send_to(state.as_bytes(), &path)
+    });
+    if let Err(e) = result {
+        log::warn!("Could not notify systemd: {}", e);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn notify(_state: &str) {}
+
+/// First socket passed by systemd socket activation
+#[cfg(unix)]
+pub fn activated_listener() -> Option<std::net::TcpListener> {
+    use std::os::unix::io::FromRawFd;
+    /// File descriptor of first passed socket
+    const LISTEN_FDS_START: i32 = 3;
+    let pid: u32 = std::env::var("LISTEN_PID").ok()?.parse().ok()?;
+    let fds: u32 = std::env::var("LISTEN_FDS").ok()?.parse().ok()?;
+    if pid != std::process::id() || fds == 0 {
+        return None;
+    }
+    // Safe since systemd passes ownership of the socket to grawlix
+    Some(unsafe { std::net::TcpListener::from_raw_fd(LISTEN_FDS_START) })
+}
+
+#[cfg(not(unix))]
+pub fn activated_listener() -> Option<std::net::TcpListener> {
+    None
+}
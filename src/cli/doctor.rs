@@ -0,0 +1,63 @@
+use crate::{CliError, Result, utils, options::Config};
+use colored::Colorize;
+use grawlix::source::{
+    ComicId, HEALTH_CHECK_URLS, download_series_metadata, metadata_from_comicid,
+};
+use std::time::Instant;
+
+/// Result of checking a single source
+enum Health {
+    Working,
+    Skipped(&'static str),
+    Broken(CliError),
+}
+
+/// Download metadata of comic or series at `url`
+async fn check_url(url: &str, config: &Config) -> Result<Health> {
+    let (source, client) = utils::get_source_from_url(url, config).await?;
+    if source.metadata_require_authentication() && !utils::has_credentials(&*source, config) {
+        return Ok(Health::Skipped("No credentials in config"));
+    }
+    match source.id_from_url(url)? {
+        ComicId::Series(id) => {
+            download_series_metadata(&client, &*source, &ComicId::Series(id)).await?;
+        },
+        comicid => {
+            metadata_from_comicid(&*source, &client, comicid).await?;
+        }
+    }
+    Ok(Health::Working)
+}
+
+/// Make a request to every source and report which of them fail
+pub async fn doctor(config: &Config) -> Result<()> {
+    // Sources without credentials are skipped instead of asking for them
+    let mut config = config.clone();
+    config.no_prompt = true;
+    let config = &config;
+    let mut broken = 0;
+    for url in HEALTH_CHECK_URLS {
+        let name = grawlix::source::source_from_url(url)?.name();
+        let start = Instant::now();
+        let health = if config.source_disabled(&name) || !config.source_enabled(&name) {
+            Health::Skipped("Disabled in config")
+        } else {
+            check_url(url, config).await
+                .unwrap_or_else(Health::Broken)
+        };
+        let time = start.elapsed().as_secs_f32();
+        match health {
+            Health::Working => println!("{} {} ({:.1}s)", "OK     ".green(), name, time),
+            Health::Skipped(reason) => println!("{} {} ({})", "SKIPPED".yellow(), name, reason),
+            Health::Broken(e) => {
+                broken += 1;
+                println!("{} {} ({:.1}s): {}", "FAILED ".red(), name, time, e);
+            },
+        }
+    }
+    if broken > 0 {
+        Err(CliError::BrokenSources(broken))
+    } else {
+        Ok(())
+    }
+}
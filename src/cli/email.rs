@@ -0,0 +1,81 @@
+use crate::options::EmailConfig;
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    transport::smtp::authentication::Credentials,
+};
+
+/// Summary of an update run sent by email
+#[derive(Default)]
+pub struct Digest {
+    /// Titles of downloaded issues by series
+    pub downloaded: Vec<(String, Vec<String>)>,
+    /// Errors that stopped the update
+    pub failures: Vec<String>,
+}
+
+impl Digest {
+    /// Number of downloaded issues
+    fn issue_count(&self) -> usize {
+        self.downloaded.iter().map(|(_, titles)| titles.len()).sum()
+    }
+
+    /// Returns true if nothing was downloaded and nothing failed
+    pub fn is_empty(&self) -> bool {
+        self.downloaded.is_empty() && self.failures.is_empty()
+    }
+
+    fn subject(&self) -> String {
+        if self.failures.is_empty() {
+            format!("grawlix: {} new issues", self.issue_count())
+        } else {
+            format!("grawlix: {} new issues, update failed", self.issue_count())
+        }
+    }
+
+    fn body(&self) -> String {
+        let mut body = format!("Downloaded {} new issues\n", self.issue_count());
+        for (series, titles) in &self.downloaded {
+            body.push_str(&format!("\n{}\n", series));
+            for title in titles {
+                body.push_str(&format!("- {}\n", title));
+            }
+        }
+        if !self.failures.is_empty() {
+            body.push_str("\nFailures\n");
+            for failure in &self.failures {
+                body.push_str(&format!("- {}\n", failure));
+            }
+        }
+        body
+    }
+}
+
+/// Send `digest` with the smtp server in `config`
+async fn send(config: &EmailConfig, digest: &Digest) -> Result<(), Box<dyn std::error::Error>> {
+    let message = Message::builder()
+        .from(config.from.parse()?)
+        .to(config.to.parse()?)
+        .subject(digest.subject())
+        .body(digest.body())?;
+    let mut transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_server)?;
+    if let Some(port) = config.port {
+        transport = transport.port(port);
+    }
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+    transport.build().send(message).await?;
+    Ok(())
+}
+
+/// Send `digest` by email unless it is empty. Failures are logged but do not stop the update.
+pub async fn send_digest(config: &EmailConfig, digest: &Digest) {
+    if digest.is_empty() {
+        log::debug!("Nothing to send in update digest");
+        return;
+    }
+    log::debug!("Sending update digest to {}", config.to);
+    if let Err(e) = send(config, digest).await {
+        log::warn!("Could not send update digest: {}", e);
+    }
+}
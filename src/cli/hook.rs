@@ -0,0 +1,72 @@
+use grawlix::comic::Comic;
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Run `command` after `comic` has been written to `path`. `{path}`, `{title}`, and `{series}` are
+/// replaced with quoted values from the comic. Failures are logged but do not stop the download.
+pub fn post_download(command: &str, comic: &Comic, path: &str) {
+    let command = command
+        .replace("{path}", &quote(path))
+        .replace("{title}", &quote(comic.title()))
+        .replace("{series}", &quote(comic.metadata.series.as_deref().unwrap_or("")));
+    log::debug!("Running post download command: {}", command);
+    match shell(&command).status() {
+        Ok(status) if status.success() => (),
+        Ok(status) => log::warn!("Post download command failed for {} ({})", comic.title(), status),
+        Err(e) => log::warn!("Could not run post download command: {}", e),
+    }
+}
+
+/// Run `command` with metadata of `comic` as json on stdin. Returns true if the comic should be
+/// downloaded, which is when the command exits successfully.
+pub fn filter(command: &str, comic: &Comic) -> bool {
+    let result = (|| {
+        let mut child = shell(command)
+            .stdin(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(&serde_json::to_vec(&comic.metadata)?)?;
+        }
+        child.wait()
+    })();
+    match result {
+        Ok(status) => {
+            if !status.success() {
+                log::info!("Skipping {} (Filtered by filter command)", comic.title());
+            }
+            status.success()
+        },
+        Err(e) => {
+            log::warn!("Could not run filter command, skipping {}: {}", comic.title(), e);
+            false
+        }
+    }
+}
+
+/// Create command running `command` in the shell of the OS
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.args(["/C", command]);
+    shell
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.args(["-c", command]);
+    shell
+}
+
+/// Quote `value` so it is passed to the shell as a single argument
+#[cfg(windows)]
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', ""))
+}
+
+#[cfg(not(windows))]
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
@@ -1,31 +1,139 @@
 use log::{Level, LevelFilter, Metadata};
 use colored::{Color, Colorize};
+use std::{fs::File, io::{IsTerminal, Write}};
 
-/// Setup logging system
-pub fn setup_logger(level: LevelFilter) -> Result<(), fern::InitError> {
-    fern::Dispatch::new()
-        .format(|out, message, record| {
-            let (first, rest, color) = format_log_message(
-                message.to_string(),
-                record.level(),
-                record.target()
-            );
-            out.finish(format_args!(
-                "{:>12} {}",
-                first.bold().color(color),
-                rest,
-            ))
-        })
+/// Log files larger than this are rotated
+const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Find log level from command line flags. `quiet` and `verbose` overrides `level`.
+pub fn log_level(level: LevelFilter, quiet: bool, verbose: bool) -> LevelFilter {
+    if quiet {
+        LevelFilter::Warn
+    } else if verbose {
+        LevelFilter::Debug
+    } else {
+        level
+    }
+}
+
+/// Setup logging system. Logs are written to stderr and `log_file` if it is set.
+pub fn setup_logger(level: LevelFilter, log_file: Option<&str>, rotations: usize) -> Result<(), fern::InitError> {
+    // Disable colors when output is piped to another program or file
+    if !std::io::stdout().is_terminal() {
+        colored::control::set_override(false);
+    }
+    let stderr = if std::io::stderr().is_terminal() {
+        fern::Dispatch::new()
+            .format(|out, message, record| {
+                let (first, rest, color) = format_log_message(
+                    message.to_string(),
+                    record.level(),
+                    record.target()
+                );
+                out.finish(format_args!(
+                    "{:>12} {}",
+                    first.bold().color(color),
+                    rest,
+                ))
+            })
+    } else {
+        // Plain lines that are easy to parse
+        fern::Dispatch::new()
+            .format(|out, message, record| {
+                out.finish(format_args!("{} {}", record.level(), message))
+            })
+    }.chain(std::io::stderr());
+    let mut dispatch = fern::Dispatch::new()
         .level(level)
         .filter(|metadata| {
             (metadata.level() != Level::Debug && metadata.level() != Level::Trace)
             || filter_log_message(metadata)
         })
-        .chain(std::io::stderr())
-        .apply()?;
+        .chain(stderr);
+    if let Some(path) = log_file {
+        let file = fern::Dispatch::new()
+            .format(|out, message, record| {
+                out.finish(format_args!(
+                    "{} {:<5} {} {}",
+                    humantime::format_rfc3339_seconds(std::time::SystemTime::now()),
+                    record.level(),
+                    record.target(),
+                    message,
+                ))
+            })
+            .chain(Box::new(RotatingLogFile::open(path, rotations)?) as Box<dyn Write + Send>);
+        dispatch = dispatch.chain(file);
+    }
+    dispatch.apply()?;
     Ok(())
 }
 
+/// Log file that is rotated when it grows larger than `MAX_LOG_SIZE`, so long running commands
+/// like `serve` and `watch` don't fill the disk
+struct RotatingLogFile {
+    path: String,
+    rotations: usize,
+    /// Open log file. Closed while the file is rotated.
+    file: Option<File>,
+    /// Current size of log file
+    size: u64,
+}
+
+impl RotatingLogFile {
+    fn open(path: &str, rotations: usize) -> std::io::Result<Self> {
+        let mut log_file = Self { path: path.to_string(), rotations, file: None, size: 0 };
+        log_file.reopen()?;
+        Ok(log_file)
+    }
+
+    /// Rotate log file if it is too large and open the current one
+    fn reopen(&mut self) -> std::io::Result<&mut File> {
+        self.file = None;
+        rotate_log_file(&self.path, self.rotations)?;
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = file.metadata()?.len();
+        Ok(self.file.insert(file))
+    }
+}
+
+impl Write for RotatingLogFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let file = match &mut self.file {
+            Some(file) if self.size < MAX_LOG_SIZE => file,
+            _ => self.reopen()?,
+        };
+        let written = file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Rotate log file if it is larger than `MAX_LOG_SIZE`. The current log file is moved to
+/// `<path>.1`, `<path>.1` to `<path>.2` and so on. At most `rotations` old files are kept.
+fn rotate_log_file(path: &str, rotations: usize) -> std::io::Result<()> {
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.len() >= MAX_LOG_SIZE => (),
+        _ => return Ok(()),
+    }
+    if rotations == 0 {
+        return std::fs::remove_file(path);
+    }
+    for n in (1..rotations).rev() {
+        let old = format!("{}.{}", path, n);
+        if std::path::Path::new(&old).exists() {
+            std::fs::rename(&old, format!("{}.{}", path, n + 1))?;
+        }
+    }
+    std::fs::rename(path, format!("{}.1", path))
+}
+
 
 fn format_log_message(msg: String, level: Level, target: &str) -> (String, String, Color) {
     match level {
@@ -70,6 +178,8 @@ pub fn print_comic(comic: &grawlix::comic::Comic, json: bool) {
             ("Series", &metadata.series),
             ("Relase date", &metadata.date()),
             ("Publisher", &metadata.publisher),
+            ("Language", &metadata.language),
+            ("Age rating", &metadata.age_rating.map(|x| x.to_string())),
             ("Pages", &Some(comic.pages.len().to_string())),
         ];
         for (name, opt_value) in data {
@@ -86,3 +196,63 @@ pub fn print_comic(comic: &grawlix::comic::Comic, json: bool) {
         println!();
     }
 }
+
+/// Print resolved pages of comic. Urls are only printed if `urls` is enabled.
+pub fn print_pages(comic: &grawlix::comic::Comic, urls: bool) {
+    use grawlix::comic::PageType;
+    let mut formats: Vec<(&str, usize)> = Vec::new();
+    for page in &comic.pages {
+        match formats.iter_mut().find(|(format, _)| *format == page.file_format) {
+            Some((_, count)) => *count += 1,
+            None => formats.push((&page.file_format, 1)),
+        }
+    }
+    let formats: Vec<String> = formats.iter()
+        .map(|(format, count)| format!("{} ({})", format, count))
+        .collect();
+    println!("Formats: {}", formats.join(", "));
+    for (n, page) in comic.pages.iter().enumerate() {
+        let location = match &page.page_type {
+            PageType::Url(online) => {
+                let encrypted = if online.encryption.is_some() { " (encrypted)" } else { "" };
+                let mirrors = if online.mirrors.is_empty() { String::new() } else { format!(" (+{} mirrors)", online.mirrors.len()) };
+                if urls {
+                    format!("{}{}{}", online.url, mirrors, encrypted)
+                } else {
+                    format!("online{}{}", mirrors, encrypted)
+                }
+            },
+            PageType::Container(name) => format!("file {}", name),
+        };
+        println!(" - {:>3} {} {}", n + 1, page.file_format, location);
+    }
+    println!();
+}
+
+/// Print rows as a table with aligned columns and a colored header. With `plain` enabled columns
+/// are separated by tabs without header, which is easier to use in scripts.
+pub fn print_table<S: AsRef<str>>(header: &[&str], rows: &[Vec<S>], plain: bool) {
+    if plain {
+        for row in rows {
+            let cells: Vec<&str> = row.iter().map(|x| x.as_ref()).collect();
+            println!("{}", cells.join("\t"));
+        }
+        return;
+    }
+    let mut widths: Vec<usize> = header.iter().map(|x| x.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.as_ref().chars().count());
+        }
+    }
+    let header: Vec<String> = header.iter().zip(&widths)
+        .map(|(name, width)| format!("{:<width$}", name, width = width))
+        .collect();
+    println!("{}", header.join("  ").trim_end().bold());
+    for row in rows {
+        let cells: Vec<String> = row.iter().zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell.as_ref(), width = width))
+            .collect();
+        println!("{}", cells.join("  ").trim_end());
+    }
+}
@@ -1,11 +1,29 @@
+mod backup;
+mod check;
+mod daemon;
+mod doctor;
+mod email;
+mod hook;
 mod options;
 mod logging;
+mod newznab;
+mod notify;
+mod queue;
+mod readinglist;
+mod secrets;
+mod server;
+mod stats;
+mod sync;
+mod template;
 mod update;
 mod utils;
+mod watch;
+mod webhook;
 
 
 use log::{info, error};
-use options::{Arguments, Command, Config};
+use grawlix::metadata::CatalogFormat;
+use options::{Arguments, Command, Config, QueueCommand, ReadingListCommand, TemplateCommand, expand_home};
 use structopt::StructOpt;
 use thiserror::Error;
 use displaydoc::Display;
@@ -18,11 +36,53 @@ pub enum CliError {
     /// Could not find file: {0}
     FileNotFound(String),
     /// {0}
+    Grawlix(#[from] grawlix::Error),
+    /// {0}
     Write(#[from] grawlix::error::GrawlixIOError),
     /// {0}
     Download(#[from] grawlix::error::GrawlixDownloadError),
     /// {0}
     Update(#[from] update::UpdateError),
+    /// {0}
+    Queue(#[from] queue::QueueError),
+    /// {0} sources failed the health check
+    BrokenSources(usize),
+    /// Invalid address to listen on: {0}
+    InvalidAddress(String),
+    /// Syncing needs output_dir in config as the library directory
+    MissingLibraryDir,
+    /// Can not sync library to {0} since it is inside the library
+    SyncInsideLibrary(String),
+    /// Host key of {0} is not in ~/.ssh/known_hosts or has changed
+    UnknownHostKey(String),
+    /// Sftp error: {0}
+    Sftp(#[from] ssh2::Error),
+    /// Listening on {0} requires server_token in config. Without a token only localhost can be used
+    MissingServerToken(String),
+    /// {0}
+    Server(#[from] hyper::Error),
+    /// Could not listen for signals: {0}
+    Signal(std::io::Error),
+    /// Invalid option in link file: {0}
+    InvalidLinkOption(String),
+    /// {0} links could not be downloaded
+    FailedLinks(usize),
+    /// Invalid date {0}, expected year-month-day
+    InvalidDate(String),
+    /// Invalid template: {0}
+    InvalidTemplate(String),
+    /// Invalid replacement pattern {0}: {1}
+    InvalidReplacement(String, regex::Error),
+    /// Invalid pattern {0}: {1}
+    InvalidPattern(String, regex::Error),
+    /// Could not find profile {0} in config file
+    UnknownProfile(String),
+    /// {0} is not one of the sources in config
+    SourceNotEnabled(String),
+    /// {0} is disabled in config
+    SourceDisabled(String),
+    /// Failed to decrypt credentials. Check the passphrase.
+    DecryptCredentials,
     /// Could not create credentials from input
     InvalidCredentials,
     /// No Credentials found for source {0}
@@ -49,15 +109,101 @@ async fn main() {
 async fn run() -> Result<()> {
     // Loading options
     let args = Arguments::from_args();
-    logging::setup_logger(args.log_level)?;
-    let config: Config = options::load_options(&args)?;
-    match &args.cmd {
+    let log_level = logging::log_level(args.log_level, args.quiet, args.verbose);
+    let config: Config = match options::load_options(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            // Logging to terminal so errors in config are shown
+            logging::setup_logger(log_level, None, 0)?;
+            return Err(e);
+        }
+    };
+    logging::setup_logger(log_level, config.log_file.as_deref(), config.log_rotations)?;
+    grawlix::source::set_client_options(config.client_options());
+    for (host, max) in &config.connection_limits {
+        grawlix::source::CONNECTION_LIMITS.set_limit(host, *max);
+    }
+    if let Some(path) = config.id_cache_path() {
+        grawlix::source::ID_CACHE.load(path, config.id_cache_age());
+    }
+    backup::remove_old_backups(&config);
+    if let Some(dir) = &args.record_fixtures {
+        log::debug!("Recording responses to {}", dir.display());
+        grawlix::source::FIXTURES.start(dir)
+            .map_err(grawlix::error::GrawlixIOError::from)?;
+    }
+    let result = match &args.cmd {
         Command::Add { inputs } => update::add(&args, &config, inputs).await,
-        Command::Download{ inputs } => download(inputs, &args, &config).await,
-        Command::Info { inputs } => info(&args, &config, inputs).await,
+        Command::CheckSeries { input, download } => check::check_series(input, *download, &config).await,
+        Command::Doctor => doctor::doctor(&config).await,
+        Command::Diff { a, b } => {
+            print!("{}", grawlix::comic::ComicDiff::from_files(&expand_home(a), &expand_home(b))?);
+            Ok(())
+        },
+        Command::Download { inputs, metadata_only: true, format } => {
+            write_catalog(inputs, format.unwrap_or_default(), &args, &config).await
+        },
+        Command::Download{ inputs, .. } => download(inputs, &args, &config).await,
+        Command::Edit { name, template, format, reset } => {
+            // The global output directory argument is used as output directory of the series
+            let settings = update::SeriesSettings {
+                template: template.clone(),
+                output_dir: args.output_dir.clone(),
+                output_format: format.clone(),
+            };
+            update::edit(name, settings, *reset, &config)
+        },
+        Command::EncryptCredentials { path } => secrets::encrypt_file(path, config.credentials_key_file.as_deref()),
+        Command::ExportCatalog { format, output } => {
+            update::export_catalog(format.unwrap_or_default(), output.as_deref(), &config)
+        },
+        Command::Info { inputs, metadata_only: true, format, .. } => {
+            let metadata = utils::get_metadata(&args, &config, inputs).await?;
+            print!("{}", grawlix::metadata::export_catalog(&metadata, format.unwrap_or_default())?);
+            Ok(())
+        },
+        Command::Info { inputs, pages, urls, confirm_urls, .. } => {
+            info(&args, &config, inputs, *pages, *urls && *confirm_urls).await
+        },
         Command::List => update::list(&config),
-        Command::Update => update::update(&config).await
+        Command::Owned { source } => utils::list_owned(source, &config).await,
+        Command::Pause { name } => update::set_enabled(name, false, &config),
+        Command::Prune { dry_run } => update::prune(&config, *dry_run),
+        Command::Queue { cmd: QueueCommand::Add { priority, inputs } } => queue::add(inputs, *priority, &args, &config).await,
+        Command::Queue { cmd: QueueCommand::List } => queue::list(&config),
+        Command::Queue { cmd: QueueCommand::Clear } => queue::clear(&config),
+        Command::Queue { cmd: QueueCommand::Promote { position, priority } } => queue::promote(*position, *priority, &config),
+        Command::Queue { cmd: QueueCommand::Run } => queue::run(&config).await,
+        Command::ReadingList { cmd: ReadingListCommand::Export { name, output, inputs } } => {
+            readinglist::export(name, output, inputs, &args, &config).await
+        },
+        Command::ReadingList { cmd: ReadingListCommand::Import { path } } => readinglist::import(path, &config).await,
+        Command::Refresh { inputs } => refresh(inputs, &args, &config).await,
+        Command::Resume { name } => update::set_enabled(name, true, &config),
+        Command::Stats => update::stats(&config),
+        Command::Serve { listen, interval } => server::serve(listen, *interval, &config).await,
+        Command::Sync { remote_dir } => sync::sync(remote_dir, &config),
+        Command::Template { cmd: TemplateCommand::Test { template } } => template::test_template(template, &config),
+        Command::Update { heal } => update::update(&config, *heal).await,
+        Command::Watch { dir, interval } => watch::watch(dir, *interval, &config).await,
+    };
+    if let Err(e) = grawlix::source::ID_CACHE.save() {
+        log::warn!("Failed to save resolved ids: {}", e);
+    }
+    if let Some(path) = &config.metrics_file {
+        if let Err(e) = write_metrics(path) {
+            log::warn!("Failed to write metrics to {}: {}", path, e);
+        }
     }
+    result
+}
+
+/// Write request metrics in Prometheus format to `path`
+fn write_metrics(path: &str) -> Result<()> {
+    log::debug!("Writing metrics to {}", path);
+    std::fs::write(path, grawlix::source::METRICS.to_prometheus())
+        .map_err(grawlix::error::GrawlixIOError::from)?;
+    Ok(())
 }
 
 
@@ -66,23 +212,62 @@ async fn download(inputs: &Vec<String>, args: &Arguments, config: &Config) -> Re
     info!("Searching for comics");
     let links = utils::get_all_links(inputs, args)?;
     for link in links {
-        let (source, client) = utils::get_source_from_url(&link, config).await?;
-        let link_id = source.id_from_url(&link)?;
-        let comicids = grawlix::source::get_all_ids(&source, &client, link_id).await?;
-        utils::download_and_write_comics(&source, &client, &comicids, config).await;
+        utils::download_link(&link, config).await?;
+    }
+    Ok(())
+}
+
+/// Write catalog with metadata of all comics in inputs to output directory
+async fn write_catalog(inputs: &Vec<String>, format: CatalogFormat, args: &Arguments, config: &Config) -> Result<()> {
+    info!("Searching for comics");
+    let metadata = utils::get_metadata(args, config, inputs).await?;
+    let path = format!(
+        "{}/catalog.{}",
+        config.output_dir.as_deref().unwrap_or(".").trim_end_matches('/'),
+        format.extension()
+    );
+    info!("Writing metadata of {} comics to {}", metadata.len(), path);
+    std::fs::write(&path, grawlix::metadata::export_catalog(&metadata, format)?)
+        .map_err(grawlix::error::GrawlixIOError::from)?;
+    Ok(())
+}
+
+/// Redownload comics that have changed on their source
+async fn refresh(inputs: &Vec<String>, args: &Arguments, config: &Config) -> Result<()> {
+    info!("Searching for comics");
+    let links = utils::get_all_links(inputs, args)?;
+    for link in links {
+        let config = link.apply(config);
+        let (source, client) = utils::get_source_from_url(&link.url, &config).await?;
+        let link_id = source.id_from_url(&link.url)?;
+        let comicids = utils::resolve_ids(&*source, &client, link_id, &config).await?;
+        utils::refresh_comics(&*source, &client, &comicids, Some(&link.url), &config).await;
     }
     Ok(())
 }
 
-/// Print comics to stdout
-async fn info(args: &Arguments, config: &Config, inputs: &Vec<String>) -> Result<()> {
+/// Print comics to stdout. Resolved pages are listed if `pages` is enabled.
+async fn info(args: &Arguments, config: &Config, inputs: &Vec<String>, pages: bool, urls: bool) -> Result<()> {
     let comics = utils::get_comics(args, config, inputs).await?;
     log::debug!("Found {} comics", comics.len());
     if config.json {
         println!("{}", serde_json::to_string_pretty(&comics).unwrap());
+    } else if comics.len() > 1 && !pages {
+        let rows: Vec<Vec<String>> = comics.iter()
+            .map(|comic| vec![
+                comic.metadata.issue_number.map(|x| x.to_string()).unwrap_or_default(),
+                comic.title().to_string(),
+                comic.metadata.date().unwrap_or_default(),
+                comic.pages.len().to_string(),
+            ])
+            .collect();
+        logging::print_table(&["#", "Title", "Released", "Pages"], &rows, config.plain);
     } else {
         for comic in comics {
             logging::print_comic(&comic, config.json);
+            if pages {
+                logging::print_pages(&comic, urls);
+            }
         }
     }
     Ok(())
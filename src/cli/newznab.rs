@@ -0,0 +1,147 @@
+use crate::update::FoundIssue;
+use xml::writer::{EmitterConfig, EventWriter, XmlEvent, Error as WriteError};
+
+/// Newznab category of comics
+const COMICS_CATEGORY: &str = "7030";
+
+/// Series and issue number from newznab search query like "Moon Knight 001 (2016)". A year at
+/// the end of the query is ignored.
+pub fn parse_query(query: &str) -> (String, Option<u32>) {
+    let mut words: Vec<&str> = query.split_whitespace().collect();
+    let is_year = |x: &str| {
+        let x = x.trim_matches(|c| c == '(' || c == ')');
+        x.len() == 4 && (x.starts_with("19") || x.starts_with("20")) && x.parse::<u32>().is_ok()
+    };
+    if words.len() > 2 && is_year(words[words.len() - 1]) {
+        words.pop();
+    }
+    let issue = words.last()
+        .filter(|_| words.len() > 1)
+        .and_then(|x| x.trim_start_matches('#').parse::<u32>().ok());
+    if issue.is_some() {
+        words.pop();
+    }
+    (words.join(" "), issue)
+}
+
+fn write_element<W: std::io::Write>(w: &mut EventWriter<W>, tag: &str, content: &str) -> Result<(), WriteError> {
+    w.write(XmlEvent::start_element(tag))?;
+    w.write(content)?;
+    w.write(XmlEvent::end_element())?;
+    Ok(())
+}
+
+fn write_attr<W: std::io::Write>(w: &mut EventWriter<W>, name: &str, value: &str) -> Result<(), WriteError> {
+    w.write(XmlEvent::start_element("newznab:attr").attr("name", name).attr("value", value))?;
+    w.write(XmlEvent::end_element())?;
+    Ok(())
+}
+
+/// Run `f` with xml writer and return the written document
+fn document<F>(f: F) -> String
+where
+    F: FnOnce(&mut EventWriter<&mut Vec<u8>>) -> Result<(), WriteError>
+{
+    let mut buffer = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .perform_indent(true)
+            .create_writer(&mut buffer);
+        // Writing to a vector only fails with invalid documents
+        f(&mut w).expect("Invalid newznab document");
+    }
+    String::from_utf8(buffer).unwrap()
+}
+
+/// Capabilities of api
+pub fn caps() -> String {
+    document(|w| {
+        w.write(XmlEvent::start_element("caps"))?;
+        w.write(XmlEvent::start_element("server").attr("title", "grawlix"))?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::start_element("searching"))?;
+        for function in ["search", "comic-search"] {
+            w.write(XmlEvent::start_element(function).attr("available", "yes").attr("supportedParams", "q"))?;
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::start_element("categories"))?;
+        w.write(XmlEvent::start_element("category").attr("id", "7000").attr("name", "Books"))?;
+        w.write(XmlEvent::start_element("subcat").attr("id", COMICS_CATEGORY).attr("name", "Comics"))?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+        Ok(())
+    })
+}
+
+/// Error in newznab format
+pub fn error(code: u32, description: &str) -> String {
+    document(|w| {
+        w.write(XmlEvent::start_element("error").attr("code", &code.to_string()).attr("description", description))?;
+        w.write(XmlEvent::end_element())?;
+        Ok(())
+    })
+}
+
+/// Name of issue in the form Mylar expects, like "Moon Knight 001 (2016)"
+fn issue_name(issue: &FoundIssue) -> String {
+    let mut name = issue.series.clone();
+    if let Some(number) = issue.issue {
+        name.push_str(&format!(" {:03}", number));
+    }
+    if let Some(year) = issue.year {
+        name.push_str(&format!(" ({})", year));
+    }
+    name
+}
+
+/// Rss feed with `issues`. Issues are downloaded from `{base_url}/api?t=get`, authenticated with
+/// `apikey` if it is set. Links of issues that have not been downloaded include the source and
+/// series they are downloaded from.
+pub fn results(issues: &[FoundIssue], base_url: &str, apikey: Option<&str>) -> String {
+    document(|w| {
+        w.write(XmlEvent::start_element("rss")
+            .attr("version", "2.0")
+            .ns("newznab", "http://www.newznab.com/DTD/2010/feeds/attributes/"))?;
+        w.write(XmlEvent::start_element("channel"))?;
+        write_element(w, "title", "grawlix")?;
+        w.write(XmlEvent::start_element("newznab:response")
+            .attr("offset", "0")
+            .attr("total", &issues.len().to_string()))?;
+        w.write(XmlEvent::end_element())?;
+        for issue in issues {
+            let mut params = vec![("t", "get"), ("id", issue.id.as_str())];
+            if let Some((source, series)) = &issue.available {
+                params.extend([("source", source.as_str()), ("series", series.as_str())]);
+            }
+            if let Some(apikey) = apikey {
+                params.push(("apikey", apikey));
+            }
+            let link = reqwest::Url::parse_with_params(&format!("{}/api", base_url), &params)
+                .map(String::from)
+                .unwrap_or_default();
+            let size = issue.size.to_string();
+            w.write(XmlEvent::start_element("item"))?;
+            write_element(w, "title", &issue_name(issue))?;
+            write_element(w, "guid", &issue.id)?;
+            write_element(w, "link", &link)?;
+            if let Some(modified) = issue.modified {
+                write_element(w, "pubDate", &httpdate::fmt_http_date(modified))?;
+            }
+            write_element(w, "category", COMICS_CATEGORY)?;
+            w.write(XmlEvent::start_element("enclosure")
+                .attr("url", &link)
+                .attr("length", &size)
+                .attr("type", "application/x-cbz"))?;
+            w.write(XmlEvent::end_element())?;
+            write_attr(w, "category", COMICS_CATEGORY)?;
+            write_attr(w, "size", &size)?;
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+        Ok(())
+    })
+}
@@ -0,0 +1,65 @@
+use crate::options::NotifierConfig;
+
+/// Maximum number of issue titles listed in a notification
+const MAX_TITLES: usize = 20;
+
+/// List of issue titles with one title per line
+fn issue_list(titles: &[String]) -> String {
+    let mut lines: Vec<String> = titles.iter()
+        .take(MAX_TITLES)
+        .map(|title| format!("- {}", title))
+        .collect();
+    if titles.len() > MAX_TITLES {
+        lines.push(format!("and {} more", titles.len() - MAX_TITLES));
+    }
+    lines.join("\n")
+}
+
+/// Create message listing new issues of `series`
+fn message(series: &str, titles: &[String]) -> String {
+    format!("New in {}:\n{}", series, issue_list(titles))
+}
+
+/// Request posting notification to `notifier`
+fn request(notifier: &NotifierConfig, client: &reqwest::Client, series: &str, titles: &[String], cover_url: Option<&str>) -> reqwest::RequestBuilder {
+    match notifier {
+        NotifierConfig::Discord { webhook } => {
+            let mut embed = serde_json::json!({
+                "title": format!("New in {}", series),
+                "description": issue_list(titles),
+            });
+            if let Some(cover_url) = cover_url {
+                embed["thumbnail"] = serde_json::json!({ "url": cover_url });
+            }
+            client.post(webhook).json(&serde_json::json!({ "embeds": [embed] }))
+        },
+        NotifierConfig::Telegram { token, chat_id } => {
+            let url = |method| format!("https://api.telegram.org/bot{}/{}", token, method);
+            match cover_url {
+                Some(cover_url) => client.post(url("sendPhoto")).json(&serde_json::json!({
+                    "chat_id": chat_id,
+                    "photo": cover_url,
+                    "caption": message(series, titles),
+                })),
+                None => client.post(url("sendMessage")).json(&serde_json::json!({
+                    "chat_id": chat_id,
+                    "text": message(series, titles),
+                })),
+            }
+        },
+    }
+}
+
+/// Post notification about new issues of `series` to all `notifiers`. Failures are logged but do
+/// not stop the update.
+pub async fn new_issues(notifiers: &[NotifierConfig], series: &str, titles: &[String], cover_url: Option<&str>) {
+    let client = reqwest::Client::new();
+    for notifier in notifiers {
+        log::debug!("Sending notification about {} to {}", series, notifier.name());
+        match request(notifier, &client, series, titles, cover_url).send().await {
+            Ok(response) if response.status().is_success() => (),
+            Ok(response) => log::warn!("{} notification failed ({})", notifier.name(), response.status()),
+            Err(e) => log::warn!("{} notification failed: {}", notifier.name(), e),
+        }
+    }
+}
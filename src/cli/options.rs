@@ -1,9 +1,17 @@
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
 use structopt::StructOpt;
 use serde::Deserialize;
-use grawlix::source::Credentials;
+use grawlix::{
+    DownloadOptions, OverwritePolicy,
+    comic::{Comic, ComicTaggerNaming, GroupBy, MissingField, PageFilter, Replacement, TEMPLATE_FIELDS},
+    metadata::{AgeRating, CatalogFormat, MergePolicy, MetadataFormat, TitleLanguage, date_from_str},
+    source::{ClientOptions, Credentials, Gallery, SeriesInfo},
+};
 use crate::CliError;
 
+/// Year, month, and day of a release date
+type Date = (u32, u32, u32);
+
 /// Command line comic book tool
 #[derive(StructOpt)]
 pub struct Arguments {
@@ -13,12 +21,111 @@ pub struct Arguments {
     /// Logging level
     #[structopt(short, long, default_value="info", global = true)]
     pub log_level: log::LevelFilter,
+    /// Only print warnings and errors
+    #[structopt(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Print debug messages
+    #[structopt(short, long, global = true)]
+    pub verbose: bool,
+    /// Write log messages to file
+    #[structopt(long, global = true)]
+    pub log_file: Option<String>,
+    /// Use settings from profile in config file
+    #[structopt(long, global = true)]
+    pub profile: Option<String>,
+    /// Directory comics are written to. The output template is relative to this directory.
+    #[structopt(long, global = true)]
+    pub output_dir: Option<String>,
+    /// How fields missing from comics are written (Either unknown, empty, skip, or error)
+    #[structopt(long, global = true)]
+    pub missing_fields: Option<MissingField>,
+    /// Template for file names of pages
+    #[structopt(long, global = true)]
+    pub page_template: Option<String>,
+    /// Number of the first page in file names
+    #[structopt(long, global = true)]
+    pub first_page: Option<u32>,
+    /// Metadata formats written to comics (Comma separated list of comicinfo, tachiyomi, grawlix,
+    /// comicbookinfo, or opf)
+    #[structopt(long, global = true, use_delimiter = true)]
+    pub metadata_formats: Vec<MetadataFormat>,
+    /// Do not write any metadata to comics
+    #[structopt(long, global = true)]
+    pub no_metadata: bool,
+    /// Combine metadata of existing comics with new metadata when refreshing (Either
+    /// prefer-existing, prefer-remote, or merge)
+    #[structopt(long, global = true)]
+    pub metadata_merge: Option<MergePolicy>,
     /// Output format (Either cbz or dir)
     #[structopt(long, global = true)]
     pub output_format: Option<grawlix::comic::ComicFormat>,
     /// Overwrite already existing files
     #[structopt(long, global = true)]
     pub overwrite: bool,
+    /// When already existing files are overwritten (Either never, always, if-larger,
+    /// if-newer-metadata, or if-page-count-differs)
+    #[structopt(long, global = true, conflicts_with = "overwrite")]
+    pub overwrite_policy: Option<OverwritePolicy>,
+    /// Store source, download time, and page urls in downloaded comics
+    #[structopt(long, global = true)]
+    pub provenance: bool,
+    /// Keep Mylar series.json files in series folders up to date
+    #[structopt(long, global = true)]
+    pub series_json: bool,
+    /// Name files with the rename template from ComicTagger settings
+    #[structopt(long, global = true)]
+    pub comictagger: bool,
+    /// Preferred language of titles (Either native, romanized, or english)
+    #[structopt(long, global = true)]
+    pub title_language: Option<TitleLanguage>,
+    /// Locale of comics on sources with more than one edition or translation, like de_de
+    #[structopt(long, global = true)]
+    pub locale: Option<String>,
+    /// Only download comics in these languages (Comma separated language codes like en,fr)
+    #[structopt(long, global = true, use_delimiter = true)]
+    pub languages: Vec<String>,
+    /// Never use these sources (Comma separated source names like marvel,webtoon)
+    #[structopt(long, global = true, use_delimiter = true)]
+    pub disable_source: Vec<String>,
+    /// Skip comics with a higher age rating (Either everyone, teen, mature, or adult)
+    #[structopt(long, global = true)]
+    pub max_rating: Option<AgeRating>,
+    /// Only download comics released on or after date (year-month-day)
+    #[structopt(long, global = true)]
+    pub published_after: Option<String>,
+    /// Only download comics released on or before date (year-month-day)
+    #[structopt(long, global = true)]
+    pub published_before: Option<String>,
+    /// Combine downloaded chapters (Either none or volume)
+    #[structopt(long, global = true)]
+    pub group_by: Option<GroupBy>,
+    /// Number of comics downloaded at the same time
+    #[structopt(long, global = true)]
+    pub concurrency: Option<usize>,
+    /// Seconds to wait for a connection to be established (0 to disable)
+    #[structopt(long, global = true)]
+    pub connect_timeout: Option<u64>,
+    /// Seconds to wait for more data from a response (0 to disable)
+    #[structopt(long, global = true)]
+    pub read_timeout: Option<u64>,
+    /// Maximum number of seconds a single request can take (0 to disable)
+    #[structopt(long, global = true)]
+    pub timeout: Option<u64>,
+    /// Maximum number of idle connections kept open to each host
+    #[structopt(long, global = true)]
+    pub pool_size: Option<usize>,
+    /// Disable HTTP/2
+    #[structopt(long, global = true)]
+    pub http1_only: bool,
+    /// Do not ask for credentials of sources missing from config
+    #[structopt(long, global = true)]
+    pub no_prompt: bool,
+    /// Skip issues that could not be resolved instead of failing the whole series
+    #[structopt(long, global = true)]
+    pub skip_unresolved: bool,
+    /// Download comics even if only a preview is available
+    #[structopt(long, global = true)]
+    pub allow_previews: bool,
     /// Path of file containing input urls
     #[structopt(short, long, global = true)]
     pub file: Option<PathBuf>,
@@ -28,9 +135,34 @@ pub struct Arguments {
     /// Output as json
     #[structopt(long, global = true)]
     json: bool,
+    /// Print tables as tab separated lines without header
+    #[structopt(long, global = true)]
+    plain: bool,
     /// Location of update file to use
     #[structopt(long, global = true)]
     pub update_location: Option<String>,
+    /// Location of download queue to use
+    #[structopt(long, global = true)]
+    pub queue_location: Option<String>,
+    /// Command deciding if a comic is downloaded. Metadata of the comic is given as json on stdin.
+    #[structopt(long, global = true)]
+    pub filter_command: Option<String>,
+    /// Command run after each comic is written. {path}, {title}, and {series} are replaced with
+    /// values from the comic.
+    #[structopt(long, global = true)]
+    pub post_download: Option<String>,
+    /// Write Prometheus metrics to file when finished
+    #[structopt(long, global = true)]
+    pub metrics_file: Option<String>,
+    /// Save responses from sources in directory for use as test data
+    #[structopt(long, global = true)]
+    pub record_fixtures: Option<PathBuf>,
+    /// Css selector for images when downloading from unsupported sites
+    #[structopt(long, global = true)]
+    pub gallery_images: Option<String>,
+    /// Css selector for link to next page when downloading from unsupported sites
+    #[structopt(long, global = true)]
+    pub gallery_next: Option<String>,
     /// Subcommand
     #[structopt(subcommand)]
     pub cmd: Command,
@@ -43,64 +175,732 @@ pub enum Command {
         /// Links to comic books
         inputs: Vec<String>,
     },
+    /// Check if all sources are working
+    Doctor,
+    /// Compare pages and metadata of two comic files
+    Diff {
+        /// First comic file
+        a: String,
+        /// Second comic file
+        b: String,
+    },
     /// Download comics
     Download {
         /// Link to comic book
         inputs: Vec<String>,
+        /// Write a catalog of metadata instead of downloading comics
+        #[structopt(long)]
+        metadata_only: bool,
+        /// Format of catalog (Either csv, json, or md)
+        #[structopt(long, requires = "metadata-only")]
+        format: Option<CatalogFormat>,
+    },
+    /// Report gaps and duplicates in downloaded issues of a series
+    CheckSeries {
+        /// Link to series
+        input: String,
+        /// Download missing issues
+        #[structopt(long)]
+        download: bool,
+    },
+    /// Change output settings of series in update file
+    Edit {
+        /// Name or id of series
+        name: String,
+        /// Output template for series
+        #[structopt(long)]
+        template: Option<String>,
+        /// Output format for series (Either cbz or dir)
+        #[structopt(long)]
+        format: Option<grawlix::comic::ComicFormat>,
+        /// Remove all settings from series before applying new ones
+        #[structopt(long)]
+        reset: bool,
+    },
+    /// Encrypt toml file with source credentials for use in the config file
+    EncryptCredentials {
+        /// File with source sections
+        path: String,
+    },
+    /// Export list of all downloaded issues in update file
+    ExportCatalog {
+        /// Format of catalog (Either csv, json, or md)
+        #[structopt(long)]
+        format: Option<CatalogFormat>,
+        /// File catalog is written to instead of stdout
+        #[structopt(long)]
+        output: Option<String>,
+    },
+    /// Stop updating series without removing it from update file
+    Pause {
+        /// Name or id of series
+        name: String,
+    },
+    /// Delete old issues according to keep_issues and max_source_size in config
+    Prune {
+        /// List files that would be deleted without deleting them
+        #[structopt(long)]
+        dry_run: bool,
     },
     /// Print comic metadata to stdout
     Info {
         /// Link to comic book
         inputs: Vec<String>,
+        /// List resolved pages without downloading them
+        #[structopt(long)]
+        pages: bool,
+        /// Show page urls. Urls can contain access tokens for your account.
+        #[structopt(long, requires_all = &["pages", "confirm-urls"])]
+        urls: bool,
+        /// Confirm that page urls should be printed
+        #[structopt(long)]
+        confirm_urls: bool,
+        /// Print a catalog of metadata
+        #[structopt(long, conflicts_with = "pages")]
+        metadata_only: bool,
+        /// Format of catalog (Either csv, json, or md)
+        #[structopt(long, requires = "metadata-only")]
+        format: Option<CatalogFormat>,
     },
     /// List all series added to updatefile
     List,
+    /// List urls of comics the account on a source owns, has borrowed, or can read with a
+    /// subscription
+    Owned {
+        /// Name of source
+        source: String,
+    },
+    /// Redownload comics where pages have changed since they were downloaded
+    Refresh {
+        /// Link to comic book or series
+        inputs: Vec<String>,
+    },
+    /// Resume updating paused series
+    Resume {
+        /// Name or id of series
+        name: String,
+    },
+    /// Queue comics to download them later in order of priority
+    Queue {
+        #[structopt(subcommand)]
+        cmd: QueueCommand,
+    },
+    /// Export and import ComicRack reading lists
+    ReadingList {
+        #[structopt(subcommand)]
+        cmd: ReadingListCommand,
+    },
+    /// Print statistics about series in update file
+    Stats,
+    /// Work with output templates
+    Template {
+        #[structopt(subcommand)]
+        cmd: TemplateCommand,
+    },
     /// Update comics in updatefile
-    Update
+    Update {
+        /// Download issues again if their files have been deleted
+        #[structopt(long)]
+        heal: bool,
+    },
+    /// Watch folder for new link files and comics
+    Watch {
+        /// Folder to watch
+        dir: String,
+        /// Seconds between checking folder for new files
+        #[structopt(long, default_value = "10")]
+        interval: u64,
+    },
+    /// Run http api for controlling grawlix remotely
+    Serve {
+        /// Address to listen on
+        #[structopt(long, default_value = "127.0.0.1:7878")]
+        listen: String,
+        /// Minutes between automatic updates of all series
+        #[structopt(long)]
+        interval: Option<u64>,
+    },
+    /// Copy new and changed files in library to another directory
+    Sync {
+        /// Directory or sftp url like sftp://user@host/path to copy library to
+        remote_dir: String,
+    },
 }
 
+#[derive(StructOpt)]
+pub enum ReadingListCommand {
+    /// Write reading list (cbl) with comics in the given order
+    Export {
+        /// Name of reading list
+        #[structopt(long, default_value = "grawlix")]
+        name: String,
+        /// Output file
+        #[structopt(short = "O", long)]
+        output: String,
+        /// Comic files or urls
+        inputs: Vec<String>,
+    },
+    /// Download all comics in reading list (cbl)
+    Import {
+        /// Reading list file
+        path: String,
+    },
+}
+
+#[derive(StructOpt)]
+pub enum QueueCommand {
+    /// Add all comics at links to queue
+    Add {
+        /// Comics with higher priority are downloaded first
+        #[structopt(long, default_value = "0", allow_hyphen_values = true)]
+        priority: i32,
+        /// Links to comic books
+        inputs: Vec<String>,
+    },
+    /// Print comics in queue in the order they are downloaded
+    List,
+    /// Remove all comics from queue
+    Clear,
+    /// Change priority of comic in queue
+    Promote {
+        /// Position of comic in queue as shown by list
+        position: usize,
+        /// New priority. The comic is moved to the front of the queue if not set.
+        #[structopt(long, allow_hyphen_values = true)]
+        priority: Option<i32>,
+    },
+    /// Download comics in queue
+    Run,
+}
+
+#[derive(StructOpt)]
+pub enum TemplateCommand {
+    /// Check template for unknown fields and print it formatted with sample metadata
+    Test {
+        /// Output template
+        template: String,
+    },
+}
+
+/// Template setting in config. Either only the template or a table with the template and
+/// replacements.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum TemplateConfig {
+    Template(String),
+    Table {
+        /// Template for output locations of comics
+        #[serde(default = "default_template")]
+        format: String,
+        /// Replacements for fields by field name and pattern
+        #[serde(default = "Default::default")]
+        replacements: BTreeMap<String, BTreeMap<String, String>>,
+    },
+}
+
+impl Default for TemplateConfig {
+    fn default() -> Self {
+        Self::Template(default_template())
+    }
+}
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Config {
+    /// Template setting from config file
+    #[serde(rename = "template", default = "Default::default")]
+    template_config: TemplateConfig,
     /// Template for output locations of comics
-    #[serde(rename = "template", default = "default_template")]
+    #[serde(skip_deserializing, default = "default_template")]
     pub output_template: String,
+    /// Replacements applied to template fields
+    #[serde(skip_deserializing)]
+    pub replacements: Vec<Replacement>,
+    /// Only download issues with numbers in these ranges. Set per link in link files.
+    #[serde(skip_deserializing)]
+    pub issues: Vec<(u32, u32)>,
+    /// Directory comics are written to
+    #[serde(default = "Default::default")]
+    pub output_dir: Option<String>,
+    /// How fields missing from comics are written
+    #[serde(default = "Default::default")]
+    pub missing_fields: MissingField,
+    /// Template for file names of pages
+    #[serde(default = "default_page_template")]
+    pub page_template: String,
+    /// Number of the first page in file names
+    #[serde(default = "Default::default")]
+    pub first_page: u32,
+    /// Metadata formats written to comics
+    #[serde(default = "default_metadata_formats")]
+    pub metadata_formats: Vec<MetadataFormat>,
+    /// Combine metadata of existing comics with new metadata when refreshing
+    #[serde(default = "Default::default")]
+    pub metadata_merge: Option<MergePolicy>,
+    /// Separator between names in template fields with multiple authors
+    #[serde(default = "default_author_separator")]
+    pub author_separator: String,
+    /// Maximum number of names in template fields with multiple authors
+    #[serde(default = "Default::default")]
+    pub max_authors: Option<usize>,
+    /// Capitalize each word in titles and series names in templates
+    #[serde(default = "Default::default")]
+    pub title_case: bool,
     /// File format for output comics
     #[serde(default = "Default::default")]
     pub output_format: grawlix::comic::ComicFormat,
-    /// Should overwrite already existing files if enabled
+    /// When already existing files are overwritten
     #[serde(default = "Default::default")]
-    pub overwrite: bool,
+    pub overwrite: OverwritePolicy,
+    /// Directory overwritten files are moved to, or "trash" for the trash of the OS
+    #[serde(default = "Default::default")]
+    pub backup_dir: Option<String>,
+    /// Number of days backups of overwritten files are kept
+    #[serde(default = "Default::default")]
+    pub backup_days: Option<u64>,
+    /// Store source, download time, and page urls in downloaded comics
+    #[serde(default = "Default::default")]
+    pub provenance: bool,
+    /// Keep Mylar series.json files in series folders up to date
+    #[serde(default = "Default::default")]
+    pub series_json: bool,
+    /// Info about the series being updated, used in series.json
+    #[serde(skip)]
+    pub series_info: Option<SeriesInfo>,
+    /// Name files with the rename template from ComicTagger settings
+    #[serde(default = "Default::default")]
+    pub comictagger: bool,
+    /// ComicTagger settings file. Found in the default locations of ComicTagger if not set.
+    #[serde(default = "Default::default")]
+    pub comictagger_file: Option<String>,
+    /// Naming settings loaded from ComicTagger settings
+    #[serde(skip)]
+    pub comictagger_naming: Option<ComicTaggerNaming>,
+    /// Preferred language of titles
+    #[serde(default = "Default::default")]
+    pub title_language: TitleLanguage,
+    /// Locale of comics on sources with more than one edition or translation
+    #[serde(default = "Default::default")]
+    pub locale: Option<String>,
+    /// Only download comics in these languages
+    #[serde(default = "Default::default")]
+    pub languages: Vec<String>,
+    /// Skip comics with a higher age rating
+    #[serde(default = "Default::default")]
+    pub max_rating: Option<AgeRating>,
+    /// Only download comics released on or after date
+    #[serde(default = "Default::default")]
+    pub published_after: Option<String>,
+    /// Only download comics released on or before date
+    #[serde(default = "Default::default")]
+    pub published_before: Option<String>,
+    /// Combine downloaded chapters
+    #[serde(default = "Default::default")]
+    pub group_by: GroupBy,
+    /// Only use these sources. All sources are used if empty.
+    #[serde(default = "Default::default")]
+    pub enabled_sources: Vec<String>,
+    /// Sources where pages that look the same as an earlier page in the same comic are removed
+    #[serde(default = "Default::default")]
+    pub remove_duplicate_pages: Vec<String>,
+    /// Sources where nearly blank pages are removed
+    #[serde(default = "Default::default")]
+    pub remove_blank_pages: Vec<String>,
+    /// Patterns for urls of advert pages by source
+    #[serde(default = "Default::default")]
+    pub advert_pages: BTreeMap<String, Vec<String>>,
+    /// Patterns for finding issue numbers and years in titles and urls by source
+    #[serde(default = "Default::default")]
+    pub number_patterns: BTreeMap<String, Vec<String>>,
+    /// Canonical publisher names and other names used for the same publisher
+    #[serde(default = "Default::default")]
+    pub publishers: BTreeMap<String, Vec<String>>,
+    /// Number of newest issues kept of each series when pruning
+    #[serde(default = "Default::default")]
+    pub keep_issues: Option<usize>,
+    /// Maximum size in GB of issues from each source when pruning
+    #[serde(default = "Default::default")]
+    pub max_source_size: Option<f64>,
+    /// Number of comics downloaded at the same time
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// Seconds to wait for a connection to be established
+    #[serde(default = "Default::default")]
+    pub connect_timeout: Option<u64>,
+    /// Seconds to wait for more data from a response
+    #[serde(default = "Default::default")]
+    pub read_timeout: Option<u64>,
+    /// Maximum number of seconds a single request can take
+    #[serde(default = "Default::default")]
+    pub timeout: Option<u64>,
+    /// Maximum number of idle connections kept open to each host
+    #[serde(default = "Default::default")]
+    pub pool_size: Option<usize>,
+    /// Disable HTTP/2
+    #[serde(default = "Default::default")]
+    pub http1_only: bool,
+    /// Maximum number of simultaneous connections by host
+    #[serde(default = "Default::default")]
+    pub connection_limits: std::collections::HashMap<String, usize>,
+    /// Do not ask for credentials of sources missing from config
+    #[serde(default = "Default::default")]
+    pub no_prompt: bool,
+    /// Skip issues that could not be resolved instead of failing the whole series
+    #[serde(default = "Default::default")]
+    pub skip_unresolved: bool,
+    /// Download comics even if only a preview is available
+    #[serde(default = "Default::default")]
+    pub allow_previews: bool,
     /// Print extra information to stdout
     #[serde(default = "Default::default")]
     pub info: bool,
     /// Print output as json
     #[serde(default = "Default::default")]
     pub json: bool,
+    /// Print tables as tab separated lines
+    #[serde(default = "Default::default")]
+    pub plain: bool,
     /// Update file
     #[serde(default = "default_update")]
     pub update_location: String,
+    /// Download queue
+    #[serde(default = "default_queue")]
+    pub queue_location: String,
     #[serde(default = "Default::default")]
     pub update_series_info: bool,
-    /// DC Universe Infinite Config
+    /// File log messages are written to
+    #[serde(default = "Default::default")]
+    pub log_file: Option<String>,
+    /// Number of rotated log files to keep
+    #[serde(default = "default_log_rotations")]
+    pub log_rotations: usize,
+    /// Command deciding if a comic is downloaded
+    #[serde(default = "Default::default")]
+    pub filter_command: Option<String>,
+    /// Command run after each comic is written
+    #[serde(default = "Default::default")]
+    pub post_download: Option<String>,
+    /// File Prometheus metrics are written to
+    #[serde(default = "Default::default")]
+    pub metrics_file: Option<String>,
+    /// File resolved ids are cached in. An empty path disables the cache.
     #[serde(default = "Default::default")]
-    pub dcuniverseinfinite: Option<SourceData>,
-    /// Marvel Config
+    pub id_cache: Option<String>,
+    /// Number of days resolved ids are cached for
     #[serde(default = "Default::default")]
-    pub marvel: Option<SourceData>,
-    /// Izneo config
+    pub id_cache_days: Option<u64>,
+    /// Source sections encrypted as one value by older versions of `encrypt-credentials`
     #[serde(default = "Default::default")]
-    pub izneo: Option<SourceData>
+    pub encrypted_credentials: Option<String>,
+    /// File containing passphrase for encrypted credentials
+    #[serde(default = "Default::default")]
+    pub credentials_key_file: Option<String>,
+    /// Css selectors for downloading from unsupported sites
+    #[serde(default = "Default::default")]
+    pub gallery: Option<GalleryConfig>,
+    /// Endpoint the metadata of downloaded comics is posted to
+    #[serde(default = "Default::default")]
+    pub webhook: Option<WebhookConfig>,
+    /// Services notified when updates download new issues
+    #[serde(default = "Default::default")]
+    pub notifiers: Vec<NotifierConfig>,
+    /// Smtp settings for sending a digest after each update
+    #[serde(default = "Default::default")]
+    pub email: Option<EmailConfig>,
+    /// Token required by the http api
+    #[serde(default = "Default::default")]
+    pub server_token: Option<String>,
+    /// Settings for sources keyed by source name
+    #[serde(default = "Default::default")]
+    pub sources: BTreeMap<String, SourceData>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, Default)]
 pub struct SourceData {
+    /// Source is not used if set to false
+    pub enabled: Option<bool>,
     pub username: Option<String>,
     pub password: Option<String>,
     pub api_key: Option<String>,
     pub cookies: Option<std::collections::HashMap<String, String>>,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct WebhookConfig {
+    /// Url comics are posted to
+    pub url: String,
+    /// Api key sent as bearer token
+    pub api_key: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct EmailConfig {
+    /// Address of smtp server
+    pub smtp_server: String,
+    /// Port of smtp server. The default submission port is used if not set.
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Sender of email
+    pub from: String,
+    /// Recipient of email
+    pub to: String,
+}
+
+/// Service notified about new issues
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum NotifierConfig {
+    /// Discord channel webhook
+    Discord {
+        webhook: String,
+    },
+    /// Telegram bot sending messages to a chat
+    Telegram {
+        token: String,
+        chat_id: String,
+    },
+}
+
+impl NotifierConfig {
+    /// Name of service
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Discord { .. } => "Discord",
+            Self::Telegram { .. } => "Telegram",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GalleryConfig {
+    /// Css selector for page images
+    pub images: String,
+    /// Css selector for link to next page
+    pub next: Option<String>,
+    /// Attribute of image elements containing the image url
+    pub attribute: Option<String>,
+}
+
+impl GalleryConfig {
+    /// Create `Gallery` source from config
+    pub fn to_source(&self) -> Gallery {
+        let mut gallery = Gallery::new(&self.images);
+        if let Some(next) = &self.next {
+            gallery = gallery.next_selector(next);
+        }
+        if let Some(attribute) = &self.attribute {
+            gallery = gallery.image_attribute(attribute);
+        }
+        gallery
+    }
+}
+
+impl Config {
+    /// Full output template with output directory prepended and home directory expanded
+    pub fn template(&self) -> String {
+        let template = match &self.output_dir {
+            Some(dir) => format!("{}/{}", dir.trim_end_matches('/'), self.output_template),
+            None => self.output_template.clone(),
+        };
+        expand_home(&template)
+    }
+
+    /// Directory containing all comics written with the output template
+    pub fn library_dir(&self) -> String {
+        let template = self.template();
+        let fixed = &template[..template.find('{').unwrap_or(template.len())];
+        match fixed.rfind('/') {
+            Some(0) => "/".to_string(),
+            Some(i) => fixed[..i].to_string(),
+            None => ".".to_string(),
+        }
+    }
+
+    /// Connection settings for clients. Timeouts of 0 seconds are disabled.
+    pub fn client_options(&self) -> ClientOptions {
+        let seconds = |timeout: Option<u64>, default: Option<Duration>| match timeout {
+            Some(0) => None,
+            Some(x) => Some(Duration::from_secs(x)),
+            None => default,
+        };
+        let default = ClientOptions::default();
+        ClientOptions {
+            connect_timeout: seconds(self.connect_timeout, default.connect_timeout),
+            read_timeout: seconds(self.read_timeout, default.read_timeout),
+            timeout: seconds(self.timeout, default.timeout),
+            pool_max_idle_per_host: self.pool_size,
+            http2: !self.http1_only,
+        }
+    }
+
+    /// How long resolved ids are cached for
+    pub fn id_cache_age(&self) -> std::time::Duration {
+        self.id_cache_days
+            .map(|days| std::time::Duration::from_secs(days * 24 * 60 * 60))
+            .unwrap_or(grawlix::source::DEFAULT_MAX_AGE)
+    }
+
+    /// Location of cache of resolved ids. Defaults to the cache directory of the user.
+    pub fn id_cache_path(&self) -> Option<PathBuf> {
+        match self.id_cache.as_deref() {
+            Some("") => None,
+            Some(path) => Some(PathBuf::from(expand_home(path))),
+            None => dirs::cache_dir().map(|x| x.join("grawlix/ids.json")),
+        }
+    }
+
+    /// Release date range of comics that should be downloaded
+    pub fn published_range(&self) -> (Option<Date>, Option<Date>) {
+        (
+            self.published_after.as_deref().and_then(date_from_str),
+            self.published_before.as_deref().and_then(date_from_str),
+        )
+    }
+
+    /// Options for downloading comics
+    pub fn download_options(&self) -> DownloadOptions {
+        DownloadOptions::new()
+            .template(&self.template())
+            .format(self.output_format.clone())
+            .concurrency(self.concurrency)
+            .overwrite(self.overwrite)
+            .provenance(self.provenance)
+            .missing_fields(self.missing_fields)
+            .author_separator(&self.author_separator)
+            .max_authors(self.max_authors)
+            .title_case(self.title_case)
+            .replacements(self.replacements.clone())
+            .page_template(&self.page_template)
+            .first_page(self.first_page)
+            .metadata_formats(&self.metadata_formats)
+            .comictagger_naming(self.comictagger_naming.clone())
+    }
+
+    /// Naming settings from ComicTagger settings file. The defaults of ComicTagger are used if
+    /// the file can not be found or read.
+    fn load_comictagger_naming(&self) -> ComicTaggerNaming {
+        let paths: Vec<PathBuf> = match &self.comictagger_file {
+            Some(path) => vec![PathBuf::from(expand_home(path))],
+            None => dirs::config_dir().map(|x| x.join("ComicTagger/settings.json")).into_iter()
+                .chain(dirs::home_dir().map(|x| x.join(".ComicTagger/settings")))
+                .collect(),
+        };
+        let found = paths.iter()
+            .find_map(|path| std::fs::read_to_string(path).ok().map(|contents| (path, contents)));
+        match found {
+            Some((path, contents)) => match ComicTaggerNaming::from_settings(&contents) {
+                Some(naming) => {
+                    log::debug!("Using ComicTagger naming from {}", path.display());
+                    naming
+                },
+                None => {
+                    log::warn!("Could not read ComicTagger settings {}, using default naming", path.display());
+                    ComicTaggerNaming::default()
+                },
+            },
+            None => {
+                log::debug!("No ComicTagger settings found, using default naming");
+                ComicTaggerNaming::default()
+            },
+        }
+    }
+
+    /// Use source settings from `other` for sources that are not set in this config
+    fn add_credentials(&mut self, other: Config) {
+        for (name, sourcedata) in other.sources {
+            if self.source_settings(&name).is_none() {
+                self.sources.insert(name, sourcedata);
+            }
+        }
+    }
+
+    /// Settings for source with `name`
+    pub fn source_settings(&self, name: &str) -> Option<&SourceData> {
+        let key = source_key(name);
+        self.sources.iter()
+            .find(|(x, _)| source_key(x) == key)
+            .map(|(_, sourcedata)| sourcedata)
+    }
+
+    /// Returns true if source with `name` is in `enabled_sources` or `enabled_sources` is empty
+    pub fn source_enabled(&self, name: &str) -> bool {
+        let key = source_key(name);
+        self.enabled_sources.is_empty() || self.enabled_sources.iter().any(|x| source_key(x) == key)
+    }
+
+    /// Returns true if source with `name` has been disabled in its settings
+    pub fn source_disabled(&self, name: &str) -> bool {
+        self.source_settings(name)
+            .map(|x| x.enabled == Some(false))
+            .unwrap_or(false)
+    }
+
+    /// Disable source with `name`
+    fn disable_source(&mut self, name: &str) {
+        let key = source_key(name);
+        match self.sources.iter_mut().find(|(x, _)| source_key(x) == key) {
+            Some((_, sourcedata)) => sourcedata.enabled = Some(false),
+            None => {
+                let sourcedata = SourceData { enabled: Some(false), ..Default::default() };
+                self.sources.insert(key, sourcedata);
+            },
+        }
+    }
+
+    /// Rules for removing pages from `comic` based on the source it is from
+    pub fn page_filter(&self, comic: &Comic) -> PageFilter {
+        let names: Vec<&str> = comic.metadata.source.iter()
+            .chain(comic.metadata.identifiers.iter().map(|x| &x.source))
+            .map(|x| x.as_str())
+            .collect();
+        let enabled = |sources: &Vec<String>| sources.iter()
+            .any(|x| x == "all" || names.iter().any(|name| x.eq_ignore_ascii_case(name)));
+        // Patterns are checked when the config is loaded
+        let url_patterns = self.advert_pages.iter()
+            .filter(|(source, _)| *source == "all" || names.iter().any(|name| source.eq_ignore_ascii_case(name)))
+            .flat_map(|(_, patterns)| patterns.iter().filter_map(|x| regex::Regex::new(x).ok()))
+            .collect();
+        PageFilter {
+            duplicates: enabled(&self.remove_duplicate_pages),
+            // Gaps between panels in vertical strips are part of the comic
+            blank: enabled(&self.remove_blank_pages) && comic.page_layout().is_paged(),
+            url_patterns,
+        }
+    }
+
+    /// Patterns for finding missing issue numbers and years of `comic` based on the source it is
+    /// from
+    pub fn number_patterns(&self, comic: &Comic) -> Vec<regex::Regex> {
+        let source = comic.metadata.source.as_deref().unwrap_or_default();
+        // Patterns are checked when the config is loaded
+        self.number_patterns.iter()
+            .filter(|(name, _)| *name == "all" || name.eq_ignore_ascii_case(source))
+            .flat_map(|(_, patterns)| patterns.iter().filter_map(|x| regex::Regex::new(x).ok()))
+            .collect()
+    }
+
+    /// Set output template and replacements from template setting in config file
+    fn load_template_config(&mut self) -> Result<(), CliError> {
+        match &self.template_config {
+            TemplateConfig::Template(template) => self.output_template = template.clone(),
+            TemplateConfig::Table { format, replacements } => {
+                self.output_template = format.clone();
+                for (field, patterns) in replacements {
+                    if !TEMPLATE_FIELDS.contains(&field.as_str()) {
+                        log::warn!("Replacements for unknown template field {}", field);
+                    }
+                    for (pattern, replacement) in patterns {
+                        let replacement = Replacement::new(field, pattern, replacement)
+                            .map_err(|e| CliError::InvalidReplacement(pattern.clone(), e))?;
+                        self.replacements.push(replacement);
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
 impl TryInto<Credentials> for SourceData {
     type Error = crate::CliError;
 
@@ -115,20 +915,92 @@ impl TryInto<Credentials> for SourceData {
     }
 }
 
-/// Loads config file if it exists
-fn load_config_from_file() -> Result<Config, CliError> {
-    let config_path = dirs::config_dir()
+/// Add values from `overlay` to `base`. Tables in both are merged.
+fn merge_tables(base: &mut toml::value::Table, overlay: toml::value::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(table)) => merge_tables(base_table, table),
+            (_, value) => { base.insert(key, value); },
+        }
+    }
+}
+
+/// Name of source as used in config file. Source names are lowercase without spaces, so
+/// "DC Universe Infinite" is configured in `[sources.dcuniverseinfinite]`.
+pub fn source_key(name: &str) -> String {
+    name.chars()
+        .filter(|x| x.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Sources that used to be configured in a section at the top of the config file
+const LEGACY_SOURCE_SECTIONS: [&str; 7] = [
+    "azuki", "dcuniverseinfinite", "hoopla", "izneo", "kmanga", "marvel", "pocketcomics"
+];
+
+/// Move source sections at the top of the file from old config layouts to `sources`
+fn migrate_config_table(table: &mut toml::value::Table) {
+    for name in LEGACY_SOURCE_SECTIONS {
+        if let Some(section @ toml::Value::Table(_)) = table.remove(name) {
+            let sources = table.entry("sources")
+                .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+            if let toml::Value::Table(sources) = sources {
+                sources.entry(name).or_insert(section);
+            }
+        }
+    }
+}
+
+/// Location of config file
+pub fn config_path() -> Result<PathBuf, CliError> {
+    Ok(dirs::config_dir()
         // TODO: Better error
         .ok_or(CliError::Unknown)?
         .as_path()
-        .join("grawlix/grawlix.toml");
+        .join("grawlix/grawlix.toml"))
+}
+
+/// Loads config file if it exists. Settings from `profile` replace the ones at the top of the
+/// file.
+fn load_config_from_file(profile: Option<&str>) -> Result<Config, CliError> {
+    let config_path = config_path()?;
     let config = if config_path.exists() {
         std::fs::read_to_string(config_path)
             .unwrap_or_else(|_| String::from(""))
     } else {
         String::from("")
     };
-    let config = toml::from_str(&config)?;
+    let mut table: toml::value::Table = toml::from_str(&config)?;
+    let profiles = match table.remove("profile") {
+        Some(toml::Value::Table(profiles)) => profiles,
+        _ => toml::value::Table::new(),
+    };
+    migrate_config_table(&mut table);
+    if let Some(name) = profile {
+        match profiles.get(name) {
+            Some(toml::Value::Table(profile)) => {
+                let mut profile = profile.clone();
+                migrate_config_table(&mut profile);
+                merge_tables(&mut table, profile);
+            },
+            _ => return Err(CliError::UnknownProfile(name.to_string())),
+        }
+    }
+    let key_file = table.get("credentials_key_file").and_then(|x| x.as_str()).map(String::from);
+    let mut passphrase = None;
+    let mut table = toml::Value::Table(table);
+    crate::secrets::decrypt_values(&mut table, key_file.as_deref(), &mut passphrase)?;
+    let mut config: Config = table.try_into()?;
+    if let Some(blob) = &config.encrypted_credentials {
+        let passphrase = match passphrase {
+            Some(passphrase) => passphrase,
+            None => crate::secrets::passphrase(key_file.as_deref())?,
+        };
+        let mut credentials: toml::value::Table = toml::from_str(&crate::secrets::decrypt(blob, &passphrase)?)?;
+        migrate_config_table(&mut credentials);
+        config.add_credentials(toml::Value::Table(credentials).try_into()?);
+    }
     Ok(config)
 }
 
@@ -156,25 +1028,159 @@ macro_rules! args_into_config_bool {
 /// Loads options from config file and command line arguments
 pub fn load_options(args: &Arguments) -> Result<Config, CliError> {
     log::debug!("Loading file from config");
-    let mut config = load_config_from_file()?;
+    let mut config = load_config_from_file(args.profile.as_deref())?;
+    config.load_template_config()?;
     log::debug!("Adding options from cli arguments to config");
     args_into_config_opt!(args, config,
         output_template,
         output_format,
-        update_location
+        missing_fields,
+        page_template,
+        first_page,
+        concurrency,
+        title_language,
+        group_by,
+        update_location,
+        queue_location
     );
     args_into_config_bool!(args, config,
-        overwrite,
+        provenance,
+        series_json,
+        comictagger,
+        http1_only,
+        no_prompt,
+        skip_unresolved,
+        allow_previews,
         info,
-        json
+        json,
+        plain
     );
+    config.concurrency = config.concurrency.max(1);
+    if args.overwrite {
+        config.overwrite = OverwritePolicy::Always;
+    }
+    if let Some(policy) = args.overwrite_policy {
+        config.overwrite = policy;
+    }
+    if args.output_dir.is_some() {
+        config.output_dir = args.output_dir.clone();
+    }
+    if args.max_rating.is_some() {
+        config.max_rating = args.max_rating;
+    }
+    if args.published_after.is_some() {
+        config.published_after = args.published_after.clone();
+    }
+    if args.published_before.is_some() {
+        config.published_before = args.published_before.clone();
+    }
+    if args.locale.is_some() {
+        config.locale = args.locale.clone();
+    }
+    if config.comictagger {
+        config.comictagger_naming = Some(config.load_comictagger_naming());
+    }
+    for date in config.published_after.iter().chain(&config.published_before) {
+        if date_from_str(date).is_none() {
+            return Err(CliError::InvalidDate(date.clone()));
+        }
+    }
+    for pattern in config.advert_pages.values().chain(config.number_patterns.values()).flatten() {
+        regex::Regex::new(pattern).map_err(|e| CliError::InvalidPattern(pattern.clone(), e))?;
+    }
+    if !args.languages.is_empty() {
+        config.languages = args.languages.clone();
+    }
+    for name in &args.disable_source {
+        config.disable_source(name);
+    }
+    if !args.metadata_formats.is_empty() {
+        config.metadata_formats = args.metadata_formats.clone();
+    }
+    if args.no_metadata {
+        config.metadata_formats.clear();
+    }
+    if args.metadata_merge.is_some() {
+        config.metadata_merge = args.metadata_merge;
+    }
+    if args.connect_timeout.is_some() {
+        config.connect_timeout = args.connect_timeout;
+    }
+    if args.read_timeout.is_some() {
+        config.read_timeout = args.read_timeout;
+    }
+    if args.timeout.is_some() {
+        config.timeout = args.timeout;
+    }
+    if args.pool_size.is_some() {
+        config.pool_size = args.pool_size;
+    }
+    if args.log_file.is_some() {
+        config.log_file = args.log_file.clone();
+    }
+    if args.filter_command.is_some() {
+        config.filter_command = args.filter_command.clone();
+    }
+    if args.post_download.is_some() {
+        config.post_download = args.post_download.clone();
+    }
+    if args.metrics_file.is_some() {
+        config.metrics_file = args.metrics_file.clone();
+    }
+    if let Some(images) = &args.gallery_images {
+        config.gallery = Some(GalleryConfig {
+            images: images.clone(),
+            next: args.gallery_next.clone(),
+            attribute: None,
+        });
+    } else if let (Some(gallery), Some(next)) = (&mut config.gallery, &args.gallery_next) {
+        gallery.next = Some(next.clone());
+    }
     return Ok(config);
 }
 
+/// Replace `~` at the start of `path` and `{home}` with the home directory of the user
+pub fn expand_home(path: &str) -> String {
+    let home = match dirs::home_dir() {
+        Some(home) => home.to_string_lossy().to_string(),
+        None => return path.to_string(),
+    };
+    let path = path.replace("{home}", &home);
+    if path == "~" || path.starts_with("~/") {
+        format!("{}{}", home, &path[1..])
+    } else {
+        path
+    }
+}
+
 fn default_template() -> String {
     String::from("{series}/{title}.cbz")
 }
 
+fn default_author_separator() -> String {
+    String::from(", ")
+}
+
+fn default_page_template() -> String {
+    grawlix::comic::DEFAULT_PAGE_TEMPLATE.to_string()
+}
+
+fn default_metadata_formats() -> Vec<MetadataFormat> {
+    grawlix::metadata::DEFAULT_METADATA_FORMATS.to_vec()
+}
+
+fn default_concurrency() -> usize {
+    5
+}
+
+fn default_log_rotations() -> usize {
+    5
+}
+
 fn default_update() -> String {
     String::from("./.grawlix-update")
 }
+
+fn default_queue() -> String {
+    String::from("./.grawlix-queue")
+}
@@ -0,0 +1,289 @@
+use crate::{
+    CliError, daemon, logging,
+    options::{Arguments, Config},
+    utils::{Link, download_and_write_comic, get_all_links, get_source_from_url, lock_file, resolve_ids},
+};
+use grawlix::{metadata::TitleLanguage, source::{ComicId, Source}};
+use log::{error, info};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+use displaydoc::Display;
+
+/// Current version of the queue file format
+const QUEUE_VERSION: u32 = 1;
+
+#[derive(Debug, Error, Display)]
+pub enum QueueError {
+    /// Could not load queue from {0}
+    LoadQueue(String),
+    /// Could not save queue to {0}
+    SaveQueue(String),
+    /// Could not lock queue {0}
+    LockQueue(String),
+    /// There is no comic at position {0} in the queue
+    InvalidPosition(usize),
+}
+
+/// Download queue stored on disk
+#[derive(Deserialize, Serialize)]
+struct QueueFile {
+    /// Version of file format
+    version: u32,
+    entries: Vec<QueueEntry>,
+}
+
+/// Comic waiting to be downloaded
+#[derive(Clone, Deserialize, Serialize)]
+struct QueueEntry {
+    /// Name of source
+    source: String,
+    /// Resolved id of comic on source
+    id: ComicId,
+    /// Link the comic was added from with its output options. The source is created from the url
+    /// of the link when the queue is run.
+    link: Link,
+    /// Comics with higher priority are downloaded first
+    priority: i32,
+    /// Unix time the comic was added
+    added: u64,
+    /// Process id of the `queue run` downloading the comic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    claimed_by: Option<u32>,
+}
+
+impl QueueEntry {
+    /// Title of comic if the source included it with the id, otherwise the id
+    fn name(&self) -> String {
+        match &self.id {
+            ComicId::IssueWithMetadata(_, metadata) | ComicId::OtherWithMetadata(_, metadata) =>
+                metadata.title.clone().unwrap_or_else(|| self.id.inner().clone()),
+            id => id.inner().clone(),
+        }
+    }
+
+    fn is_same(&self, other: &QueueEntry) -> bool {
+        self.source == other.source && self.id.inner() == other.id.inner()
+    }
+}
+
+fn lock_queue(path: &str, shared: bool) -> Result<File, QueueError> {
+    lock_file(path, shared).map_err(|_| QueueError::LockQueue(path.to_string()))
+}
+
+/// Path of file locked by the `queue run` with process id `pid` while it is running
+fn run_path(location: &str, pid: u32) -> String {
+    format!("{}.run-{}", location, pid)
+}
+
+/// Returns true if the `queue run` with process id `pid` is still running. The lock of a
+/// process is released by the system when it stops, even if it crashed.
+fn is_running(location: &str, pid: u32) -> bool {
+    let path = format!("{}.lock", run_path(location, pid));
+    match File::open(&path) {
+        Ok(file) => file.try_lock().is_err(),
+        Err(_) => false,
+    }
+}
+
+/// Load queue from disk sorted by priority. Comics with the same priority are kept in the order
+/// they were added.
+fn load_queue(path: &str) -> Result<Vec<QueueEntry>, QueueError> {
+    if !std::path::Path::new(path).exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(path)
+        .map_err(|_| QueueError::LoadQueue(path.to_string()))?;
+    let mut entries = serde_json::from_str::<QueueFile>(&content)
+        .map_err(|_| QueueError::LoadQueue(path.to_string()))?
+        .entries;
+    entries.sort_by_key(|x| std::cmp::Reverse(x.priority));
+    Ok(entries)
+}
+
+/// Write queue to disk. Data is written to a temporary file first which replaces the queue, so
+/// it is not lost if grawlix is stopped while writing.
+fn write_queue(entries: Vec<QueueEntry>, path: &str) -> Result<(), QueueError> {
+    let save_error = |_| QueueError::SaveQueue(path.to_string());
+    let data = serde_json::to_string(&QueueFile { version: QUEUE_VERSION, entries })
+        .map_err(|_| QueueError::SaveQueue(path.to_string()))?;
+    let tmp_path = format!("{}.tmp", path);
+    let mut file = File::create(&tmp_path).map_err(save_error)?;
+    file.write_all(data.as_bytes()).map_err(save_error)?;
+    file.sync_all().map_err(save_error)?;
+    std::fs::rename(&tmp_path, path).map_err(save_error)
+}
+
+/// Change queue on disk with `f` while it is locked
+fn modify_queue<F>(config: &Config, f: F) -> Result<(), QueueError>
+where
+    F: FnOnce(&mut Vec<QueueEntry>) -> Result<(), QueueError>
+{
+    let _lock = lock_queue(&config.queue_location, false)?;
+    let mut entries = load_queue(&config.queue_location)?;
+    f(&mut entries)?;
+    write_queue(entries, &config.queue_location)
+}
+
+/// Resolve all comics in `inputs` and add them to the queue. Comics already in the queue get the
+/// higher of their priorities.
+pub async fn add(inputs: &Vec<String>, priority: i32, args: &Arguments, config: &Config) -> Result<(), CliError> {
+    let added = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0);
+    let mut new_entries = Vec::new();
+    for link in get_all_links(inputs, args)? {
+        let config = link.apply(config);
+        let (source, client) = get_source_from_url(&link.url, &config).await?;
+        let link_id = source.id_from_url(&link.url)?;
+        for id in resolve_ids(&*source, &client, link_id, &config).await? {
+            new_entries.push(QueueEntry {
+                source: source.name(),
+                id,
+                link: link.clone(),
+                priority,
+                added,
+                claimed_by: None,
+            });
+        }
+    }
+    let count = new_entries.len();
+    modify_queue(config, |entries| {
+        for new_entry in new_entries {
+            match entries.iter_mut().find(|x| x.is_same(&new_entry)) {
+                Some(existing) => existing.priority = existing.priority.max(new_entry.priority),
+                None => entries.push(new_entry),
+            }
+        }
+        Ok(())
+    })?;
+    info!("Added {} comics to queue", count);
+    Ok(())
+}
+
+/// Print queue in the order comics are downloaded
+pub fn list(config: &Config) -> Result<(), CliError> {
+    let _lock = lock_queue(&config.queue_location, true)?;
+    let entries = load_queue(&config.queue_location)?;
+    let rows: Vec<Vec<String>> = entries.iter()
+        .enumerate()
+        .map(|(n, entry)| vec![
+            (n + 1).to_string(),
+            entry.priority.to_string(),
+            entry.source.clone(),
+            entry.name(),
+            humantime::format_rfc3339_seconds(UNIX_EPOCH + std::time::Duration::from_secs(entry.added))
+                .to_string()[..10]
+                .to_string(),
+        ])
+        .collect();
+    logging::print_table(&["#", "Priority", "Source", "Comic", "Added"], &rows, config.plain);
+    Ok(())
+}
+
+/// Remove all comics from queue
+pub fn clear(config: &Config) -> Result<(), CliError> {
+    modify_queue(config, |entries| {
+        info!("Removed {} comics from queue", entries.len());
+        entries.clear();
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Set priority of comic at `position` (1 indexed, as shown by `list`). Without a priority the
+/// comic is moved to the front of the queue.
+pub fn promote(position: usize, priority: Option<i32>, config: &Config) -> Result<(), CliError> {
+    modify_queue(config, |entries| {
+        let highest = entries.iter().map(|x| x.priority).max().unwrap_or(0);
+        let entry = position.checked_sub(1)
+            .and_then(|n| entries.get_mut(n))
+            .ok_or(QueueError::InvalidPosition(position))?;
+        entry.priority = priority.unwrap_or(highest + 1);
+        info!("Set priority of {} to {}", entry.name(), entry.priority);
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Claim the comic with the highest priority that is not being downloaded by another running
+/// `queue run` and has not failed in this run
+fn claim_next(config: &Config, failed: &[QueueEntry]) -> Result<Option<QueueEntry>, QueueError> {
+    let pid = std::process::id();
+    let mut claimed = None;
+    modify_queue(config, |entries| {
+        let next = entries.iter_mut().find(|entry| {
+            let free = match entry.claimed_by {
+                Some(other) => other == pid || !is_running(&config.queue_location, other),
+                None => true,
+            };
+            free && !failed.iter().any(|x| x.is_same(entry))
+        });
+        if let Some(entry) = next {
+            entry.claimed_by = Some(pid);
+            claimed = Some(entry.clone());
+        }
+        Ok(())
+    })?;
+    Ok(claimed)
+}
+
+/// Source settings that can differ between links
+type SourceKey = (String, TitleLanguage, Option<String>);
+
+/// Download comics in queue from the highest priority. Each comic is claimed in the queue while
+/// it is downloaded, so multiple processes can run the queue at the same time. Comics are removed
+/// from the queue when they have been written or skipped by filters, so stopping grawlix does not
+/// lose the rest of the queue. Comics that could not be downloaded stay in the queue.
+pub async fn run(config: &Config) -> Result<(), CliError> {
+    let running_path = run_path(&config.queue_location, std::process::id());
+    let running = lock_queue(&running_path, false)?;
+    let mut sources: HashMap<SourceKey, (Box<dyn Source>, Client)> = HashMap::new();
+    let mut failed = Vec::new();
+    while !daemon::shutdown_requested() {
+        let entry = match claim_next(config, &failed)? {
+            Some(entry) => entry,
+            None => break,
+        };
+        let link_config = entry.link.apply(config);
+        let key = (entry.source.clone(), link_config.title_language, link_config.locale.clone());
+        if !sources.contains_key(&key) {
+            match get_source_from_url(&entry.link.url, &link_config).await {
+                Ok(source) => { sources.insert(key.clone(), source); },
+                Err(e) => {
+                    error!("Could not download {}: {}", entry.name(), e);
+                    failed.push(entry);
+                    continue;
+                }
+            }
+        }
+        let (source, client) = &sources[&key];
+        match download_and_write_comic(&**source, client, entry.id.clone(), Some(&entry.link.url), &link_config).await {
+            Ok(Some(_)) => (),
+            Ok(None) => info!("Removing {} from queue", entry.name()),
+            Err(e) => {
+                error!("Failed to download {}, keeping it in queue: {}", entry.name(), e);
+                failed.push(entry);
+                continue;
+            },
+        }
+        modify_queue(config, |entries| {
+            entries.retain(|x| !x.is_same(&entry));
+            Ok(())
+        })?;
+    }
+    // Releasing comics that failed, so they can be tried again
+    modify_queue(config, |entries| {
+        entries.iter_mut()
+            .filter(|x| failed.iter().any(|y| y.is_same(x)))
+            .for_each(|x| x.claimed_by = None);
+        Ok(())
+    })?;
+    drop(running);
+    std::fs::remove_file(format!("{}.lock", running_path)).ok();
+    Ok(())
+}
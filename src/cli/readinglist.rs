@@ -0,0 +1,55 @@
+use crate::{
+    CliError, Result, utils,
+    options::{Arguments, Config},
+};
+use grawlix::{
+    error::GrawlixIOError,
+    metadata::ReadingList,
+    source::ComicId,
+};
+use log::{info, warn};
+
+/// Write reading list of `inputs` in the order they are given to `output`
+pub async fn export(name: &str, output: &str, inputs: &Vec<String>, args: &Arguments, config: &Config) -> Result<()> {
+    let comics = utils::get_comics(args, config, inputs).await?;
+    let list = ReadingList::from_metadata(name, comics.iter().map(|x| &x.metadata));
+    let cbl = list.to_cbl()
+        .map_err(|_| GrawlixIOError::MetadataExport("Reading list".to_string()))?;
+    std::fs::write(output, cbl).map_err(GrawlixIOError::from)?;
+    info!("Wrote {} comics to {}", list.books.len(), output);
+    Ok(())
+}
+
+/// Download all comics in reading list at `path` from sources stored in the list
+pub async fn import(path: &str, config: &Config) -> Result<()> {
+    let file = std::fs::File::open(path)
+        .map_err(|_| CliError::FileNotFound(path.to_string()))?;
+    let list = ReadingList::from_cbl(std::io::BufReader::new(file));
+    info!("Found {} comics in {}", list.books.len(), list.name);
+    // Ids grouped by source in reading order
+    let mut sources: Vec<(String, Vec<ComicId>)> = Vec::new();
+    for book in &list.books {
+        let found = book.identifiers.iter()
+            .find(|x| grawlix::source::source_from_name(&x.source).is_ok());
+        match found {
+            Some(identifier) => {
+                let id = ComicId::Issue(identifier.id.clone().into());
+                match sources.iter_mut().find(|(name, _)| name == &identifier.source) {
+                    Some((_, ids)) => ids.push(id),
+                    None => sources.push((identifier.source.clone(), vec![id])),
+                }
+            },
+            None => warn!(
+                "Could not find source for {} #{}",
+                book.series.as_deref().unwrap_or("Unknown"),
+                book.number.as_deref().unwrap_or("?")
+            ),
+        }
+    }
+    for (name, comicids) in sources {
+        info!("Downloading {} comics from {}", comicids.len(), name);
+        let (source, client) = utils::get_source_from_name(&name, config).await?;
+        utils::download_and_write_comics(&*source, &client, &comicids, None, config).await;
+    }
+    Ok(())
+}
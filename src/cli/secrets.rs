@@ -0,0 +1,231 @@
+use crate::{CliError, Result, options::{SourceData, config_path, expand_home, source_key}};
+use aes_gcm::{Aes256Gcm, Key, Nonce, aead::{Aead, KeyInit}};
+use crypto::{
+    hmac::Hmac,
+    pbkdf2::pbkdf2,
+    sha2::Sha256,
+};
+use grawlix::error::GrawlixIOError;
+use std::{
+    collections::HashMap,
+    io::Write,
+    sync::{LazyLock, Mutex},
+};
+
+/// Environment variable the passphrase for encrypted credentials can be read from
+const PASSPHRASE_VARIABLE: &str = "GRAWLIX_PASSPHRASE";
+/// Start of config values encrypted with `encrypt_values`
+const ENCRYPTED_PREFIX: &str = "encrypted:";
+/// Number of pbkdf2 iterations used when deriving keys from passphrases
+const KEY_ITERATIONS: u32 = 100_000;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// Credentials entered with `prompt_once` in this process by source key. Skipped prompts are
+/// stored as `None`.
+static PROMPTED: LazyLock<Mutex<HashMap<String, Option<SourceData>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Find passphrase for encrypted credentials. Read from the environment, `key_file`, or
+/// prompted for without showing it in that order.
+pub fn passphrase(key_file: Option<&str>) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_VARIABLE) {
+        return Ok(passphrase);
+    }
+    if let Some(key_file) = key_file {
+        let key = std::fs::read_to_string(expand_home(key_file))
+            .map_err(|_| CliError::FileNotFound(key_file.to_string()))?;
+        return Ok(key.trim().to_string());
+    }
+    Ok(rpassword::prompt_password("Passphrase for credentials: ").map_err(GrawlixIOError::from)?)
+}
+
+/// Derive 256 bit key from passphrase
+fn derive_key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::new(Sha256::new(), passphrase.as_bytes());
+    let mut key = [0; 32];
+    pbkdf2(&mut mac, salt, KEY_ITERATIONS, &mut key);
+    key
+}
+
+/// Encrypt `data` with AES-256-GCM. Returns salt, nonce, encrypted data, and tag encoded as
+/// base64.
+pub fn encrypt(data: &str, passphrase: &str) -> String {
+    let salt: [u8; SALT_LEN] = rand::random();
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let key = derive_key(passphrase, &salt);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    // Encrypted data is followed by the tag
+    let encrypted = cipher.encrypt(Nonce::from_slice(&nonce), data.as_bytes())
+        .expect("Encrypting data in memory does not fail");
+    let blob: Vec<u8> = salt.iter()
+        .chain(nonce.iter())
+        .chain(encrypted.iter())
+        .copied()
+        .collect();
+    base64::encode(blob)
+}
+
+/// Decrypt data encrypted with `encrypt`
+pub fn decrypt(blob: &str, passphrase: &str) -> Result<String> {
+    let blob: String = blob.split_whitespace().collect();
+    let blob = base64::decode(blob).map_err(|_| CliError::DecryptCredentials)?;
+    if blob.len() < SALT_LEN + NONCE_LEN + TAG_LEN {
+        return Err(CliError::DecryptCredentials);
+    }
+    let (salt, rest) = blob.split_at(SALT_LEN);
+    let (nonce, encrypted) = rest.split_at(NONCE_LEN);
+    let key = derive_key(passphrase, salt);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let data = cipher.decrypt(Nonce::from_slice(nonce), encrypted)
+        .map_err(|_| CliError::DecryptCredentials)?;
+    String::from_utf8(data).map_err(|_| CliError::DecryptCredentials)
+}
+
+/// Encrypt all strings in `value`, so the names of settings stay readable
+fn encrypt_values(value: &mut toml::Value, passphrase: &str) {
+    match value {
+        toml::Value::String(x) if !x.starts_with(ENCRYPTED_PREFIX) => {
+            *x = format!("{}{}", ENCRYPTED_PREFIX, encrypt(x, passphrase));
+        },
+        toml::Value::Table(table) => table.iter_mut().for_each(|(_, x)| encrypt_values(x, passphrase)),
+        toml::Value::Array(values) => values.iter_mut().for_each(|x| encrypt_values(x, passphrase)),
+        _ => (),
+    }
+}
+
+/// Decrypt all strings in `value` encrypted with `encrypt_values`. The passphrase is only looked
+/// up if there are encrypted values, and then stored in `passphrase`.
+pub fn decrypt_values(value: &mut toml::Value, key_file: Option<&str>, passphrase: &mut Option<String>) -> Result<()> {
+    match value {
+        toml::Value::String(x) => if let Some(blob) = x.strip_prefix(ENCRYPTED_PREFIX) {
+            if passphrase.is_none() {
+                *passphrase = Some(self::passphrase(key_file)?);
+            }
+            *x = decrypt(blob, passphrase.as_deref().unwrap_or_default())?;
+        },
+        toml::Value::Table(table) => for (_, x) in table.iter_mut() {
+            decrypt_values(x, key_file, passphrase)?;
+        },
+        toml::Value::Array(values) => for x in values {
+            decrypt_values(x, key_file, passphrase)?;
+        },
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Encrypt each value in toml file with source credentials and print the result, which can be
+/// added to the config file
+pub fn encrypt_file(path: &str, key_file: Option<&str>) -> Result<()> {
+    let data = std::fs::read_to_string(path)
+        .map_err(|_| CliError::FileNotFound(path.to_string()))?;
+    // Making sure file can be read as config later
+    toml::from_str::<crate::options::Config>(&data)?;
+    let mut value: toml::Value = toml::from_str(&data)?;
+    let passphrase = passphrase(key_file)?;
+    encrypt_values(&mut value, &passphrase);
+    print!("{}", value);
+    Ok(())
+}
+
+/// Read line from stdin after printing `prompt` to stderr
+fn read_line(prompt: &str) -> Result<String> {
+    eprint!("{}", prompt);
+    std::io::stderr().flush().map_err(GrawlixIOError::from)?;
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line).map_err(GrawlixIOError::from)?;
+    Ok(line.trim().to_string())
+}
+
+/// Ask for username and password of `source_name`. The password is not shown while it is
+/// typed. Returns `None` if no username is entered.
+pub fn prompt_credentials(source_name: &str) -> Result<Option<SourceData>> {
+    let username = read_line(&format!("Username for {} (Leave empty to skip): ", source_name))?;
+    if username.is_empty() {
+        return Ok(None);
+    }
+    let password = rpassword::prompt_password(format!("Password for {}: ", source_name))
+        .map_err(GrawlixIOError::from)?;
+    Ok(Some(SourceData {
+        username: Some(username),
+        password: Some(password),
+        ..Default::default()
+    }))
+}
+
+/// Ask for credentials of `source_name` and offer to save them, unless that has already been
+/// done for the source in this process. Earlier answers are reused, including skipped prompts.
+pub fn prompt_once(source_name: &str, key_file: Option<&str>) -> Result<Option<SourceData>> {
+    let key = source_key(source_name);
+    let mut prompted = PROMPTED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(credentials) = prompted.get(&key) {
+        return Ok(credentials.clone());
+    }
+    let credentials = prompt_credentials(source_name)?;
+    if let Some(credentials) = &credentials {
+        offer_to_save(&key, credentials, key_file)?;
+    }
+    prompted.insert(key, credentials.clone());
+    Ok(credentials)
+}
+
+/// Credentials entered for source `name` earlier in this process
+pub fn prompted_credentials(name: &str) -> Option<SourceData> {
+    PROMPTED.lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&source_key(name))
+        .cloned()
+        .flatten()
+}
+
+/// Ask if credentials should be saved and store them in the config file in the settings of
+/// source `key`. Values are encrypted if a passphrase or key file is configured.
+fn offer_to_save(key: &str, credentials: &SourceData, key_file: Option<&str>) -> Result<()> {
+    let answer = read_line("Save credentials to config file? [y/N] ")?;
+    if !answer.eq_ignore_ascii_case("y") {
+        return Ok(());
+    }
+    let mut values = toml::value::Table::new();
+    if let Some(username) = &credentials.username {
+        values.insert("username".to_string(), toml::Value::String(username.clone()));
+    }
+    if let Some(password) = &credentials.password {
+        values.insert("password".to_string(), toml::Value::String(password.clone()));
+    }
+    let mut values = toml::Value::Table(values);
+    if key_file.is_some() || std::env::var(PASSPHRASE_VARIABLE).is_ok() {
+        encrypt_values(&mut values, &passphrase(key_file)?);
+    }
+    let path = config_path()?;
+    let mut config = match std::fs::read_to_string(&path) {
+        Ok(data) => toml::from_str(&data)?,
+        Err(_) => toml::value::Table::new(),
+    };
+    add_source_values(&mut config, key, values);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(GrawlixIOError::from)?;
+    }
+    std::fs::write(&path, toml::Value::Table(config).to_string()).map_err(GrawlixIOError::from)?;
+    log::info!("Saved credentials to {}", path.display());
+    Ok(())
+}
+
+/// Add `values` to the settings of source `key` in `config`, replacing existing values with the
+/// same names
+fn add_source_values(config: &mut toml::value::Table, key: &str, values: toml::Value) {
+    let sources = config.entry("sources")
+        .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+    if !sources.is_table() {
+        *sources = toml::Value::Table(toml::value::Table::new());
+    }
+    if let toml::Value::Table(sources) = sources {
+        let settings = sources.entry(key)
+            .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+        match (settings, values) {
+            (toml::Value::Table(settings), toml::Value::Table(values)) => settings.extend(values),
+            (settings, values) => *settings = values,
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use crate::{
+    CliError, Result, daemon, newznab, update, utils,
+    options::Config,
+};
+use hyper::{
+    Body, Method, Request, Response, Server, StatusCode,
+    header::{AUTHORIZATION, CONTENT_DISPOSITION, CONTENT_TYPE, HOST},
+    service::{make_service_fn, service_fn},
+};
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, convert::Infallible, net::SocketAddr, sync::Arc, time::Duration};
+
+/// Body of request adding a series
+#[derive(Deserialize)]
+struct AddRequest {
+    /// Link to series
+    url: String,
+}
+
+/// Create json response with `status`
+fn json_response<T: Serialize>(status: StatusCode, value: &T) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_string(value).unwrap()))
+        .unwrap()
+}
+
+/// Create json response with error message
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    json_response(status, &serde_json::json!({ "error": message }))
+}
+
+/// Start update in the background. Returns false if an update is already running.
+fn start_update(config: &Arc<Config>) -> bool {
+    if daemon::shutdown_requested() {
+        return false;
+    }
+    {
+        let mut progress = match update::PROGRESS.lock() {
+            Ok(progress) => progress,
+            Err(_) => return false,
+        };
+        if progress.running {
+            return false;
+        }
+        progress.running = true;
+    }
+    let config = Arc::clone(config);
+    tokio::spawn(async move {
+        if let Err(e) = update::update(&config, false).await {
+            error!("Update failed: {}", e);
+        }
+    });
+    true
+}
+
+/// Create xml response with `status`
+fn xml_response(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/xml")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+/// Parameters in query string of request
+fn query_params(req: &Request<Body>) -> HashMap<String, String> {
+    req.uri().query()
+        .and_then(|query| reqwest::Url::parse(&format!("http://localhost/?{}", query)).ok())
+        .map(|url| url.query_pairs().into_owned().collect())
+        .unwrap_or_default()
+}
+
+/// Returns true if the request has the bearer token from config. Newznab clients send the
+/// token as the `apikey` parameter instead.
+fn authorized(req: &Request<Body>, config: &Config) -> bool {
+    match &config.server_token {
+        Some(token) => req.headers()
+            .get(AUTHORIZATION)
+            .and_then(|x| x.to_str().ok())
+            .map(|x| x == format!("Bearer {}", token))
+            .unwrap_or(false)
+            || (req.uri().path() == "/api" && query_params(req).get("apikey") == Some(token)),
+        None => true,
+    }
+}
+
+/// Newznab compatible search of series in the update file, so grawlix can be used as an indexer
+/// by Mylar. Found issues are downloaded from this api as cbz files.
+async fn newznab_api(req: Request<Body>, config: Arc<Config>) -> Response<Body> {
+    let params = query_params(&req);
+    match params.get("t").map(String::as_str) {
+        Some("caps") => xml_response(StatusCode::OK, newznab::caps()),
+        Some("search") | Some("comic") => {
+            let base_url = match req.headers().get(HOST).and_then(|x| x.to_str().ok()) {
+                Some(host) => format!("http://{}", host),
+                None => return xml_response(StatusCode::BAD_REQUEST, newznab::error(200, "Missing host")),
+            };
+            let (series, issue) = newznab::parse_query(params.get("q").map(String::as_str).unwrap_or_default());
+            // Feeds without a query are not supported
+            if series.is_empty() {
+                return xml_response(StatusCode::OK, newznab::results(&[], &base_url, None));
+            }
+            match update::find_issues(&series, issue, &config).await {
+                Ok(issues) => xml_response(
+                    StatusCode::OK,
+                    newznab::results(&issues, &base_url, config.server_token.as_deref())
+                ),
+                Err(e) => xml_response(StatusCode::INTERNAL_SERVER_ERROR, newznab::error(900, &e.to_string())),
+            }
+        },
+        Some("get") => {
+            let id = match params.get("id") {
+                Some(id) => id.clone(),
+                None => return xml_response(StatusCode::BAD_REQUEST, newznab::error(200, "Missing id")),
+            };
+            let path_config = Arc::clone(&config);
+            let path = match tokio::task::spawn_blocking(move || update::issue_path(&id, &path_config)).await {
+                Ok(Ok(Some(path))) => path,
+                // Issues that have not been downloaded are downloaded from the source they were
+                // found on
+                Ok(Ok(None)) => match (params.get("id"), params.get("source"), params.get("series")) {
+                    (Some(id), Some(source), Some(series)) => match update::download_issue(source, series, id, &config).await {
+                        Ok(Some(path)) => path,
+                        Ok(None) => return xml_response(StatusCode::NOT_FOUND, newznab::error(300, "No such item")),
+                        Err(e) => return xml_response(StatusCode::INTERNAL_SERVER_ERROR, newznab::error(900, &e.to_string())),
+                    },
+                    _ => return xml_response(StatusCode::NOT_FOUND, newznab::error(300, "No such item")),
+                },
+                Ok(Err(e)) => return xml_response(StatusCode::INTERNAL_SERVER_ERROR, newznab::error(900, &e.to_string())),
+                Err(e) => return xml_response(StatusCode::INTERNAL_SERVER_ERROR, newznab::error(900, &e.to_string())),
+            };
+            // Issues written as directories can not be sent as one file
+            match tokio::fs::read(&path).await {
+                Ok(data) => {
+                    let name = std::path::Path::new(&path).file_name()
+                        .map(|x| x.to_string_lossy().replace('"', ""))
+                        .unwrap_or_default();
+                    Response::builder()
+                        .header(CONTENT_TYPE, "application/x-cbz")
+                        .header(CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", name))
+                        .body(Body::from(data))
+                        .unwrap()
+                },
+                Err(e) => xml_response(StatusCode::NOT_FOUND, newznab::error(300, &e.to_string())),
+            }
+        },
+        _ => xml_response(StatusCode::BAD_REQUEST, newznab::error(202, "No such function")),
+    }
+}
+
+/// Add series from body of request to update file
+async fn add_series(req: Request<Body>, config: &Config) -> Response<Body> {
+    let body = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(body) => body,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    let request: AddRequest = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    let result = match utils::Link::parse(&request.url) {
+        Ok(link) => update::add_links(vec![link], config).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(added) => json_response(StatusCode::OK, &serde_json::json!({ "added": added })),
+        Err(e) => error_response(StatusCode::UNPROCESSABLE_ENTITY, &e.to_string()),
+    }
+}
+
+/// Handle request to api
+async fn handle(req: Request<Body>, config: Arc<Config>) -> std::result::Result<Response<Body>, Infallible> {
+    debug!("{} {}", req.method(), req.uri().path());
+    if !authorized(&req, &config) {
+        if req.uri().path() == "/api" {
+            return Ok(xml_response(StatusCode::UNAUTHORIZED, newznab::error(100, "Incorrect user credentials")));
+        }
+        return Ok(error_response(StatusCode::UNAUTHORIZED, "Missing or invalid token"));
+    }
+    let response = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/series") => {
+            // Reading the update file waits for running updates to release the lock
+            let summaries_config = Arc::clone(&config);
+            match tokio::task::spawn_blocking(move || update::summaries(&summaries_config)).await {
+                Ok(Ok(summaries)) => json_response(StatusCode::OK, &summaries),
+                Ok(Err(e)) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+                Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+            }
+        },
+        (&Method::POST, "/series") => add_series(req, &config).await,
+        (&Method::POST, "/update") => {
+            if start_update(&config) {
+                json_response(StatusCode::ACCEPTED, &serde_json::json!({ "started": true }))
+            } else {
+                error_response(StatusCode::CONFLICT, "Update is already running")
+            }
+        },
+        (&Method::GET, "/api") => newznab_api(req, Arc::clone(&config)).await,
+        (&Method::GET, "/metrics") => Response::builder()
+            .header(CONTENT_TYPE, "text/plain; version=0.0.4")
+            .body(Body::from(grawlix::source::METRICS.to_prometheus()))
+            .unwrap(),
+        (&Method::GET, "/progress") => match update::PROGRESS.lock() {
+            Ok(progress) => json_response(StatusCode::OK, &*progress),
+            Err(_) => error_response(StatusCode::INTERNAL_SERVER_ERROR, "Could not read progress"),
+        },
+        _ => error_response(StatusCode::NOT_FOUND, "Not found"),
+    };
+    Ok(response)
+}
+
+/// Run http api on `listen` until stopped. Series are updated every `interval` minutes if set.
+pub async fn serve(listen: &str, interval: Option<u64>, config: &Config) -> Result<()> {
+    let addr: SocketAddr = listen.parse()
+        .map_err(|_| CliError::InvalidAddress(listen.to_string()))?;
+    let config = Arc::new(config.clone());
+    let builder = match daemon::activated_listener() {
+        Some(listener) => {
+            if let Ok(addr) = listener.local_addr() {
+                require_token(&addr, &config)?;
+            }
+            info!("Listening on socket from systemd");
+            Server::from_tcp(listener)?
+        },
+        None => {
+            require_token(&addr, &config)?;
+            info!("Listening on http://{}", addr);
+            Server::try_bind(&addr)?
+        },
+    };
+    if let Some(minutes) = interval {
+        let config = Arc::clone(&config);
+        tokio::spawn(async move {
+            loop {
+                if !start_update(&config) {
+                    debug!("Skipping scheduled update (Update is already running)");
+                }
+                tokio::time::sleep(Duration::from_secs(minutes.max(1) * 60)).await;
+            }
+        });
+    }
+    let make_service = make_service_fn(move |_| {
+        let config = Arc::clone(&config);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| handle(req, Arc::clone(&config))))
+        }
+    });
+    let server = builder.serve(make_service)
+        .with_graceful_shutdown(daemon::shutdown_signal().map_err(CliError::Signal)?);
+    daemon::notify("READY=1");
+    server.await?;
+    wait_for_update().await;
+    Ok(())
+}
+
+/// The api can change the update file and download comics, so it is only open to other machines
+/// when `server_token` is set
+fn require_token(addr: &SocketAddr, config: &Config) -> Result<()> {
+    if config.server_token.is_none() && !addr.ip().is_loopback() {
+        return Err(CliError::MissingServerToken(addr.to_string()));
+    }
+    Ok(())
+}
+
+/// Wait until running update has written the current comic and the update file
+async fn wait_for_update() {
+    while update::PROGRESS.lock().map(|progress| progress.running).unwrap_or(false) {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
@@ -0,0 +1,85 @@
+use crate::logging::print_table;
+use grawlix::comic::Comic;
+use serde::Serialize;
+use std::{collections::BTreeMap, path::Path, time::SystemTime};
+
+/// Statistics about comics managed by grawlix
+#[derive(Default, Serialize)]
+pub struct Stats {
+    /// Number of series
+    series: usize,
+    /// Number of downloaded issues
+    issues: usize,
+    /// Number of downloaded issues by source
+    issues_by_source: BTreeMap<String, usize>,
+    /// Bytes on disk by publisher
+    size_by_publisher: BTreeMap<String, u64>,
+    /// Number of issues by the month their file was written
+    issues_by_month: BTreeMap<String, usize>,
+}
+
+impl Stats {
+    /// Add series from `source` with `issues` downloaded issues stored at `paths`. Paths that
+    /// no longer exist are ignored.
+    pub fn add_series<'a>(&mut self, source: &str, issues: usize, paths: impl Iterator<Item = &'a String>) {
+        self.series += 1;
+        self.issues += issues;
+        *self.issues_by_source.entry(source.to_string()).or_default() += issues;
+        for path in paths {
+            let file = Path::new(path);
+            let modified = match file.metadata().and_then(|x| x.modified()) {
+                Ok(modified) => modified,
+                Err(_) => continue,
+            };
+            let publisher = Comic::from_file(path).ok()
+                .and_then(|x| x.metadata.publisher)
+                .unwrap_or_else(|| "Unknown".to_string());
+            *self.size_by_publisher.entry(publisher).or_default() += disk_usage(file);
+            *self.issues_by_month.entry(month(modified)).or_default() += 1;
+        }
+    }
+
+    /// Print statistics as tables
+    pub fn print(&self, plain: bool) {
+        print_table(&["Series", "Issues"], &[vec![self.series.to_string(), self.issues.to_string()]], plain);
+        let tables = [
+            ("Source", "Issues", self.issues_by_source.iter().map(|(k, v)| vec![k.clone(), v.to_string()]).collect::<Vec<_>>()),
+            ("Publisher", "Size", self.size_by_publisher.iter().map(|(k, v)| vec![k.clone(), format_size(*v)]).collect()),
+            ("Month", "Issues", self.issues_by_month.iter().map(|(k, v)| vec![k.clone(), v.to_string()]).collect()),
+        ];
+        for (key, value, rows) in tables {
+            if !rows.is_empty() {
+                println!();
+                print_table(&[key, value], &rows, plain);
+            }
+        }
+    }
+}
+
+/// Size of file or all files in directory
+pub fn disk_usage(path: &Path) -> u64 {
+    if path.is_dir() {
+        std::fs::read_dir(path)
+            .map(|entries| entries.filter_map(|x| x.ok()).map(|x| disk_usage(&x.path())).sum())
+            .unwrap_or(0)
+    } else {
+        path.metadata().map(|x| x.len()).unwrap_or(0)
+    }
+}
+
+/// Month of time in the format year-month
+fn month(time: SystemTime) -> String {
+    humantime::format_rfc3339_seconds(time).to_string()[..7].to_string()
+}
+
+/// Format number of bytes with unit
+pub fn format_size(bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < units.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, units[unit])
+}
@@ -0,0 +1,250 @@
+use crate::{
+    CliError, Result,
+    options::{Config, expand_home},
+};
+use crypto::{digest::Digest, sha2::Sha256};
+use grawlix::error::GrawlixIOError;
+use log::{debug, info};
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// Name of file in remote directory storing checksums of copied files
+const MANIFEST_NAME: &str = ".grawlix-sync.json";
+
+/// Checksums of files by their path relative to the library
+type Manifest = BTreeMap<String, String>;
+
+/// Directory files are copied to
+trait Target {
+    /// Contents of file at `relative` path in target
+    fn read(&self, relative: &str) -> Option<String>;
+    /// Returns true if there is a file at `relative` path in target
+    fn exists(&self, relative: &str) -> bool;
+    /// Copy local file at `path` to `relative` path in target. Missing directories are created.
+    fn copy(&self, path: &Path, relative: &str) -> Result<()>;
+    /// Write `data` to `relative` path in target
+    fn write(&self, relative: &str, data: &str) -> Result<()>;
+}
+
+/// Local directory, for example a mounted network share
+struct LocalDir(PathBuf);
+
+impl Target for LocalDir {
+    fn read(&self, relative: &str) -> Option<String> {
+        std::fs::read_to_string(self.0.join(relative)).ok()
+    }
+
+    fn exists(&self, relative: &str) -> bool {
+        self.0.join(relative).exists()
+    }
+
+    fn copy(&self, path: &Path, relative: &str) -> Result<()> {
+        let target = self.0.join(relative);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(GrawlixIOError::from)?;
+        }
+        std::fs::copy(path, &target).map_err(GrawlixIOError::from)?;
+        Ok(())
+    }
+
+    fn write(&self, relative: &str, data: &str) -> Result<()> {
+        std::fs::create_dir_all(&self.0).map_err(GrawlixIOError::from)?;
+        std::fs::write(self.0.join(relative), data).map_err(GrawlixIOError::from)?;
+        Ok(())
+    }
+}
+
+/// Directory on sftp server
+struct SftpDir {
+    sftp: ssh2::Sftp,
+    dir: PathBuf,
+}
+
+impl SftpDir {
+    /// Connect to server in url like `sftp://user@host:port/path`. The host key has to be in
+    /// `~/.ssh/known_hosts`. Keys from the ssh agent are tried before asking for a password.
+    fn connect(url: &str) -> Result<Self> {
+        let rest = url.trim_start_matches("sftp://");
+        let (authority, dir) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, "."),
+        };
+        let (user, host) = match authority.rsplit_once('@') {
+            Some((user, host)) => (user.to_string(), host),
+            None => (std::env::var("USER").unwrap_or_default(), authority),
+        };
+        let (host, port) = match host.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| CliError::Input(url.to_string()))?),
+            None => (host, 22),
+        };
+        let stream = std::net::TcpStream::connect((host, port)).map_err(GrawlixIOError::from)?;
+        let mut session = ssh2::Session::new()?;
+        session.set_tcp_stream(stream);
+        session.handshake()?;
+        check_host_key(&session, host, port)?;
+        if session.userauth_agent(&user).is_err() {
+            let password = rpassword::prompt_password(format!("Password for {}@{}: ", user, host))
+                .map_err(GrawlixIOError::from)?;
+            session.userauth_password(&user, &password)?;
+        }
+        Ok(Self { sftp: session.sftp()?, dir: PathBuf::from(dir) })
+    }
+
+    /// Create `dir` and its parents if they do not exist
+    fn create_dir_all(&self, dir: &Path) -> Result<()> {
+        let mut missing: Vec<&Path> = dir.ancestors()
+            .take_while(|x| !x.as_os_str().is_empty() && self.sftp.stat(x).is_err())
+            .collect();
+        missing.reverse();
+        for dir in missing {
+            self.sftp.mkdir(dir, 0o755)?;
+        }
+        Ok(())
+    }
+}
+
+impl Target for SftpDir {
+    fn read(&self, relative: &str) -> Option<String> {
+        let mut data = String::new();
+        self.sftp.open(self.dir.join(relative)).ok()?.read_to_string(&mut data).ok()?;
+        Some(data)
+    }
+
+    fn exists(&self, relative: &str) -> bool {
+        self.sftp.stat(&self.dir.join(relative)).is_ok()
+    }
+
+    fn copy(&self, path: &Path, relative: &str) -> Result<()> {
+        let target = self.dir.join(relative);
+        if let Some(parent) = target.parent() {
+            self.create_dir_all(parent)?;
+        }
+        let mut file = std::fs::File::open(path).map_err(GrawlixIOError::from)?;
+        let mut remote = self.sftp.create(&target)?;
+        std::io::copy(&mut file, &mut remote).map_err(GrawlixIOError::from)?;
+        Ok(())
+    }
+
+    fn write(&self, relative: &str, data: &str) -> Result<()> {
+        self.create_dir_all(&self.dir)?;
+        self.sftp.create(&self.dir.join(relative))?
+            .write_all(data.as_bytes())
+            .map_err(GrawlixIOError::from)?;
+        Ok(())
+    }
+}
+
+/// Fail unless the key of the server matches the one in `~/.ssh/known_hosts`
+fn check_host_key(session: &ssh2::Session, host: &str, port: u16) -> Result<()> {
+    let unknown = || CliError::UnknownHostKey(host.to_string());
+    let (key, _) = session.host_key().ok_or_else(unknown)?;
+    let mut known_hosts = session.known_hosts()?;
+    let file = PathBuf::from(expand_home("~/.ssh/known_hosts"));
+    known_hosts.read_file(&file, ssh2::KnownHostFileKind::OpenSSH).map_err(|_| unknown())?;
+    match known_hosts.check_port(host, port, key) {
+        ssh2::CheckResult::Match => Ok(()),
+        _ => Err(unknown()),
+    }
+}
+
+/// Copy files in library that are new or have changed since the last sync to `remote_dir`, which
+/// is a local directory or an url like `sftp://user@host/path`. The library is `output_dir` from
+/// config, since a library found from the template could be the current directory.
+pub fn sync(remote_dir: &str, config: &Config) -> Result<()> {
+    let library = config.output_dir.as_ref()
+        .map(|x| PathBuf::from(expand_home(x)))
+        .ok_or(CliError::MissingLibraryDir)?;
+    let target: Box<dyn Target> = if remote_dir.starts_with("sftp://") {
+        Box::new(SftpDir::connect(remote_dir)?)
+    } else {
+        let remote = PathBuf::from(expand_home(remote_dir));
+        // Files copied into the library would be copied again by the next sync
+        if absolute(&remote).starts_with(absolute(&library)) {
+            return Err(CliError::SyncInsideLibrary(remote_dir.to_string()));
+        }
+        Box::new(LocalDir(remote))
+    };
+    info!("Syncing {} to {}", library.display(), remote_dir);
+    let old_manifest: Manifest = target.read(MANIFEST_NAME)
+        .and_then(|x| serde_json::from_str(&x).ok())
+        .unwrap_or_default();
+    let mut manifest = Manifest::new();
+    let mut copied = 0;
+    for path in library_files(&library) {
+        let relative = match path.strip_prefix(&library) {
+            Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+            Err(_) => continue,
+        };
+        let checksum = checksum(&path)?;
+        if old_manifest.get(&relative) != Some(&checksum) || !target.exists(&relative) {
+            debug!("Copying {}", relative);
+            target.copy(&path, &relative)?;
+            copied += 1;
+        }
+        manifest.insert(relative, checksum);
+        // Saving progress so an interrupted sync does not copy the same files again
+        if copied > 0 && copied % 50 == 0 {
+            write_manifest(&*target, &old_manifest, &manifest)?;
+        }
+    }
+    // Files that are no longer in the library are dropped from the manifest
+    write_manifest(&*target, &Manifest::new(), &manifest)?;
+    info!("Copied {} of {} files", copied, manifest.len());
+    Ok(())
+}
+
+/// Absolute path of `path` with symlinks resolved if it exists
+fn absolute(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// All files in library. Files and directories starting with `.` and unfinished `.part` outputs
+/// are skipped.
+fn library_files(dir: &Path) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|x| x.ok()).map(|x| x.path()).collect(),
+        Err(_) => return Vec::new(),
+    };
+    entries.sort();
+    let mut files = Vec::new();
+    for path in entries {
+        let name = path.file_name()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if name.starts_with('.') || (name.ends_with(".part") && path.is_file()) {
+            continue;
+        } else if path.is_dir() {
+            files.append(&mut library_files(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// Sha256 checksum of file as hex string
+fn checksum(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path).map_err(GrawlixIOError::from)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buffer).map_err(GrawlixIOError::from)?;
+        if n == 0 {
+            break;
+        }
+        hasher.input(&buffer[..n]);
+    }
+    Ok(hasher.result_str())
+}
+
+/// Write manifest to target. Files from the last sync that have not been checked yet are kept.
+fn write_manifest(target: &dyn Target, old: &Manifest, new: &Manifest) -> Result<()> {
+    let mut manifest = old.clone();
+    manifest.extend(new.iter().map(|(k, v)| (k.clone(), v.clone())));
+    target.write(MANIFEST_NAME, &serde_json::to_string_pretty(&manifest).unwrap())
+}
@@ -0,0 +1,49 @@
+use crate::{CliError, Result, options::{Config, expand_home}};
+use grawlix::{
+    comic::{Comic, Page, validate_template},
+    metadata::{Author, AuthorType, Metadata},
+};
+use log::error;
+
+/// Comic used to preview templates
+fn sample_comic() -> Comic {
+    let mut comic = Comic::new();
+    comic.metadata = Metadata {
+        title: Some("Moon Knight #1".to_string()),
+        series: Some("Moon Knight (2016 - 2018)".to_string()),
+        publisher: Some("Marvel".to_string()),
+        issue_number: Some(1),
+        volume: Some(1),
+        year: Some(2016),
+        month: Some(4),
+        day: Some(13),
+        language: Some("en".to_string()),
+        authors: vec![
+            Author { name: "Jeff Lemire".to_string(), author_type: AuthorType::Writer },
+            Author { name: "Greg Smallwood".to_string(), author_type: AuthorType::Penciller },
+            Author { name: "Jordie Bellaire".to_string(), author_type: AuthorType::Colorist },
+        ],
+        ..Default::default()
+    };
+    comic.pages = (0..22).map(|_| Page::from_url("", "jpg")).collect();
+    comic
+}
+
+/// Validate template and print it formatted with sample metadata. Replacements and other template
+/// options are used from `config`.
+pub fn test_template(template: &str, config: &Config) -> Result<()> {
+    let template = expand_home(template);
+    let unknown = validate_template(&template);
+    for field in &unknown {
+        match field.suggestion {
+            Some(suggestion) => error!("Unknown field {{{}}}, did you mean {{{}}}?", field.name, suggestion),
+            None => error!("Unknown field {{{}}}", field.name),
+        }
+    }
+    if !unknown.is_empty() {
+        return Err(CliError::InvalidTemplate(template));
+    }
+    let options = config.download_options().template(&template);
+    println!("{}", options.format_path(&sample_comic())?);
+    Ok(())
+}
@@ -1,25 +1,73 @@
 use crate::{
-    CliError,
-    utils,
-    options::{Arguments, Config}
+    CliError, daemon,
+    email::{self, Digest},
+    logging, notify, utils,
+    options::{Arguments, Config},
+    stats::{Stats, disk_usage, format_size},
 };
-use grawlix::source::{
-    Source, ComicId, get_all_ids, download_series_metadata
+use grawlix::{
+    comic::{Comic, ComicFormat},
+    error::{GrawlixDownloadError, GrawlixIOError},
+    metadata::{CatalogFormat, format_table, same_text, text_key},
+    source::{Source, ComicId, SeriesInfo, format_years, get_latest_ids, download_series_metadata, metadata_from_comicid},
 };
 use thiserror::Error;
 use displaydoc::Display;
 use log::{info, warn, error, debug};
 use serde::{Deserialize, Serialize};
-use std::io::Write;
+use std::{collections::HashMap, fs::File, io::Write, sync::Mutex};
 use reqwest::Client;
 
+/// Current version of the update file format
+const UPDATEFILE_VERSION: u32 = 2;
+
 /// Errors for automatic updates
 #[derive(Debug, Error, Display)]
 pub enum UpdateError {
     /// {0} is not a series
     NotASeries(String),
+    /// Could not find {0} in update file
+    SeriesNotFound(String),
     /// Could not load update file from {0}
     LoadUpdateFile(String),
+    /// Could not save update file to {0}
+    SaveUpdateFile(String),
+    /// Could not lock update file {0}
+    LockUpdateFile(String),
+    /// Update file {0} has version {1} which is newer than supported by this version of grawlix
+    UnsupportedVersion(String, u32),
+}
+
+/// Update file stored on disk
+#[derive(Deserialize, Serialize)]
+struct UpdateFile {
+    /// Version of file format
+    version: u32,
+    /// Series managed by grawlix
+    series: Vec<UpdateSeries>,
+}
+
+/// Migrations between versions of the update file. The migration at index `n` converts a file
+/// from version `n + 1` to version `n + 2`.
+const MIGRATIONS: [fn(serde_json::Value) -> serde_json::Value; 1] = [
+    migrate_v1,
+];
+
+/// Version 1 stored series as a list without version info
+fn migrate_v1(data: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "version": 2,
+        "series": data,
+    })
+}
+
+/// Version of update file data
+fn updatefile_version(data: &serde_json::Value) -> Option<u32> {
+    if data.is_array() {
+        Some(1)
+    } else {
+        data["version"].as_u64().filter(|x| *x > 0).map(|x| x as u32)
+    }
 }
 
 /// Stores necassary information to update a series
@@ -34,73 +82,600 @@ struct UpdateSeries {
     /// True if the series has ended
     #[serde(default = "Default::default")]
     ended: bool,
+    /// Number of issues in series
+    #[serde(default = "Default::default", skip_serializing_if = "Option::is_none")]
+    issue_count: Option<usize>,
+    /// Year the series started
+    #[serde(default = "Default::default", skip_serializing_if = "Option::is_none")]
+    start_year: Option<u32>,
+    /// Year the series ended
+    #[serde(default = "Default::default", skip_serializing_if = "Option::is_none")]
+    end_year: Option<u32>,
+    /// Publisher of series
+    #[serde(default = "Default::default", skip_serializing_if = "Option::is_none")]
+    publisher: Option<String>,
+    /// Description of series
+    #[serde(default = "Default::default", skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    /// Url of cover image for series
+    #[serde(default = "Default::default", skip_serializing_if = "Option::is_none")]
+    cover_url: Option<String>,
+    /// False if the series should be skipped when updating
+    #[serde(default = "default_enabled")]
+    enabled: bool,
     /// List of issues already downloaded
-    downloaded_issues: Vec<String>
+    downloaded_issues: Vec<String>,
+    /// Output paths of downloaded issues by id
+    #[serde(default = "Default::default", skip_serializing_if = "HashMap::is_empty")]
+    paths: HashMap<String, String>,
+    /// Settings overriding config when updating series
+    #[serde(default = "Default::default", skip_serializing_if = "SeriesSettings::is_empty")]
+    settings: SeriesSettings,
 }
 
-/// Load updatefile from disk if it exists
+fn default_enabled() -> bool {
+    true
+}
+
+/// Settings for a single series in the update file
+#[derive(Default, Deserialize, Serialize)]
+pub struct SeriesSettings {
+    /// Output template used instead of the one in config
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Directory comics are written to. The output template is relative to this directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<String>,
+    /// Output format used instead of the one in config
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<ComicFormat>,
+}
+
+impl SeriesSettings {
+    /// Returns true if no settings are overridden
+    fn is_empty(&self) -> bool {
+        self.template.is_none() && self.output_dir.is_none() && self.output_format.is_none()
+    }
+
+    /// Overwrite values in `settings` that are set
+    fn merge(&mut self, settings: SeriesSettings) {
+        if settings.template.is_some() {
+            self.template = settings.template;
+        }
+        if settings.output_dir.is_some() {
+            self.output_dir = settings.output_dir;
+        }
+        if settings.output_format.is_some() {
+            self.output_format = settings.output_format;
+        }
+    }
+
+    /// Create copy of `config` with settings applied
+    fn apply(&self, config: &Config) -> Config {
+        let mut config = config.clone();
+        if let Some(template) = &self.template {
+            config.output_template = template.clone();
+        }
+        if let Some(output_dir) = &self.output_dir {
+            config.output_dir = Some(output_dir.clone());
+        }
+        if let Some(output_format) = &self.output_format {
+            config.output_format = output_format.clone();
+        }
+        config
+    }
+}
+
+/// Lock update file so other grawlix processes can't change it until the returned file is
+/// dropped. Multiple processes can hold a shared lock at the same time.
+fn lock_updatefile(path: &str, shared: bool) -> Result<File, UpdateError> {
+    utils::lock_file(path, shared).map_err(|_| UpdateError::LockUpdateFile(path.to_string()))
+}
+
+/// Load updatefile from disk if it exists. Files in older formats are migrated to the current
+/// version.
 fn load_updatefile(path: &str) -> Result<Vec<UpdateSeries>, UpdateError> {
-    if std::path::Path::new(&path).exists() {
-        std::fs::read_to_string(&path)
-            .ok()
-            .map(|x| serde_json::from_str(&x).ok())
-            .flatten()
-            .ok_or(UpdateError::LoadUpdateFile(path.to_string()))
-    } else {
+    if !std::path::Path::new(&path).exists() {
         return Ok(Vec::new());
     }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|_| UpdateError::LoadUpdateFile(path.to_string()))?;
+    let mut data: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|_| UpdateError::LoadUpdateFile(path.to_string()))?;
+    let mut version = updatefile_version(&data)
+        .ok_or(UpdateError::LoadUpdateFile(path.to_string()))?;
+    if version > UPDATEFILE_VERSION {
+        return Err(UpdateError::UnsupportedVersion(path.to_string(), version));
+    }
+    if version < UPDATEFILE_VERSION {
+        // Keeping old file in case the migration loses data
+        let backup_path = format!("{}.v{}.bak", path, version);
+        info!("Migrating update file to version {} (Backup saved to {})", UPDATEFILE_VERSION, backup_path);
+        std::fs::write(&backup_path, &content)
+            .map_err(|_| UpdateError::SaveUpdateFile(backup_path.clone()))?;
+    }
+    while version < UPDATEFILE_VERSION {
+        debug!("Migrating update file from version {}", version);
+        data = MIGRATIONS[version as usize - 1](data);
+        version += 1;
+    }
+    serde_json::from_value::<UpdateFile>(data)
+        .map(|file| file.series)
+        .map_err(|_| UpdateError::LoadUpdateFile(path.to_string()))
 }
 
-/// Write `update_data` to disk
-fn write_updatefile(update_data: &Vec<UpdateSeries>, path: &str) {
-    let mut file = std::fs::File::create(path).unwrap();
-    match file.write_all(serde_json::to_string(&update_data).unwrap().as_bytes()) {
-        Ok(_) => (),
-        Err(_) => error!("Could not save update file to {}", path)
-    }
+/// Write `update_data` to disk. Data is written to a temporary file first which replaces the
+/// update file when complete.
+fn write_updatefile(update_data: Vec<UpdateSeries>, path: &str) -> Result<(), UpdateError> {
+    let save_error = |_| UpdateError::SaveUpdateFile(path.to_string());
+    let data = serde_json::to_string(&UpdateFile {
+        version: UPDATEFILE_VERSION,
+        series: update_data,
+    }).map_err(|_| UpdateError::SaveUpdateFile(path.to_string()))?;
+    let tmp_path = format!("{}.tmp", path);
+    let mut file = File::create(&tmp_path).map_err(save_error)?;
+    file.write_all(data.as_bytes()).map_err(save_error)?;
+    file.sync_all().map_err(save_error)?;
+    std::fs::rename(&tmp_path, path).map_err(|e| {
+        error!("Could not replace update file: {}", e);
+        UpdateError::SaveUpdateFile(path.to_string())
+    })
 }
 
 /// Download `crate::source::SeriesInfo` for given series
-async fn create_new_updateseries(source: &Box<dyn Source>, client: &Client, id: &ComicId) -> Result<UpdateSeries, CliError> {
+async fn create_new_updateseries(source: &dyn Source, client: &Client, id: &ComicId) -> Result<UpdateSeries, CliError> {
     let series_info = download_series_metadata(client, source, id).await?;
-    Ok(UpdateSeries {
+    let mut update_series = UpdateSeries {
         source: source.name(),
-        name: series_info.name.clone(),
-        ended: series_info.ended,
+        name: String::new(),
+        ended: false,
+        issue_count: None,
+        start_year: None,
+        end_year: None,
+        publisher: None,
+        description: None,
+        cover_url: None,
         id: id.inner().to_string(),
-        downloaded_issues: Vec::new()
-    })
+        enabled: true,
+        downloaded_issues: Vec::new(),
+        paths: HashMap::new(),
+        settings: SeriesSettings::default(),
+    };
+    update_series.set_info(series_info);
+    Ok(update_series)
+}
+
+impl UpdateSeries {
+    /// Replace info about series with `info` from source
+    fn set_info(&mut self, info: SeriesInfo) {
+        self.name = info.name;
+        self.ended = info.ended;
+        self.issue_count = info.issue_count;
+        self.start_year = info.start_year;
+        self.end_year = info.end_year;
+        self.publisher = info.publisher;
+        self.description = info.description;
+        self.cover_url = info.cover_url;
+    }
+
+    /// Info about series stored in update file
+    fn info(&self) -> SeriesInfo {
+        SeriesInfo {
+            name: self.name.clone(),
+            ended: self.ended,
+            issue_count: self.issue_count,
+            start_year: self.start_year,
+            end_year: self.end_year,
+            publisher: self.publisher.clone(),
+            description: self.description.clone(),
+            cover_url: self.cover_url.clone(),
+        }
+    }
+
+    /// Short description of series with publisher, years, and number of issues
+    fn details(&self) -> String {
+        let mut details = Vec::new();
+        if let Some(publisher) = &self.publisher {
+            details.push(publisher.clone());
+        }
+        if let Some(years) = format_years(self.start_year, self.end_year, self.ended) {
+            details.push(years);
+        }
+        if let Some(issue_count) = self.issue_count {
+            details.push(format!("{} issues", issue_count));
+        }
+        details.join(", ")
+    }
 }
 
 /// Add series to update file
 pub async fn add(args: &Arguments, config: &Config, inputs: &Vec<String>) -> std::result::Result<(), CliError> {
     let links = utils::get_all_links(inputs, args)?;
-    let mut update_data = load_updatefile(&config.update_location)?;
+    add_links(links, config).await?;
+    Ok(())
+}
+
+/// Add series at `links` to update file. Returns names of the added series. The update file is
+/// locked on a blocking thread after the series have been found, so running updates are not
+/// blocked while sources are queried.
+pub async fn add_links(links: Vec<utils::Link>, config: &Config) -> Result<Vec<String>, CliError> {
+    let mut new_series = Vec::new();
     for link in links {
-        let (source, client) = utils::get_source_from_url(&link, config).await?;
-        let id = source.id_from_url(&link)?;
+        let (source, client) = utils::get_source_from_url(&link.url, config).await?;
+        let id = source.id_from_url(&link.url)?;
         debug!("Found id: {:?}", id);
         if let ComicId::Series(_) = &id {
-            let update_series = create_new_updateseries(&source, &client, &id).await?;
-            if !update_data.iter().any(|x| x.source == update_series.source && x.id == update_series.id) {
-                info!("Added {}", &update_series.name);
-                update_data.push(update_series);
+            let mut update_series = create_new_updateseries(&*source, &client, &id).await?;
+            update_series.settings.merge(SeriesSettings {
+                template: link.template.clone(),
+                output_dir: link.output_dir.clone(),
+                output_format: link.format.clone(),
+            });
+            if !link.issues.is_empty() {
+                warn!("Issue ranges are ignored when adding series to update file");
             }
+            new_series.push(update_series);
         } else {
-            warn!("Can't add {} to update file since it is not a series", link);
+            warn!("Can't add {} to update file since it is not a series", link.url);
+        }
+    }
+    let location = config.update_location.clone();
+    let added = tokio::task::spawn_blocking(move || add_to_updatefile(new_series, &location))
+        .await
+        .map_err(|_| UpdateError::SaveUpdateFile(config.update_location.clone()))??;
+    Ok(added)
+}
+
+/// Add series that are not already in the update file at `location`. Returns names of the added
+/// series.
+fn add_to_updatefile(new_series: Vec<UpdateSeries>, location: &str) -> Result<Vec<String>, UpdateError> {
+    let mut added = Vec::new();
+    let _lock = lock_updatefile(location, false)?;
+    let mut update_data = load_updatefile(location)?;
+    for update_series in new_series {
+        if !update_data.iter().any(|x| x.source == update_series.source && x.id == update_series.id) {
+            match update_series.details().as_str() {
+                "" => info!("Added {}", &update_series.name),
+                details => info!("Added {} ({})", &update_series.name, details),
+            }
+            if let Some(description) = &update_series.description {
+                debug!("{}", description);
+            }
+            added.push(update_series.name.clone());
+            update_data.push(update_series);
         }
     }
     update_data.sort_by(|x, y| x.name.cmp(&y.name));
-    write_updatefile(&update_data, &config.update_location);
-    Ok(())
+    write_updatefile(update_data, location)?;
+    Ok(added)
+}
+
+/// Summary of series in update file
+#[derive(Serialize)]
+pub struct SeriesSummary {
+    name: String,
+    source: String,
+    id: String,
+    enabled: bool,
+    ended: bool,
+    downloaded_issues: usize,
+    issue_count: Option<usize>,
+    publisher: Option<String>,
+    years: Option<String>,
+}
+
+/// Summaries of all series in update file
+pub fn summaries(config: &Config) -> Result<Vec<SeriesSummary>, CliError> {
+    let _lock = lock_updatefile(&config.update_location, true)?;
+    let update_data = load_updatefile(&config.update_location)?;
+    Ok(update_data.into_iter()
+        .map(|series| SeriesSummary {
+            years: format_years(series.start_year, series.end_year, series.ended),
+            name: series.name,
+            source: series.source,
+            id: series.id,
+            enabled: series.enabled,
+            ended: series.ended,
+            downloaded_issues: series.downloaded_issues.len(),
+            issue_count: series.issue_count,
+            publisher: series.publisher,
+        })
+        .collect())
 }
 
 /// Print all series in updatefile
 pub fn list(config: &Config) -> Result<(), CliError> {
+    let _lock = lock_updatefile(&config.update_location, true)?;
     let update_data = load_updatefile(&config.update_location)?;
-    for series in update_data {
+    let rows: Vec<Vec<String>> = update_data.iter()
+        .map(|series| vec![
+            series.name.clone(),
+            series.source.clone(),
+            series.publisher.clone().unwrap_or_default(),
+            format_years(series.start_year, series.end_year, series.ended).unwrap_or_default(),
+            match series.issue_count {
+                Some(total) => format!("{}/{}", series.downloaded_issues.len(), total),
+                None => series.downloaded_issues.len().to_string(),
+            },
+            if !series.enabled { "paused" } else if series.ended { "ended" } else { "active" }.to_string(),
+        ])
+        .collect();
+    logging::print_table(&["Name", "Source", "Publisher", "Years", "Issues", "Status"], &rows, config.plain);
+    Ok(())
+}
+
+/// Issue in catalog of update file
+#[derive(Serialize)]
+struct CatalogEntry {
+    series: String,
+    issue: Option<u32>,
+    title: Option<String>,
+    date: Option<String>,
+    path: Option<String>,
+    source: String,
+}
+
+impl CatalogEntry {
+    /// Fields in the order of `CATALOG_COLUMNS`
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.series.clone(),
+            self.issue.map(|x| x.to_string()).unwrap_or_default(),
+            self.title.clone().unwrap_or_default(),
+            self.date.clone().unwrap_or_default(),
+            self.path.clone().unwrap_or_default(),
+            self.source.clone(),
+        ]
+    }
+}
+
+/// Columns of csv and markdown catalogs
+const CATALOG_COLUMNS: [&str; 6] = ["series", "issue", "title", "date", "path", "source"];
+
+/// Create catalog entries for downloaded issues of `series`. Issue number, title, and date are
+/// read from the downloaded files if they still exist.
+fn catalog_entries(series: &UpdateSeries) -> Vec<CatalogEntry> {
+    series.downloaded_issues.iter()
+        .map(|id| {
+            let path = series.paths.get(id).cloned();
+            let metadata = path.as_deref()
+                .filter(|x| std::path::Path::new(x).exists())
+                .and_then(|x| Comic::from_file(x).ok())
+                .map(|x| x.metadata)
+                .unwrap_or_default();
+            CatalogEntry {
+                series: series.name.clone(),
+                issue: metadata.issue_number,
+                title: metadata.title.clone(),
+                date: metadata.date().or_else(|| metadata.year.map(|x| x.to_string())),
+                path,
+                source: series.source.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Export list of all downloaded issues in update file as a catalog. The catalog is written to
+/// `output` or stdout.
+pub fn export_catalog(format: CatalogFormat, output: Option<&str>, config: &Config) -> Result<(), CliError> {
+    let _lock = lock_updatefile(&config.update_location, true)?;
+    let update_data = load_updatefile(&config.update_location)?;
+    let entries: Vec<CatalogEntry> = update_data.iter()
+        .flat_map(catalog_entries)
+        .collect();
+    let rows: Vec<Vec<String>> = entries.iter().map(CatalogEntry::fields).collect();
+    let catalog = match format_table(&CATALOG_COLUMNS, &rows, format) {
+        Some(table) => table,
+        None => serde_json::to_string_pretty(&entries).unwrap() + "\n",
+    };
+    match output {
+        Some(path) => {
+            info!("Writing {} issues to {}", entries.len(), path);
+            std::fs::write(path, catalog).map_err(GrawlixIOError::from)?;
+        },
+        None => print!("{}", catalog),
+    }
+    Ok(())
+}
+
+/// Issue found with `find_issues`
+pub struct FoundIssue {
+    /// Id of issue on source
+    pub id: String,
+    /// Source and series id the issue can be downloaded from. Not set for downloaded issues.
+    pub available: Option<(String, String)>,
+    pub series: String,
+    pub issue: Option<u32>,
+    pub year: Option<u32>,
+    /// Size of issue in bytes. 0 for issues that have not been downloaded.
+    pub size: u64,
+    pub modified: Option<std::time::SystemTime>,
+}
+
+/// Run `f` with the update file at `location` locked on a blocking thread, so waiting for other
+/// processes holding the lock does not block the async runtime
+async fn with_lock<T, F>(location: &str, shared: bool, f: F) -> Result<T, UpdateError>
+where
+    T: Send + 'static,
+    F: FnOnce(&str) -> Result<T, UpdateError> + Send + 'static,
+{
+    let path = location.to_string();
+    tokio::task::spawn_blocking(move || {
+        let _lock = lock_updatefile(&path, shared)?;
+        f(&path)
+    })
+        .await
+        .map_err(|_| UpdateError::LockUpdateFile(location.to_string()))?
+}
+
+/// Load series from update file on a blocking thread, so running updates holding the lock do
+/// not block the async runtime
+async fn load_series<F>(config: &Config, filter: F) -> Result<Vec<UpdateSeries>, UpdateError>
+where
+    F: Fn(&UpdateSeries) -> bool + Send + 'static
+{
+    with_lock(&config.update_location, true, move |location| {
+        Ok(load_updatefile(location)?.into_iter().filter(filter).collect())
+    }).await
+}
+
+/// Issues of series whose name contains `series`, compared with `text_key`. Only issues
+/// numbered `issue` are returned if it is set. Downloaded issues are read from disk and the
+/// other issues of the series are looked up on their source. Series whose source can not be
+/// reached only return downloaded issues.
+pub async fn find_issues(series: &str, issue: Option<u32>, config: &Config) -> Result<Vec<FoundIssue>, CliError> {
+    let key = text_key(series);
+    let matching = load_series(config, move |x| text_key(&x.name).contains(&key)).await?;
+    let mut found = Vec::new();
+    for series in &matching {
+        found.extend(downloaded_issues(series, issue));
+        match available_issues(series, issue, config).await {
+            Ok(available) => found.extend(available),
+            Err(e) => warn!("Could not search for {} on {}: {}", series.name, series.source, e),
+        }
+    }
+    found.sort_by(|a, b| (&a.series, a.issue).cmp(&(&b.series, b.issue)));
+    Ok(found)
+}
+
+/// Downloaded issues of `series` numbered `issue`. Issues that no longer exist on disk are
+/// skipped.
+fn downloaded_issues(series: &UpdateSeries, issue: Option<u32>) -> Vec<FoundIssue> {
+    let mut found = Vec::new();
+    for (id, path) in &series.paths {
+        let file = std::path::Path::new(path);
+        let metadata = match Comic::from_file(path) {
+            Ok(comic) => comic.metadata,
+            Err(_) => continue,
+        };
+        if issue.is_some() && metadata.issue_number != issue {
+            continue;
+        }
+        found.push(FoundIssue {
+            id: id.clone(),
+            available: None,
+            series: series.name.clone(),
+            issue: metadata.issue_number,
+            year: metadata.year,
+            size: disk_usage(file),
+            modified: file.metadata().and_then(|x| x.modified()).ok(),
+        });
+    }
+    found
+}
+
+/// Issues of `series` numbered `issue` on its source that have not been downloaded
+async fn available_issues(series: &UpdateSeries, issue: Option<u32>, config: &Config) -> Result<Vec<FoundIssue>, CliError> {
+    let (source, client) = utils::get_source_from_name(&series.source, config).await?;
+    let seriesid = ComicId::Series(series.id.as_str().into());
+    let mut found = Vec::new();
+    for id in utils::resolve_ids(&*source, &client, seriesid, config).await? {
+        if series.paths.contains_key(id.inner()) {
+            continue;
+        }
+        let id_str = id.inner().clone();
+        let metadata = metadata_from_comicid(&*source, &client, id).await?;
+        if issue.is_some() && metadata.issue_number != issue {
+            continue;
+        }
+        found.push(FoundIssue {
+            id: id_str,
+            available: Some((series.source.clone(), series.id.clone())),
+            series: series.name.clone(),
+            issue: metadata.issue_number,
+            year: metadata.year,
+            size: 0,
+            modified: None,
+        });
+    }
+    Ok(found)
+}
+
+/// Output path of downloaded issue with `id`
+pub fn issue_path(id: &str, config: &Config) -> Result<Option<String>, CliError> {
+    let _lock = lock_updatefile(&config.update_location, true)?;
+    let update_data = load_updatefile(&config.update_location)?;
+    Ok(update_data.iter().find_map(|series| series.paths.get(id).cloned()))
+}
+
+/// Download issue with `id` from `source` using the settings of the series with `seriesid` in
+/// the update file. Returns the output path, or `None` if the series is not in the update file or
+/// the issue was skipped by the filters in config. The issue is added to the list of downloaded
+/// issues by the next update.
+pub async fn download_issue(source: &str, seriesid: &str, id: &str, config: &Config) -> Result<Option<String>, CliError> {
+    let (source_name, series_id) = (source.to_string(), seriesid.to_string());
+    let series = load_series(config, move |x| x.source == source_name && x.id == series_id).await?;
+    let series = match series.first() {
+        Some(series) => series,
+        None => return Ok(None),
+    };
+    let mut issue_config = series.settings.apply(config);
+    issue_config.series_info = Some(series.info());
+    let (source, client) = utils::get_source_from_name(source, config).await?;
+    let written = utils::download_and_write_comic(&*source, &client, ComicId::Issue(id.into()), None, &issue_config).await?;
+    Ok(written.map(|x| x.path))
+}
+
+/// Print statistics about all series in update file
+pub fn stats(config: &Config) -> Result<(), CliError> {
+    let _lock = lock_updatefile(&config.update_location, true)?;
+    let update_data = load_updatefile(&config.update_location)?;
+    let mut stats = Stats::default();
+    for series in &update_data {
+        stats.add_series(&series.source, series.downloaded_issues.len(), series.paths.values());
+    }
+    if config.json {
+        println!("{}", serde_json::to_string_pretty(&stats).unwrap());
+    } else {
+        stats.print(config.plain);
+    }
+    Ok(())
+}
+
+/// Find series in update data by name or id
+fn find_series<'a>(update_data: &'a mut [UpdateSeries], name: &str) -> Result<&'a mut UpdateSeries, UpdateError> {
+    update_data.iter_mut()
+        .find(|x| same_text(&x.name, name) || x.id == name)
+        .ok_or(UpdateError::SeriesNotFound(name.to_string()))
+}
+
+/// Enable or disable updates of series. `name` can be the name or id of the series.
+pub fn set_enabled(name: &str, enabled: bool, config: &Config) -> Result<(), CliError> {
+    let _lock = lock_updatefile(&config.update_location, false)?;
+    let mut update_data = load_updatefile(&config.update_location)?;
+    let series = find_series(&mut update_data, name)?;
+    series.enabled = enabled;
+    if enabled {
+        info!("Resumed updates of {}", series.name);
+    } else {
+        info!("Paused updates of {}", series.name);
+    }
+    write_updatefile(update_data, &config.update_location)?;
+    Ok(())
+}
+
+/// Change settings of series in update file. `name` can be the name or id of the series. Current
+/// settings are printed if no settings are given.
+pub fn edit(name: &str, settings: SeriesSettings, reset: bool, config: &Config) -> Result<(), CliError> {
+    let _lock = lock_updatefile(&config.update_location, false)?;
+    let mut update_data = load_updatefile(&config.update_location)?;
+    let series = find_series(&mut update_data, name)?;
+    if reset {
+        series.settings = SeriesSettings::default();
+    }
+    if settings.is_empty() && !reset {
         println!("{}", series.name);
+        println!("  template: {}", series.settings.template.as_deref().unwrap_or("(config)"));
+        println!("  output dir: {}", series.settings.output_dir.as_deref().unwrap_or("(config)"));
+        match &series.settings.output_format {
+            Some(format) => println!("  output format: {:?}", format),
+            None => println!("  output format: (config)"),
+        }
+        return Ok(());
     }
+    series.settings.merge(settings);
+    info!("Updated settings for {}", series.name);
+    write_updatefile(update_data, &config.update_location)?;
     Ok(())
 }
 
@@ -109,43 +684,146 @@ async fn update_series_info(mut update_data: Vec<UpdateSeries>, config: &Config)
     for series in &mut update_data {
         debug!("Updating info for {} ({})", series.name, series.id);
         let (source, client) = utils::get_source_from_name(&series.source, config).await?;
-        let new_data = create_new_updateseries(&source, &client, &ComicId::Series(series.id.clone())).await?;
-        series.name = new_data.name;
-        series.ended = new_data.ended;
+        let info = download_series_metadata(&client, &*source, &ComicId::Series(series.id.clone().into())).await?;
+        series.set_info(info);
     }
     Ok(update_data)
 }
 
+/// Number of recent comics checked before listing the whole series
+const LATEST_COUNT: usize = 10;
+
 // Returns a list of new ids in current series
-async fn find_new_ids(source: &Box<dyn Source>, client: &Client, series: &UpdateSeries) -> Result<Vec<ComicId>, CliError> {
-    let seriesid = ComicId::Series(series.id.to_string());
-    Ok(get_all_ids(source, client, seriesid).await?
+async fn find_new_ids(source: &dyn Source, client: &Client, series: &UpdateSeries, config: &Config) -> Result<Vec<ComicId>, CliError> {
+    let seriesid = ComicId::Series(series.id.as_str().into());
+    // If some of the latest comics have already been downloaded there are no other new comics
+    match get_latest_ids(source, client, &seriesid, LATEST_COUNT).await {
+        Ok(latest) => {
+            let new: Vec<ComicId> = latest.iter()
+                .filter(|x| !series.downloaded_issues.contains(x.inner()))
+                .cloned()
+                .collect();
+            if new.len() < latest.len() {
+                return Ok(new);
+            }
+            debug!("All latest comics in {} are new, listing whole series", series.name);
+        },
+        Err(GrawlixDownloadError::LatestNotSupported(_)) => (),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(utils::resolve_ids(source, client, seriesid, config).await?
         .into_iter()
         .filter(|x| !series.downloaded_issues.contains(x.inner()))
         .collect())
 }
 
+/// Progress of the running or last update
+#[derive(Clone, Serialize)]
+pub struct Progress {
+    /// True while an update is running
+    pub running: bool,
+    /// Series currently being updated
+    pub current_series: Option<String>,
+    /// Number of series checked for new issues
+    pub series_done: usize,
+    /// Number of series in update
+    pub series_total: usize,
+    /// Number of comics downloaded
+    pub downloaded: usize,
+    /// Error that stopped the last update
+    pub error: Option<String>,
+}
+
+impl Progress {
+    const fn new() -> Self {
+        Self {
+            running: false,
+            current_series: None,
+            series_done: 0,
+            series_total: 0,
+            downloaded: 0,
+            error: None,
+        }
+    }
+}
+
+/// Progress of update in this process
+pub static PROGRESS: Mutex<Progress> = Mutex::new(Progress::new());
+
+/// Change progress of update
+fn set_progress<F: FnOnce(&mut Progress)>(f: F) {
+    if let Ok(mut progress) = PROGRESS.lock() {
+        f(&mut progress);
+    }
+}
+
 /// Downloads new comics for all series in `update_data`
-async fn download_new_comics(update_data: &mut Vec<UpdateSeries>, config: &Config) -> Result<(), CliError> {
+async fn download_new_comics(update_data: &mut Vec<UpdateSeries>, config: &Config, digest: &mut Digest) -> Result<(), CliError> {
+    set_progress(|x| x.series_total = update_data.iter().filter(|x| x.enabled).count());
     for series in update_data {
+        if !series.enabled {
+            debug!("Skipping {} (Paused)", series.name);
+            continue
+        }
         info!("Searching for updates in {}", series.name);
+        set_progress(|x| x.current_series = Some(series.name.clone()));
         let (source, client) = utils::get_source_from_name(&series.source, config).await?;
         // Finding new ids
-        let comicids = find_new_ids(&source, &client, series).await?;
+        let comicids = find_new_ids(&*source, &client, series, config).await?;
         // Downloading new comics
         if comicids.len() == 0 {
+            set_progress(|x| x.series_done += 1);
             continue
         }
         info!("Retrieving data for {} comics from {}", comicids.len(), series.name);
-        utils::download_and_write_comics(&source, &client, &comicids, config).await;
-        // Adding new ids to update file
-        for id in comicids {
-            series.downloaded_issues.push(id.inner().to_string());
+        let mut series_config = series.settings.apply(config);
+        series_config.series_info = Some(series.info());
+        let written = utils::download_and_write_comics(&*source, &client, &comicids, None, &series_config).await;
+        for comic in &written {
+            series.paths.extend(comic.ids.iter().map(|id| (id.clone(), comic.path.clone())));
+        }
+        if !written.is_empty() {
+            let titles: Vec<String> = written.iter().map(|x| x.title.clone()).collect();
+            if !config.notifiers.is_empty() {
+                notify::new_issues(&config.notifiers, &series.name, &titles, series.cover_url.as_deref()).await;
+            }
+            digest.downloaded.push((series.name.clone(), titles));
         }
+        // Adding written ids to update file. Issues that were skipped, like previews or issues
+        // that failed, are tried again in the next update.
+        for id in written.iter().flat_map(|x| &x.ids) {
+            series.downloaded_issues.push(id.clone());
+        }
+        if daemon::shutdown_requested() {
+            info!("Stopped update after {}", series.name);
+            break;
+        }
+        set_progress(|x| {
+            x.series_done += 1;
+            x.downloaded += written.len();
+        });
     }
     Ok(())
 }
 
+/// Remove issues from the list of downloaded issues if their file has been deleted, so they are
+/// downloaded again. Issues downloaded before paths were stored are not checked, and neither are
+/// relative paths stored by older versions, since they depend on the directory grawlix was
+/// started from.
+fn heal_series(series: &mut UpdateSeries) {
+    let missing: Vec<String> = series.downloaded_issues.iter()
+        .filter(|id| match series.paths.get(*id).map(std::path::Path::new) {
+            Some(path) => path.is_absolute() && !path.exists(),
+            None => false,
+        })
+        .cloned()
+        .collect();
+    if !missing.is_empty() {
+        info!("Found {} deleted issues in {}", missing.len(), series.name);
+        series.downloaded_issues.retain(|id| !missing.contains(id));
+    }
+}
+
 /// Remove all series that have ended
 fn remove_ended_series(update_data: Vec<UpdateSeries>) -> Vec<UpdateSeries> {
     update_data.into_iter()
@@ -153,16 +831,181 @@ fn remove_ended_series(update_data: Vec<UpdateSeries>) -> Vec<UpdateSeries> {
         .collect()
 }
 
-/// Update all files stored in updatefile
-pub async fn update(config: &Config) -> Result<(), CliError> {
-    let mut update_data = load_updatefile(&config.update_location)?;
+/// Update all files stored in updatefile. Deleted issues are downloaded again if `heal` is
+/// enabled.
+pub async fn update(config: &Config, heal: bool) -> Result<(), CliError> {
+    set_progress(|x| *x = Progress { running: true, ..Progress::new() });
+    let result = run_update(config, heal).await;
+    set_progress(|x| {
+        x.running = false;
+        x.current_series = None;
+        x.error = result.as_ref().err().map(|e| e.to_string());
+    });
+    result
+}
+
+/// Runs the update. Other processes can read and change the update file while comics are
+/// downloaded, since the lock is only held while loading the update file and merging the results
+/// back into it. A second lock keeps updates from running at the same time.
+async fn run_update(config: &Config, heal: bool) -> Result<(), CliError> {
+    let running_path = format!("{}.update", config.update_location);
+    let _running = tokio::task::spawn_blocking(move || lock_updatefile(&running_path, false))
+        .await
+        .map_err(|_| UpdateError::LockUpdateFile(config.update_location.clone()))??;
+    let mut update_data = with_lock(&config.update_location, true, load_updatefile).await?;
+    let started: HashMap<(String, String), Vec<String>> = update_data.iter()
+        .map(|series| ((series.source.clone(), series.id.clone()), series.downloaded_issues.clone()))
+        .collect();
+    if heal {
+        update_data.iter_mut()
+            .filter(|series| series.enabled)
+            .for_each(heal_series);
+    }
     if config.update_series_info {
         info!("Updating series info");
         update_data = update_series_info(update_data, config).await?;
     }
-    download_new_comics(&mut update_data, config).await?;
-    let update_data = remove_ended_series(update_data);
-    write_updatefile(&update_data, &config.update_location);
+    let mut digest = Digest::default();
+    let result = download_new_comics(&mut update_data, config, &mut digest).await;
+    if let Some(email) = &config.email {
+        if let Err(e) = &result {
+            digest.failures.push(e.to_string());
+        }
+        email::send_digest(email, &digest).await;
+    }
+    result?;
+    let prune_config = config.clone();
+    with_lock(&config.update_location, false, move |location| {
+        let mut update_data = merge_update(load_updatefile(location)?, update_data, &started);
+        if prune_config.keep_issues.is_some() || prune_config.max_source_size.is_some() {
+            prune_series(&mut update_data, &prune_config, false);
+        }
+        write_updatefile(remove_ended_series(update_data), location)
+    }).await?;
     info!("Completed update");
     Ok(())
 }
+
+/// Merge series from an update into `current`, the update file as it is on disk after the
+/// update. `started` has the downloaded issues of each series when the update started. Series
+/// added or removed while updating are kept or left out, settings changed while updating are
+/// kept, and issues downloaded by other processes are added to the updated series.
+fn merge_update(current: Vec<UpdateSeries>, mut updated: Vec<UpdateSeries>, started: &HashMap<(String, String), Vec<String>>) -> Vec<UpdateSeries> {
+    current.into_iter()
+        .map(|current| {
+            let position = updated.iter().position(|x| x.source == current.source && x.id == current.id);
+            let mut series = match position {
+                Some(n) => updated.swap_remove(n),
+                None => return current,
+            };
+            let before = started.get(&(current.source.clone(), current.id.clone()));
+            for id in &current.downloaded_issues {
+                let new = before.map(|x| !x.contains(id)).unwrap_or(true);
+                if new && !series.downloaded_issues.contains(id) {
+                    series.downloaded_issues.push(id.clone());
+                    if let Some(path) = current.paths.get(id) {
+                        series.paths.insert(id.clone(), path.clone());
+                    }
+                }
+            }
+            series.enabled = current.enabled;
+            series.settings = current.settings;
+            series
+        })
+        .collect()
+}
+
+/// Find downloaded issues that should be deleted according to `keep_issues` and
+/// `max_source_size` in config. Returns index of series, id of issue, and size of file.
+fn find_prunable(update_data: &[UpdateSeries], config: &Config) -> Vec<(usize, String, u64)> {
+    let mut prunable = Vec::new();
+    // Remaining files by source with their modification time
+    let mut kept: HashMap<&str, Vec<(std::time::SystemTime, usize, String, u64)>> = HashMap::new();
+    for (n, series) in update_data.iter().enumerate() {
+        let existing: Vec<&String> = series.downloaded_issues.iter()
+            .filter(|id| series.paths.get(*id).map(|x| std::path::Path::new(x).exists()).unwrap_or(false))
+            .collect();
+        let keep = config.keep_issues.unwrap_or(existing.len()).min(existing.len());
+        let (old, new) = existing.split_at(existing.len() - keep);
+        for id in old {
+            prunable.push((n, id.to_string(), disk_usage(std::path::Path::new(&series.paths[*id]))));
+        }
+        for id in new {
+            let path = std::path::Path::new(&series.paths[*id]);
+            let modified = path.metadata().and_then(|x| x.modified()).unwrap_or(std::time::UNIX_EPOCH);
+            kept.entry(&series.source).or_default().push((modified, n, id.to_string(), disk_usage(path)));
+        }
+    }
+    if let Some(max_size) = config.max_source_size {
+        let max_bytes = (max_size * 1e9) as u64;
+        for (_, mut files) in kept {
+            // Deleting oldest files first
+            files.sort();
+            let mut size: u64 = files.iter().map(|x| x.3).sum();
+            for (_, n, id, file_size) in files {
+                if size <= max_bytes {
+                    break;
+                }
+                size -= file_size;
+                prunable.push((n, id, file_size));
+            }
+        }
+    }
+    prunable
+}
+
+/// Delete issues according to retention rules in config. Files are only listed if `dry_run` is
+/// enabled. Deleted issues stay in the list of downloaded issues so they are not downloaded
+/// again.
+fn prune_series(update_data: &mut [UpdateSeries], config: &Config, dry_run: bool) {
+    let prunable = find_prunable(update_data, config);
+    let mut total = 0;
+    for (n, id, size) in prunable {
+        let series = &mut update_data[n];
+        let path = match series.paths.get(&id) {
+            Some(path) => path.clone(),
+            None => continue,
+        };
+        if dry_run {
+            println!("{} ({})", path, format_size(size));
+        } else {
+            let path_ref = std::path::Path::new(&path);
+            let result = if path_ref.is_dir() {
+                std::fs::remove_dir_all(path_ref)
+            } else {
+                std::fs::remove_file(path_ref)
+            };
+            match result {
+                Ok(()) => {
+                    debug!("Deleted {}", path);
+                    series.paths.remove(&id);
+                },
+                Err(e) => {
+                    warn!("Could not delete {}: {}", path, e);
+                    continue;
+                },
+            }
+        }
+        total += size;
+    }
+    if dry_run {
+        info!("Would free {}", format_size(total));
+    } else if total > 0 {
+        info!("Pruned {} of old issues", format_size(total));
+    }
+}
+
+/// Delete downloaded issues according to `keep_issues` and `max_source_size` in config
+pub fn prune(config: &Config, dry_run: bool) -> Result<(), CliError> {
+    let _lock = lock_updatefile(&config.update_location, false)?;
+    let mut update_data = load_updatefile(&config.update_location)?;
+    if config.keep_issues.is_none() && config.max_source_size.is_none() {
+        warn!("No retention rules set in config (keep_issues or max_source_size)");
+        return Ok(());
+    }
+    prune_series(&mut update_data, config, dry_run);
+    if !dry_run {
+        write_updatefile(update_data, &config.update_location)?;
+    }
+    Ok(())
+}
@@ -1,33 +1,52 @@
 use crate::{
     CliError, Result,
-    logging,
+    backup, daemon, hook, logging, webhook,
     options::{Arguments, Config, SourceData}
 };
 use grawlix::{
-    error::GrawlixIOError,
-    comic::Comic,
+    error::{GrawlixIOError, GrawlixDownloadError},
+    DownloadOptions,
+    comic::{Comic, ComicFormat, GroupBy, PageType, find_comics, group_by_volume, same_volume},
+    metadata::{Metadata, parse_ranges},
     source::{
         self,
-        Source, ComicId, source_from_url, get_all_ids, download_comics,
-        source_from_name, comic_from_comicid
+        Source, ComicId, source_from_url, get_all_ids, get_all_ids_partial, download_comics,
+        source_from_name, download_comics_stream, download_comics_metadata, get_owned_ids,
+        comic_from_comicid
     }
 };
 use reqwest::Client;
-use futures::{StreamExt, stream};
+use serde::{Deserialize, Serialize};
+use futures::{StreamExt, future, stream};
+use std::{fs::File, io::IsTerminal};
+
+/// Lock `<path>.lock` so other grawlix processes can't use the file at `path` until the returned
+/// file is dropped. Multiple processes can hold a shared lock at the same time.
+pub fn lock_file(path: &str, shared: bool) -> std::io::Result<File> {
+    let lock_path = format!("{}.lock", path);
+    let file = File::create(&lock_path)?;
+    log::debug!("Locking {}", lock_path);
+    if shared { file.lock_shared()? } else { file.lock()? }
+    Ok(file)
+}
 
 /// Get settings for source from config
-fn get_source_settings(source: &Box<dyn Source>, config: &Config) -> Option<SourceData> {
-    match source.name().as_str() {
-        "DC Universe Infinite" => config.dcuniverseinfinite.clone(),
-        "Izneo" => config.izneo.clone(),
-        "Marvel" => config.marvel.clone(),
-        _ => None
-    }
+fn get_source_settings(source: &dyn Source, config: &Config) -> Option<SourceData> {
+    config.source_settings(&source.name())
+        .cloned()
+        .or_else(|| crate::secrets::prompted_credentials(&source.name()))
+}
+
+/// Returns true if credentials or cookies for `source` are set in config
+pub fn has_credentials(source: &dyn Source, config: &Config) -> bool {
+    get_source_settings(source, config)
+        .map(|x| x.username.is_some() || x.api_key.is_some() || x.cookies.is_some())
+        .unwrap_or(false)
 }
 
-fn load_cookies(source: &Box<dyn Source>, clientbuilder: &mut source::ClientBuilder, config: &Config) {
+fn load_cookies(source: &dyn Source, clientbuilder: &mut source::ClientBuilder, config: &Config) {
     log::debug!("Adding cookies to clientbuilder");
-    if let Some(sourcedata) = get_source_settings(&source, config) {
+    if let Some(sourcedata) = get_source_settings(source, config) {
         if let Some(cookies) = sourcedata.cookies {
             for (key, value) in cookies {
                 clientbuilder.add_cookie(key, value);
@@ -37,8 +56,15 @@ fn load_cookies(source: &Box<dyn Source>, clientbuilder: &mut source::ClientBuil
 }
 
 /// Authenticate `source` with credentials from `config`
-pub async fn authenticate_source(source: &mut Box<dyn Source>, client: &mut Client, config: &Config) -> Result<()> {
-    if let Some(sourcedata) = get_source_settings(&source, config) {
+pub async fn authenticate_source(source: &mut dyn Source, client: &mut Client, config: &Config) -> Result<()> {
+    let sourcedata = match get_source_settings(source, config) {
+        Some(sourcedata) => Some(sourcedata),
+        // Asking for credentials when running interactively
+        None if source.supports_login() && !config.no_prompt && std::io::stdin().is_terminal() =>
+            crate::secrets::prompt_once(&source.name(), config.credentials_key_file.as_deref())?,
+        None => None,
+    };
+    if let Some(sourcedata) = sourcedata {
         if let Ok(credentials) = sourcedata.try_into() {
             log::debug!("Authenticating source");
             source.authenticate(client, &credentials).await?;
@@ -53,18 +79,37 @@ where
     F: Fn(&str) -> std::result::Result<Box<dyn Source>, grawlix::error::GrawlixDownloadError>,
 {
     let mut source = method(param)?;
+    if config.source_disabled(&source.name()) {
+        return Err(CliError::SourceDisabled(source.name()));
+    }
+    if !config.source_enabled(&source.name()) {
+        return Err(CliError::SourceNotEnabled(source.name()));
+    }
+    source.set_title_language(config.title_language);
+    if let Some(locale) = &config.locale {
+        source.set_locale(locale);
+    }
     let mut clientbuilder = source.client_builder();
-    load_cookies(&source, &mut clientbuilder, config);
+    load_cookies(&*source, &mut clientbuilder, config);
     let mut client = clientbuilder.to_reqwest_client();
-    if source.requires_authentication() {
-        authenticate_source(&mut source, &mut client, config).await?;
+    // Sources with optional login give access to more comics when credentials are set
+    if source.requires_authentication() || (source.supports_login() && has_credentials(&*source, config)) {
+        authenticate_source(&mut *source, &mut client, config).await?;
     }
     Ok((source, client))
 }
 
-/// Create source from url and authenticate if credentials are available
+/// Create source from url and authenticate if credentials are available. Falls back to the
+/// gallery source for unsupported urls if gallery selectors are configured.
 pub async fn get_source_from_url(url: &str, config: &Config) -> Result<(Box<dyn Source>, Client)> {
-    get_source(&source_from_url, url, config).await
+    match (get_source(&source_from_url, url, config).await, &config.gallery) {
+        (Err(CliError::Download(GrawlixDownloadError::UrlNotSupported(_))), Some(gallery)) => {
+            log::debug!("Using gallery source for {}", url);
+            let gallery_source = |_: &str| Ok(Box::new(gallery.to_source()) as Box<dyn Source>);
+            get_source(&gallery_source, url, config).await
+        },
+        (result, _) => result
+    }
 }
 
 /// Create source from name of source and authenticate if credentials are available
@@ -72,39 +117,193 @@ pub async fn get_source_from_name(name: &str, config: &Config) -> Result<(Box<dy
     get_source(&source_from_name, name, config).await
 }
 
+/// Print urls of comics the account on source `name` has access to, one per line so they can be
+/// given to `download` or `add`. Ids are printed for comics without a known url.
+pub async fn list_owned(name: &str, config: &Config) -> Result<()> {
+    let (source, client) = get_source_from_name(name, config).await?;
+    for comicid in get_owned_ids(&*source, &client).await? {
+        match source.url_from_id(&comicid) {
+            Some(url) => println!("{}", url),
+            None => println!("{}", comicid.inner()),
+        }
+    }
+    Ok(())
+}
+
+/// Input url with options from link file
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Link {
+    /// Url of comic or series
+    pub url: String,
+    /// Output template used instead of the one in config
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Directory comics are written to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<String>,
+    /// Output format used instead of the one in config
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<ComicFormat>,
+    /// Only download issues with numbers in these ranges
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub issues: Vec<(u32, u32)>,
+}
+
+impl Link {
+    /// Parse line in the format `<url> | key=value, key=value`
+    pub fn parse(line: &str) -> Result<Self> {
+        let (url, options) = line.split_once('|').unwrap_or((line, ""));
+        let mut link = Link {
+            url: url.trim().to_string(),
+            template: None,
+            output_dir: None,
+            format: None,
+            issues: Vec::new(),
+        };
+        // Values can contain commas, so parts without "=" belong to the previous option
+        let mut pairs: Vec<(String, String)> = Vec::new();
+        for part in options.split(',').filter(|x| !x.trim().is_empty()) {
+            match (part.split_once('='), pairs.last_mut()) {
+                (Some((key, value)), _) => pairs.push((key.trim().to_string(), value.trim().to_string())),
+                (None, Some((_, value))) => *value = format!("{},{}", value, part),
+                (None, None) => return Err(CliError::InvalidLinkOption(part.trim().to_string())),
+            }
+        }
+        for (key, value) in pairs {
+            let invalid = || CliError::InvalidLinkOption(format!("{}={}", key, value));
+            match key.as_str() {
+                "template" => link.template = Some(value.clone()),
+                "output_dir" => link.output_dir = Some(value.clone()),
+                "format" => link.format = Some(value.parse().map_err(|_| invalid())?),
+                "issues" => link.issues = parse_ranges(&value).ok_or_else(invalid)?,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(link)
+    }
+
+    /// Create copy of `config` with options of link applied
+    pub fn apply(&self, config: &Config) -> Config {
+        let mut config = config.clone();
+        if let Some(template) = &self.template {
+            config.output_template = template.clone();
+        }
+        if self.output_dir.is_some() {
+            config.output_dir = self.output_dir.clone();
+        }
+        if let Some(format) = &self.format {
+            config.output_format = format.clone();
+        }
+        if !self.issues.is_empty() {
+            config.issues = self.issues.clone();
+        }
+        config
+    }
+}
+
+/// Find ids of all comics in `comicid`. Issues that could not be resolved are skipped with a
+/// warning if `skip_unresolved` is enabled.
+pub async fn resolve_ids(source: &dyn Source, client: &Client, comicid: ComicId, config: &Config) -> Result<Vec<ComicId>> {
+    if !config.skip_unresolved {
+        return Ok(get_all_ids(source, client, comicid).await?);
+    }
+    let (comicids, errors) = get_all_ids_partial(source, client, comicid).await?;
+    for (id, e) in &errors {
+        log::warn!("Skipping {} ({})", id, e);
+    }
+    Ok(comicids)
+}
+
+/// Download and write all comics at link
+pub async fn download_link(link: &Link, config: &Config) -> Result<()> {
+    let config = link.apply(config);
+    let (source, client) = get_source_from_url(&link.url, &config).await?;
+    let link_id = source.id_from_url(&link.url)?;
+    let comicids = resolve_ids(&*source, &client, link_id, &config).await?;
+    download_and_write_comics(&*source, &client, &comicids, Some(&link.url), &config).await;
+    Ok(())
+}
+
 async fn download_comics_from_url(url: &str, config: &Config) -> Result<Vec<Comic>> {
     let (source, client) = get_source_from_url(url, config).await?;
     let comicid = source.id_from_url(url)?;
     log::debug!("Got id from url: {:?}", comicid);
-    let all_ids = get_all_ids(&source, &client, comicid).await?;
-    let comics = download_comics(all_ids, &client, &source).await?;
+    let all_ids = resolve_ids(&*source, &client, comicid, config).await?;
+    let mut comics = download_comics(all_ids, &client, &*source).await?;
+    for comic in &mut comics {
+        prepare_comic(comic, Some(url), config);
+    }
     Ok(comics)
 }
 
+/// Download metadata of all comics at link without downloading pages. Metadata of local files is
+/// read from the files.
+async fn metadata_from_link(link: &Link, config: &Config) -> Result<Vec<Metadata>> {
+    if !link.url.starts_with("http") {
+        let comics = load_inputs(std::slice::from_ref(link), config).await?;
+        return Ok(comics.into_iter().map(|x| x.metadata).collect());
+    }
+    let (source, client) = get_source_from_url(&link.url, config).await?;
+    let comicid = source.id_from_url(&link.url)?;
+    let mut metadata = download_comics_metadata(&*source, &client, comicid).await?;
+    for x in &mut metadata {
+        x.localize_titles(config.title_language);
+    }
+    Ok(metadata)
+}
+
+/// Returns metadata of all comics in inputs
+pub async fn get_metadata(args: &Arguments, config: &Config, inputs: &Vec<String>) -> Result<Vec<Metadata>> {
+    let mut metadata = Vec::new();
+    for link in get_all_links(inputs, args)? {
+        metadata.append(&mut metadata_from_link(&link, &link.apply(config)).await?);
+    }
+    Ok(metadata)
+}
+
 /// Create vector of comics from list of inputs
-async fn load_inputs(inputs: &[String], config: &Config) -> Result<Vec<Comic>> {
+async fn load_inputs(inputs: &[Link], config: &Config) -> Result<Vec<Comic>> {
     let mut comics: Vec<Comic> = Vec::new();
     let re = regex::Regex::new(r"https?://.+\.[a-zA-Z0-9]+").unwrap();
-    for i in inputs {
+    for link in inputs {
+        let i = &link.url;
         let mut comic = if re.is_match(&i) {
-            download_comics_from_url(&i, config).await?
-        } else if std::path::Path::new(&i).exists() {
+            download_comics_from_url(&i, &link.apply(config)).await?
+        } else if std::path::Path::new(&i).is_file() {
             vec![Comic::from_file(&i)?]
         } else {
-            return Err(CliError::Input(i.to_string()))
+            let paths = find_comics(&i)?;
+            if paths.is_empty() {
+                return Err(CliError::Input(i.to_string()))
+            }
+            load_files(paths, config).await?
         };
         comics.append(&mut comic);
     }
     return Ok(comics);
 }
 
+/// Read comics from files in parallel
+async fn load_files(paths: Vec<String>, config: &Config) -> Result<Vec<Comic>> {
+    log::debug!("Reading {} comics", paths.len());
+    stream::iter(paths)
+        .map(|path| tokio::task::spawn_blocking(move || Comic::from_file(&path)))
+        .buffered(config.concurrency)
+        .map(|result| -> Result<Comic> { Ok(result.map_err(|_| CliError::Unknown)??) })
+        .collect::<Vec<Result<Comic>>>()
+        .await
+        .into_iter()
+        .collect()
+}
+
 
-/// Load all links from a file
-fn load_links_from_file(link_file: &std::path::PathBuf) -> Result<Vec<String>> {
+/// Load all links from a file. Empty lines and lines starting with `#` are skipped.
+pub fn load_links_from_file(link_file: &std::path::PathBuf) -> Result<Vec<String>> {
     if link_file.exists() {
         let links = std::fs::read_to_string(link_file)
             .map_err(|x| GrawlixIOError::from(x))?
             .lines()
+            .filter(|x| !x.trim().is_empty() && !x.trim_start().starts_with('#'))
             .map(String::from)
             .collect();
         Ok(links)
@@ -113,13 +312,35 @@ fn load_links_from_file(link_file: &std::path::PathBuf) -> Result<Vec<String>> {
     }
 }
 
-/// Return all links from arguments, files, and pipe
-pub fn get_all_links(inputs: &Vec<String>, args: &Arguments) -> Result<Vec<String>> {
-    let mut x = inputs.clone();
+/// Read links from stdin, one per line
+fn load_links_from_stdin() -> Result<Vec<String>> {
+    let links = std::io::stdin()
+        .lines()
+        .collect::<std::io::Result<Vec<String>>>()
+        .map_err(GrawlixIOError::from)?
+        .into_iter()
+        .filter(|x| !x.trim().is_empty() && !x.trim_start().starts_with('#'))
+        .collect();
+    Ok(links)
+}
+
+/// Return all links from arguments, files, and pipe. Links are read from stdin if `-` is given as
+/// input or if no inputs are given and stdin is not a terminal.
+pub fn get_all_links(inputs: &Vec<String>, args: &Arguments) -> Result<Vec<Link>> {
+    let read_stdin = inputs.iter().any(|x| x == "-")
+        || (inputs.is_empty() && args.file.is_none() && !std::io::stdin().is_terminal());
+    let mut x: Vec<String> = inputs.iter()
+        .filter(|x| *x != "-")
+        .cloned()
+        .collect();
     if let Some(link_file) = &args.file {
         x.append(&mut load_links_from_file(link_file)?);
     }
-    return Ok(x);
+    if read_stdin {
+        log::debug!("Reading links from stdin");
+        x.append(&mut load_links_from_stdin()?);
+    }
+    x.iter().map(|line| Link::parse(line)).collect()
 }
 
 
@@ -134,14 +355,195 @@ pub async fn get_comics(args: &Arguments, config: &Config, inputs: &Vec<String>)
     }
 }
 
+/// Apply title language and add provenance info to comic if enabled in config. `url` is the url
+/// the comic was found from.
+fn prepare_comic(comic: &mut Comic, url: Option<&str>, config: &Config) {
+    comic.metadata.localize_titles(config.title_language);
+    if !config.number_patterns.is_empty() {
+        let patterns = config.number_patterns(comic);
+        comic.metadata.infer_numbers(url, &patterns);
+    }
+    comic.metadata.normalize_publisher(&config.publishers);
+    if config.provenance {
+        comic.add_provenance(url);
+    }
+}
+
+/// Returns false if `comic` is only a preview, is not in one of the languages in config, has a
+/// higher age rating than allowed, is released outside the allowed dates, or is rejected by the
+/// filter command. Comics without a known language, age rating, or release date are kept.
+fn keep_comic(comic: &Comic, config: &Config) -> bool {
+    if comic.metadata.preview && !config.allow_previews {
+        log::warn!("Skipping {} (Only a preview is available, use --allow-previews to download it anyway)", comic.title());
+        return false;
+    }
+    if let Some(number) = comic.metadata.issue_number {
+        if !config.issues.is_empty() && !config.issues.iter().any(|(start, end)| (*start..=*end).contains(&number)) {
+            log::info!("Skipping {} (Issue {} is not selected)", comic.title(), number);
+            return false;
+        }
+    }
+    let (after, before) = config.published_range();
+    if !comic.metadata.released_within(after, before) {
+        let date = comic.metadata.date()
+            .or_else(|| comic.metadata.year.map(|x| x.to_string()))
+            .unwrap_or_default();
+        log::info!("Skipping {} (Released {})", comic.title(), date);
+        return false;
+    }
+    if let (Some(rating), Some(max_rating)) = (comic.metadata.age_rating, config.max_rating) {
+        if rating > max_rating {
+            log::info!("Skipping {} (Rated {})", comic.title(), rating);
+            return false;
+        }
+    }
+    if let Some(language) = &comic.metadata.language {
+        if !config.languages.is_empty() && !config.languages.iter().any(|x| x.eq_ignore_ascii_case(language)) {
+            log::info!("Skipping {} (Language is {})", comic.title(), language);
+            return false;
+        }
+    }
+    match &config.filter_command {
+        Some(command) => hook::filter(command, comic),
+        None => true,
+    }
+}
+
+/// Ids on `source` of issues contained in `comic`
+fn issue_ids<'a>(comic: &'a Comic, source: &'a dyn Source) -> impl Iterator<Item = String> + 'a {
+    comic.metadata.identifiers.iter()
+        .filter(move |x| x.source == source.name())
+        .map(|x| x.id.clone())
+}
+
+/// Comic written to disk by `download_and_write_comics`
+pub struct WrittenComic {
+    /// Title of comic
+    pub title: String,
+    /// Absolute output path of comic
+    pub path: String,
+    /// Ids on source of issues contained in comic
+    pub ids: Vec<String>,
+}
+
+impl WrittenComic {
+    fn new(comic: &Comic, path: String, source: &dyn Source) -> Self {
+        // Relative paths would depend on the directory grawlix was started from
+        let path = std::fs::canonicalize(&path)
+            .ok()
+            .and_then(|x| x.to_str().map(String::from))
+            .unwrap_or(path);
+        Self {
+            title: comic.title().to_string(),
+            path,
+            ids: issue_ids(comic, source).collect(),
+        }
+    }
+}
+
 /// Download data about all comics and write them to disk
-pub async fn download_and_write_comics(source: &Box<dyn Source>, client: &Client, comicids: &Vec<ComicId>, config: &Config) {
-    stream::iter(comicids.clone())
-        .map(|comicid| comic_from_comicid(&source, &client, comicid))
-        .buffered(5)
+pub async fn download_and_write_comics(source: &dyn Source, client: &Client, comicids: &Vec<ComicId>, url: Option<&str>, config: &Config) -> Vec<WrittenComic> {
+    if config.group_by == GroupBy::Volume {
+        return download_and_write_volumes(source, client, comicids, url, config).await;
+    }
+    download_comics_stream(comicids.clone(), client, source, config.concurrency)
+        // Comics that are already being written are finished before stopping
+        .take_while(|_| future::ready(!daemon::shutdown_requested()))
+        .filter_map(|comic| async {
+            match comic {
+                Ok(mut x) => {
+                    prepare_comic(&mut x, url, config);
+                    if !keep_comic(&x, config) {
+                        return None;
+                    }
+                    match write_comic(&x, client, config).await {
+                        Ok(path) => Some(WrittenComic::new(&x, path, source)),
+                        Err(e) => {
+                            log::error!("Failed to write {}: {}", x.title(), e);
+                            None
+                        },
+                    }
+                },
+                Err(e) => {
+                    log::info!("Failed to download comic info: {}", e);
+                    None
+                },
+            }
+        })
+        .collect()
+        .await
+}
+
+/// Download and write a single comic. Returns `None` if the comic was skipped by the filters in
+/// `config`.
+pub async fn download_and_write_comic(source: &dyn Source, client: &Client, comicid: ComicId, url: Option<&str>, config: &Config) -> Result<Option<WrittenComic>> {
+    let mut comic = comic_from_comicid(source, client, comicid).await?;
+    prepare_comic(&mut comic, url, config);
+    if !keep_comic(&comic, config) {
+        return Ok(None);
+    }
+    let path = write_comic(&comic, client, config).await?;
+    Ok(Some(WrittenComic::new(&comic, path, source)))
+}
+
+/// Download data about all comics and write them to disk with chapters of the same volume
+/// combined. Each volume is written as soon as a chapter from another volume is downloaded, so
+/// chapters of a volume have to be next to each other in `comicids`.
+async fn download_and_write_volumes(source: &dyn Source, client: &Client, comicids: &Vec<ComicId>, url: Option<&str>, config: &Config) -> Vec<WrittenComic> {
+    let mut comics = Box::pin(download_comics_stream(comicids.clone(), client, source, config.concurrency)
+        .filter_map(|comic| async {
+            match comic {
+                Ok(mut x) => {
+                    prepare_comic(&mut x, url, config);
+                    if keep_comic(&x, config) { Some(x) } else { None }
+                },
+                Err(e) => {
+                    log::info!("Failed to download comic info: {}", e);
+                    None
+                },
+            }
+        }));
+    let mut written = Vec::new();
+    // Chapters of the volume currently being downloaded
+    let mut chapters: Vec<Comic> = Vec::new();
+    while let Some(comic) = comics.next().await {
+        if daemon::shutdown_requested() {
+            // Volumes are not written with only some of their chapters
+            return written;
+        }
+        if !chapters.is_empty() && !same_volume(&chapters[0].metadata, &comic.metadata) {
+            write_volume(std::mem::take(&mut chapters), source, client, config, &mut written).await;
+        }
+        chapters.push(comic);
+    }
+    write_volume(chapters, source, client, config, &mut written).await;
+    written
+}
+
+/// Combine `chapters` into one comic and write it to disk
+async fn write_volume(chapters: Vec<Comic>, source: &dyn Source, client: &Client, config: &Config, written: &mut Vec<WrittenComic>) {
+    for comic in group_by_volume(chapters) {
+        match write_comic(&comic, client, config).await {
+            Ok(path) => written.push(WrittenComic::new(&comic, path, source)),
+            Err(e) => log::error!("Failed to write {}: {}", comic.title(), e),
+        }
+    }
+}
+
+/// Download data about all comics and rewrite those that differ from the file on disk
+pub async fn refresh_comics(source: &dyn Source, client: &Client, comicids: &Vec<ComicId>, url: Option<&str>, config: &Config) {
+    download_comics_stream(comicids.clone(), client, source, config.concurrency)
         .for_each(|comic| async {
             match comic {
-                Ok(x) => write_comic(&x, client, config).await.unwrap(),
+                Ok(mut x) => {
+                    prepare_comic(&mut x, url, config);
+                    if !keep_comic(&x, config) {
+                        return;
+                    }
+                    if let Err(e) = refresh_comic(&mut x, client, config).await {
+                        log::error!("Failed to refresh {}: {}", x.title(), e);
+                    }
+                },
                 Err(e) => {
                     log::info!("Failed to download comic info: {}", e);
                 },
@@ -150,19 +552,144 @@ pub async fn download_and_write_comics(source: &Box<dyn Source>, client: &Client
         .await;
 }
 
-pub async fn write_comic(comic: &Comic, client: &Client, config: &Config) -> Result<()> {
-    // Creating output path
-    let path = comic.format(&config.output_template)?;
-    // Checking if file already exists if overwrite is not enabled
-    if !config.overwrite && std::path::Path::new(&path).exists() {
-        log::info!("Skipping {} (File already exists)", comic.title());
-        // Downloading comic
+/// Rewrite `comic` if its pages differ from the existing file. Metadata of the existing file is
+/// combined with the new metadata if `metadata_merge` is set.
+async fn refresh_comic(comic: &mut Comic, client: &Client, config: &Config) -> Result<()> {
+    let path = config.download_options().format_path(comic)?;
+    if std::path::Path::new(&path).exists() {
+        let existing = Comic::from_file(&path)?;
+        match page_changes(&existing, comic, client).await {
+            Some(changes) => log::info!("Updating {} ({})", comic.title(), changes),
+            None => {
+                log::info!("Skipping {} (No changes)", comic.title());
+                return Ok(());
+            },
+        }
+        if let Some(policy) = config.metadata_merge {
+            let mut metadata = existing.metadata;
+            metadata.merge(std::mem::take(&mut comic.metadata), policy);
+            comic.metadata = metadata;
+        }
     } else {
         log::info!("Downloading {}", comic.title());
-        if config.info {
-            logging::print_comic(comic, config.json);
-        }
-        comic.write(&path, &config.output_format, client).await?;
+    }
+    let options = config.download_options().page_filter(config.page_filter(comic)).overwrite(true);
+    write_with_backup(comic, &path, client, &options, config).await?;
+    if config.series_json {
+        write_series_json(comic, &path, config);
+    }
+    if let Some(command) = &config.post_download {
+        hook::post_download(command, comic, &path);
+    }
+    if let Some(webhook) = &config.webhook {
+        webhook::comic_downloaded(webhook, comic).await;
     }
     Ok(())
 }
+
+/// Describes how the pages of `comic` differ from `existing`, or `None` if they are the same.
+/// Page urls are compared when the existing file has them in its provenance, without query
+/// strings since many sources sign their urls for every request. Pages with the same url are
+/// checked with conditional requests if their etag or modification time was stored when they
+/// were downloaded. Otherwise only the number of pages is compared.
+async fn page_changes(existing: &Comic, comic: &Comic, client: &Client) -> Option<String> {
+    let (old, new) = (existing.pages.len(), comic.pages.len());
+    if old != new {
+        return Some(format!("{} -> {} pages", old, new));
+    }
+    let existing_urls = existing.metadata.provenance.as_ref()
+        .map(|x| x.page_urls.as_slice())
+        .filter(|x| !x.is_empty())?;
+    let without_query = |url: &str| url.split(['?', '#']).next().unwrap_or_default().to_string();
+    let urls = comic.pages.iter()
+        .filter_map(|page| match &page.page_type {
+            PageType::Url(x) => Some(without_query(&x.url)),
+            PageType::Container(_) => None,
+        });
+    let changed = existing_urls.iter().map(|x| without_query(x))
+        .zip(urls)
+        .filter(|(old, new)| old != new)
+        .count();
+    if changed > 0 {
+        return Some(format!("{} pages changed", changed));
+    }
+    let validators = &existing.metadata.provenance.as_ref()?.page_validators;
+    let online_pages = comic.pages.iter()
+        .filter_map(|page| match &page.page_type {
+            PageType::Url(x) => Some(x),
+            PageType::Container(_) => None,
+        });
+    let mut modified = 0;
+    for (page, validator) in online_pages.zip(validators).filter(|(_, x)| !x.is_empty()) {
+        match page.changed(client, validator).await {
+            Ok(true) => modified += 1,
+            Ok(false) => (),
+            Err(e) => log::debug!("Could not check if {} has changed: {}", page.url, e),
+        }
+    }
+    (modified > 0).then(|| format!("{} pages modified", modified))
+}
+
+/// Write `comic` to `path`. An existing file is moved to the backup directory after the new
+/// comic has been written and before it is moved into place, so a failed download keeps the
+/// existing file. Returns true if the comic was written.
+async fn write_with_backup(comic: &Comic, path: &str, client: &Client, options: &DownloadOptions, config: &Config) -> Result<bool> {
+    comic.write_replacing(path, &options.format, client, &options.write_options, |new| {
+        if !options.overwrite.should_replace(new, path) {
+            return Ok(false);
+        }
+        backup::backup(path, config)?;
+        Ok(true)
+    }).await
+}
+
+/// Create or update Mylar series.json in the folder `comic` was written to at `path`. Errors are
+/// logged since the comic itself has been written.
+fn write_series_json(comic: &Comic, path: &str, config: &Config) {
+    let file = match std::path::Path::new(path).parent() {
+        Some(folder) => folder.join("series.json"),
+        None => return,
+    };
+    let existing = std::fs::read_to_string(&file).ok();
+    let result = comic.metadata.update_series_json(existing.as_deref(), config.series_info.as_ref())
+        .map_err(|e| e.to_string())
+        .and_then(|contents| match contents {
+            Some(contents) if Some(&contents) != existing.as_ref() =>
+                std::fs::write(&file, contents).map_err(|e| e.to_string()),
+            _ => Ok(()),
+        });
+    if let Err(e) = result {
+        log::warn!("Could not write {}: {}", file.display(), e);
+    }
+}
+
+/// Write comic to disk unless it already exists. Returns output path of comic.
+pub async fn write_comic(comic: &Comic, client: &Client, config: &Config) -> Result<String> {
+    let options = config.download_options().page_filter(config.page_filter(comic));
+    let output_path = options.format_path(comic)?;
+    // Checking if file already exists and should be kept according to the overwrite policy
+    match options.output_path(comic)? {
+        None => log::info!("Skipping {} (File already exists)", comic.title()),
+        // Downloading comic
+        Some(path) => {
+            log::info!("Downloading {}", comic.title());
+            if config.info {
+                logging::print_comic(comic, config.json);
+            }
+            if !write_with_backup(comic, &path, client, &options, config).await? {
+                log::info!("Kept existing file for {}", comic.title());
+                return Ok(output_path);
+            }
+            if config.series_json {
+                write_series_json(comic, &path, config);
+            }
+            if let Some(command) = &config.post_download {
+                hook::post_download(command, comic, &path);
+            }
+            if let Some(webhook) = &config.webhook {
+                webhook::comic_downloaded(webhook, comic).await;
+            }
+        }
+    }
+    Ok(output_path)
+}
@@ -0,0 +1,120 @@
+use crate::{
+    CliError, Result, utils,
+    options::Config,
+};
+use grawlix::{comic::Comic, error::GrawlixIOError, source::ClientBuilder};
+use log::{info, warn};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Files with links to download
+const LINK_EXTENSIONS: [&str; 2] = ["urls", "txt"];
+/// Comic files that are written into the library
+const COMIC_EXTENSIONS: [&str; 2] = ["cbz", "zip"];
+
+/// Watch `dir` for new link files and comics. Links are downloaded and comics are written to
+/// their location in the output template. Handled files are moved to `processed` or `failed` in
+/// `dir`.
+pub async fn watch(dir: &str, interval: u64, config: &Config) -> Result<()> {
+    let dir = Path::new(dir);
+    if !dir.is_dir() {
+        return Err(CliError::FileNotFound(dir.display().to_string()));
+    }
+    info!("Watching {} for new files", dir.display());
+    // Files are only handled when their size has not changed since the last check, so files
+    // that are still being written are skipped
+    let mut sizes: HashMap<PathBuf, u64> = HashMap::new();
+    // Files that could not be moved after they were handled
+    let mut handled: HashSet<PathBuf> = HashSet::new();
+    loop {
+        let mut current = HashMap::new();
+        for path in watched_files(dir)? {
+            if handled.contains(&path) {
+                continue;
+            }
+            let size = match path.metadata() {
+                Ok(metadata) => metadata.len(),
+                Err(_) => continue,
+            };
+            if sizes.get(&path) == Some(&size) {
+                let target = match handle_file(&path, config).await {
+                    Ok(()) => "processed",
+                    Err(e) => {
+                        warn!("Failed to handle {}: {}", path.display(), e);
+                        "failed"
+                    }
+                };
+                if let Err(e) = move_to(&path, &dir.join(target)) {
+                    warn!("Could not move {} to {}: {}", path.display(), target, e);
+                    handled.insert(path);
+                }
+            } else {
+                current.insert(path, size);
+            }
+        }
+        sizes = current;
+        tokio::time::sleep(Duration::from_secs(interval.max(1))).await;
+    }
+}
+
+/// Link files and comics directly in `dir`
+fn watched_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(GrawlixIOError::from)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && (has_extension(path, &LINK_EXTENSIONS) || has_extension(path, &COMIC_EXTENSIONS)))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|x| x.to_str())
+        .map(|x| extensions.contains(&x.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Download links in link file or write comic into library. Links that fail do not stop the
+/// others. Only the failed links are kept in the link file, so moving it back into the watched
+/// folder retries them.
+async fn handle_file(path: &Path, config: &Config) -> Result<()> {
+    if has_extension(path, &LINK_EXTENSIONS) {
+        info!("Downloading links from {}", path.display());
+        let mut failed = Vec::new();
+        for line in utils::load_links_from_file(&path.to_path_buf())? {
+            let result = match utils::Link::parse(&line) {
+                Ok(link) => utils::download_link(&link, config).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                warn!("Failed to download {}: {}", line, e);
+                failed.push(line);
+            }
+        }
+        if !failed.is_empty() {
+            std::fs::write(path, failed.join("\n") + "\n").map_err(GrawlixIOError::from)?;
+            return Err(CliError::FailedLinks(failed.len()));
+        }
+    } else {
+        let path_str = path.to_str().ok_or(CliError::Unknown)?;
+        let comic = Comic::from_file(path_str)?;
+        info!("Importing {}", comic.title());
+        // Pages are read from the file, so the client is not used for requests
+        let client = ClientBuilder::default().to_reqwest_client();
+        utils::write_comic(&comic, &client, config).await?;
+    }
+    Ok(())
+}
+
+/// Move file to directory
+fn move_to(path: &Path, dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir).map_err(GrawlixIOError::from)?;
+    let name = path.file_name().ok_or(CliError::Unknown)?;
+    std::fs::rename(path, dir.join(name)).map_err(GrawlixIOError::from)?;
+    Ok(())
+}
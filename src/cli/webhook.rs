@@ -0,0 +1,32 @@
+use crate::options::WebhookConfig;
+use grawlix::{comic::Comic, source::ClientBuilder};
+
+/// Post the metadata of `comic` as json to the endpoint in `webhook` after it has been
+/// downloaded. The api key is sent as bearer token if set. Failures are logged but do not stop
+/// the download.
+pub async fn comic_downloaded(webhook: &WebhookConfig, comic: &Comic) {
+    let metadata = &comic.metadata;
+    let body = serde_json::json!({
+        "event": "downloaded",
+        "series": metadata.series,
+        "issue_number": metadata.issue_number,
+        "volume": metadata.volume,
+        "title": metadata.title,
+        "publisher": metadata.publisher,
+        "date": metadata.date(),
+        "identifiers": metadata.identifiers,
+    });
+    log::debug!("Posting {} to {}", comic.title(), webhook.url);
+    let mut request = ClientBuilder::default()
+        .to_reqwest_client()
+        .post(&webhook.url)
+        .json(&body);
+    if let Some(api_key) = &webhook.api_key {
+        request = request.bearer_auth(api_key);
+    }
+    match request.send().await {
+        Ok(response) if response.status().is_success() => (),
+        Ok(response) => log::warn!("Webhook failed for {} ({})", comic.title(), response.status()),
+        Err(e) => log::warn!("Webhook failed for {}: {}", comic.title(), e),
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{
+    comic::Comic,
+    error::GrawlixDownloadError,
+    metadata::Metadata,
+    source::{
+        self, ComicId, Credentials, Series, Source,
+        comic_from_comicid, get_all_ids, get_all_ids_partial, metadata_from_comicid, resolve_series_with_source, source_from_url,
+    },
+    DownloadOptions, Result,
+};
+use futures::{StreamExt, TryStreamExt, stream};
+use reqwest::Client;
+use std::{collections::HashMap, future::Future, time::Duration};
+
+/// High level client for resolving and downloading comics. Handles authentication, rate limiting,
+/// and retries of failed downloads.
+///
+/// ```ignore
+/// let client = GrawlixClient::new()
+///     .credentials("Marvel", Credentials::UsernamePassword(username, password))
+///     .retries(3);
+/// let comics = client.download(url, &DownloadOptions::new()).await?;
+/// ```
+#[derive(Default)]
+pub struct GrawlixClient {
+    /// Credentials for sources by source name
+    credentials: HashMap<String, Credentials>,
+    /// Cookies for sources by source name
+    cookies: HashMap<String, Vec<(String, String)>>,
+    /// Number of times failed downloads are retried
+    retries: usize,
+    /// Delay between downloading comics
+    delay: Duration,
+    /// Skip issues that could not be resolved instead of failing the whole series
+    skip_unresolved: bool,
+}
+
+impl GrawlixClient {
+    /// Create new `GrawlixClient` without credentials
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add credentials for source
+    pub fn credentials(mut self, source: &str, credentials: Credentials) -> Self {
+        self.credentials.insert(source.to_string(), credentials);
+        self
+    }
+
+    /// Add cookie to all requests to source
+    pub fn cookie(mut self, source: &str, key: &str, value: &str) -> Self {
+        self.cookies.entry(source.to_string())
+            .or_default()
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Set number of times failed downloads are retried
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Set delay between downloading comics
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Skip issues of a series that could not be resolved instead of failing the whole series
+    pub fn skip_unresolved(mut self, skip_unresolved: bool) -> Self {
+        self.skip_unresolved = skip_unresolved;
+        self
+    }
+
+    /// Create source from url and authenticate it if credentials are available
+    pub async fn source(&self, url: &str) -> Result<(Box<dyn Source>, Client)> {
+        let mut source = source_from_url(url)?;
+        let mut clientbuilder = source.client_builder();
+        for (key, value) in self.cookies.get(&source.name()).into_iter().flatten() {
+            clientbuilder.add_cookie(key, value);
+        }
+        let mut client = clientbuilder.to_reqwest_client();
+        if let Some(credentials) = self.credentials.get(&source.name()) {
+            log::debug!("Authenticating {}", source.name());
+            source.authenticate(&mut client, credentials).await?;
+        }
+        Ok((source, client))
+    }
+
+    /// Find ids of all comics at url
+    pub async fn resolve(&self, url: &str) -> Result<Vec<ComicId>> {
+        let (source, client) = self.source(url).await?;
+        let comicid = source.id_from_url(url)?;
+        let ids = self.all_ids(&*source, &client, comicid).await?;
+        Ok(ids)
+    }
+
+    /// Resolve series at url. Issues of the series can be filtered before they are downloaded.
+    pub async fn series(&self, url: &str) -> Result<Series> {
+        let (source, client) = self.source(url).await?;
+        let seriesid = source.id_from_url(url)?;
+        Ok(resolve_series_with_source(source.into(), client, seriesid).await?)
+    }
+
+    /// Download metadata of all comics at url
+    pub async fn metadata(&self, url: &str) -> Result<Vec<Metadata>> {
+        let (source, client) = self.source(url).await?;
+        let comicid = source.id_from_url(url)?;
+        let ids = self.all_ids(&*source, &client, comicid).await?;
+        let mut metadata = Vec::new();
+        for id in ids {
+            metadata.push(self.retry(|| metadata_from_comicid(&*source, &client, id.clone())).await?);
+            self.wait().await;
+        }
+        Ok(metadata)
+    }
+
+    /// Download all comics at url and write them to disk. Comics where the output file already
+    /// exists are skipped unless overwriting is enabled in `options`.
+    pub async fn download(&self, url: &str, options: &DownloadOptions) -> Result<Vec<Comic>> {
+        let (source, client) = self.source(url).await?;
+        let comicid = source.id_from_url(url)?;
+        let ids = self.all_ids(&*source, &client, comicid).await?;
+        stream::iter(ids)
+            .map(|id| {
+                let source = &*source;
+                let client = &client;
+                async move {
+                    let mut comic = self.retry(|| comic_from_comicid(source, client, id.clone())).await?;
+                    if options.provenance {
+                        comic.add_provenance(Some(url));
+                    }
+                    match options.output_path(&comic)? {
+                        Some(path) => {
+                            log::info!("Downloading {}", comic.title());
+                            if !options.write(&comic, &path, client).await? {
+                                log::info!("Kept existing file for {}", comic.title());
+                            }
+                        },
+                        None => log::info!("Skipping {} (File already exists)", comic.title()),
+                    }
+                    self.wait().await;
+                    Ok::<_, crate::Error>(comic)
+                }
+            })
+            .buffered(options.concurrency)
+            .try_collect()
+            .await
+    }
+
+    /// Find ids of all comics in `comicid`. Ids that could not be resolved are skipped if
+    /// `skip_unresolved` is enabled.
+    async fn all_ids(&self, source: &dyn Source, client: &Client, comicid: ComicId) -> source::Result<Vec<ComicId>> {
+        if !self.skip_unresolved {
+            return self.retry(|| get_all_ids(source, client, comicid.clone())).await;
+        }
+        let (ids, errors) = self.retry(|| get_all_ids_partial(source, client, comicid.clone())).await?;
+        for (id, e) in errors {
+            log::warn!("Skipping {} ({})", id, e);
+        }
+        Ok(ids)
+    }
+
+    /// Run `f` until it succeeds or has failed more times than the allowed number of retries
+    async fn retry<T, F, Fut>(&self, f: F) -> source::Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = std::result::Result<T, GrawlixDownloadError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match f().await {
+                Err(e) if attempt < self.retries => {
+                    log::debug!("Retrying after error: {}", e);
+                    attempt += 1;
+                    tokio::time::sleep(self.delay).await;
+                },
+                result => return result,
+            }
+        }
+    }
+
+    /// Wait between downloads
+    async fn wait(&self) {
+        if !self.delay.is_zero() {
+            tokio::time::sleep(self.delay).await;
+        }
+    }
+}
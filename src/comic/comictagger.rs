@@ -0,0 +1,179 @@
+use crate::metadata::Metadata;
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Field in rename template, like `{series}` or `{issue!u}`
+static FIELD: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{(\w+)[^}]*\}").unwrap());
+/// Field in rename template of old ini settings, like `%series%`
+static INI_FIELD: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"%(\w+)%").unwrap());
+/// Patterns and replacements used by smart cleanup
+static CLEANUP_RULES: LazyLock<Vec<(Regex, &str)>> = LazyLock::new(|| {
+    [
+        // Empty brackets
+        (r"\(\s*[-:]*\s*\)", ""),
+        (r"\[\s*[-:]*\s*\]", ""),
+        (r"\{\s*[-:]*\s*\}", ""),
+        // Issue sign without issue number
+        (r"#(\s|$)", "$1"),
+        (r"\s+", " "),
+        (r"(\s-)+", " -"),
+        // Dashes at the start or end
+        (r"-{1,2}\s*$", ""),
+        (r"^\s*-{1,2}", ""),
+    ].into_iter()
+        .map(|(pattern, replacement)| (Regex::new(pattern).unwrap(), replacement))
+        .collect()
+});
+
+/// File naming settings of ComicTagger, used to name files the same way as ComicTagger renames
+/// them
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComicTaggerNaming {
+    /// Rename template with fields like `{series}` and `{issue}`
+    pub template: String,
+    /// Minimum number of digits in issue numbers
+    pub issue_padding: usize,
+    /// Remove empty brackets and dangling separators left by missing fields
+    pub smart_cleanup: bool,
+}
+
+impl Default for ComicTaggerNaming {
+    fn default() -> Self {
+        Self {
+            template: "{series} #{issue} - {title} ({year})".to_string(),
+            issue_padding: 3,
+            smart_cleanup: true,
+        }
+    }
+}
+
+impl ComicTaggerNaming {
+    /// Read naming settings from contents of a ComicTagger settings file. Both settings.json from
+    /// ComicTagger 1.5 and later and the ini settings file of older versions are supported.
+    /// Settings missing from the file are set to the defaults of ComicTagger.
+    pub fn from_settings(contents: &str) -> Option<Self> {
+        match serde_json::from_str::<serde_json::Value>(contents) {
+            Ok(root) => Some(Self::from_json(&root["File Rename"])),
+            Err(_) => Self::from_ini(contents),
+        }
+    }
+
+    fn from_json(section: &serde_json::Value) -> Self {
+        let setting = |name: &str| {
+            let value = &section[name];
+            if value.is_null() { &section[format!("rename_{}", name)] } else { value }
+        };
+        let default = Self::default();
+        Self {
+            template: setting("template").as_str().map(String::from).unwrap_or(default.template),
+            issue_padding: setting("issue_number_padding").as_u64()
+                .map(|x| x as usize)
+                .unwrap_or(default.issue_padding),
+            smart_cleanup: setting("use_smart_string_cleanup").as_bool().unwrap_or(default.smart_cleanup),
+        }
+    }
+
+    fn from_ini(contents: &str) -> Option<Self> {
+        let mut naming = Self::default();
+        let mut found = false;
+        for line in contents.lines() {
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+            match key {
+                // Old templates use %field% instead of {field}
+                "rename_template" => naming.template = INI_FIELD.replace_all(value, "{$1}").to_string(),
+                "rename_issue_number_padding" => naming.issue_padding = value.parse().ok()?,
+                "rename_use_smart_string_cleanup" => naming.smart_cleanup = value.eq_ignore_ascii_case("true"),
+                _ => continue,
+            }
+            found = true;
+        }
+        found.then_some(naming)
+    }
+
+    /// Value of field in rename template. Fields that are unknown or missing are empty.
+    fn field(&self, metadata: &Metadata, name: &str) -> String {
+        let number = |x: Option<u32>| x.map(|x| x.to_string()).unwrap_or_default();
+        match name {
+            "series" => metadata.series.clone().unwrap_or_default(),
+            "title" => metadata.title.clone().unwrap_or_default(),
+            "publisher" => metadata.publisher.clone().unwrap_or_default(),
+            "language" => metadata.language.clone().unwrap_or_default(),
+            "issue" => metadata.issue_number
+                .map(|x| format!("{:0width$}", x, width = self.issue_padding))
+                .unwrap_or_default(),
+            "volume" => number(metadata.volume),
+            "year" => number(metadata.year),
+            "month" => number(metadata.month),
+            "day" => number(metadata.day),
+            _ => String::new(),
+        }
+    }
+
+    /// File name of comic without extension
+    pub fn file_name(&self, metadata: &Metadata) -> String {
+        // Conversions and format specifications like {series!u} are not supported
+        let name = FIELD
+            .replace_all(&self.template, |captures: &regex::Captures| self.field(metadata, &captures[1]))
+            // Directories are not created from rename templates
+            .replace('/', "-");
+        if self.smart_cleanup {
+            smart_cleanup(&name)
+        } else {
+            name
+        }
+    }
+}
+
+/// Remove empty brackets, repeated whitespace, and dangling dashes like ComicTagger does
+fn smart_cleanup(name: &str) -> String {
+    let mut name = name.to_string();
+    for (pattern, replacement) in CLEANUP_RULES.iter() {
+        name = pattern.replace_all(&name, *replacement).to_string();
+    }
+    name.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ComicTaggerNaming;
+    use crate::metadata::Metadata;
+
+    fn metadata() -> Metadata {
+        Metadata {
+            series: Some("Moon Knight".to_string()),
+            title: Some("Lunatic".to_string()),
+            issue_number: Some(1),
+            year: Some(2016),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn file_names() {
+        let naming = ComicTaggerNaming::default();
+        assert_eq!(naming.file_name(&metadata()), "Moon Knight #001 - Lunatic (2016)");
+        let missing = Metadata { title: None, year: None, ..metadata() };
+        assert_eq!(naming.file_name(&missing), "Moon Knight #001");
+        let no_issue = Metadata { issue_number: None, ..metadata() };
+        assert_eq!(naming.file_name(&no_issue), "Moon Knight - Lunatic (2016)");
+        let raw = ComicTaggerNaming { smart_cleanup: false, ..Default::default() };
+        assert_eq!(raw.file_name(&missing), "Moon Knight #001 -  ()");
+    }
+
+    #[test]
+    fn settings_files() {
+        let json = r#"{"File Rename": {"template": "{series} v{volume} {issue}", "issue_number_padding": 2}}"#;
+        let naming = ComicTaggerNaming::from_settings(json).unwrap();
+        assert_eq!(naming.template, "{series} v{volume} {issue}");
+        assert_eq!(naming.issue_padding, 2);
+        assert!(naming.smart_cleanup);
+        let ini = "[rename]\nrename_template = %series% #%issue%\nrename_use_smart_string_cleanup = False\n";
+        let naming = ComicTaggerNaming::from_settings(ini).unwrap();
+        assert_eq!(naming.template, "{series} #{issue}");
+        assert!(!naming.smart_cleanup);
+        assert_eq!(ComicTaggerNaming::from_settings("[other]\n"), None);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::error::GrawlixIOError as Error;
+use super::{Comic, filter::hash};
+use std::fmt;
+
+/// Differences between two comic files
+#[derive(Debug, Default, PartialEq)]
+pub struct ComicDiff {
+    /// Number of pages in the first and second comic
+    pub page_counts: (usize, usize),
+    /// Page numbers (1 indexed) of pages with different content in both comics
+    pub changed_pages: Vec<usize>,
+    /// Metadata fields with different values given as json
+    pub metadata: Vec<(String, Option<String>, Option<String>)>,
+}
+
+impl ComicDiff {
+    /// Compare pages and metadata of comic files at `a` and `b`
+    pub fn from_files(a: &str, b: &str) -> Result<Self, Error> {
+        let (comic_a, comic_b) = (Comic::from_file(a)?, Comic::from_file(b)?);
+        Ok(Self::new(&comic_a, &page_hashes(&comic_a)?, &comic_b, &page_hashes(&comic_b)?))
+    }
+
+    /// Compare metadata of comics and hashes of their pages. Provenance is not compared since it
+    /// differs between every download.
+    fn new(a: &Comic, hashes_a: &[u64], b: &Comic, hashes_b: &[u64]) -> Self {
+        let changed_pages = hashes_a.iter()
+            .zip(hashes_b)
+            .enumerate()
+            .filter(|(_, (x, y))| x != y)
+            .map(|(n, _)| n + 1)
+            .collect();
+        let fields = |comic: &Comic| match serde_json::to_value(&comic.metadata) {
+            Ok(serde_json::Value::Object(fields)) => fields,
+            _ => serde_json::Map::new(),
+        };
+        let (fields_a, fields_b) = (fields(a), fields(b));
+        let mut names: Vec<&String> = fields_a.keys().chain(fields_b.keys()).collect();
+        names.sort();
+        names.dedup();
+        // Empty values are treated as missing
+        let value = |fields: &serde_json::Map<String, serde_json::Value>, name: &str| fields.get(name)
+            .filter(|x| !(x.is_null() || x.as_array().map(Vec::is_empty).unwrap_or(false)))
+            .map(|x| x.to_string());
+        let metadata = names.into_iter()
+            .filter(|name| *name != "provenance")
+            .map(|name| (name.clone(), value(&fields_a, name), value(&fields_b, name)))
+            .filter(|(_, x, y)| x != y)
+            .collect();
+        Self {
+            page_counts: (hashes_a.len(), hashes_b.len()),
+            changed_pages,
+            metadata,
+        }
+    }
+
+    /// Returns true if the comics have the same pages and metadata
+    pub fn is_empty(&self) -> bool {
+        self.page_counts.0 == self.page_counts.1 && self.changed_pages.is_empty() && self.metadata.is_empty()
+    }
+}
+
+impl fmt::Display for ComicDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No differences");
+        }
+        let (a, b) = self.page_counts;
+        if a != b {
+            writeln!(f, "Pages: {} -> {}", a, b)?;
+        }
+        for page in &self.changed_pages {
+            writeln!(f, "Page {} changed", page)?;
+        }
+        for page in b + 1..=a {
+            writeln!(f, "- Page {}", page)?;
+        }
+        for page in a + 1..=b {
+            writeln!(f, "+ Page {}", page)?;
+        }
+        for (name, a, b) in &self.metadata {
+            match (a, b) {
+                (Some(a), Some(b)) => writeln!(f, "{}: {} -> {}", name, a, b)?,
+                (Some(a), None) => writeln!(f, "- {}: {}", name, a)?,
+                (None, Some(b)) => writeln!(f, "+ {}: {}", name, b)?,
+                (None, None) => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Hashes of page contents of `comic`
+fn page_hashes(comic: &Comic) -> Result<Vec<u64>, Error> {
+    let mut pages = comic.container_pages()?;
+    (0..comic.pages.len())
+        .map(|index| Ok(hash(&pages.read(index)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ComicDiff;
+    use crate::{comic::Comic, metadata::Metadata};
+
+    #[test]
+    fn comic_diff() {
+        let comic = |title: &str| {
+            let mut comic = Comic::new();
+            comic.metadata = Metadata { title: Some(title.to_string()), ..Default::default() };
+            comic
+        };
+        let a = comic("Moon Knight #1");
+        let b = comic("Moon Knight (2016) #1");
+        let diff = ComicDiff::new(&a, &[1, 2, 3], &b, &[1, 5, 3, 4]);
+        assert_eq!(diff.page_counts, (3, 4));
+        assert_eq!(diff.changed_pages, vec![2]);
+        assert_eq!(diff.metadata, vec![(
+            "title".to_string(),
+            Some("\"Moon Knight #1\"".to_string()),
+            Some("\"Moon Knight (2016) #1\"".to_string())
+        )]);
+        assert_eq!(
+            diff.to_string(),
+            "Pages: 3 -> 4\nPage 2 changed\n+ Page 4\ntitle: \"Moon Knight #1\" -> \"Moon Knight (2016) #1\"\n"
+        );
+        let same = ComicDiff::new(&a, &[1, 2], &comic("Moon Knight #1"), &[1, 2]);
+        assert!(same.is_empty());
+        assert_eq!(same.to_string(), "No differences\n");
+    }
+}
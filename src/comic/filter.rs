@@ -0,0 +1,164 @@
+use image::{DynamicImage, imageops::FilterType};
+use regex::Regex;
+use std::{
+    collections::{HashSet, hash_map::DefaultHasher},
+    hash::{Hash, Hasher},
+};
+
+/// Pages whose perceptual hashes differ in at most this many bits are considered duplicates
+const DUPLICATE_DISTANCE: u32 = 4;
+/// Pages with a lower standard deviation of brightness (0-255) are considered blank
+const BLANK_DEVIATION: f64 = 4.0;
+
+/// Rules for removing pages when comics are written
+#[derive(Clone, Debug, Default)]
+pub struct PageFilter {
+    /// Remove pages that look the same as an earlier page in the same comic
+    pub duplicates: bool,
+    /// Remove pages that are nearly blank
+    pub blank: bool,
+    /// Remove pages with urls matching any of these patterns
+    pub url_patterns: Vec<Regex>,
+}
+
+impl PageFilter {
+    /// Returns true if no pages are removed by filter
+    pub fn is_empty(&self) -> bool {
+        !self.duplicates && !self.blank && self.url_patterns.is_empty()
+    }
+
+    /// Returns the reason the page at `url` should be removed without downloading it
+    pub(crate) fn check_url(&self, url: &str) -> Option<&'static str> {
+        if self.url_patterns.iter().any(|x| x.is_match(url)) {
+            return Some("advert");
+        }
+        None
+    }
+
+    /// Returns the reason the page with `data` should be removed or `None` if it should be kept.
+    /// Pages that can not be decoded are only compared byte for byte.
+    pub(crate) fn check(&self, data: &[u8], state: &mut FilterState) -> Option<&'static str> {
+        if !self.blank && !self.duplicates {
+            return None;
+        }
+        let image = image::load_from_memory(data).ok();
+        if self.blank && image.as_ref().map(is_blank).unwrap_or(false) {
+            return Some("blank");
+        }
+        if self.duplicates {
+            if !state.hashes.insert(hash(data)) {
+                return Some("duplicate");
+            }
+            if let Some(image) = &image {
+                let new = difference_hash(image);
+                if state.images.iter().any(|x| (x ^ new).count_ones() <= DUPLICATE_DISTANCE) {
+                    return Some("duplicate");
+                }
+                state.images.push(new);
+            }
+        }
+        None
+    }
+}
+
+/// Pages seen while writing a single comic
+#[derive(Default)]
+pub(crate) struct FilterState {
+    /// Hashes of page data
+    hashes: HashSet<u64>,
+    /// Perceptual hashes of decoded pages
+    images: Vec<u64>,
+}
+
+pub(crate) fn hash(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Perceptual hash of `image` where each bit is set if a pixel is brighter than its right
+/// neighbour in a 9x8 grayscale version of the image. Similar images have hashes that only differ
+/// in a few bits, so recompressed or resized copies of a page are also found.
+fn difference_hash(image: &DynamicImage) -> u64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            let bit = small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | bit as u64;
+        }
+    }
+    hash
+}
+
+/// Returns true if the brightness of `image` barely varies. The image is scaled down first, which
+/// also keeps the check fast for large pages.
+fn is_blank(image: &DynamicImage) -> bool {
+    let small = image.thumbnail(256, 256).to_luma8();
+    let len = small.len() as f64;
+    if len == 0.0 {
+        return true;
+    }
+    let mean = small.iter().map(|x| *x as f64).sum::<f64>() / len;
+    let variance = small.iter().map(|x| (*x as f64 - mean).powi(2)).sum::<f64>() / len;
+    variance.sqrt() < BLANK_DEVIATION
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FilterState, PageFilter};
+
+    /// Encode `image` as png
+    fn png(image: image::GrayImage) -> Vec<u8> {
+        let mut data = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut data, image::ImageFormat::Png).unwrap();
+        data.into_inner()
+    }
+
+    /// Image getting brighter from left to right with a dark square
+    fn gradient(width: u32, height: u32) -> image::GrayImage {
+        image::GrayImage::from_fn(width, height, |x, y| {
+            let dark = x < width / 4 && y < height / 4;
+            image::Luma([if dark { 0 } else { (x * 255 / width) as u8 }])
+        })
+    }
+
+    #[test]
+    fn duplicates() {
+        let filter = PageFilter { duplicates: true, ..Default::default() };
+        let mut state = FilterState::default();
+        assert_eq!(filter.check(b"page 1", &mut state), None);
+        assert_eq!(filter.check(b"credits", &mut state), None);
+        assert_eq!(filter.check(b"credits", &mut state), Some("duplicate"));
+        assert_eq!(PageFilter::default().check(b"page 1", &mut state), None);
+    }
+
+    #[test]
+    fn similar_pages() {
+        let filter = PageFilter { duplicates: true, ..Default::default() };
+        let mut state = FilterState::default();
+        assert_eq!(filter.check(&png(gradient(64, 96)), &mut state), None);
+        // Same page in a different resolution
+        assert_eq!(filter.check(&png(gradient(128, 192)), &mut state), Some("duplicate"));
+        let flipped = image::imageops::flip_horizontal(&gradient(64, 96));
+        assert_eq!(filter.check(&png(flipped), &mut state), None);
+    }
+
+    #[test]
+    fn blank_and_adverts() {
+        let filter = PageFilter {
+            blank: true,
+            url_patterns: vec![regex::Regex::new("/ads/").unwrap()],
+            ..Default::default()
+        };
+        let mut state = FilterState::default();
+        assert_eq!(filter.check(&png(gradient(64, 64)), &mut state), None);
+        let mut blank = image::GrayImage::from_pixel(64, 64, image::Luma([250]));
+        blank.put_pixel(10, 10, image::Luma([0]));
+        assert_eq!(filter.check(&png(blank), &mut state), Some("blank"));
+        // Pages that can not be decoded are kept
+        assert_eq!(filter.check(&[0xff; 4096], &mut state), None);
+        assert_eq!(filter.check_url("https://example.com/ads/1.jpg"), Some("advert"));
+        assert_eq!(filter.check_url("https://example.com/pages/1.jpg"), None);
+    }
+}
@@ -1,8 +1,14 @@
 use super::Comic;
+use regex::Regex;
 use rt_format::{Format, FormatArgument, ParsedFormat, Specifier};
+use serde::Deserialize;
 use std::collections::HashMap;
 use std::fmt;
-use crate::metadata::{Author, AuthorType};
+use std::str::FromStr;
+use crate::{
+    error::GrawlixIOError,
+    metadata::{Author, AuthorType, normalize_text, title_case},
+};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Variant {
@@ -11,8 +17,9 @@ pub enum Variant {
 }
 
 impl Variant {
+    /// Text field normalized so the same text always gives the same output
     fn string(s: &Option<String>) -> Option<Self> {
-        s.as_ref().map(|x| Self::String(x.clone()))
+        s.as_deref().map(|x| Self::String(normalize_text(x)))
     }
 
     fn int(s: &Option<u32>) -> Option<Self> {
@@ -98,16 +105,46 @@ fn get_first_author(authors: &Vec<Author>, author_type: AuthorType) -> Option<St
         .map(|x| x.name.clone())
 }
 
-fn comic_options(comic: &Comic) -> HashMap<&str, Variant> {
+/// Names of authors with one of `author_types` joined with separator from `options`. All authors
+/// are used if `author_types` is empty.
+fn join_authors(authors: &[Author], author_types: &[AuthorType], options: &TemplateOptions) -> Option<String> {
+    let mut names: Vec<&str> = Vec::new();
+    for author in authors {
+        let included = author_types.is_empty() || author_types.contains(&author.author_type);
+        if included && !names.contains(&author.name.as_str()) {
+            names.push(&author.name);
+        }
+    }
+    if let Some(max) = options.max_authors {
+        names.truncate(max);
+    }
+    if names.is_empty() {
+        None
+    } else {
+        Some(names.join(&options.author_separator))
+    }
+}
+
+fn comic_options<'a>(comic: &Comic, options: &TemplateOptions) -> HashMap<&'a str, Option<Variant>> {
     let meta = &comic.metadata;
+    let artists = [
+        AuthorType::Penciller, AuthorType::Inker, AuthorType::Colorist, AuthorType::Letterer,
+        AuthorType::CoverArtist
+    ];
+    let name = |s: &Option<String>| match options.title_case {
+        true => s.as_deref().map(|x| Variant::String(title_case(x, meta.language.as_deref()))),
+        false => Variant::string(s),
+    };
     [
-        ("title", Variant::string(&meta.title)),
-        ("series", Variant::string(&meta.series)),
+        ("title", name(&meta.title)),
+        ("series", name(&meta.series)),
         ("publisher", Variant::string(&meta.publisher)),
         ("issuenumber", Variant::int(&meta.issue_number)),
+        ("volume", Variant::int(&meta.volume)),
         ("year", Variant::int(&meta.year)),
         ("month", Variant::int(&meta.month)),
         ("day", Variant::int(&meta.day)),
+        ("language", Variant::string(&meta.language)),
         ("writer", Variant::string(&get_first_author(&meta.authors, AuthorType::Writer))),
         ("penciller", Variant::string(&get_first_author(&meta.authors, AuthorType::Penciller))),
         ("inker", Variant::string(&get_first_author(&meta.authors, AuthorType::Inker))),
@@ -115,27 +152,402 @@ fn comic_options(comic: &Comic) -> HashMap<&str, Variant> {
         ("letterer", Variant::string(&get_first_author(&meta.authors, AuthorType::Letterer))),
         ("coverartist", Variant::string(&get_first_author(&meta.authors, AuthorType::CoverArtist))),
         ("editor", Variant::string(&get_first_author(&meta.authors, AuthorType::Editor))),
+        ("writers", join_authors(&meta.authors, &[AuthorType::Writer], options).map(Variant::String)),
+        ("artists", join_authors(&meta.authors, &artists, options).map(Variant::String)),
+        ("authors", join_authors(&meta.authors, &[], options).map(Variant::String)),
         ("pages", Some(Variant::Int(comic.pages.len() as u32))),
     ].into_iter()
-        .map(|(k, v)| (k, v.unwrap_or(Variant::String("Unknown".to_string()))))
+        .map(|(key, value)| (key, value.map(|x| apply_replacements(key, x, &options.replacements))))
+        .collect()
+}
+
+/// Replacement of text in a template field
+#[derive(Clone, Debug)]
+pub struct Replacement {
+    /// Name of field
+    pub field: String,
+    /// Pattern to replace
+    pub pattern: Regex,
+    /// Text matches are replaced with. Can refer to groups in pattern with `$name`.
+    pub replacement: String,
+}
+
+impl Replacement {
+    /// Create new replacement for `field`
+    pub fn new(field: &str, pattern: &str, replacement: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            field: field.to_string(),
+            pattern: Regex::new(pattern)?,
+            replacement: replacement.to_string(),
+        })
+    }
+}
+
+/// Apply all replacements for `field` to text values
+fn apply_replacements(field: &str, value: Variant, replacements: &[Replacement]) -> Variant {
+    match value {
+        Variant::String(mut text) => {
+            for replacement in replacements.iter().filter(|x| x.field == field) {
+                text = replacement.pattern
+                    .replace_all(&text, replacement.replacement.as_str())
+                    .trim()
+                    .to_string();
+            }
+            Variant::String(text)
+        },
+        int => int,
+    }
+}
+
+/// Options for formatting comics with templates
+#[derive(Clone, Debug)]
+pub struct TemplateOptions {
+    /// How fields missing from comic are formatted
+    pub missing_fields: MissingField,
+    /// Separator between names in `writers`, `artists`, and `authors`
+    pub author_separator: String,
+    /// Maximum number of names in `writers`, `artists`, and `authors`
+    pub max_authors: Option<usize>,
+    /// Replacements applied to fields before formatting
+    pub replacements: Vec<Replacement>,
+    /// Capitalize each word in `title` and `series`
+    pub title_case: bool,
+}
+
+impl Default for TemplateOptions {
+    fn default() -> Self {
+        Self {
+            missing_fields: MissingField::default(),
+            author_separator: ", ".to_string(),
+            max_authors: None,
+            replacements: Vec::new(),
+            title_case: false,
+        }
+    }
+}
+
+/// How fields missing from a comic are formatted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MissingField {
+    /// Replace field with "Unknown"
+    #[default]
+    Unknown,
+    /// Replace field with an empty string
+    Empty,
+    /// Leave out directories containing missing fields
+    Skip,
+    /// Fail formatting comic
+    Error,
+}
+
+impl FromStr for MissingField {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "unknown" => Ok(Self::Unknown),
+            "empty" => Ok(Self::Empty),
+            "skip" => Ok(Self::Skip),
+            "error" => Ok(Self::Error),
+            _ => Err("Could not parse missing field policy (Either unknown, empty, skip, or error)")
+        }
+    }
+}
+
+/// Part of template
+#[derive(Debug, PartialEq)]
+enum Token {
+    /// Text copied directly to output
+    Text(String),
+    /// Field with fallback fields and format specification
+    Field {
+        names: Vec<String>,
+        spec: String,
+    },
+}
+
+/// Split template into text and fields
+fn parse_template(template: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            // Escaped braces are kept for the formatter
+            '{' | '}' if chars.peek() == Some(&c) => {
+                chars.next();
+                text.push(c);
+                text.push(c);
+            },
+            '{' => {
+                if !text.is_empty() {
+                    tokens.push(Token::Text(std::mem::take(&mut text)));
+                }
+                let field: String = chars.by_ref().take_while(|x| *x != '}').collect();
+                let (names, spec) = match field.find(':') {
+                    Some(i) => field.split_at(i),
+                    None => (field.as_str(), ""),
+                };
+                tokens.push(Token::Field {
+                    names: names.split('|').map(|x| x.trim().to_string()).collect(),
+                    spec: spec.to_string(),
+                });
+            },
+            _ => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    tokens
+}
+
+/// Names of all fields available in templates
+pub const TEMPLATE_FIELDS: [&str; 20] = [
+    "title", "series", "publisher", "issuenumber", "volume", "year", "month", "day", "language",
+    "writer", "penciller", "inker", "colorist", "letterer", "coverartist", "editor", "writers",
+    "artists", "authors", "pages",
+];
+
+/// Field in template that does not exist
+#[derive(Debug, PartialEq)]
+pub struct UnknownField {
+    /// Name of field
+    pub name: String,
+    /// Known field with a similar name
+    pub suggestion: Option<&'static str>,
+}
+
+/// Names of all fields used in template, including fallback fields
+pub fn template_fields(template: &str) -> Vec<String> {
+    parse_template(template).into_iter()
+        .flat_map(|token| match token {
+            Token::Field { names, .. } => names,
+            Token::Text(_) => Vec::new(),
+        })
+        .collect()
+}
+
+/// Find fields in template that are not available
+pub fn validate_template(template: &str) -> Vec<UnknownField> {
+    template_fields(template).into_iter()
+        .filter(|field| !TEMPLATE_FIELDS.contains(&field.as_str()))
+        .map(|name| UnknownField {
+            suggestion: TEMPLATE_FIELDS.iter()
+                .map(|known| (edit_distance(&name, known), *known))
+                .filter(|(distance, _)| *distance <= 2)
+                .min_by_key(|(distance, _)| *distance)
+                .map(|(_, known)| known),
+            name,
+        })
         .collect()
 }
 
+/// Number of single character edits needed to change `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current.push((previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
 impl Comic {
-    /// Format comic as string based on metadata and template
-    pub fn format(&self, template: &str) -> Result<String, crate::error::GrawlixIOError> {
-        let named_options = comic_options(self);
-        let args = ParsedFormat::parse(template, &[], &named_options)
-            .map_err(|e| crate::error::GrawlixIOError::StringFormat(e, template.to_string()))?;
-        return Ok(format!("{}", args));
+    /// Format comic as string based on metadata and template. Missing fields are replaced with
+    /// "Unknown".
+    pub fn format(&self, template: &str) -> Result<String, GrawlixIOError> {
+        self.format_with_options(template, &TemplateOptions::default())
+    }
+
+    /// Format comic as string based on metadata and template. Fields can have fallbacks like
+    /// `{series|title}` where the first available field is used. If none of them are available
+    /// the field is handled based on `options.missing_fields`.
+    pub fn format_with_options(&self, template: &str, template_options: &TemplateOptions) -> Result<String, GrawlixIOError> {
+        self.format_with_fields(template, template_options, Vec::new())
+    }
+
+    /// Format name of page number `page` with file extension `ext`. The template has the fields
+    /// `page` and `ext` in addition to the normal template fields.
+    pub fn format_page_name(&self, template: &str, page: u32, ext: &str) -> Result<String, GrawlixIOError> {
+        self.format_with_fields(template, &TemplateOptions::default(), vec![
+            ("title", Variant::String(self.title().to_string())),
+            ("page", Variant::Int(page)),
+            ("ext", Variant::String(ext.to_string())),
+        ])
+    }
+
+    /// Format comic with `extra` fields added to the fields from metadata
+    fn format_with_fields(&self, template: &str, template_options: &TemplateOptions, extra: Vec<(&str, Variant)>) -> Result<String, GrawlixIOError> {
+        let missing = template_options.missing_fields;
+        let mut options = comic_options(self, template_options);
+        options.extend(extra.into_iter().map(|(key, value)| (key, Some(value))));
+        let mut values = Vec::new();
+        let mut segments = Vec::new();
+        let mut segment = String::new();
+        let mut skip_segment = false;
+        for token in parse_template(template) {
+            match token {
+                Token::Text(text) => {
+                    for (n, part) in text.split('/').enumerate() {
+                        if n > 0 {
+                            let finished = std::mem::take(&mut segment);
+                            if !skip_segment {
+                                segments.push(finished);
+                            }
+                            skip_segment = false;
+                        }
+                        segment.push_str(part);
+                    }
+                },
+                Token::Field { names, spec } => {
+                    if let Some(name) = names.iter().find(|x| !options.contains_key(x.as_str())) {
+                        return Err(GrawlixIOError::UnknownTemplateField(name.clone(), template.to_string()));
+                    }
+                    let value = names.iter().find_map(|x| options[x.as_str()].clone());
+                    let value = match (value, missing) {
+                        (Some(value), _) => value,
+                        (None, MissingField::Error) => {
+                            return Err(GrawlixIOError::MissingTemplateField(names.join("|"), template.to_string()));
+                        },
+                        (None, MissingField::Skip) => {
+                            skip_segment = true;
+                            Variant::String(String::new())
+                        },
+                        (None, MissingField::Empty) => Variant::String(String::new()),
+                        (None, MissingField::Unknown) => Variant::String("Unknown".to_string()),
+                    };
+                    segment.push_str(&format!("{{field{}{}}}", values.len(), spec));
+                    values.push(value);
+                },
+            }
+        }
+        // The file name is always kept
+        segments.push(segment);
+        let resolved = segments.join("/");
+        let keys: Vec<String> = (0..values.len()).map(|n| format!("field{}", n)).collect();
+        let named_options: HashMap<&str, Variant> = keys.iter()
+            .map(|x| x.as_str())
+            .zip(values)
+            .collect();
+        let args = ParsedFormat::parse(&resolved, &[], &named_options)
+            .map_err(|e| GrawlixIOError::StringFormat(e, template.to_string()))?;
+        let output = format!("{}", args);
+        if missing == MissingField::Empty {
+            // Removing directories that are empty because of missing fields
+            let path = output.split('/')
+                .filter(|x| !x.is_empty())
+                .collect::<Vec<&str>>()
+                .join("/");
+            if template.starts_with('/') {
+                return Ok(format!("/{}", path));
+            }
+            return Ok(path);
+        }
+        Ok(output)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::comic::{Page, Comic};
+    use crate::comic::{Page, Comic, MissingField, TemplateOptions};
+
+    fn options(missing_fields: MissingField) -> TemplateOptions {
+        TemplateOptions { missing_fields, ..Default::default() }
+    }
     use crate::metadata::*;
 
+    #[test]
+    fn all_fields_known() {
+        let options = super::comic_options(&Comic::new(), &Default::default());
+        assert_eq!(options.len(), super::TEMPLATE_FIELDS.len());
+        assert!(super::TEMPLATE_FIELDS.iter().all(|x| options.contains_key(x)));
+    }
+
+    #[test]
+    fn author_lists() {
+        let mut comic = Comic::new();
+        comic.metadata.authors = vec![
+            Author { name: "Jeff Lemire".to_string(), author_type: AuthorType::Writer },
+            Author { name: "Greg Smallwood".to_string(), author_type: AuthorType::CoverArtist },
+            Author { name: "Greg Smallwood".to_string(), author_type: AuthorType::Penciller },
+            Author { name: "Jordie Bellaire".to_string(), author_type: AuthorType::Colorist },
+        ];
+        assert_eq!(
+            comic.format("{writers} - {artists}").unwrap(),
+            "Jeff Lemire - Greg Smallwood, Jordie Bellaire"
+        );
+        let options = TemplateOptions {
+            author_separator: " & ".to_string(),
+            max_authors: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            comic.format_with_options("{authors}", &options).unwrap(),
+            "Jeff Lemire & Greg Smallwood"
+        );
+    }
+
+    #[test]
+    fn replacements() {
+        let mut comic = Comic::new();
+        comic.metadata.series = Some("Moon Knight (2016 - 2018)".to_string());
+        let options = TemplateOptions {
+            replacements: vec![
+                super::Replacement::new("series", r"\(\d{4} - \d{4}\)", "").unwrap(),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(comic.format_with_options("{series}", &options).unwrap(), "Moon Knight");
+    }
+
+    #[test]
+    fn normalized_fields() {
+        let mut comic = Comic::new();
+        // "å" written as "a" followed by a combining ring
+        comic.metadata.series = Some("Ma\u{30a}nens  born".to_string());
+        assert_eq!(comic.format("{series}").unwrap(), "Månens born");
+        comic.metadata.language = Some("da".to_string());
+        let options = TemplateOptions { title_case: true, ..Default::default() };
+        assert_eq!(comic.format_with_options("{series}", &options).unwrap(), "Månens Born");
+    }
+
+    #[test]
+    fn missing_fields() {
+        let mut comic = Comic::new();
+        comic.metadata.title = Some("Moon Knight #1".to_string());
+        let template = "{publisher}/{series|title}/{title} {issuenumber}.cbz";
+        assert_eq!(comic.format(template).unwrap(), "Unknown/Moon Knight #1/Moon Knight #1 Unknown.cbz");
+        assert_eq!(
+            comic.format_with_options(template, &options(MissingField::Empty)).unwrap(),
+            "Moon Knight #1/Moon Knight #1 .cbz"
+        );
+        assert_eq!(
+            comic.format_with_options(template, &options(MissingField::Skip)).unwrap(),
+            "Moon Knight #1/Moon Knight #1 .cbz"
+        );
+        assert!(comic.format_with_options(template, &options(MissingField::Error)).is_err());
+        assert!(comic.format("{foo}").is_err());
+    }
+
+    #[test]
+    fn template_validation() {
+        assert_eq!(super::validate_template("{series|title}/{title} #{issuenumber:03}.cbz"), vec![]);
+        assert_eq!(
+            super::validate_template("{{series}}/{serie}/{foo}.cbz"),
+            vec![
+                super::UnknownField { name: "serie".to_string(), suggestion: Some("series") },
+                super::UnknownField { name: "foo".to_string(), suggestion: None },
+            ]
+        );
+    }
+
     #[test]
     fn comic_formatting() {
         let mut comic = Comic::new();
@@ -168,4 +580,13 @@ mod tests {
             comic.format("{title} {series} {publisher} {issuenumber} {year} {month} {day} {writer} {coverartist} {pages}").unwrap()
         );
     }
+
+    #[test]
+    fn page_names() {
+        let mut comic = Comic::new();
+        comic.metadata.title = Some("Saga #1".to_string());
+        assert_eq!(comic.format_page_name("{title} #{page:03}.{ext}", 7, "jpg").unwrap(), "Saga #1 #007.jpg");
+        assert_eq!(comic.format_page_name("{page:04}.{ext}", 12, "png").unwrap(), "0012.png");
+        assert_eq!(Comic::new().format_page_name("{title} {page}.{ext}", 1, "jpg").unwrap(), "UNKNOWN 1.jpg");
+    }
 }
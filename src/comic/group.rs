@@ -0,0 +1,114 @@
+use super::Comic;
+use crate::metadata::{Metadata, same_text};
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// How downloaded comics are combined before they are written
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    /// Write each comic to its own file
+    #[default]
+    None,
+    /// Combine chapters from the same volume into one comic
+    Volume,
+}
+
+impl FromStr for GroupBy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" | "chapter" => Ok(Self::None),
+            "volume" => Ok(Self::Volume),
+            _ => Err("Could not parse grouping (Either none or volume)")
+        }
+    }
+}
+
+/// Returns true if `a` and `b` are chapters in the same volume of a series. Chapters without
+/// volume info are never in the same volume.
+pub fn same_volume(a: &Metadata, b: &Metadata) -> bool {
+    let same_series = match (&a.series, &b.series) {
+        (Some(a), Some(b)) => same_text(a, b),
+        (a, b) => a == b,
+    };
+    same_series && a.volume.is_some() && a.volume == b.volume
+}
+
+/// Combine chapters with the same series and volume into one comic per volume. Pages are ordered
+/// by issue number of the chapters. Comics without volume info are returned unchanged.
+pub fn group_by_volume(comics: Vec<Comic>) -> Vec<Comic> {
+    let mut output: Vec<Comic> = Vec::new();
+    let mut volumes: Vec<Vec<Comic>> = Vec::new();
+    for comic in comics {
+        if comic.metadata.volume.is_none() {
+            output.push(comic);
+            continue;
+        }
+        match volumes.iter_mut().find(|x| same_volume(&x[0].metadata, &comic.metadata)) {
+            Some(chapters) => chapters.push(comic),
+            None => volumes.push(vec![comic]),
+        }
+    }
+    for mut chapters in volumes {
+        chapters.sort_by_key(|x| x.metadata.issue_number);
+        let series = chapters[0].metadata.series.clone();
+        let volume = chapters[0].metadata.volume.unwrap_or_default();
+        let mut metadata = chapters[0].metadata.clone();
+        metadata.title = Some(match &series {
+            Some(series) => format!("{} Vol. {}", series, volume),
+            None => format!("Vol. {}", volume),
+        });
+        metadata.issue_number = Some(volume);
+        metadata.preview = chapters.iter().any(|x| x.metadata.preview);
+        metadata.identifiers = chapters.iter()
+            .flat_map(|x| x.metadata.identifiers.clone())
+            .collect();
+        let pages = chapters.into_iter()
+            .flat_map(|x| x.pages)
+            .collect();
+        output.push(Comic { metadata, pages, ..Default::default() });
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::comic::{Comic, Page};
+
+    fn chapter(number: u32, volume: Option<u32>, pages: usize) -> Comic {
+        let mut comic = Comic::new();
+        comic.metadata.series = Some("One Piece".to_string());
+        comic.metadata.issue_number = Some(number);
+        comic.metadata.volume = volume;
+        comic.pages = (0..pages)
+            .map(|n| Page::from_url(&format!("{}-{}", number, n), "jpg"))
+            .collect();
+        comic
+    }
+
+    #[test]
+    fn group_chapters() {
+        let comics = vec![
+            chapter(2, Some(1), 2),
+            chapter(1, Some(1), 3),
+            chapter(3, Some(2), 1),
+            chapter(4, None, 1),
+        ];
+        let grouped = super::group_by_volume(comics);
+        assert_eq!(grouped.len(), 3);
+        assert_eq!(grouped[0].metadata.issue_number, Some(4));
+        assert_eq!(grouped[1].title(), "One Piece Vol. 1");
+        assert_eq!(grouped[1].pages.len(), 5);
+        assert_eq!(grouped[2].title(), "One Piece Vol. 2");
+    }
+
+    #[test]
+    fn same_volume() {
+        let same = |a: Comic, b: Comic| super::same_volume(&a.metadata, &b.metadata);
+        assert!(same(chapter(1, Some(1), 1), chapter(2, Some(1), 1)));
+        assert!(!same(chapter(1, Some(1), 1), chapter(2, Some(2), 1)));
+        assert!(!same(chapter(1, None, 1), chapter(2, None, 1)));
+    }
+}
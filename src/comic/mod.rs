@@ -1,18 +1,33 @@
+mod comictagger;
+mod diff;
+mod filter;
 mod format;
+mod group;
 mod page;
 pub mod read;
 mod write;
 
+pub use comictagger::ComicTaggerNaming;
+pub use diff::ComicDiff;
+pub use filter::PageFilter;
+pub use format::{MissingField, Replacement, TemplateOptions, TEMPLATE_FIELDS, UnknownField, template_fields, validate_template};
+pub use group::{GroupBy, group_by_volume, same_volume};
 pub use page::*;
+pub use read::{ContainerPages, find_comics};
+pub use write::{ComicFile, DEFAULT_PAGE_TEMPLATE, WriteOptions, sanitize_path};
 
-use crate::metadata::Metadata;
+use crate::metadata::{Metadata, PageLayout, Provenance};
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
+use std::{path::PathBuf, str::FromStr};
 
 #[derive(Default, Debug, Deserialize, Serialize)]
 pub struct Comic {
     pub metadata: Metadata,
     pub pages: Vec<Page>,
+    /// File or directory the comic was read from. Pages stored in the container are read from
+    /// here.
+    #[serde(skip)]
+    pub(crate) source_path: Option<PathBuf>,
 }
 
 impl Comic {
@@ -29,9 +44,28 @@ impl Comic {
         }
     }
 
+    /// How pages of the comic are read
+    pub fn page_layout(&self) -> PageLayout {
+        self.metadata.page_layout
+    }
+
+    /// Add information about source, download time, and original page urls to metadata. `url`
+    /// is the url the comic was found from.
+    pub fn add_provenance(&mut self, url: Option<&str>) {
+        let source = self.metadata.source.clone()
+            .or_else(|| self.metadata.identifiers.first().map(|x| x.source.clone()));
+        let page_urls = self.pages.iter()
+            .filter_map(|page| match &page.page_type {
+                PageType::Url(x) => Some(x.url.clone()),
+                PageType::Container(_) => None,
+            })
+            .collect();
+        self.metadata.provenance = Some(Provenance::new(source, url.map(String::from), page_urls));
+    }
+
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 /// Indicator for output format
 pub enum ComicFormat {
     CBZ,
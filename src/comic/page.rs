@@ -1,4 +1,11 @@
+use crate::{
+    error::GrawlixDownloadError,
+    metadata::PageValidator,
+    source::{CONNECTION_LIMITS, METRICS, read_response, with_timeout},
+};
+use tracing::Instrument;
 use std::collections::HashMap;
+use image::GenericImage;
 use serde::{Deserialize, Serialize};
 use crypto::{
     aes::{KeySize, cbc_decryptor},
@@ -6,13 +13,13 @@ use crypto::{
     buffer::{RefReadBuffer, RefWriteBuffer, WriteBuffer, ReadBuffer},
 };
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Page {
     pub file_format: String,
     pub page_type: PageType,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum PageType {
     /// Page on website
     Url(OnlinePage),
@@ -21,17 +28,20 @@ pub enum PageType {
 }
 
 /// Instructions on how to download a page
-#[derive(Default, Debug, Deserialize, Serialize)]
+#[derive(Clone, Default, Debug, Deserialize, Serialize)]
 pub struct OnlinePage {
     /// Url of page
     pub url: String,
+    /// Fallback urls tried in order if downloading from `url` fails
+    #[serde(default)]
+    pub mirrors: Vec<String>,
     /// Required headers for request
     pub headers: Option<HashMap<String, String>>,
     /// Encryption scheme of page
     pub encryption: Option<PageEncryptionScheme>
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum PageEncryptionScheme {
     /// AES encryption
     AES {
@@ -42,6 +52,12 @@ pub enum PageEncryptionScheme {
     DCUniverseInfinite([u8; 32]),
     /// XOR encryption
     XOR(Vec<u8>),
+    /// Encryption scheme used by ComicWalker. Pages are xored with the first 8 bytes of the hex
+    /// encoded hash.
+    ComicWalker(String),
+    /// Tile scrambling used by K Manga. Pages are split in a 4x4 grid of tiles shuffled in an
+    /// order generated from the seed.
+    KManga(u32),
 }
 
 impl Page {
@@ -61,7 +77,18 @@ impl Page {
             page_type: PageType::Url(OnlinePage {
                 url: url.to_string(),
                 headers: Some(headers),
-                encryption: None,
+                ..Default::default()
+            })
+        }
+    }
+
+    pub fn from_url_with_mirrors(url: &str, mirrors: Vec<String>, file_format: &str) -> Self {
+        Self {
+            file_format: file_format.to_string(),
+            page_type: PageType::Url(OnlinePage {
+                url: url.to_string(),
+                mirrors,
+                ..Default::default()
             })
         }
     }
@@ -71,8 +98,8 @@ impl Page {
             file_format: file_format.to_string(),
             page_type: PageType::Url(OnlinePage {
                 url: url.to_string(),
-                headers: None,
-                encryption: Some(PageEncryptionScheme::XOR(key))
+                encryption: Some(PageEncryptionScheme::XOR(key)),
+                ..Default::default()
             })
         }
     }
@@ -85,26 +112,130 @@ impl Page {
     }
 }
 
+/// File extension of image format detected from the first bytes of `data`. Sources set the format
+/// of pages before downloading them, which does not always match what is returned.
+pub fn image_format(data: &[u8]) -> Option<&'static str> {
+    let signatures: [(&[u8], usize, &str); 6] = [
+        (b"\xFF\xD8\xFF", 0, "jpg"),
+        (b"\x89PNG\r\n\x1A\n", 0, "png"),
+        (b"GIF8", 0, "gif"),
+        (b"WEBP", 8, "webp"),
+        (b"ftypavif", 4, "avif"),
+        // Animated AVIF
+        (b"ftypavis", 4, "avif"),
+    ];
+    signatures.iter()
+        .find(|(signature, offset, _)| data.get(*offset..offset + signature.len()) == Some(*signature))
+        .map(|(_, _, format)| *format)
+}
+
 impl OnlinePage {
-    pub async fn download_page(&self, client: &reqwest::Client) -> Vec<u8> {
-        log::trace!("Downloading page: {}", self.url);
-        let mut req = client.get(&self.url);
+    /// All candidate urls of page in the order they should be tried
+    pub fn urls(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.url).chain(self.mirrors.iter())
+    }
+
+    pub async fn download_page(&self, client: &reqwest::Client) -> Result<Vec<u8>, GrawlixDownloadError> {
+        Ok(self.download_page_with_validator(client).await?.0)
+    }
+
+    /// Download page along with the validators of the response, which `changed` can use to
+    /// check if the page has changed later
+    pub async fn download_page_with_validator(&self, client: &reqwest::Client) -> Result<(Vec<u8>, PageValidator), GrawlixDownloadError> {
+        let mut timed_out = None;
+        for url in self.urls() {
+            match self.download_from_url(client, url).await {
+                Ok((bytes, validator)) => return Ok((match &self.encryption {
+                    Some(enc) => decrypt_page(bytes, enc, url)?,
+                    None => bytes
+                }, validator)),
+                Err(e) => {
+                    log::debug!("Failed to download page from {}: {}", url, e);
+                    if let GrawlixDownloadError::Timeout { .. } = e {
+                        timed_out = Some(e);
+                    }
+                },
+            }
+        }
+        Err(timed_out.unwrap_or_else(|| GrawlixDownloadError::FailedDownload(self.url.clone())))
+    }
+
+    /// Check if page has changed since it was downloaded with `validator` with a conditional
+    /// request. Pages are assumed to be unchanged if neither response has validators.
+    pub async fn changed(&self, client: &reqwest::Client, validator: &PageValidator) -> Result<bool, GrawlixDownloadError> {
+        let mut req = client.head(&self.url);
         if let Some(headers) = &self.headers {
+            req = req.headers(headers.try_into().map_err(|_| GrawlixDownloadError::FailedDownload(self.url.clone()))?);
+        }
+        if let Some(etag) = &validator.etag {
+            req = req.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validator.last_modified {
+            req = req.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+        let _permit = CONNECTION_LIMITS.acquire(&self.url).await;
+        let response = with_timeout(&self.url, async { Ok(req.send().await?) }).await?;
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(false);
+        }
+        if !response.status().is_success() {
+            return Err(GrawlixDownloadError::FailedDownload(self.url.clone()));
+        }
+        Ok(validator_changed(validator, &page_validator(response.headers())))
+    }
+
+    /// Download raw page data and validators from a single url
+    async fn download_from_url(&self, client: &reqwest::Client, url: &str) -> Result<(Vec<u8>, PageValidator), GrawlixDownloadError> {
+        log::trace!("Downloading page: {}", url);
+        let mut req = client.get(url);
+        if let Some(headers) = &self.headers {
+            // TODO: Remove unwrap
             req = req.headers(headers.try_into().unwrap());
         }
-        // TODO: Remove unwraps
-        let resp = req.send().await.unwrap();
-        let bytes = resp.bytes().await.unwrap().as_ref().into();
-        match &self.encryption {
-            Some(enc) => decrypt_page(bytes, enc),
-            None => bytes
+        let _permit = CONNECTION_LIMITS.acquire(url).await;
+        let start = std::time::Instant::now();
+        let response = with_timeout(url, async {
+            let response = req.send().await?.error_for_status()?;
+            let validator = page_validator(response.headers());
+            Ok((read_response(response).await?, validator))
+        })
+            .instrument(tracing::debug_span!("page", url = %url))
+            .await;
+        match response {
+            Ok((bytes, validator)) => {
+                METRICS.record_request(bytes.len(), start.elapsed());
+                Ok((bytes.as_ref().into(), validator))
+            },
+            Err(e) => {
+                METRICS.record_failed_request();
+                Err(e)
+            }
         }
     }
 }
 
-fn decrypt_page(bytes: Vec<u8>, enc: &PageEncryptionScheme) -> Vec<u8> {
+/// Validators from headers of page response
+fn page_validator(headers: &reqwest::header::HeaderMap) -> PageValidator {
+    let header = |name| headers.get(name).and_then(|x| x.to_str().ok()).map(String::from);
+    PageValidator {
+        etag: header(reqwest::header::ETAG),
+        last_modified: header(reqwest::header::LAST_MODIFIED),
+    }
+}
+
+/// Returns true if the validators of a page response differ from the ones it was downloaded
+/// with. Etags are compared if both have them, otherwise modification times.
+fn validator_changed(old: &PageValidator, new: &PageValidator) -> bool {
+    match ((&old.etag, &new.etag), (&old.last_modified, &new.last_modified)) {
+        ((Some(old), Some(new)), _) => old != new,
+        (_, (Some(old), Some(new))) => old != new,
+        _ => false,
+    }
+}
+
+fn decrypt_page(bytes: Vec<u8>, enc: &PageEncryptionScheme, url: &str) -> Result<Vec<u8>, GrawlixDownloadError> {
     log::trace!("Decrypting page");
-    match enc {
+    Ok(match enc {
         PageEncryptionScheme::AES { key, iv } => {
             let mut image_buffer = RefReadBuffer::new(&bytes);
             let size = bytes.len();
@@ -118,12 +249,14 @@ fn decrypt_page(bytes: Vec<u8>, enc: &PageEncryptionScheme) -> Vec<u8> {
             let mut image = decrypted_buffer.take_read_buffer();
             image.take_remaining().to_vec()
         },
-        PageEncryptionScheme::XOR(key) => {
-            bytes.iter()
-                .zip(key.iter().cycle())
-                .map(|(v, k)| v ^ k)
-                .collect()
+        PageEncryptionScheme::XOR(key) => xor(&bytes, key),
+        PageEncryptionScheme::ComicWalker(hash) => {
+            let key = comicwalker_key(hash)
+                .ok_or_else(|| GrawlixDownloadError::FailedDecryption(url.to_string()))?;
+            xor(&bytes, &key)
         },
+        PageEncryptionScheme::KManga(seed) => descramble_kmanga(&bytes, *seed)
+            .ok_or_else(|| GrawlixDownloadError::FailedDecryption(url.to_string()))?,
         PageEncryptionScheme::DCUniverseInfinite(key) => {
             // The first 8 bytes contains the size of the output file
             let original_size = &bytes[0..8];
@@ -153,5 +286,151 @@ fn decrypt_page(bytes: Vec<u8>, enc: &PageEncryptionScheme) -> Vec<u8> {
             let mut image = decrypted_buffer.take_read_buffer();
             image.take_remaining().to_vec()
         }
+    })
+}
+
+/// Xor key of ComicWalker page from the first 8 bytes of hex encoded `hash`. Returns `None` if
+/// `hash` is too short or not hex encoded.
+pub(crate) fn comicwalker_key(hash: &str) -> Option<Vec<u8>> {
+    (0..16)
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hash.get(i..i+2)?, 16).ok())
+        .collect()
+}
+
+/// Number of rows and columns of tiles in scrambled K Manga pages
+const KMANGA_GRID: u32 = 4;
+
+/// Position each tile of a scrambled K Manga page should be moved to. Positions are numbered
+/// from left to right, top to bottom.
+fn kmanga_tile_order(seed: u32) -> Vec<u32> {
+    let mut state = seed;
+    let mut tiles: Vec<(u32, u32)> = (0..KMANGA_GRID * KMANGA_GRID)
+        .map(|position| {
+            // xorshift32
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state, position)
+        })
+        .collect();
+    tiles.sort_by_key(|(value, _)| *value);
+    tiles.into_iter().map(|(_, position)| position).collect()
+}
+
+/// Move tiles of K Manga page back in place. Tiles are a multiple of 8 pixels wide and high,
+/// so pixels at the right and bottom edges outside the grid are not moved. The page is encoded
+/// in the format it was downloaded in.
+fn descramble_kmanga(bytes: &[u8], seed: u32) -> Option<Vec<u8>> {
+    let format = image::guess_format(bytes).ok()?;
+    let scrambled = image::load_from_memory_with_format(bytes, format).ok()?;
+    let tile_width = scrambled.width() / KMANGA_GRID / 8 * 8;
+    let tile_height = scrambled.height() / KMANGA_GRID / 8 * 8;
+    let mut image = scrambled.clone();
+    for (from, to) in kmanga_tile_order(seed).into_iter().enumerate() {
+        let from = from as u32;
+        let tile = scrambled.crop_imm(
+            from % KMANGA_GRID * tile_width,
+            from / KMANGA_GRID * tile_height,
+            tile_width,
+            tile_height
+        );
+        image.copy_from(&tile, to % KMANGA_GRID * tile_width, to / KMANGA_GRID * tile_height).ok()?;
+    }
+    let mut output = std::io::Cursor::new(Vec::new());
+    match format {
+        image::ImageFormat::Jpeg => image.to_rgb8()
+            .write_with_encoder(image::codecs::jpeg::JpegEncoder::new_with_quality(&mut output, 95))
+            .ok()?,
+        _ => image.write_to(&mut output, image::ImageFormat::Png).ok()?,
+    }
+    Some(output.into_inner())
+}
+
+/// Xor `bytes` with repeating `key`
+fn xor(bytes: &[u8], key: &[u8]) -> Vec<u8> {
+    bytes.iter()
+        .zip(key.iter().cycle())
+        .map(|(v, k)| v ^ k)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Page, PageType, PageEncryptionScheme};
+
+    #[test]
+    fn mirrors_tried_after_url() {
+        let page = Page::from_url_with_mirrors(
+            "https://a.example/1.jpg",
+            vec!["https://b.example/1.jpg".to_string(), "https://c.example/1.jpg".to_string()],
+            "jpg"
+        );
+        if let PageType::Url(online_page) = page.page_type {
+            assert_eq!(
+                online_page.urls().collect::<Vec<_>>(),
+                vec!["https://a.example/1.jpg", "https://b.example/1.jpg", "https://c.example/1.jpg"]
+            );
+        } else { unreachable!() }
+    }
+
+    #[test]
+    fn image_formats() {
+        assert_eq!(super::image_format(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00]), Some("jpg"));
+        assert_eq!(super::image_format(b"\x89PNG\r\n\x1A\n\x00\x00"), Some("png"));
+        assert_eq!(super::image_format(b"RIFF\x10\x00\x00\x00WEBPVP8 "), Some("webp"));
+        assert_eq!(super::image_format(b"\x00\x00\x00\x1CftypavifS"), Some("avif"));
+        assert_eq!(super::image_format(b"RIFF"), None);
+        assert_eq!(super::image_format(b"<html>"), None);
+    }
+
+    #[test]
+    fn changed_validators() {
+        use crate::metadata::PageValidator;
+        let validator = |etag: Option<&str>, last_modified: Option<&str>| PageValidator {
+            etag: etag.map(String::from),
+            last_modified: last_modified.map(String::from),
+        };
+        let old = validator(Some("\"a\""), Some("Sat, 01 Oct 2022 12:00:00 GMT"));
+        assert!(!super::validator_changed(&old, &old));
+        assert!(super::validator_changed(&old, &validator(Some("\"b\""), Some("Sat, 01 Oct 2022 12:00:00 GMT"))));
+        assert!(super::validator_changed(&old, &validator(None, Some("Sun, 02 Oct 2022 12:00:00 GMT"))));
+        assert!(!super::validator_changed(&old, &validator(None, None)));
+    }
+
+    #[test]
+    fn kmanga_descrambling() {
+        let order = super::kmanga_tile_order(12345);
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..16).collect::<Vec<u32>>());
+        // Each tile is colored with the position it belongs at
+        let scrambled = image::GrayImage::from_fn(70, 70, |x, y| {
+            if x >= 64 || y >= 64 {
+                return image::Luma([255]);
+            }
+            image::Luma([order[(y / 16 * 4 + x / 16) as usize] as u8])
+        });
+        let mut data = std::io::Cursor::new(Vec::new());
+        scrambled.write_to(&mut data, image::ImageFormat::Png).unwrap();
+        let descrambled = super::decrypt_page(data.into_inner(), &PageEncryptionScheme::KManga(12345), "").unwrap();
+        let descrambled = image::load_from_memory(&descrambled).unwrap().to_luma8();
+        for (x, y, pixel) in descrambled.enumerate_pixels() {
+            let expected = if x >= 64 || y >= 64 { 255 } else { (y / 16 * 4 + x / 16) as u8 };
+            assert_eq!(pixel.0[0], expected, "pixel at {}, {}", x, y);
+        }
+        assert!(super::decrypt_page(b"<html>".to_vec(), &PageEncryptionScheme::KManga(1), "").is_err());
+    }
+
+    #[test]
+    fn comicwalker_decryption() {
+        let encrypted = [0x01 ^ 0xff, 0x02 ^ 0xd8, 0x03 ^ 0xff, 0x04 ^ 0xe0, 0x05, 0x06 ^ 0x10, 0x07 ^ 0x4a, 0x08 ^ 0x46, 0x09 ^ 0xff];
+        assert_eq!(
+            super::decrypt_page(encrypted.to_vec(), &PageEncryptionScheme::ComicWalker("ffd8ffe000104a46aabbccdd".to_string()), "").unwrap(),
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 9]
+        );
+        for hash in ["ffd8ff", "ffd8ffe000104a4g"] {
+            assert!(super::decrypt_page(encrypted.to_vec(), &PageEncryptionScheme::ComicWalker(hash.to_string()), "").is_err());
+        }
     }
 }
@@ -1,20 +1,39 @@
 use crate::{
     error::GrawlixIOError as Error,
-    comic::{Comic, Page},
+    comic::{Comic, Page, PageType},
     metadata::Metadata
 };
 
-static IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+use std::{io::Read, path::{Path, PathBuf}};
+
+static IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "webp", "gif", "avif"];
+
+/// Extensions of comic archives that can be read
+static ARCHIVE_EXTENSIONS: [&str; 2] = ["cbz", "zip"];
 
 impl super::Comic {
 
     /// Create `Comic` object from file
     pub fn from_file(path: &str) -> Result<Self, Error> {
-        if path.ends_with(".cbz") || path.ends_with(".zip") {
+        let mut comic = if std::path::Path::new(path).is_dir() {
+            Self::from_dir(path)
+        } else if path.ends_with(".cbz") || path.ends_with(".zip") {
             Self::from_cbz_file(path)
         } else {
             Err(Error::UnknownFileType(path.to_string()))
-        }
+        }?;
+        comic.source_path = Some(PathBuf::from(path));
+        Ok(comic)
+    }
+
+    /// Open the file the comic was read from to read the pages stored in it. Archives are only
+    /// opened once, so reading every page does not parse the archive again for each page.
+    pub fn container_pages(&self) -> Result<ContainerPages<'_>, Error> {
+        let zip = match &self.source_path {
+            Some(path) if !path.is_dir() => Some(zip::ZipArchive::new(std::fs::File::open(path)?)?),
+            _ => None,
+        };
+        Ok(ContainerPages { comic: self, zip })
     }
 
     /// Create `Comic` object from cbz file
@@ -24,21 +43,251 @@ impl super::Comic {
         let mut zip = zip::ZipArchive::new(file)?;
         // Creating `Comic` object
         let mut comic = Comic::default();
+        let mut found_metadata = false;
         // Adding files
         for i in 0..zip.len() {
             let mut file = zip.by_index(i)?;
             let name = file.name().to_string();
-            let path = std::path::Path::new(file.name());
             // Add file as page
-            if let Some(ext) = path.extension() {
-                if IMAGE_EXTENSIONS.contains(&ext.to_str().unwrap()) {
-                    comic.pages.push(Page::from_filename(&name, &ext.to_str().unwrap()))
-                }
+            if let Some(ext) = image_extension(Path::new(&name)) {
+                comic.pages.push(Page::from_filename(&name, &ext))
             // Try creating metadata from file
             } else if let Some(metadata) = Metadata::from_metadata_file(&name, &mut file) {
                 comic.metadata = metadata;
+                found_metadata = true;
+            }
+        }
+        // Using ComicBookInfo from archive comment if there are no metadata files
+        if !found_metadata {
+            if let Some(metadata) = std::str::from_utf8(zip.comment()).ok().and_then(Metadata::from_comicbookinfo) {
+                comic.metadata = metadata;
             }
         }
         return Ok(comic);
     }
+
+    /// Create `Comic` object from directory
+    fn from_dir(path: &str) -> Result<Self, Error> {
+        let mut comic = Comic::default();
+        let mut entries = std::fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect::<Vec<_>>();
+        entries.sort();
+        for entry in entries {
+            let name = match entry.file_name().and_then(|x| x.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue
+            };
+            // Add file as page
+            if let Some(ext) = image_extension(&entry) {
+                comic.pages.push(Page::from_filename(&name, &ext));
+                continue;
+            }
+            // Try creating metadata from file
+            let file = std::fs::File::open(&entry)?;
+            if let Some(metadata) = Metadata::from_metadata_file(&name, file) {
+                comic.metadata = metadata;
+            }
+        }
+        return Ok(comic);
+    }
+}
+
+/// Pages stored in the file or directory a comic was read from
+pub struct ContainerPages<'a> {
+    comic: &'a Comic,
+    zip: Option<zip::ZipArchive<std::fs::File>>,
+}
+
+impl ContainerPages<'_> {
+    /// Read page at `index` (0 indexed). Pages in cbz files are decompressed into memory, pages in
+    /// directories are read directly from disk.
+    pub fn read(&mut self, index: usize) -> Result<Vec<u8>, Error> {
+        let page = self.comic.pages.get(index).map(|x| &x.page_type);
+        let (name, path) = match (page, &self.comic.source_path) {
+            (Some(PageType::Container(name)), Some(path)) => (name, path),
+            _ => return Err(Error::PageNotInContainer(index + 1)),
+        };
+        let mut data = Vec::new();
+        match &mut self.zip {
+            Some(zip) => zip.by_name(name)?.read_to_end(&mut data)?,
+            None => std::fs::File::open(path.join(name))?.read_to_end(&mut data)?,
+        };
+        Ok(data)
+    }
+}
+
+/// Find paths of all comics matching `pattern`. The pattern can be a file, a directory, or a glob
+/// pattern with `*`, `?`, and `**` for any number of directories. Directories that are not comics
+/// themselves are searched recursively.
+pub fn find_comics(pattern: &str) -> Result<Vec<String>, Error> {
+    let components: Vec<&str> = pattern.split('/').collect();
+    let first_wildcard = components.iter().position(|x| x.contains(['*', '?']));
+    let mut comics = Vec::new();
+    match first_wildcard {
+        None => find_comics_in(Path::new(pattern), &mut comics)?,
+        Some(n) => {
+            let base = if n == 0 { ".".to_string() } else { components[..n].join("/") };
+            let base = if base.is_empty() { "/".to_string() } else { base };
+            let mut entries = Vec::new();
+            walk(Path::new(&base), &mut entries)?;
+            for entry in entries {
+                let relative = match entry.strip_prefix(&base) {
+                    Ok(relative) => relative,
+                    Err(_) => continue,
+                };
+                let parts: Vec<&str> = relative.iter().filter_map(|x| x.to_str()).collect();
+                if match_components(&components[n..], &parts) {
+                    find_comics_in(&entry, &mut comics)?;
+                }
+            }
+            // Comics can be found both from a matching directory and the files in it
+            let mut seen = std::collections::HashSet::new();
+            comics.retain(|x| seen.insert(x.clone()));
+        }
+    }
+    Ok(comics)
+}
+
+/// Add `path` to `comics` if it is a comic, or all comics in it if it is a directory
+fn find_comics_in(path: &Path, comics: &mut Vec<String>) -> Result<(), Error> {
+    if path.is_dir() {
+        if is_comic_dir(path)? {
+            comics.push(path.to_string_lossy().to_string());
+            return Ok(());
+        }
+        let mut entries: Vec<PathBuf> = std::fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect();
+        entries.sort();
+        for entry in entries {
+            find_comics_in(&entry, comics)?;
+        }
+    } else if has_extension(path, &ARCHIVE_EXTENSIONS) {
+        comics.push(path.to_string_lossy().to_string());
+    }
+    Ok(())
+}
+
+/// Returns true if directory contains images or metadata files directly
+fn is_comic_dir(path: &Path) -> Result<bool, Error> {
+    Ok(std::fs::read_dir(path)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .any(|x| x.is_file() && (
+            has_extension(&x, &IMAGE_EXTENSIONS)
+            || x.file_name().and_then(|x| x.to_str()).map(|x| x == "grawlix.json" || x == "comicinfo.xml").unwrap_or(false)
+        )))
+}
+
+/// Lowercase extension of `path` if it is an image
+fn image_extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|x| x.to_str())
+        .map(|x| x.to_lowercase())
+        .filter(|x| IMAGE_EXTENSIONS.contains(&x.as_str()))
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|x| x.to_str())
+        .map(|x| extensions.contains(&x.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Add all files and directories in `dir` recursively to `entries`
+fn walk(dir: &Path, entries: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let mut children: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect();
+    children.sort();
+    for child in children {
+        entries.push(child.clone());
+        if child.is_dir() {
+            walk(&child, entries)?;
+        }
+    }
+    Ok(())
+}
+
+/// Match path components against glob pattern components
+fn match_components(pattern: &[&str], path: &[&str]) -> bool {
+    match (pattern.first(), path.first()) {
+        (None, None) => true,
+        (Some(&"**"), _) => {
+            match_components(&pattern[1..], path)
+                || (!path.is_empty() && match_components(pattern, &path[1..]))
+        },
+        (Some(p), Some(name)) => wildcard_match(p, name) && match_components(&pattern[1..], &path[1..]),
+        _ => false,
+    }
+}
+
+/// Match name against pattern with `*` and `?` wildcards
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    fn matches(p: &[char], n: &[char]) -> bool {
+        match (p.first(), n.first()) {
+            (None, None) => true,
+            (Some('*'), _) => matches(&p[1..], n) || (!n.is_empty() && matches(p, &n[1..])),
+            (Some('?'), Some(_)) => matches(&p[1..], &n[1..]),
+            (Some(a), Some(b)) => a == b && matches(&p[1..], &n[1..]),
+            _ => false,
+        }
+    }
+    matches(&pattern, &name)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn glob_matching() {
+        assert!(super::wildcard_match("*.cbz", "Moon Knight #1.cbz"));
+        assert!(super::wildcard_match("issue ?.cbz", "issue 1.cbz"));
+        assert!(!super::wildcard_match("*.cbz", "issue.zip"));
+        assert!(super::match_components(&["**", "*.cbz"], &["Marvel", "Moon Knight", "1.cbz"]));
+        assert!(super::match_components(&["**", "*.cbz"], &["1.cbz"]));
+        assert!(!super::match_components(&["*", "*.cbz"], &["Marvel", "Moon Knight", "1.cbz"]));
+    }
+
+    #[test]
+    fn image_pages() {
+        let dir = std::env::temp_dir().join(format!("grawlix-pages-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["1.webp", "2.AVIF", "3.jpg", "notes.txt"] {
+            std::fs::write(dir.join(name), b"page").unwrap();
+        }
+        let comic = crate::comic::Comic::from_file(dir.to_str().unwrap()).unwrap();
+        assert_eq!(comic.container_pages().unwrap().read(2).unwrap(), b"page");
+        std::fs::remove_dir_all(&dir).unwrap();
+        let formats: Vec<&str> = comic.pages.iter().map(|x| x.file_format.as_str()).collect();
+        assert_eq!(formats, vec!["webp", "avif", "jpg"]);
+    }
+
+    #[test]
+    fn cbz_metadata() {
+        use std::io::Write;
+        let path = std::env::temp_dir().join(format!("grawlix-read-{}.cbz", std::process::id()));
+        {
+            let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+            let options = zip::write::FileOptions::default();
+            zip.start_file("0.jpg", options).unwrap();
+            zip.write_all(b"page").unwrap();
+            zip.start_file("comicinfo.xml", options).unwrap();
+            zip.write_all(b"<ComicInfo><Series>Moon Knight</Series><Number>1</Number></ComicInfo>").unwrap();
+            zip.finish().unwrap();
+        }
+        let comic = crate::comic::Comic::from_file(path.to_str().unwrap()).unwrap();
+        let mut pages = comic.container_pages().unwrap();
+        assert_eq!(pages.read(0).unwrap(), b"page");
+        assert!(pages.read(1).is_err());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(comic.pages.len(), 1);
+        assert_eq!(comic.metadata.series.as_deref(), Some("Moon Knight"));
+        assert_eq!(comic.metadata.issue_number, Some(1));
+    }
 }
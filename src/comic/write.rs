@@ -1,50 +1,341 @@
 use crate::error::GrawlixIOError as Error;
-use super::{Comic, ComicFormat, PageType};
+use crate::metadata::{MetadataFormat, PageValidator, DEFAULT_METADATA_FORMATS};
+use super::{Comic, ComicFormat, PageFilter, PageType, filter::FilterState, page::image_format};
 use std::{
-    io::prelude::Write,
+    borrow::Cow,
+    collections::HashSet,
+    io::{Seek, prelude::Write},
     path::{Path, PathBuf},
+    sync::Mutex,
 };
 
 use reqwest::Client;
 
+/// Default template for file names of pages
+pub const DEFAULT_PAGE_TEMPLATE: &str = "{title} #{page:03}.{ext}";
+
+/// Options for how pages are written
+#[derive(Clone, Debug)]
+pub struct WriteOptions {
+    /// Pages removed when writing comics
+    pub page_filter: PageFilter,
+    /// Template for file names of pages. Has the fields `page` and `ext` in addition to the
+    /// normal template fields.
+    pub page_template: String,
+    /// Number of the first page in file names
+    pub first_page: u32,
+    /// Metadata formats written to comics
+    pub metadata_formats: Vec<MetadataFormat>,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            page_filter: PageFilter::default(),
+            page_template: DEFAULT_PAGE_TEMPLATE.to_string(),
+            first_page: 0,
+            metadata_formats: DEFAULT_METADATA_FORMATS.to_vec(),
+        }
+    }
+}
+
+/// Output paths that are being written. Bad metadata can make different comics format to the
+/// same path, which corrupts the output when they are written at the same time.
+struct PathLocks {
+    /// Path and title of comic being written to it
+    paths: Mutex<Vec<(PathBuf, String)>>,
+}
+
+/// Paths being written by `Comic::write_with_options`
+static PATH_LOCKS: PathLocks = PathLocks::new();
+
+/// Claim on an output path. The path is released when dropped.
+struct PathLock<'a> {
+    locks: &'a PathLocks,
+    path: PathBuf,
+}
+
+impl PathLocks {
+    const fn new() -> Self {
+        Self { paths: Mutex::new(Vec::new()) }
+    }
+
+    /// Claim `path` for writing comic with `title`. Fails if another comic is being written to
+    /// the same path.
+    fn lock(&self, path: &str, title: &str) -> Result<PathLock<'_>, Error> {
+        let relative = PathBuf::from(sanitize_path(path));
+        let key = match std::env::current_dir() {
+            Ok(dir) => dir.join(relative),
+            Err(_) => relative,
+        };
+        let mut paths = self.paths.lock().unwrap();
+        if let Some((_, other)) = paths.iter().find(|(x, _)| *x == key) {
+            return Err(Error::PathCollision {
+                path: path.to_string(),
+                title: title.to_string(),
+                other: other.clone(),
+            });
+        }
+        paths.push((key.clone(), title.to_string()));
+        Ok(PathLock { locks: self, path: key })
+    }
+}
+
+impl Drop for PathLock<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut paths) = self.locks.paths.lock() {
+            paths.retain(|(x, _)| *x != self.path);
+        }
+    }
+}
+
 impl Comic {
 
     /// Write comic book to disk
-    pub async fn write(&self, path: &str, comic_format: &ComicFormat, client: &Client) -> Result<(), Error> {
+    pub async fn write(&self, path: &str, comic_format: &ComicFormat, client: &Client) -> crate::Result<()> {
+        self.write_with_options(path, comic_format, client, &WriteOptions::default()).await
+    }
+
+    /// Write comic book to disk with custom page names and filters
+    pub async fn write_with_options(&self, path: &str, comic_format: &ComicFormat, client: &Client, options: &WriteOptions) -> crate::Result<()> {
+        let _lock = PATH_LOCKS.lock(path, self.title())?;
         let mut comic_file = new_comic_file(&path, comic_format)?;
+        self.write_to_with_options(comic_file.as_mut(), client, options).await
+    }
+
+    /// Write comic book next to `path` and move it into place when finished, so an existing file
+    /// is only replaced by a complete comic. `replace` is called with the path of the new file
+    /// before an existing file is replaced and the new file is removed if it returns false.
+    /// Returns true if the comic was moved to `path`.
+    pub async fn write_replacing<F, E>(&self, path: &str, comic_format: &ComicFormat, client: &Client, options: &WriteOptions, replace: F) -> Result<bool, E>
+    where
+        F: FnOnce(&str) -> Result<bool, E>,
+        E: From<crate::Error>,
+    {
+        let _lock = PATH_LOCKS.lock(path, self.title()).map_err(crate::Error::from)?;
+        let partial = format!("{}.part", path);
+        let target = output_path(path).map_err(crate::Error::from)?;
+        let partial_path = output_path(&partial).map_err(crate::Error::from)?;
+        remove_path(&partial_path).map_err(crate::Error::from)?;
+        let written = async {
+            let mut comic_file = new_comic_file(&partial, comic_format)?;
+            self.write_to_with_options(comic_file.as_mut(), client, options).await?;
+            // Closing the file before it is moved
+            drop(comic_file);
+            Ok::<_, crate::Error>(())
+        }.await;
+        let moved = match written {
+            Ok(()) if !target.exists() => Ok(true),
+            Ok(()) => replace(&partial_path.to_string_lossy()),
+            Err(e) => Err(e.into()),
+        };
+        let moved = match moved {
+            Ok(moved) => moved,
+            Err(e) => {
+                remove_path(&partial_path).ok();
+                return Err(e);
+            },
+        };
+        if moved {
+            // Directories can not be renamed over existing directories
+            if target.is_dir() {
+                remove_path(&target).map_err(crate::Error::from)?;
+            }
+            std::fs::rename(&partial_path, &target).map_err(|e| crate::Error::from(Error::from(e)))?;
+        } else {
+            remove_path(&partial_path).map_err(crate::Error::from)?;
+        }
+        Ok(moved)
+    }
+
+    /// Write comic book to a custom output container. Pages are downloaded with `client` so it
+    /// should be the authenticated client of the source the comic came from.
+    pub async fn write_to(&self, comic_file: &mut dyn ComicFile, client: &Client) -> crate::Result<()> {
+        self.write_to_with_options(comic_file, client, &WriteOptions::default()).await
+    }
+
+    /// Write comic book as cbz to `writer`, for example a buffer or the body of a response,
+    /// without touching the disk. The writer is returned when the archive is finished.
+    pub async fn write_cbz_to<W>(&self, writer: W, client: &Client, options: &WriteOptions) -> crate::Result<W>
+    where
+        W: Write + Seek + Send
+    {
+        let mut comic_file = ZipComic::new(writer, self.title());
+        self.write_to_with_options(&mut comic_file, client, options).await?;
+        Ok(comic_file.finished.expect("Archive is finished after writing"))
+    }
+
+    /// Write comic book to a custom output container with custom page names and filters
+    pub async fn write_to_with_options(&self, comic_file: &mut dyn ComicFile, client: &Client, options: &WriteOptions) -> crate::Result<()> {
+        let filter = &options.page_filter;
+        let mut filter_state = FilterState::default();
+        let mut removed = 0;
+        // Validators of online pages in the order of the page urls in provenance
+        let mut validators = Vec::new();
+        // Pages of comics read from a file are copied from it
+        let mut container = match self.source_path {
+            Some(_) => Some(self.container_pages()?),
+            None => None,
+        };
         for (n, page) in self.pages.iter().enumerate() {
             // Getting page data
             let page_data = match &page.page_type {
-                // TODO Remove unwraps
-                // Download page
-                PageType::Url(x) => x.download_page(&client).await,
-                    // client.get(url)
-                    //     .send().await.unwrap()
-                    //     .bytes().await.unwrap(),
-                // PageType::UrlWithHeaders(url, headers) =>
-                //     client.get(url)
-                //         .headers(headers.try_into().unwrap())
-                //         .send().await.unwrap()
-                //         .bytes().await.unwrap(),
-                // Skipping rewriting pages already stored in file
-                PageType::Container(_) => continue,
+                PageType::Url(x) => match filter.check_url(&x.url) {
+                    Some(reason) => {
+                        log::info!("Removed page {} from {} ({})", n + 1, self.title(), reason);
+                        removed += 1;
+                        validators.push(PageValidator::default());
+                        continue;
+                    },
+                    // Download page
+                    None => {
+                        let (data, validator) = x.download_page_with_validator(&client).await?;
+                        validators.push(validator);
+                        data
+                    },
+                },
+                PageType::Container(_) => match &mut container {
+                    Some(pages) => pages.read(n)?,
+                    // Skipping rewriting pages already stored in file
+                    None => continue,
+                },
+            };
+            if let Some(reason) = filter.check(&page_data, &mut filter_state) {
+                log::info!("Removed page {} from {} ({})", n + 1, self.title(), reason);
+                removed += 1;
+                continue;
+            }
+            // The format given by the source is used if the downloaded data is not recognized
+            let file_format = match image_format(&page_data) {
+                Some(format) => {
+                    if !same_format(format, &page.file_format) {
+                        log::debug!("Page {} of {} is {} instead of {}", n + 1, self.title(), format, page.file_format);
+                    }
+                    format
+                },
+                None => &page.file_format,
             };
-            let filename = format!("{} #{:0>3}.{}", self.title(), n, &page.file_format);
+            let number = (n - removed) as u32 + options.first_page;
+            let filename = self.format_page_name(&options.page_template, number, file_format)?;
             comic_file.write_file(&page_data, &filename)?;
         }
-        for (name, data) in self.metadata.export_all()? {
+        let mut metadata = Cow::Borrowed(&self.metadata);
+        if self.metadata.provenance.is_some() && validators.iter().any(|x| !x.is_empty()) {
+            if let Some(provenance) = &mut metadata.to_mut().provenance {
+                provenance.page_validators = validators;
+            }
+        }
+        for (name, data) in metadata.export(&options.metadata_formats)? {
             comic_file.write_file(&data.as_bytes(), name)?;
         }
+        // ComicBookInfo includes provenance, so both fit in the one comment of the archive
+        if options.metadata_formats.contains(&MetadataFormat::ComicBookInfo) {
+            comic_file.set_comment(&self.metadata.to_comicbookinfo()?);
+        } else if let Some(provenance) = &self.metadata.provenance {
+            comic_file.set_comment(&provenance.to_comment());
+        }
         comic_file.finish()?;
         Ok(())
     }
 
 }
 
+/// Returns true if file extensions `a` and `b` are the same image format
+fn same_format(a: &str, b: &str) -> bool {
+    let normalize = |x: &str| if x.eq_ignore_ascii_case("jpeg") { "jpg".to_string() } else { x.to_lowercase() };
+    normalize(a) == normalize(b)
+}
+
+/// Characters that are not allowed in file names on Windows
+const WINDOWS_RESERVED_CHARS: [char; 7] = ['<', '>', ':', '"', '|', '?', '*'];
+
+/// File names that are not allowed on Windows
+const WINDOWS_RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Replace characters in path that are not allowed on the current OS
+pub fn sanitize_path(path: &str) -> String {
+    sanitize_path_for_os(path, cfg!(windows))
+}
+
+fn sanitize_path_for_os(path: &str, windows: bool) -> String {
+    if !windows {
+        return path.replace('\0', "_");
+    }
+    path.split(['/', '\\'])
+        .enumerate()
+        .map(|(n, component)| {
+            // Keep drive letter of absolute paths
+            if n == 0 && component.len() == 2 && component.ends_with(':') {
+                return component.to_string();
+            }
+            sanitize_windows_component(component)
+        })
+        .collect::<Vec<String>>()
+        .join("\\")
+}
+
+/// Make single path component valid on Windows
+fn sanitize_windows_component(component: &str) -> String {
+    if component == "." || component == ".." {
+        return component.to_string();
+    }
+    let mut sanitized: String = component.chars()
+        .map(|c| if WINDOWS_RESERVED_CHARS.contains(&c) || c.is_control() { '_' } else { c })
+        .collect();
+    // Trailing dots and spaces are removed by Windows
+    while sanitized.ends_with('.') || sanitized.ends_with(' ') {
+        sanitized.pop();
+    }
+    let stem = sanitized.split('.').next().unwrap_or("");
+    if WINDOWS_RESERVED_NAMES.iter().any(|name| name.eq_ignore_ascii_case(stem)) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+/// Add `\\?\` prefix to long paths on Windows to get around the MAX_PATH limit
+#[cfg(windows)]
+fn long_path(path: PathBuf) -> Result<PathBuf, Error> {
+    if path.as_os_str().len() < 260 {
+        return Ok(path);
+    }
+    let absolute = if path.is_absolute() {
+        path
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    Ok(PathBuf::from(format!(r"\\?\{}", absolute.display())))
+}
+
+#[cfg(not(windows))]
+fn long_path(path: PathBuf) -> Result<PathBuf, Error> {
+    Ok(path)
+}
+
+/// Location comic written to `path` is stored at
+fn output_path(path: &str) -> Result<PathBuf, Error> {
+    long_path(PathBuf::from(sanitize_path(path)))
+}
+
+/// Remove file or directory at `path` if it exists
+fn remove_path(path: &Path) -> Result<(), Error> {
+    if path.is_dir() {
+        std::fs::remove_dir_all(path)?;
+    } else if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
 /// Create new output container for comic
 fn new_comic_file(path_str: &str, format: &ComicFormat) -> Result<Box<dyn ComicFile>, Error> {
     // Finding path
-    let path = Path::new(path_str);
+    let path_buf = output_path(path_str)?;
+    let path = path_buf.as_path();
     // Creating parent dir if it does not exist
     let parent = path.parent().ok_or(Error::InvalidLocation(path_str.to_string()))?;
     if !parent.exists() {
@@ -53,52 +344,116 @@ fn new_comic_file(path_str: &str, format: &ComicFormat) -> Result<Box<dyn ComicF
     Ok(match format {
         ComicFormat::CBZ => {
             let file = std::fs::File::create(&path)?;
-            let zip = zip::ZipWriter::new(file);
-            let options = zip::write::FileOptions::default()
-                .compression_method(zip::CompressionMethod::Stored);
-            Box::new(ZipComic {zip, options})
+            Box::new(ZipComic::new(file, path_str))
         },
         ComicFormat::Dir => {
             std::fs::create_dir_all(path)?;
-            Box::new(DirComic { dir: path.to_path_buf() })
+            Box::new(DirComic { dir: path.to_path_buf(), names: HashSet::new() })
         }
     })
 }
 
 /// Specifies an output container a comic can be written to
-trait ComicFile {
+pub trait ComicFile: Send {
     /// Write file to container
     fn write_file(&mut self, data: &[u8], name: &str) -> Result<(), Error>;
+    /// Set comment of container. Ignored by containers without support for comments.
+    fn set_comment(&mut self, _comment: &str) {}
     /// Finish writing to container
     fn finish(&mut self) -> Result<(), Error>;
 }
 
+/// Returns `name` if it has not been used before, otherwise a suffix is added before the file
+/// extension until the name is unique. The returned name is added to `names`.
+fn unique_name(names: &mut HashSet<String>, name: &str) -> String {
+    let (stem, extension) = match name.rfind('.') {
+        Some(i) if i > 0 => name.split_at(i),
+        _ => (name, ""),
+    };
+    let mut unique = name.to_string();
+    let mut n = 1;
+    while names.contains(&unique) {
+        unique = format!("{} ({}){}", stem, n, extension);
+        n += 1;
+    }
+    names.insert(unique.clone());
+    unique
+}
+
+/// Largest number of entries in a zip archive without zip64
+const ZIP64_ENTRIES: usize = u16::MAX as usize;
+
+/// Largest size of a file or archive without zip64
+const ZIP64_BYTES: u64 = u32::MAX as u64;
+
+/// Returns true if an archive with `entries` files and `bytes` of file data is written with zip64
+/// extensions. Long series combined into volumes can get there.
+fn needs_zip64(entries: usize, bytes: u64) -> bool {
+    entries > ZIP64_ENTRIES || bytes > ZIP64_BYTES
+}
+
 /// Zip formatted comic book output
-struct ZipComic {
-    zip: zip::ZipWriter<std::fs::File>,
+struct ZipComic<W: Write + Seek> {
+    zip: zip::ZipWriter<W>,
     options: zip::write::FileOptions,
+    names: HashSet<String>,
+    /// Output path used in warnings
+    path: String,
+    /// Size of all written files
+    bytes: u64,
+    /// Writer returned when the archive is finished
+    finished: Option<W>,
+}
+
+impl<W: Write + Seek> ZipComic<W> {
+    fn new(writer: W, path: &str) -> Self {
+        Self {
+            zip: zip::ZipWriter::new(writer),
+            options: zip::write::FileOptions::default()
+                .compression_method(zip::CompressionMethod::Stored),
+            names: HashSet::new(),
+            path: path.to_string(),
+            bytes: 0,
+            finished: None,
+        }
+    }
 }
 
-impl ComicFile for ZipComic {
+impl<W: Write + Seek + Send> ComicFile for ZipComic<W> {
     fn write_file(&mut self, data: &[u8], name: &str) -> Result<(), Error> {
-        self.zip.start_file(name, self.options)?;
+        let name = unique_name(&mut self.names, name);
+        // Offsets and the number of entries are switched to zip64 automatically when finishing
+        // the archive, but single files larger than 4GB have to be marked before writing
+        let options = self.options.large_file(data.len() as u64 > ZIP64_BYTES);
+        self.zip.start_file(name, options)?;
         self.zip.write_all(data)?;
+        self.bytes += data.len() as u64;
         Ok(())
     }
+    fn set_comment(&mut self, comment: &str) {
+        self.zip.set_comment(comment);
+    }
     fn finish(&mut self) -> Result<(), Error> {
-        self.zip.finish()?;
+        self.finished = Some(self.zip.finish()?);
+        if needs_zip64(self.names.len(), self.bytes) {
+            log::warn!(
+                "{} has {} files and is {} MB, so it is written as zip64. Some comic readers can not open it",
+                self.path, self.names.len(), self.bytes / 1_000_000
+            );
+        }
         Ok(())
     }
 }
 
 /// Write comic files to a directory
 struct DirComic {
-    dir: PathBuf
+    dir: PathBuf,
+    names: HashSet<String>,
 }
 
 impl ComicFile for DirComic {
     fn write_file(&mut self, data: &[u8], name: &str) -> Result<(), Error> {
-        let file_path = &self.dir.join(name);
+        let file_path = &self.dir.join(unique_name(&mut self.names, name));
         let mut file = std::fs::File::create(&file_path)?;
         file.write_all(data)?;
         Ok(())
@@ -108,3 +463,134 @@ impl ComicFile for DirComic {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ComicFile, PathLocks};
+    use crate::{comic::{Comic, ComicFormat, Page}, error::GrawlixIOError};
+
+    /// Stores names of written files
+    #[derive(Default)]
+    struct FileNames(Vec<String>);
+
+    impl ComicFile for FileNames {
+        fn write_file(&mut self, _data: &[u8], name: &str) -> Result<(), GrawlixIOError> {
+            self.0.push(name.to_string());
+            Ok(())
+        }
+        fn finish(&mut self) -> Result<(), GrawlixIOError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn windows_paths() {
+        assert_eq!(
+            super::sanitize_path_for_os("Marvel/Spider-Man: Blue/Issue #1?.cbz", true),
+            "Marvel\\Spider-Man_ Blue\\Issue #1_.cbz"
+        );
+        assert_eq!(
+            super::sanitize_path_for_os("C:/Comics/Con/Aux.cbz", true),
+            "C:\\Comics\\_Con\\_Aux.cbz"
+        );
+        assert_eq!(
+            super::sanitize_path_for_os("Marvel/Spider-Man: Blue/Issue #1?.cbz", false),
+            "Marvel/Spider-Man: Blue/Issue #1?.cbz"
+        );
+    }
+
+    #[test]
+    fn unique_names() {
+        let mut names = std::collections::HashSet::new();
+        assert_eq!(super::unique_name(&mut names, "page.jpg"), "page.jpg");
+        assert_eq!(super::unique_name(&mut names, "page.jpg"), "page (1).jpg");
+        assert_eq!(super::unique_name(&mut names, "page.jpg"), "page (2).jpg");
+        assert_eq!(super::unique_name(&mut names, "README"), "README");
+        assert_eq!(super::unique_name(&mut names, "README"), "README (1)");
+    }
+
+    #[tokio::test]
+    async fn write_to_custom_container() {
+        let mut comic = Comic::new();
+        comic.pages = vec![Page::from_filename("page.jpg", "jpg")];
+        let mut output = FileNames::default();
+        comic.write_to(&mut output, &reqwest::Client::new()).await.unwrap();
+        assert_eq!(output.0, vec!["comicinfo.xml", "details.json", "grawlix.json"]);
+    }
+
+    #[tokio::test]
+    async fn write_pages_from_file() {
+        let dir = std::env::temp_dir().join(format!("grawlix-rewrite-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("1.jpg"), b"page").unwrap();
+        let comic = Comic::from_file(dir.to_str().unwrap()).unwrap();
+        let mut output = FileNames::default();
+        comic.write_to(&mut output, &reqwest::Client::new()).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(output.0.len(), 4);
+        assert!(output.0[0].ends_with(".jpg"));
+    }
+
+    #[tokio::test]
+    async fn failed_replace_removes_partial_file() {
+        let path = std::env::temp_dir().join(format!("grawlix-replace-{}.cbz", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, b"old").unwrap();
+        let result = Comic::new()
+            .write_replacing(path, &ComicFormat::CBZ, &reqwest::Client::new(), &Default::default(), |_| {
+                Err(crate::Error::from(GrawlixIOError::InvalidLocation(path.to_string())))
+            })
+            .await;
+        assert!(result.is_err());
+        assert!(!std::path::Path::new(&format!("{}.part", path)).exists());
+        assert_eq!(std::fs::read(path).unwrap(), b"old");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn write_cbz_to_buffer() {
+        let mut comic = Comic::new();
+        comic.metadata.title = Some("Moon Knight #1".to_string());
+        let buffer = comic.write_cbz_to(std::io::Cursor::new(Vec::new()), &reqwest::Client::new(), &Default::default())
+            .await
+            .unwrap();
+        let zip = zip::ZipArchive::new(buffer).unwrap();
+        let mut names: Vec<&str> = zip.file_names().collect();
+        names.sort();
+        assert_eq!(names, vec!["comicinfo.xml", "details.json", "grawlix.json"]);
+    }
+
+    #[test]
+    fn zip64_archives() {
+        use std::io::Read;
+        assert!(!super::needs_zip64(100, 1_000_000));
+        assert!(super::needs_zip64(70_000, 1_000_000));
+        assert!(super::needs_zip64(100, 5_000_000_000));
+        // More entries than fit in a zip without zip64
+        let path = std::env::temp_dir().join(format!("grawlix-zip64-{}.cbz", std::process::id()));
+        let mut output = super::new_comic_file(path.to_str().unwrap(), &ComicFormat::CBZ).unwrap();
+        for n in 0..70_000 {
+            output.write_file(n.to_string().as_bytes(), &format!("{}.txt", n)).unwrap();
+        }
+        output.finish().unwrap();
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(zip.len(), 70_000);
+        let mut last = String::new();
+        zip.by_name("69999.txt").unwrap().read_to_string(&mut last).unwrap();
+        assert_eq!(last, "69999");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn path_collisions() {
+        let locks = PathLocks::new();
+        let first = locks.lock("Moon Knight/Moon Knight #1.cbz", "Moon Knight #1").unwrap();
+        match locks.lock("./Moon Knight/Moon Knight #1.cbz", "Moon Knight #2") {
+            Err(GrawlixIOError::PathCollision { other, .. }) => assert_eq!(other, "Moon Knight #1"),
+            _ => panic!("Collision not detected"),
+        }
+        assert!(locks.lock("Moon Knight/Moon Knight #2.cbz", "Moon Knight #2").is_ok());
+        drop(first);
+        assert!(locks.lock("Moon Knight/Moon Knight #1.cbz", "Moon Knight #2").is_ok());
+    }
+}
@@ -25,15 +25,31 @@ pub enum GrawlixIOError {
     Zip(#[from] zip::result::ZipError),
     /// Could not format comic. Error at index {0} in template: {1}
     StringFormat(usize, String),
+    /// Unknown field {0} in template: {1}
+    UnknownTemplateField(String, String),
+    /// Field {0} is missing from comic in template: {1}
+    MissingTemplateField(String, String),
     /// Could not recognize filetype of {0}
     UnknownFileType(String),
+    /// Page {0} is not stored in a comic file
+    PageNotInContainer(usize),
+    /// Could not write {title} to {path}: {other} is being written to the same path
+    PathCollision { path: String, title: String, other: String },
 }
 
 #[derive(Debug, Error, Display)]
 /// Error for download related problems
 pub enum GrawlixDownloadError {
+    /// Downloading metadata is not supported on {0}
+    MetadataNotSupported(String),
     /// Downloading pages of comic book is not supported on {0}
     PagesNotSupported(String),
+    /// Finding latest comics is not supported on {0}
+    LatestNotSupported(String),
+    /// Listing owned comics is not supported on {0}
+    OwnedNotSupported(String),
+    /// Series metadata is not supported on {0}
+    SeriesInfoNotSupported(String),
     /// Failed to authenticate with {0}
     FailedAuthentication(String),
     /// Failed to download from {0}
@@ -44,6 +60,16 @@ pub enum GrawlixDownloadError {
     UrlNotSupported(String),
     /// Invalid source name: {0}
     InvalidSourceName(String),
+    /// {0} is not a series
+    NotASeries(String),
+    /// {0} is not an issue
+    NotAnIssue(String),
+    /// Could not find id of {0}
+    UnresolvedId(String),
     /// Failed to parse response
     FailedResponseParse,
+    /// Could not decrypt page from {0}
+    FailedDecryption(String),
+    /// Request to {url} timed out
+    Timeout { url: String },
 }
@@ -1,7 +1,11 @@
+pub mod client;
 pub mod comic;
 pub mod error;
 pub mod metadata;
+pub mod options;
 pub mod source;
 
+pub use client::GrawlixClient;
 pub use error::GrawlixError as Error;
+pub use options::{DownloadOptions, OverwritePolicy};
 pub type Result<T> = std::result::Result<T, error::GrawlixError>;
@@ -0,0 +1,160 @@
+use super::Metadata;
+use crate::error::GrawlixIOError as Error;
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// File format of catalogs listing multiple comics
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CatalogFormat {
+    /// Comma separated values with one comic per line
+    #[default]
+    Csv,
+    /// Json list of metadata
+    Json,
+    /// Markdown table with one comic per row
+    Markdown,
+}
+
+impl FromStr for CatalogFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            "md" | "markdown" => Ok(Self::Markdown),
+            _ => Err("Could not parse catalog format (Either csv, json, or md)")
+        }
+    }
+}
+
+impl CatalogFormat {
+    /// File extension of format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+            Self::Markdown => "md",
+        }
+    }
+}
+
+/// Columns of csv and markdown catalogs
+const COLUMNS: [&str; 10] = [
+    "series", "issue", "volume", "title", "date", "publisher", "authors", "language", "source",
+    "identifiers",
+];
+
+/// Quote field if it contains characters with special meaning in csv
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Create csv line from `fields`
+fn csv_line<S: AsRef<str>>(fields: &[S]) -> String {
+    fields.iter()
+        .map(|x| escape_csv(x.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Create markdown table row from `fields`
+fn markdown_line<S: AsRef<str>>(fields: &[S]) -> String {
+    let fields: Vec<String> = fields.iter()
+        .map(|x| x.as_ref().replace('|', "\\|").replace('\n', " "))
+        .collect();
+    format!("| {} |", fields.join(" | "))
+}
+
+/// Format `rows` as a csv or markdown table. Returns `None` if `format` is not a table format.
+pub fn format_table<S: AsRef<str>>(columns: &[&str], rows: &[Vec<S>], format: CatalogFormat) -> Option<String> {
+    let line: fn(&[String]) -> String = match format {
+        CatalogFormat::Csv => csv_line,
+        CatalogFormat::Markdown => markdown_line,
+        CatalogFormat::Json => return None,
+    };
+    let columns: Vec<String> = columns.iter().map(|x| x.to_string()).collect();
+    let mut output = line(&columns);
+    output.push('\n');
+    if format == CatalogFormat::Markdown {
+        output.push_str(&line(&vec!["---".to_string(); columns.len()]));
+        output.push('\n');
+    }
+    for row in rows {
+        let row: Vec<String> = row.iter().map(|x| x.as_ref().to_string()).collect();
+        output.push_str(&line(&row));
+        output.push('\n');
+    }
+    Some(output)
+}
+
+/// Fields of `metadata` in the order of `COLUMNS`
+fn fields(metadata: &Metadata) -> Vec<String> {
+    let optional = |x: Option<u32>| x.map(|x| x.to_string()).unwrap_or_default();
+    vec![
+        metadata.series.clone().unwrap_or_default(),
+        optional(metadata.issue_number),
+        optional(metadata.volume),
+        metadata.title.clone().unwrap_or_default(),
+        metadata.date().or_else(|| metadata.year.map(|x| x.to_string())).unwrap_or_default(),
+        metadata.publisher.clone().unwrap_or_default(),
+        metadata.authors.iter().map(|x| x.name.as_str()).collect::<Vec<_>>().join("; "),
+        metadata.language.clone().unwrap_or_default(),
+        metadata.source.clone().unwrap_or_default(),
+        metadata.identifiers.iter()
+            .map(|x| format!("{}:{}", x.source, x.id))
+            .collect::<Vec<_>>()
+            .join("; "),
+    ]
+}
+
+/// Export catalog of `metadata` in `format`
+pub fn export(metadata: &[Metadata], format: CatalogFormat) -> Result<String, Error> {
+    let rows: Vec<Vec<String>> = metadata.iter().map(fields).collect();
+    match format_table(&COLUMNS, &rows, format) {
+        Some(table) => Ok(table),
+        None => serde_json::to_string_pretty(metadata)
+            .or(Err(Error::MetadataExport("catalog".to_string()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CatalogFormat, export, csv_line, format_table};
+    use crate::metadata::{Author, AuthorType, Metadata};
+
+    #[test]
+    fn csv_catalog() {
+        let metadata = Metadata {
+            title: Some("Issue \"1\"".to_string()),
+            series: Some("Hawkeye, Vol. 1".to_string()),
+            issue_number: Some(1),
+            year: Some(2012),
+            authors: vec![
+                Author { name: "Matt Fraction".to_string(), author_type: AuthorType::Writer },
+                Author { name: "David Aja".to_string(), author_type: AuthorType::Penciller },
+            ],
+            ..Default::default()
+        };
+        let catalog = export(&[metadata], CatalogFormat::Csv).unwrap();
+        let lines: Vec<&str> = catalog.lines().collect();
+        assert_eq!(lines[0], "series,issue,volume,title,date,publisher,authors,language,source,identifiers");
+        assert_eq!(lines[1], r#""Hawkeye, Vol. 1",1,,"Issue ""1""",2012,,Matt Fraction; David Aja,,,"#);
+        assert_eq!(csv_line(&["a", "b\nc"]), "a,\"b\nc\"");
+    }
+
+    #[test]
+    fn markdown_table() {
+        let rows = vec![vec!["Hawkeye", "a|b"]];
+        assert_eq!(
+            format_table(&["series", "path"], &rows, CatalogFormat::Markdown).unwrap(),
+            "| series | path |\n| --- | --- |\n| Hawkeye | a\\|b |\n"
+        );
+        assert_eq!(format_table(&["series"], &rows, CatalogFormat::Json), None);
+    }
+}
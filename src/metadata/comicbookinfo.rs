@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+use crate::{
+    metadata::{Metadata, Author, AuthorType, Provenance},
+    error::GrawlixIOError
+};
+
+/// Key of metadata in ComicBookInfo document
+const VERSION_KEY: &str = "ComicBookInfo/1.0";
+
+/// ComicBookInfo document stored as archive comment
+#[derive(Deserialize, Serialize)]
+struct ComicBookInfo {
+    #[serde(rename = "appID", default)]
+    app_id: Option<String>,
+    #[serde(rename = "lastModified", default, skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+    #[serde(rename = "ComicBookInfo/1.0")]
+    info: Info,
+    /// Where the comic was downloaded from. Stored here since the archive only has one comment.
+    #[serde(rename = "grawlixProvenance", default, skip_serializing_if = "Option::is_none")]
+    provenance: Option<Download>,
+}
+
+/// Provenance without the original page urls
+#[derive(Deserialize, Serialize)]
+struct Download {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    downloaded: String,
+    version: String,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Info {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    series: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    publisher: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    publication_month: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    publication_year: Option<u32>,
+    /// Issue number. Some programs store it as a string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    issue: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    volume: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    genre: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    comments: Option<String>,
+    #[serde(default)]
+    credits: Vec<Credit>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Credit {
+    person: String,
+    role: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    primary: Option<bool>,
+}
+
+/// Name of author type in ComicBookInfo
+fn role_name(author_type: &AuthorType) -> &'static str {
+    match author_type {
+        AuthorType::Writer => "Writer",
+        AuthorType::Penciller => "Penciller",
+        AuthorType::Inker => "Inker",
+        AuthorType::Colorist => "Colorist",
+        AuthorType::Letterer => "Letterer",
+        AuthorType::CoverArtist => "Cover",
+        AuthorType::Editor => "Editor",
+        AuthorType::Other => "Other",
+    }
+}
+
+/// Author type from ComicBookInfo role
+fn author_type(role: &str) -> AuthorType {
+    match role.to_lowercase().as_str() {
+        "writer" | "plotter" | "scripter" => AuthorType::Writer,
+        "penciller" | "penciler" | "artist" => AuthorType::Penciller,
+        "inker" => AuthorType::Inker,
+        "colorist" | "colorer" | "colourist" => AuthorType::Colorist,
+        "letterer" => AuthorType::Letterer,
+        "cover" | "cover artist" | "coverartist" => AuthorType::CoverArtist,
+        "editor" => AuthorType::Editor,
+        _ => AuthorType::Other,
+    }
+}
+
+/// Export to ComicBookInfo format
+/// https://code.google.com/archive/p/comicbookinfo/wikis/Example.wiki
+pub fn export(metadata: &Metadata) -> Result<String, GrawlixIOError> {
+    let document = ComicBookInfo {
+        app_id: Some(format!("grawlix/{}", env!("CARGO_PKG_VERSION"))),
+        // Only known for downloaded comics, so writing the same metadata gives the same document
+        last_modified: metadata.provenance.as_ref().map(|x| x.downloaded.clone()),
+        info: Info {
+            series: metadata.series.clone(),
+            title: metadata.title.clone(),
+            publisher: metadata.publisher.clone(),
+            publication_month: metadata.month,
+            publication_year: metadata.year,
+            issue: metadata.issue_number.map(serde_json::Value::from),
+            volume: metadata.volume,
+            genre: Some(metadata.genres.join(", ")).filter(|x| !x.is_empty()),
+            language: metadata.language.clone(),
+            comments: metadata.description.clone(),
+            credits: metadata.authors.iter()
+                .map(|author| Credit {
+                    person: author.name.clone(),
+                    role: role_name(&author.author_type).to_string(),
+                    primary: None,
+                })
+                .collect(),
+            tags: Vec::new(),
+        },
+        provenance: metadata.provenance.as_ref().map(|x| Download {
+            source: x.source.clone(),
+            url: x.url.clone(),
+            downloaded: x.downloaded.clone(),
+            version: x.version.clone(),
+        }),
+    };
+    serde_json::to_string(&document)
+        .or(Err(GrawlixIOError::MetadataExport("ComicBookInfo".to_string())))
+}
+
+/// Import from ComicBookInfo format
+pub fn import(source: &str) -> Result<Metadata, GrawlixIOError> {
+    if !source.contains(VERSION_KEY) {
+        return Err(GrawlixIOError::MetadataImport("ComicBookInfo".to_string()));
+    }
+    let document: ComicBookInfo = serde_json::from_str(source)
+        .or(Err(GrawlixIOError::MetadataImport("ComicBookInfo".to_string())))?;
+    let info = document.info;
+    let issue_number = match info.issue {
+        Some(serde_json::Value::Number(n)) => n.as_u64().map(|x| x as u32),
+        Some(serde_json::Value::String(s)) => s.trim().parse().ok(),
+        _ => None,
+    };
+    Ok(Metadata {
+        title: info.title,
+        series: info.series,
+        publisher: info.publisher,
+        issue_number,
+        volume: info.volume,
+        year: info.publication_year,
+        month: info.publication_month,
+        language: info.language,
+        description: info.comments,
+        genres: info.genre.iter()
+            .flat_map(|x| x.split(','))
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect(),
+        authors: info.credits.into_iter()
+            .map(|credit| Author {
+                author_type: author_type(&credit.role),
+                name: credit.person,
+            })
+            .collect(),
+        provenance: document.provenance.map(|x| Provenance {
+            source: x.source,
+            url: x.url,
+            downloaded: x.downloaded,
+            version: x.version,
+            page_urls: Vec::new(),
+            page_validators: Vec::new(),
+        }),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::metadata::tests::test_metadata;
+
+    #[test]
+    fn roundtrip() {
+        let metadata = test_metadata();
+        let exported = super::export(&metadata).unwrap();
+        assert!(exported.contains(r#""ComicBookInfo/1.0":{"series":"Moon Knight (2016 - 2018)""#));
+        let imported = super::import(&exported).unwrap();
+        assert_eq!(imported, crate::metadata::Metadata { day: None, ..metadata });
+    }
+
+    #[test]
+    fn provenance() {
+        let mut metadata = test_metadata();
+        metadata.provenance = Some(crate::metadata::Provenance {
+            source: Some("Marvel".to_string()),
+            url: None,
+            downloaded: "2022-10-01T12:00:00Z".to_string(),
+            version: "0.1.0".to_string(),
+            page_urls: vec!["https://example.com/1.jpg".to_string()],
+            page_validators: Vec::new(),
+        });
+        let exported = super::export(&metadata).unwrap();
+        assert_eq!(exported, super::export(&metadata).unwrap());
+        assert!(exported.contains(r#""lastModified":"2022-10-01T12:00:00Z""#));
+        let provenance = super::import(&exported).unwrap().provenance.unwrap();
+        assert_eq!(provenance.source.as_deref(), Some("Marvel"));
+        assert!(provenance.page_urls.is_empty());
+        assert!(!super::export(&test_metadata()).unwrap().contains("lastModified"));
+    }
+
+    #[test]
+    fn import_string_issue() {
+        let source = r#"{"appID":"ComicTagger/1.0","ComicBookInfo/1.0":{"series":"Saga","issue":"12","credits":[{"person":"Fiona Staples","role":"Artist","primary":true}]}}"#;
+        let metadata = super::import(source).unwrap();
+        assert_eq!(metadata.issue_number, Some(12));
+        assert_eq!(metadata.authors[0].author_type, crate::metadata::AuthorType::Penciller);
+        assert!(super::import("Downloaded with grawlix").is_err());
+    }
+}
@@ -39,9 +39,12 @@ pub fn export(metadata: &Metadata) -> Result<String, WriteError> {
         write_option(&mut w, "Series", &metadata.series)?;
         write_option(&mut w, "Publisher", &metadata.publisher)?;
         write_option(&mut w, "Number", &metadata.issue_number)?;
+        write_option(&mut w, "Volume", &metadata.volume)?;
         write_option(&mut w, "Year", &metadata.year)?;
         write_option(&mut w, "Month", &metadata.month)?;
         write_option(&mut w, "Day", &metadata.day)?;
+        write_option(&mut w, "LanguageISO", &metadata.language)?;
+        write_option(&mut w, "AgeRating", &metadata.age_rating)?;
         for author in &metadata.authors {
             write_simple(&mut w, author.author_type.to_string().as_ref(), author.name.as_ref())?
         }
@@ -72,9 +75,12 @@ pub fn import<R: std::io::Read>(source: R) -> Metadata {
                     "Series" => new.series = Some(content),
                     "Publisher" => new.publisher = Some(content),
                     "Number" => new.issue_number = content.parse().ok(),
+                    "Volume" => new.volume = content.parse().ok(),
                     "Year" => new.year = content.parse().ok(),
                     "Month" => new.month = content.parse().ok(),
                     "Day" => new.day = content.parse().ok(),
+                    "LanguageISO" => new.language = Some(content),
+                    "AgeRating" => new.age_rating = content.parse().ok(),
                     "Writer" | "Penciller" | "Inker" | "Colorist" | "Letterer" | "CoverArtist" | "Editor" =>
                         new.authors.push(Author{name:content, author_type: current.clone().into()}),
                     _ => (),
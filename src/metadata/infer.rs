@@ -0,0 +1,56 @@
+use super::Metadata;
+use regex::Regex;
+
+impl Metadata {
+    /// Fill in missing issue number, volume, and year from the title of the comic or `url` with
+    /// `patterns`. Patterns use the named groups `issue`, `volume`, and `year`. Values from the
+    /// first matching pattern are used, and values already in metadata are kept.
+    pub fn infer_numbers(&mut self, url: Option<&str>, patterns: &[Regex]) {
+        let texts: Vec<String> = self.title.iter()
+            .cloned()
+            .chain(url.map(str::to_string))
+            .collect();
+        for pattern in patterns {
+            for text in &texts {
+                let captures = match pattern.captures(text) {
+                    Some(captures) => captures,
+                    None => continue,
+                };
+                let number = |name| captures.name(name).and_then(|x| x.as_str().parse::<u32>().ok());
+                self.issue_number = self.issue_number.or_else(|| number("issue"));
+                self.volume = self.volume.or_else(|| number("volume"));
+                self.year = self.year.or_else(|| number("year"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metadata::Metadata;
+    use regex::Regex;
+
+    #[test]
+    fn inferred_numbers() {
+        let patterns = vec![
+            Regex::new(r"Nr\. (?P<issue>\d+)").unwrap(),
+            Regex::new(r"(?P<year>(19|20)\d{2})$").unwrap(),
+        ];
+        let mut metadata = Metadata {
+            title: Some("Anders And & Co. Nr. 23 2022".to_string()),
+            ..Default::default()
+        };
+        metadata.infer_numbers(None, &patterns);
+        assert_eq!(metadata.issue_number, Some(23));
+        assert_eq!(metadata.year, Some(2022));
+        // Existing values are kept and urls are used when the title does not match
+        let mut metadata = Metadata {
+            title: Some("Anders And & Co.".to_string()),
+            issue_number: Some(5),
+            ..Default::default()
+        };
+        metadata.infer_numbers(Some("https://example.com/comics/2019"), &patterns);
+        assert_eq!(metadata.issue_number, Some(5));
+        assert_eq!(metadata.year, Some(2019));
+    }
+}
@@ -0,0 +1,134 @@
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// Preferred language of comic titles
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TitleLanguage {
+    /// Keep titles in their original script
+    #[default]
+    Native,
+    /// Transliterate japanese kana in titles to latin characters
+    Romanized,
+    /// Prefer english titles when the source has them
+    English,
+}
+
+impl FromStr for TitleLanguage {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "native" => Ok(Self::Native),
+            "romanized" | "romanised" | "romaji" => Ok(Self::Romanized),
+            "english" | "en" => Ok(Self::English),
+            _ => Err("Could not parse title language (Either native, romanized, or english)")
+        }
+    }
+}
+
+impl TitleLanguage {
+    /// Choose between a native and an english title. Falls back to the other title if the
+    /// preferred one is not available.
+    pub fn choose(&self, native: Option<String>, english: Option<String>) -> Option<String> {
+        match self {
+            Self::Native => native.or(english),
+            Self::Romanized => native.map(|x| romanize(&x)).or(english),
+            Self::English => english.or(native),
+        }
+    }
+}
+
+/// Romaji of hiragana characters from "ぁ" to "ゖ"
+const HIRAGANA: [&str; 86] = [
+    "a", "a", "i", "i", "u", "u", "e", "e", "o", "o",
+    "ka", "ga", "ki", "gi", "ku", "gu", "ke", "ge", "ko", "go",
+    "sa", "za", "shi", "ji", "su", "zu", "se", "ze", "so", "zo",
+    "ta", "da", "chi", "ji", "", "tsu", "zu", "te", "de", "to", "do",
+    "na", "ni", "nu", "ne", "no",
+    "ha", "ba", "pa", "hi", "bi", "pi", "fu", "bu", "pu", "he", "be", "pe", "ho", "bo", "po",
+    "ma", "mi", "mu", "me", "mo",
+    "ya", "ya", "yu", "yu", "yo", "yo",
+    "ra", "ri", "ru", "re", "ro",
+    "wa", "wa", "wi", "we", "wo", "n", "vu", "ka", "ke",
+];
+
+/// Small "tsu" doubling the next consonant
+const SMALL_TSU: char = 'っ';
+
+/// Convert katakana to hiragana
+fn to_hiragana(c: char) -> char {
+    match c {
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+/// Transliterate hiragana and katakana in `s` to latin characters. Other characters are kept.
+pub fn romanize(s: &str) -> String {
+    let mut output = String::new();
+    let mut double_next = false;
+    let mut chars = s.chars().map(to_hiragana).peekable();
+    while let Some(c) = chars.next() {
+        if c == SMALL_TSU {
+            double_next = true;
+            continue;
+        }
+        let mut romaji = match c {
+            'ぁ'..='ゖ' => HIRAGANA[(c as u32 - 'ぁ' as u32) as usize].to_string(),
+            // Long vowel mark repeats previous vowel
+            'ー' => output.chars().last().filter(|x| "aiueo".contains(*x)).map(String::from).unwrap_or_default(),
+            '・' | '\u{3000}' => " ".to_string(),
+            _ => {
+                output.push(c);
+                double_next = false;
+                continue;
+            }
+        };
+        // Combined sounds like "きゃ"
+        if let Some(small @ ('ゃ' | 'ゅ' | 'ょ')) = chars.peek().copied() {
+            if romaji.len() > 1 && romaji.ends_with('i') {
+                chars.next();
+                romaji.pop();
+                let vowel = &HIRAGANA[(small as u32 - 'ぁ' as u32) as usize][1..];
+                if !(romaji == "sh" || romaji == "ch" || romaji == "j") {
+                    romaji.push('y');
+                }
+                romaji.push_str(vowel);
+            }
+        }
+        if double_next {
+            match romaji.chars().next() {
+                Some('c') => romaji.insert(0, 't'),
+                Some(x) if !"aiueon".contains(x) => romaji.insert(0, x),
+                _ => (),
+            }
+            double_next = false;
+        }
+        output.push_str(&romaji);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{romanize, TitleLanguage};
+
+    #[test]
+    fn romanization() {
+        assert_eq!(romanize("ドラえもん"), "doraemon");
+        assert_eq!(romanize("きゃっちゃー"), "kyatchaa");
+        assert_eq!(romanize("しょうせつ"), "shousetsu");
+        assert_eq!(romanize("ワンピース 第1話"), "wanpiisu 第1話");
+    }
+
+    #[test]
+    fn choose_title() {
+        let native = Some("ちいかわ".to_string());
+        let english = Some("Chiikawa".to_string());
+        assert_eq!(TitleLanguage::Native.choose(native.clone(), english.clone()), native);
+        assert_eq!(TitleLanguage::English.choose(native.clone(), english.clone()), english);
+        assert_eq!(TitleLanguage::Romanized.choose(native.clone(), None), Some("chiikawa".to_string()));
+        assert_eq!(TitleLanguage::English.choose(native.clone(), None), native);
+    }
+}
@@ -0,0 +1,129 @@
+use super::Metadata;
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// How metadata from two sources is combined
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergePolicy {
+    /// Keep existing values and only fill in fields that are missing
+    PreferExisting,
+    /// Use new values and keep existing values for fields missing from the new metadata
+    PreferRemote,
+    /// Use new values for single fields and combine lists like authors and genres
+    Merge,
+}
+
+impl FromStr for MergePolicy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "prefer-existing" | "existing" => Ok(Self::PreferExisting),
+            "prefer-remote" | "remote" => Ok(Self::PreferRemote),
+            "merge" => Ok(Self::Merge),
+            _ => Err("Could not parse merge policy (Either prefer-existing, prefer-remote, or merge)")
+        }
+    }
+}
+
+/// Choose between two optional values
+fn choose<T>(existing: &mut Option<T>, other: Option<T>, prefer_existing: bool) {
+    if other.is_some() && (existing.is_none() || !prefer_existing) {
+        *existing = other;
+    }
+}
+
+/// Choose between two lists. Lists are combined without duplicates if `combine` is enabled.
+fn choose_list<T: PartialEq>(existing: &mut Vec<T>, other: Vec<T>, prefer_existing: bool, combine: bool) {
+    if combine {
+        for x in other {
+            if !existing.contains(&x) {
+                existing.push(x);
+            }
+        }
+    } else if !other.is_empty() && (existing.is_empty() || !prefer_existing) {
+        *existing = other;
+    }
+}
+
+impl Metadata {
+
+    /// Merge `other` into this metadata according to `policy`. `self` is treated as the
+    /// existing metadata and `other` as the newly downloaded metadata.
+    pub fn merge(&mut self, other: Metadata, policy: MergePolicy) {
+        let prefer_existing = policy == MergePolicy::PreferExisting;
+        let combine = policy == MergePolicy::Merge;
+        choose(&mut self.title, other.title, prefer_existing);
+        choose(&mut self.publisher, other.publisher, prefer_existing);
+        choose(&mut self.series, other.series, prefer_existing);
+        choose(&mut self.issue_number, other.issue_number, prefer_existing);
+        choose(&mut self.volume, other.volume, prefer_existing);
+        // Release date is handled as one field so parts of different dates are not mixed
+        if other.year.is_some() && (self.year.is_none() || !prefer_existing) {
+            self.year = other.year;
+            self.month = other.month;
+            self.day = other.day;
+        }
+        choose(&mut self.description, other.description, prefer_existing);
+        choose(&mut self.source, other.source, prefer_existing);
+        choose(&mut self.age_rating, other.age_rating, prefer_existing);
+        choose(&mut self.language, other.language, prefer_existing);
+        choose(&mut self.provenance, other.provenance, prefer_existing);
+        if !prefer_existing {
+            self.reading_direction = other.reading_direction;
+            self.page_layout = other.page_layout;
+        }
+        // A comic is only complete if both versions are
+        self.preview |= other.preview;
+        choose_list(&mut self.authors, other.authors, prefer_existing, combine);
+        choose_list(&mut self.identifiers, other.identifiers, prefer_existing, combine);
+        choose_list(&mut self.genres, other.genres, prefer_existing, combine);
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MergePolicy;
+    use crate::metadata::{Metadata, Author, AuthorType, tests::test_metadata};
+
+    fn remote() -> Metadata {
+        Metadata {
+            title: Some("Moon Knight (2016) #1".to_string()),
+            description: Some("Who is Moon Knight?".to_string()),
+            authors: vec![
+                Author { name: "Jeff Lemire".to_string(), author_type: AuthorType::Writer },
+                Author { name: "Jordie Bellaire".to_string(), author_type: AuthorType::Colorist },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn prefer_existing() {
+        let mut metadata = test_metadata();
+        metadata.merge(remote(), MergePolicy::PreferExisting);
+        assert_eq!(metadata.title, test_metadata().title);
+        assert_eq!(metadata.description, remote().description);
+        assert_eq!(metadata.authors, test_metadata().authors);
+    }
+
+    #[test]
+    fn prefer_remote() {
+        let mut metadata = test_metadata();
+        metadata.merge(remote(), MergePolicy::PreferRemote);
+        assert_eq!(metadata.title, remote().title);
+        assert_eq!(metadata.series, test_metadata().series);
+        assert_eq!(metadata.year, Some(2016));
+        assert_eq!(metadata.authors, remote().authors);
+    }
+
+    #[test]
+    fn merge() {
+        let mut metadata = test_metadata();
+        metadata.merge(remote(), MergePolicy::Merge);
+        assert_eq!(metadata.title, remote().title);
+        assert_eq!(metadata.authors.len(), 4);
+    }
+}
@@ -1,10 +1,31 @@
+mod catalog;
+mod comicbookinfo;
 mod comicrack;
+mod infer;
+mod language;
+mod merge;
+mod mylar;
+mod numbering;
+mod opf;
+mod provenance;
+mod rating;
+mod readinglist;
 mod tachayomi;
+mod text;
 #[cfg(test)]
 mod tests;
 
+pub use catalog::{CatalogFormat, export as export_catalog, format_table};
+pub use numbering::{NumberingReport, check_issue_numbers, format_ranges, parse_ranges};
+pub use provenance::{PageValidator, Provenance};
+pub use rating::AgeRating;
+pub use readinglist::{ReadingList, ReadingListBook};
+pub use language::{TitleLanguage, romanize};
+pub use merge::MergePolicy;
+pub use text::{normalize_text, same_text, text_key, title_case};
+
 use crate::error::GrawlixIOError as Error;
-use std::{fmt, io::Read, str::FromStr};
+use std::{collections::BTreeMap, fmt, io::Read, str::FromStr};
 use serde::{Deserialize, Serialize};
 
 /// Stores metadata about a comic book
@@ -20,6 +41,8 @@ pub struct Metadata {
     pub series: Option<String>,
     /// Issue number
     pub issue_number: Option<u32>,
+    /// Volume number
+    pub volume: Option<u32>,
     /// Relase year
     pub year: Option<u32>,
     /// Relase month (1 indexed)
@@ -28,6 +51,9 @@ pub struct Metadata {
     pub day: Option<u32>,
     /// Reading Direction
     pub reading_direction: ReadingDirection,
+    /// Whether pages are read one at a time or as one continuous strip
+    #[serde(default, skip_serializing_if = "PageLayout::is_paged")]
+    pub page_layout: PageLayout,
     /// Database identifiers
     pub identifiers: Vec<Identifier>,
     /// Description
@@ -36,6 +62,18 @@ pub struct Metadata {
     pub source: Option<String>,
     /// Genres
     pub genres: Vec<String>,
+    /// Age rating
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age_rating: Option<AgeRating>,
+    /// Language of comic as ISO 639-1 code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Information about where the comic was downloaded from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+    /// Only a preview of the comic is available, usually because the account does not own it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub preview: bool,
 }
 
 impl Metadata {
@@ -49,15 +87,78 @@ impl Metadata {
         }
     }
 
+    /// Returns false if the release date is known and outside the range from `after` to `before`
+    /// (both inclusive). A missing month or day covers the whole year or month.
+    pub fn released_within(&self, after: Option<(u32, u32, u32)>, before: Option<(u32, u32, u32)>) -> bool {
+        let year = match self.year {
+            Some(year) => year,
+            None => return true,
+        };
+        let earliest = (year, self.month.unwrap_or(1), self.day.unwrap_or(1));
+        let latest = (year, self.month.unwrap_or(12), self.day.unwrap_or(31));
+        !(after.map(|x| latest < x).unwrap_or(false) || before.map(|x| earliest > x).unwrap_or(false))
+    }
+
+    /// Transliterate title and series if romanized titles are preferred
+    pub fn localize_titles(&mut self, language: TitleLanguage) {
+        if language == TitleLanguage::Romanized {
+            self.title = self.title.as_deref().map(romanize);
+            self.series = self.series.as_deref().map(romanize);
+        }
+    }
+
+    /// Replace publisher with its canonical name in `publishers`, which maps canonical names to
+    /// other names of the same publisher. Names are compared with `same_text`.
+    pub fn normalize_publisher(&mut self, publishers: &BTreeMap<String, Vec<String>>) {
+        if let Some(publisher) = &self.publisher {
+            let canonical = publishers.iter()
+                .find(|(name, aliases)| same_text(name, publisher) || aliases.iter().any(|x| same_text(x, publisher)))
+                .map(|(name, _)| name.clone());
+            if canonical.is_some() {
+                self.publisher = canonical;
+            }
+        }
+    }
+
     /// Export metadata in all available formats
     pub fn export_all(&self) -> Result<Vec<(&str, String)>, Error> {
-        Ok(vec![
-            ("comicinfo.xml", comicrack::export(&self)
-                .or(Err(Error::MetadataExport("Comicrack".to_string())))?),
-            ("details.json", tachayomi::export(self)?),
-            ("grawlix.json", serde_json::to_string(&self)
-                .or(Err(Error::MetadataExport("Grawlix".to_string())))?)
-        ])
+        self.export(&DEFAULT_METADATA_FORMATS)
+    }
+
+    /// Export metadata files in `formats`. ComicBookInfo is not a file and is skipped.
+    pub fn export(&self, formats: &[MetadataFormat]) -> Result<Vec<(&str, String)>, Error> {
+        let mut files = Vec::new();
+        for format in formats {
+            match format {
+                MetadataFormat::ComicInfo => files.push(("comicinfo.xml", comicrack::export(&self)
+                    .or(Err(Error::MetadataExport("Comicrack".to_string())))?)),
+                MetadataFormat::Tachiyomi => files.push(("details.json", tachayomi::export(self)?)),
+                MetadataFormat::Grawlix => files.push(("grawlix.json", serde_json::to_string(&self)
+                    .or(Err(Error::MetadataExport("Grawlix".to_string())))?)),
+                MetadataFormat::Opf => files.push(("metadata.opf", opf::export(self)
+                    .or(Err(Error::MetadataExport("OPF".to_string())))?)),
+                MetadataFormat::ComicBookInfo => (),
+            }
+        }
+        Ok(files)
+    }
+
+    /// Export metadata as ComicBookInfo json, which is stored as archive comment
+    pub fn to_comicbookinfo(&self) -> Result<String, Error> {
+        comicbookinfo::export(self)
+    }
+
+    /// Create Mylar series.json for the series of the comic, or update the contents of an
+    /// `existing` file. Description, cover, number of issues, and status are filled from `info`
+    /// if it is known. Returns `None` if the comic is not part of a series and fails if
+    /// `existing` is not a valid series.json.
+    pub fn update_series_json(&self, existing: Option<&str>, info: Option<&crate::source::SeriesInfo>) -> Result<Option<String>, Error> {
+        mylar::update_series(existing, self, info)
+    }
+
+    /// Import metadata from ComicBookInfo json in archive comment
+    pub fn from_comicbookinfo(comment: &str) -> Option<Self> {
+        comicbookinfo::import(comment).ok()
     }
 
     /// Import file with metadata and create `Metadata` object
@@ -75,6 +176,45 @@ impl Metadata {
     }
 }
 
+/// Metadata formats written to comics
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataFormat {
+    /// comicinfo.xml used by ComicRack
+    ComicInfo,
+    /// details.json used by Tachiyomi
+    Tachiyomi,
+    /// grawlix.json with all metadata
+    Grawlix,
+    /// ComicBookInfo json stored as archive comment
+    ComicBookInfo,
+    /// metadata.opf used by Calibre
+    Opf,
+}
+
+/// Metadata formats written when nothing else is specified
+pub const DEFAULT_METADATA_FORMATS: [MetadataFormat; 4] = [
+    MetadataFormat::ComicInfo,
+    MetadataFormat::Tachiyomi,
+    MetadataFormat::Grawlix,
+    MetadataFormat::ComicBookInfo,
+];
+
+impl FromStr for MetadataFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "comicinfo" => Ok(Self::ComicInfo),
+            "tachiyomi" => Ok(Self::Tachiyomi),
+            "grawlix" => Ok(Self::Grawlix),
+            "comicbookinfo" => Ok(Self::ComicBookInfo),
+            "opf" => Ok(Self::Opf),
+            _ => Err("Could not parse metadata format (Either comicinfo, tachiyomi, grawlix, comicbookinfo, or opf)")
+        }
+    }
+}
+
 /// Author of comic book
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct Author {
@@ -173,6 +313,24 @@ impl TryFrom<&str> for ReadingDirection {
     }
 }
 
+/// How pages of a comic are read
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageLayout {
+    /// Separate pages, possibly with spreads
+    #[default]
+    Paged,
+    /// Pages are parts of one long vertical strip, like webtoons
+    Vertical,
+}
+
+impl PageLayout {
+    /// Returns true if pages are read separately
+    pub fn is_paged(&self) -> bool {
+        *self == Self::Paged
+    }
+}
+
 /// Comic book identifier
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct Identifier {
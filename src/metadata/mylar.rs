@@ -0,0 +1,140 @@
+use super::Metadata;
+use crate::error::GrawlixIOError as Error;
+use crate::source::SeriesInfo;
+use serde::{Deserialize, Serialize};
+
+/// Series metadata file (series.json) used by Mylar
+#[derive(Debug, Deserialize, Serialize)]
+struct SeriesFile {
+    version: String,
+    metadata: SeriesMetadata,
+    /// Keys not known by grawlix, kept when the file is updated
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct SeriesMetadata {
+    #[serde(rename = "type")]
+    kind: String,
+    publisher: Option<String>,
+    imprint: Option<String>,
+    name: String,
+    /// Comicvine id of series
+    comicid: Option<u64>,
+    year: Option<u32>,
+    description_text: Option<String>,
+    description_formatted: Option<String>,
+    volume: Option<u32>,
+    booktype: String,
+    age_rating: Option<String>,
+    #[serde(default)]
+    collects: Vec<serde_json::Value>,
+    #[serde(rename = "ComicImage")]
+    comic_image: Option<String>,
+    total_issues: Option<usize>,
+    publication_run: Option<String>,
+    status: Option<String>,
+    /// Keys not known by grawlix, kept when the file is updated
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Create series.json for the series of `metadata`, or update `existing` with it. Values already
+/// in `existing` are kept except for the year, which is set to the earliest issue, and the number
+/// of issues and status, which are taken from `info`. Returns `None` if the comic is not part of a
+/// series.
+pub fn update_series(existing: Option<&str>, metadata: &Metadata, info: Option<&SeriesInfo>) -> Result<Option<String>, Error> {
+    let series = match &metadata.series {
+        Some(series) => series,
+        None => return Ok(None),
+    };
+    let mut file = match existing {
+        Some(existing) => serde_json::from_str::<SeriesFile>(existing)
+            .or(Err(Error::MetadataImport("Mylar".to_string())))?,
+        None => SeriesFile {
+            version: "1.0.2".to_string(),
+            metadata: SeriesMetadata {
+                kind: "comicSeries".to_string(),
+                name: series.clone(),
+                booktype: "Print".to_string(),
+                ..Default::default()
+            },
+            extra: serde_json::Map::new(),
+        },
+    };
+    let series_metadata = &mut file.metadata;
+    if series_metadata.publisher.is_none() {
+        series_metadata.publisher = metadata.publisher.clone();
+    }
+    if series_metadata.volume.is_none() {
+        series_metadata.volume = metadata.volume;
+    }
+    if series_metadata.age_rating.is_none() {
+        series_metadata.age_rating = metadata.age_rating.map(|x| x.to_string());
+    }
+    series_metadata.year = match (series_metadata.year, metadata.year) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+    if let Some(info) = info {
+        if series_metadata.description_text.is_none() {
+            series_metadata.description_text = info.description.clone();
+        }
+        if series_metadata.comic_image.is_none() {
+            series_metadata.comic_image = info.cover_url.clone();
+        }
+        if info.issue_count.is_some() {
+            series_metadata.total_issues = info.issue_count;
+        }
+        series_metadata.status = Some(if info.ended { "Ended" } else { "Continuing" }.to_string());
+    }
+    serde_json::to_string_pretty(&file)
+        .map(Some)
+        .or(Err(Error::MetadataExport("Mylar".to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metadata::{Metadata, tests::test_metadata};
+    use crate::source::SeriesInfo;
+
+    #[test]
+    fn series_file() {
+        let metadata = test_metadata();
+        let created = super::update_series(None, &metadata, None).unwrap().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&created).unwrap();
+        assert_eq!(value["version"], "1.0.2");
+        assert_eq!(value["metadata"]["name"], "Moon Knight (2016 - 2018)");
+        assert_eq!(value["metadata"]["publisher"], "Marvel");
+        assert_eq!(value["metadata"]["year"], 2016);
+        // Earlier issues change the year but other values are kept
+        let earlier = Metadata { year: Some(2015), publisher: Some("Other".to_string()), ..metadata };
+        let updated = super::update_series(Some(&created), &earlier, None).unwrap().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&updated).unwrap();
+        assert_eq!(value["metadata"]["year"], 2015);
+        assert_eq!(value["metadata"]["publisher"], "Marvel");
+        assert_eq!(super::update_series(None, &Metadata::default(), None).unwrap(), None);
+    }
+
+    #[test]
+    fn series_info_and_unknown_keys() {
+        let existing = r#"{"version": "1.0.2", "metadata": {"type": "comicSeries", "name": "Moon Knight",
+            "booktype": "Print", "description_text": "Kept", "custom": 1}, "other": true}"#;
+        let info = SeriesInfo {
+            description: Some("New".to_string()),
+            issue_count: Some(14),
+            cover_url: Some("https://example.com/cover.jpg".to_string()),
+            ..Default::default()
+        };
+        let updated = super::update_series(Some(existing), &test_metadata(), Some(&info)).unwrap().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&updated).unwrap();
+        assert_eq!(value["other"], true);
+        assert_eq!(value["metadata"]["custom"], 1);
+        assert_eq!(value["metadata"]["description_text"], "Kept");
+        assert_eq!(value["metadata"]["total_issues"], 14);
+        assert_eq!(value["metadata"]["ComicImage"], "https://example.com/cover.jpg");
+        assert_eq!(value["metadata"]["status"], "Continuing");
+        assert!(super::update_series(Some("{}"), &test_metadata(), None).is_err());
+    }
+}
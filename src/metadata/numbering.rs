@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+
+/// Result of analysing issue numbers in a series
+#[derive(Debug, Default, PartialEq)]
+pub struct NumberingReport {
+    /// Issue numbers missing between the first and last issue
+    pub gaps: Vec<u32>,
+    /// Issue numbers occurring more than once
+    pub duplicates: Vec<u32>,
+    /// Number of issues without an issue number
+    pub unnumbered: usize,
+}
+
+impl NumberingReport {
+    /// Returns `true` if no problems were found
+    pub fn is_ok(&self) -> bool {
+        self.gaps.is_empty() && self.duplicates.is_empty() && self.unnumbered == 0
+    }
+}
+
+/// Find gaps and duplicates in issue numbers
+pub fn check_issue_numbers<I: IntoIterator<Item = Option<u32>>>(numbers: I) -> NumberingReport {
+    let mut report = NumberingReport::default();
+    let mut counts: BTreeMap<u32, usize> = BTreeMap::new();
+    for number in numbers {
+        match number {
+            Some(n) => *counts.entry(n).or_default() += 1,
+            None => report.unnumbered += 1,
+        }
+    }
+    report.duplicates = counts.iter()
+        .filter(|(_, count)| **count > 1)
+        .map(|(number, _)| *number)
+        .collect();
+    if let (Some(first), Some(last)) = (counts.keys().next(), counts.keys().next_back()) {
+        report.gaps = (*first..*last)
+            .filter(|n| !counts.contains_key(n))
+            .collect();
+    }
+    report
+}
+
+/// Format list of numbers as ranges ("1, 3-5, 8")
+pub fn format_ranges(numbers: &[u32]) -> String {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for n in numbers {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == *n => *end = *n,
+            _ => ranges.push((*n, *n)),
+        }
+    }
+    ranges.iter()
+        .map(|(start, end)| if start == end {
+            start.to_string()
+        } else {
+            format!("{}-{}", start, end)
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Parse ranges of numbers in the format "1, 3-5, 8"
+pub fn parse_ranges(ranges: &str) -> Option<Vec<(u32, u32)>> {
+    ranges.split(',')
+        .map(|range| {
+            let range = range.trim();
+            match range.split_once('-') {
+                Some((start, end)) => Some((start.trim().parse().ok()?, end.trim().parse().ok()?)),
+                None => range.parse().ok().map(|n| (n, n)),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::NumberingReport;
+
+    #[test]
+    fn gaps_and_duplicates() {
+        let numbers = vec![Some(1), Some(2), Some(2), Some(5), None, Some(7)];
+        assert_eq!(
+            super::check_issue_numbers(numbers),
+            NumberingReport {
+                gaps: vec![3, 4, 6],
+                duplicates: vec![2],
+                unnumbered: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn format_ranges() {
+        assert_eq!(super::format_ranges(&[1, 3, 4, 5, 8]), "1, 3-5, 8");
+    }
+
+    #[test]
+    fn parse_ranges() {
+        assert_eq!(super::parse_ranges("1, 3-5,8"), Some(vec![(1, 1), (3, 5), (8, 8)]));
+        assert_eq!(super::parse_ranges("1-x"), None);
+    }
+}
@@ -0,0 +1,104 @@
+use super::{Metadata, AuthorType};
+use xml::writer::{XmlEvent as WriterEvent, EmitterConfig, EventWriter, Error as WriteError};
+
+/// Write a dublin core element
+fn write_dc<W: std::io::Write>(writer: &mut EventWriter<W>, tag: &str, content: &str) -> Result<(), WriteError> {
+    writer.write(WriterEvent::start_element(format!("dc:{}", tag).as_str()))?;
+    writer.write(content)?;
+    writer.write(WriterEvent::end_element())?;
+    Ok(())
+}
+
+/// Write a calibre specific meta element
+fn write_meta<W: std::io::Write>(writer: &mut EventWriter<W>, name: &str, content: &str) -> Result<(), WriteError> {
+    writer.write(WriterEvent::start_element("meta").attr("name", name).attr("content", content))?;
+    writer.write(WriterEvent::end_element())?;
+    Ok(())
+}
+
+/// MARC relator code of author type
+fn role(author_type: &AuthorType) -> &'static str {
+    match author_type {
+        AuthorType::Writer => "aut",
+        AuthorType::Penciller | AuthorType::Inker => "art",
+        AuthorType::Colorist => "clr",
+        AuthorType::Letterer => "ill",
+        AuthorType::CoverArtist => "cov",
+        AuthorType::Editor => "edt",
+        AuthorType::Other => "oth",
+    }
+}
+
+/// Export metadata in OPF format used by Calibre (metadata.opf)
+pub fn export(metadata: &Metadata) -> Result<String, WriteError> {
+    let mut buffer = Vec::new();
+    {
+        let mut w = EmitterConfig::new()
+            .perform_indent(true)
+            .create_writer(&mut buffer);
+        w.write(WriterEvent::start_element("package")
+            .default_ns("http://www.idpf.org/2007/opf")
+            .attr("version", "2.0"))?;
+        w.write(WriterEvent::start_element("metadata")
+            .ns("dc", "http://purl.org/dc/elements/1.1/")
+            .ns("opf", "http://www.idpf.org/2007/opf"))?;
+        if let Some(title) = &metadata.title {
+            write_dc(&mut w, "title", title)?;
+        }
+        for author in &metadata.authors {
+            w.write(WriterEvent::start_element("dc:creator").attr("opf:role", role(&author.author_type)))?;
+            w.write(author.name.as_str())?;
+            w.write(WriterEvent::end_element())?;
+        }
+        if let Some(publisher) = &metadata.publisher {
+            write_dc(&mut w, "publisher", publisher)?;
+        }
+        if let Some(year) = metadata.year {
+            let date = match (metadata.month, metadata.day) {
+                (Some(month), Some(day)) => format!("{:04}-{:02}-{:02}", year, month, day),
+                (Some(month), None) => format!("{:04}-{:02}", year, month),
+                _ => format!("{:04}", year),
+            };
+            write_dc(&mut w, "date", &date)?;
+        }
+        if let Some(description) = &metadata.description {
+            write_dc(&mut w, "description", description)?;
+        }
+        if let Some(language) = &metadata.language {
+            write_dc(&mut w, "language", language)?;
+        }
+        for genre in &metadata.genres {
+            write_dc(&mut w, "subject", genre)?;
+        }
+        for identifier in &metadata.identifiers {
+            w.write(WriterEvent::start_element("dc:identifier").attr("opf:scheme", &identifier.source))?;
+            w.write(identifier.id.as_str())?;
+            w.write(WriterEvent::end_element())?;
+        }
+        if let Some(series) = &metadata.series {
+            write_meta(&mut w, "calibre:series", series)?;
+            if let Some(issue) = metadata.issue_number {
+                write_meta(&mut w, "calibre:series_index", &issue.to_string())?;
+            }
+        }
+        w.write(WriterEvent::end_element())?;
+        w.write(WriterEvent::end_element())?;
+    }
+    let output = std::str::from_utf8(buffer.as_slice()).unwrap().to_string();
+    return Ok(output);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::metadata::tests::test_metadata;
+
+    #[test]
+    fn export() {
+        let opf = super::export(&test_metadata()).unwrap();
+        assert!(opf.contains("<dc:title>Moon Knight #1</dc:title>"));
+        assert!(opf.contains(r#"<dc:creator opf:role="aut">Jeff Lemire</dc:creator>"#));
+        assert!(opf.contains("<dc:date>2016-04-13</dc:date>"));
+        assert!(opf.contains(r#"<meta name="calibre:series" content="Moon Knight (2016 - 2018)" />"#));
+        assert!(opf.contains(r#"<meta name="calibre:series_index" content="1" />"#));
+    }
+}
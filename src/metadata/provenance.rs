@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// Information about where and when a comic was downloaded
+#[derive(Clone, Default, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Provenance {
+    /// Name of source
+    pub source: Option<String>,
+    /// Url the comic was found from
+    pub url: Option<String>,
+    /// Time of download in rfc3339 format
+    pub downloaded: String,
+    /// Version of grawlix used to download comic
+    pub version: String,
+    /// Original urls of pages without query strings, which can contain access tokens
+    pub page_urls: Vec<String>,
+    /// Validators of pages from when they were downloaded, in the same order as `page_urls`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub page_validators: Vec<PageValidator>,
+}
+
+/// Http validators of a downloaded page, used to check if the page has changed on the source
+#[derive(Clone, Default, Debug, PartialEq, Deserialize, Serialize)]
+pub struct PageValidator {
+    /// Value of `ETag` header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// Value of `Last-Modified` header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl PageValidator {
+    /// Returns true if the page response had no validators
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+impl Provenance {
+    /// Create new `Provenance` with current time and grawlix version. Query strings and
+    /// fragments are removed from `page_urls`.
+    pub fn new(source: Option<String>, url: Option<String>, page_urls: Vec<String>) -> Self {
+        Self {
+            source,
+            url,
+            downloaded: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            page_urls: page_urls.iter()
+                .map(|url| url.split(['?', '#']).next().unwrap_or_default().to_string())
+                .collect(),
+            page_validators: Vec::new(),
+        }
+    }
+
+    /// Short text description used as archive comment
+    pub fn to_comment(&self) -> String {
+        let mut lines = vec![format!("Downloaded with grawlix {} at {}", self.version, self.downloaded)];
+        if let Some(source) = &self.source {
+            lines.push(format!("Source: {}", source));
+        }
+        if let Some(url) = &self.url {
+            lines.push(format!("Url: {}", url));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Provenance;
+
+    #[test]
+    fn page_urls_without_tokens() {
+        let provenance = Provenance::new(None, None, vec![
+            "https://dash.hoopladigital.com/abc/page-001.jpg?token=secret".to_string(),
+            "https://example.com/2.jpg#page".to_string(),
+        ]);
+        assert_eq!(provenance.page_urls, vec![
+            "https://dash.hoopladigital.com/abc/page-001.jpg",
+            "https://example.com/2.jpg",
+        ]);
+    }
+
+    #[test]
+    fn comment() {
+        let provenance = Provenance {
+            source: Some("Webtoons".to_string()),
+            url: Some("https://www.webtoons.com/en/comedy/lore-olympus/list?title_no=1320".to_string()),
+            downloaded: "2022-10-01T12:00:00Z".to_string(),
+            version: "0.1.0".to_string(),
+            page_urls: Vec::new(),
+            page_validators: Vec::new(),
+        };
+        assert_eq!(
+            provenance.to_comment(),
+            "Downloaded with grawlix 0.1.0 at 2022-10-01T12:00:00Z\nSource: Webtoons\nUrl: https://www.webtoons.com/en/comedy/lore-olympus/list?title_no=1320"
+        );
+    }
+}
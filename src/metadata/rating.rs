@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// Age rating of comic. Ratings are ordered from least to most restricted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgeRating {
+    /// Suitable for all ages
+    Everyone,
+    /// Suitable for ages 12 and up
+    Teen,
+    /// Suitable for ages 17 and up
+    Mature,
+    /// Only suitable for adults
+    Adult,
+}
+
+impl FromStr for AgeRating {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "everyone" | "all ages" | "a" | "e" | "g" | "kids to adults" => Ok(Self::Everyone),
+            "teen" | "t" | "t+" | "teen+" | "everyone 10+" | "12+" | "13+" => Ok(Self::Teen),
+            "mature" | "m" | "mature 17+" | "parental advisory" | "15+" | "16+" | "17+" => Ok(Self::Mature),
+            "adult" | "adults only 18+" | "explicit" | "18+" | "r18+" | "x18+" => Ok(Self::Adult),
+            _ => Err("Could not parse age rating (Either everyone, teen, mature, or adult)")
+        }
+    }
+}
+
+impl fmt::Display for AgeRating {
+    /// Name of rating in ComicRack format
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Everyone => "Everyone",
+            Self::Teen => "Teen",
+            Self::Mature => "Mature 17+",
+            Self::Adult => "Adults Only 18+",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AgeRating;
+
+    #[test]
+    fn parse_ratings() {
+        assert_eq!("17+".parse(), Ok(AgeRating::Mature));
+        assert_eq!("T+".parse(), Ok(AgeRating::Teen));
+        assert_eq!("Adults Only 18+".parse(), Ok(AgeRating::Adult));
+        assert!("Rating Pending".parse::<AgeRating>().is_err());
+        for rating in [AgeRating::Everyone, AgeRating::Teen, AgeRating::Mature, AgeRating::Adult] {
+            assert_eq!(rating.to_string().parse(), Ok(rating));
+        }
+        assert!(AgeRating::Teen < AgeRating::Mature);
+    }
+}
@@ -0,0 +1,145 @@
+use super::{Identifier, Metadata};
+use xml::{
+    reader::{ParserConfig, XmlEvent as ReaderEvent},
+    writer::{XmlEvent as WriterEvent, EmitterConfig, Error as WriteError}
+};
+
+/// Ordered list of comics in ComicRack reading list (cbl) format
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReadingList {
+    /// Name of reading list
+    pub name: String,
+    /// Comics in reading order
+    pub books: Vec<ReadingListBook>,
+}
+
+/// Comic in reading list
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReadingListBook {
+    /// Series name
+    pub series: Option<String>,
+    /// Issue number
+    pub number: Option<String>,
+    /// Volume of series
+    pub volume: Option<String>,
+    /// Release year
+    pub year: Option<String>,
+    /// Ids of comic on sources
+    pub identifiers: Vec<Identifier>,
+}
+
+impl From<&Metadata> for ReadingListBook {
+    fn from(metadata: &Metadata) -> Self {
+        Self {
+            series: metadata.series.clone().or_else(|| metadata.title.clone()),
+            number: metadata.issue_number.map(|x| x.to_string()),
+            volume: metadata.volume.map(|x| x.to_string()),
+            year: metadata.year.map(|x| x.to_string()),
+            identifiers: metadata.identifiers.clone(),
+        }
+    }
+}
+
+impl ReadingList {
+    /// Create reading list of comics in the order they are given
+    pub fn from_metadata<'a>(name: &str, metadata: impl IntoIterator<Item = &'a Metadata>) -> Self {
+        Self {
+            name: name.to_string(),
+            books: metadata.into_iter().map(ReadingListBook::from).collect(),
+        }
+    }
+
+    /// Export reading list in cbl format. Source identifiers are stored as `Database` entries.
+    pub fn to_cbl(&self) -> Result<String, WriteError> {
+        let mut buffer = Vec::new();
+        {
+            let mut w = EmitterConfig::new()
+                .perform_indent(true)
+                .create_writer(&mut buffer);
+            w.write(WriterEvent::start_element("ReadingList"))?;
+            w.write(WriterEvent::start_element("Name"))?;
+            w.write(self.name.as_str())?;
+            w.write(WriterEvent::end_element())?;
+            w.write(WriterEvent::start_element("Books"))?;
+            for book in &self.books {
+                let mut element = WriterEvent::start_element("Book");
+                for (name, value) in [
+                    ("Series", &book.series),
+                    ("Number", &book.number),
+                    ("Volume", &book.volume),
+                    ("Year", &book.year),
+                ] {
+                    if let Some(value) = value {
+                        element = element.attr(name, value);
+                    }
+                }
+                w.write(element)?;
+                for identifier in &book.identifiers {
+                    w.write(WriterEvent::start_element("Database")
+                        .attr("Name", &identifier.source)
+                        .attr("Issue", &identifier.id))?;
+                    w.write(WriterEvent::end_element())?;
+                }
+                w.write(WriterEvent::end_element())?;
+            }
+            w.write(WriterEvent::end_element())?;
+            w.write(WriterEvent::end_element())?;
+        }
+        Ok(std::str::from_utf8(buffer.as_slice()).unwrap().to_string())
+    }
+
+    /// Read reading list in cbl format
+    pub fn from_cbl<R: std::io::Read>(source: R) -> Self {
+        let parser = ParserConfig::new()
+            .whitespace_to_characters(true)
+            .trim_whitespace(true)
+            .create_reader(source);
+        let mut list = Self::default();
+        let mut current = String::new();
+        for e in parser {
+            match e {
+                Ok(ReaderEvent::StartElement { name, attributes, .. }) => {
+                    let attr = |key: &str| attributes.iter()
+                        .find(|x| x.name.local_name == key)
+                        .map(|x| x.value.clone());
+                    match name.local_name.as_str() {
+                        "Book" => list.books.push(ReadingListBook {
+                            series: attr("Series"),
+                            number: attr("Number"),
+                            volume: attr("Volume"),
+                            year: attr("Year"),
+                            identifiers: Vec::new(),
+                        }),
+                        "Database" => {
+                            if let (Some(book), Some(source), Some(id)) = (list.books.last_mut(), attr("Name"), attr("Issue")) {
+                                book.identifiers.push(Identifier { source, id });
+                            }
+                        },
+                        _ => (),
+                    }
+                    current = name.local_name;
+                },
+                Ok(ReaderEvent::Characters(content)) if current == "Name" => list.name = content,
+                Ok(ReaderEvent::EndElement { .. }) => current.clear(),
+                _ => (),
+            }
+        }
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReadingList;
+    use crate::metadata::{Identifier, tests::test_metadata};
+
+    #[test]
+    fn cbl_roundtrip() {
+        let mut metadata = test_metadata();
+        metadata.identifiers.push(Identifier { source: "Marvel".to_string(), id: "12345".to_string() });
+        let list = ReadingList::from_metadata("Moon Knight", [&metadata]);
+        let cbl = list.to_cbl().unwrap();
+        assert!(cbl.contains(r#"<Book Series="Moon Knight (2016 - 2018)" Number="1" Year="2016">"#));
+        assert_eq!(ReadingList::from_cbl(cbl.as_bytes()), list);
+    }
+}
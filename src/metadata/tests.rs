@@ -1,4 +1,4 @@
-use super::{Metadata, Author, AuthorType};
+use super::{Metadata, MetadataFormat, Author, AuthorType};
 
 pub fn test_metadata() -> Metadata {
     Metadata {
@@ -26,3 +26,43 @@ fn date_from_str() {
         Some((2022,09,27))
     );
 }
+
+#[test]
+fn released_within() {
+    let metadata = test_metadata();
+    assert!(metadata.released_within(Some((2016, 1, 1)), Some((2016, 12, 31))));
+    assert!(!metadata.released_within(Some((2016, 4, 14)), None));
+    assert!(!metadata.released_within(None, Some((2015, 12, 31))));
+    let year_only = Metadata { year: Some(2016), ..Default::default() };
+    assert!(year_only.released_within(Some((2016, 12, 1)), None));
+    assert!(Metadata::default().released_within(Some((2020, 1, 1)), None));
+}
+
+#[test]
+fn normalize_publisher() {
+    let publishers = [
+        ("DC Comics".to_string(), vec!["DC".to_string(), "DC Entertainment".to_string()]),
+    ].into_iter().collect();
+    let publisher = |name: &str| {
+        let mut metadata = Metadata { publisher: Some(name.to_string()), ..Default::default() };
+        metadata.normalize_publisher(&publishers);
+        metadata.publisher.unwrap()
+    };
+    assert_eq!(publisher("DC Entertainment"), "DC Comics");
+    assert_eq!(publisher("dc "), "DC Comics");
+    assert_eq!(publisher("DC COMICS"), "DC Comics");
+    assert_eq!(publisher("Marvel"), "Marvel");
+}
+
+#[test]
+fn export_formats() {
+    let metadata = test_metadata();
+    let names = |formats: &[MetadataFormat]| metadata.export(formats).unwrap()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+    assert_eq!(names(&[MetadataFormat::ComicInfo, MetadataFormat::Opf]), vec!["comicinfo.xml", "metadata.opf"]);
+    assert_eq!(names(&[MetadataFormat::ComicBookInfo]), Vec::<&str>::new());
+    assert_eq!(names(&[]), Vec::<&str>::new());
+    assert_eq!(names(&super::DEFAULT_METADATA_FORMATS), vec!["comicinfo.xml", "details.json", "grawlix.json"]);
+}
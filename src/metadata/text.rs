@@ -0,0 +1,78 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// Normalize text from sources so the same title is always written the same way. Text is
+/// converted to NFC, and runs of whitespace, including non-breaking and ideographic spaces, are
+/// replaced with a single space.
+pub fn normalize_text(s: &str) -> String {
+    s.nfc()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Key for comparing text regardless of case, normalization form, and whitespace. Case is folded
+/// with the Unicode rules, so the result does not depend on the locale of the system.
+pub fn text_key(s: &str) -> String {
+    normalize_text(s).chars()
+        .flat_map(char::to_lowercase)
+        .nfc()
+        .collect()
+}
+
+/// Returns true if `a` and `b` are the same text when compared with `text_key`
+pub fn same_text(a: &str, b: &str) -> bool {
+    text_key(a) == text_key(b)
+}
+
+/// Uppercase version of first letter in a word in `language` (ISO 639-1)
+fn uppercase_first(word: &str, language: Option<&str>) -> String {
+    let mut chars = word.chars();
+    let first = match chars.next() {
+        Some(first) => first,
+        None => return String::new(),
+    };
+    let rest = chars.as_str();
+    match (language, first) {
+        // Turkish and Azerbaijani have both dotted and dotless i
+        (Some("tr") | Some("az"), 'i') => format!("İ{}", rest),
+        // IJ is capitalized as one letter in Dutch
+        (Some("nl"), 'i') if rest.starts_with('j') => format!("IJ{}", &rest[1..]),
+        _ => first.to_uppercase().chain(rest.chars()).collect(),
+    }
+}
+
+/// Capitalize the first letter of each word. The rest of each word is kept so acronyms are not
+/// changed. `language` is an ISO 639-1 code used for casing rules that differ between languages.
+/// Scripts without case, like Japanese, are returned unchanged.
+pub fn title_case(s: &str, language: Option<&str>) -> String {
+    normalize_text(s)
+        .split(' ')
+        .map(|word| uppercase_first(word, language))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_text, same_text, title_case};
+
+    #[test]
+    fn normalization() {
+        // "å" written as "a" followed by a combining ring
+        assert_eq!(normalize_text("Ma\u{30a}ne  og\u{a0}sol "), "Måne og sol");
+        assert_eq!(normalize_text("ワンピース\u{3000}第1話"), "ワンピース 第1話");
+        assert!(same_text("ÆBLEKAGEN", "æblekagen"));
+        assert!(same_text("Ma\u{30a}neskin", "månesKin"));
+        assert!(!same_text("One Piece", "One Punch"));
+    }
+
+    #[test]
+    fn title_casing() {
+        assert_eq!(title_case("ødeland og X-MEN", Some("da")), "Ødeland Og X-MEN");
+        assert_eq!(title_case("istanbul", Some("tr")), "İstanbul");
+        assert_eq!(title_case("istanbul", Some("en")), "Istanbul");
+        assert_eq!(title_case("ijsland", Some("nl")), "IJsland");
+        assert_eq!(title_case("ワンピース", Some("ja")), "ワンピース");
+    }
+}
@@ -0,0 +1,360 @@
+use crate::metadata::{Metadata, MetadataFormat};
+use crate::comic::{Comic, ComicFormat, ComicTaggerNaming, MissingField, PageFilter, PageType, Replacement, TemplateOptions, WriteOptions, sanitize_path};
+use serde::{Deserialize, Deserializer};
+use std::str::FromStr;
+
+/// Options for downloading and writing comics
+///
+/// ```ignore
+/// let options = DownloadOptions::new()
+///     .template("{publisher}/{series}/{title}.cbz")
+///     .concurrency(2)
+///     .overwrite(OverwritePolicy::IfPageCountDiffers);
+/// ```
+#[derive(Clone, Debug)]
+pub struct DownloadOptions {
+    /// Template for output path of comics
+    pub template: String,
+    /// Output format of comics
+    pub format: ComicFormat,
+    /// Number of comics downloaded at the same time
+    pub concurrency: usize,
+    /// When already existing files are overwritten
+    pub overwrite: OverwritePolicy,
+    /// Store source, download time, and page urls in written comics
+    pub provenance: bool,
+    /// Options for formatting output path
+    pub template_options: TemplateOptions,
+    /// Options for writing pages
+    pub write_options: WriteOptions,
+    /// Name files like ComicTagger instead of with the file name in the template
+    pub comictagger_naming: Option<ComicTaggerNaming>,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            template: "{series}/{title}.cbz".to_string(),
+            format: ComicFormat::default(),
+            concurrency: 5,
+            overwrite: OverwritePolicy::Never,
+            provenance: false,
+            template_options: TemplateOptions::default(),
+            write_options: WriteOptions::default(),
+            comictagger_naming: None,
+        }
+    }
+}
+
+impl DownloadOptions {
+    /// Create new `DownloadOptions` with default values
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set template for output path
+    pub fn template(mut self, template: &str) -> Self {
+        self.template = template.to_string();
+        self
+    }
+
+    /// Set output format
+    pub fn format(mut self, format: ComicFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set number of comics downloaded at the same time
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set when already existing files should be overwritten. `true` and `false` are the same
+    /// as `OverwritePolicy::Always` and `OverwritePolicy::Never`.
+    pub fn overwrite(mut self, overwrite: impl Into<OverwritePolicy>) -> Self {
+        self.overwrite = overwrite.into();
+        self
+    }
+
+    /// Set if provenance info should be stored in written comics
+    pub fn provenance(mut self, provenance: bool) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Set how fields in template missing from comics are handled
+    pub fn missing_fields(mut self, missing_fields: MissingField) -> Self {
+        self.template_options.missing_fields = missing_fields;
+        self
+    }
+
+    /// Set separator between names of authors in template fields with multiple authors
+    pub fn author_separator(mut self, separator: &str) -> Self {
+        self.template_options.author_separator = separator.to_string();
+        self
+    }
+
+    /// Set replacements applied to template fields before formatting
+    pub fn replacements(mut self, replacements: Vec<Replacement>) -> Self {
+        self.template_options.replacements = replacements;
+        self
+    }
+
+    /// Set maximum number of names in template fields with multiple authors
+    pub fn max_authors(mut self, max_authors: Option<usize>) -> Self {
+        self.template_options.max_authors = max_authors;
+        self
+    }
+
+    /// Capitalize each word in titles and series names in templates
+    pub fn title_case(mut self, title_case: bool) -> Self {
+        self.template_options.title_case = title_case;
+        self
+    }
+
+    /// Set rules for removing pages when writing comics
+    pub fn page_filter(mut self, page_filter: PageFilter) -> Self {
+        self.write_options.page_filter = page_filter;
+        self
+    }
+
+    /// Set template for file names of pages
+    pub fn page_template(mut self, template: &str) -> Self {
+        self.write_options.page_template = template.to_string();
+        self
+    }
+
+    /// Set number of the first page in file names
+    pub fn first_page(mut self, first_page: u32) -> Self {
+        self.write_options.first_page = first_page;
+        self
+    }
+
+    /// Set metadata formats written to comics
+    pub fn metadata_formats(mut self, formats: &[MetadataFormat]) -> Self {
+        self.write_options.metadata_formats = formats.to_vec();
+        self
+    }
+
+    /// Set ComicTagger settings used for naming files
+    pub fn comictagger_naming(mut self, naming: Option<ComicTaggerNaming>) -> Self {
+        self.comictagger_naming = naming;
+        self
+    }
+
+    /// Path of `comic` formatted with template
+    pub fn format_path(&self, comic: &Comic) -> crate::Result<String> {
+        let path = comic.format_with_options(&self.template, &self.template_options)?;
+        let path = match &self.comictagger_naming {
+            // Directories and extension are kept from the template
+            Some(naming) => {
+                let mut name = naming.file_name(&comic.metadata);
+                let last = self.template.rsplit('/').next().unwrap_or_default();
+                if let Some(extension) = std::path::Path::new(last).extension().and_then(|x| x.to_str()) {
+                    name = format!("{}.{}", name, extension);
+                }
+                match path.rsplit_once('/') {
+                    Some((dir, _)) => format!("{}/{}", dir, name),
+                    None => name,
+                }
+            },
+            None => path,
+        };
+        Ok(sanitize_path(&path))
+    }
+
+    /// Output path of `comic`. Returns `None` if the file already exists and should not be
+    /// overwritten.
+    pub fn output_path(&self, comic: &Comic) -> crate::Result<Option<String>> {
+        let path = self.format_path(comic)?;
+        if !std::path::Path::new(&path).exists() || self.overwrite.should_overwrite(comic, &path, &self.write_options.page_filter) {
+            Ok(Some(path))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Write `comic` to `path`. An existing file is only replaced when the finished comic passes
+    /// the overwrite policy. Returns true if the comic was written.
+    pub async fn write(&self, comic: &Comic, path: &str, client: &reqwest::Client) -> crate::Result<bool> {
+        comic.write_replacing(path, &self.format, client, &self.write_options, |new| {
+            Ok(self.overwrite.should_replace(new, path))
+        }).await
+    }
+}
+
+/// When comics are written over already existing files
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OverwritePolicy {
+    /// Existing files are kept
+    #[default]
+    Never,
+    /// Existing files are always replaced
+    Always,
+    /// Replace existing file if the new comic takes up more space on disk
+    IfLarger,
+    /// Replace existing file if the source has newer metadata
+    IfNewerMetadata,
+    /// Replace existing file if the number of pages has changed
+    IfPageCountDiffers,
+}
+
+impl From<bool> for OverwritePolicy {
+    fn from(overwrite: bool) -> Self {
+        if overwrite { Self::Always } else { Self::Never }
+    }
+}
+
+impl FromStr for OverwritePolicy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "never" | "false" => Ok(Self::Never),
+            "always" | "true" => Ok(Self::Always),
+            "if-larger" => Ok(Self::IfLarger),
+            "if-newer-metadata" => Ok(Self::IfNewerMetadata),
+            "if-page-count-differs" => Ok(Self::IfPageCountDiffers),
+            _ => Err("Could not parse overwrite policy (Either never, always, if-larger, if-newer-metadata, or if-page-count-differs)")
+        }
+    }
+}
+
+/// Policies can also be given as booleans like the old `overwrite` setting
+impl<'de> Deserialize<'de> for OverwritePolicy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Value {
+            Bool(bool),
+            Name(String),
+        }
+        match Value::deserialize(deserializer)? {
+            Value::Bool(overwrite) => Ok(overwrite.into()),
+            Value::Name(name) => name.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+impl OverwritePolicy {
+    /// Returns true if `comic` should be written over the existing file at `path`. Files that
+    /// can not be read are always replaced. Pages removed by the url patterns in `filter` are not
+    /// counted, while pages removed by their content are only known after downloading.
+    /// `IfLarger` always writes the comic and is decided by `should_replace` afterwards.
+    pub fn should_overwrite(&self, comic: &Comic, path: &str, filter: &PageFilter) -> bool {
+        let existing = match self {
+            Self::Never => return false,
+            Self::Always | Self::IfLarger => return true,
+            Self::IfNewerMetadata | Self::IfPageCountDiffers => Comic::from_file(path),
+        };
+        let existing = match existing {
+            Ok(existing) => existing,
+            Err(e) => {
+                log::debug!("Replacing unreadable file {}: {}", path, e);
+                return true;
+            }
+        };
+        if *self == Self::IfNewerMetadata {
+            return metadata_newer(&existing.metadata, &comic.metadata);
+        }
+        let pages = comic.pages.iter()
+            .filter(|page| match &page.page_type {
+                PageType::Url(x) => filter.check_url(&x.url).is_none(),
+                _ => true,
+            })
+            .count();
+        pages != existing.pages.len()
+    }
+
+    /// Returns true if the newly written comic at `new_path` should replace the existing file at
+    /// `path`
+    pub fn should_replace(&self, new_path: &str, path: &str) -> bool {
+        match self {
+            Self::IfLarger => match (disk_size(new_path.as_ref()), disk_size(path.as_ref())) {
+                (Ok(new), Ok(existing)) => new > existing,
+                _ => true,
+            },
+            _ => true,
+        }
+    }
+}
+
+/// Size of file or all files in directory
+fn disk_size(path: &std::path::Path) -> std::io::Result<u64> {
+    let metadata = std::fs::metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let mut size = 0;
+    for entry in std::fs::read_dir(path)? {
+        size += disk_size(&entry?.path())?;
+    }
+    Ok(size)
+}
+
+/// Returns true if `new` has values that differ from `existing`, keeps every field of
+/// `existing` and is not released earlier. Fields missing from `existing` are ignored, since not
+/// all metadata formats store every field, and provenance is ignored since it changes with every
+/// download.
+fn metadata_newer(existing: &Metadata, new: &Metadata) -> bool {
+    let fields = |metadata: &Metadata| match serde_json::to_value(metadata) {
+        Ok(serde_json::Value::Object(fields)) => fields,
+        _ => serde_json::Map::new(),
+    };
+    let is_set = |value: &serde_json::Value| !value.is_null()
+        && value.as_array().map(|x| !x.is_empty()).unwrap_or(true);
+    let date = |metadata: &Metadata| metadata.year.map(|year| (year, metadata.month, metadata.day));
+    if let (Some(existing), Some(new)) = (date(existing), date(new)) {
+        if new < existing {
+            return false;
+        }
+    }
+    let (existing, new) = (fields(existing), fields(new));
+    let existing: Vec<_> = existing.iter()
+        .filter(|(name, value)| *name != "provenance" && is_set(value))
+        .collect();
+    existing.iter().all(|(name, _)| new.get(*name).map(is_set).unwrap_or(false))
+        && existing.iter().any(|(name, value)| new.get(*name) != Some(*value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OverwritePolicy, metadata_newer};
+    use crate::metadata::Metadata;
+
+    #[test]
+    fn overwrite_policies() {
+        assert_eq!("if-page-count-differs".parse(), Ok(OverwritePolicy::IfPageCountDiffers));
+        assert_eq!(serde_json::from_str::<OverwritePolicy>("true").unwrap(), OverwritePolicy::Always);
+        assert_eq!(serde_json::from_str::<OverwritePolicy>("\"if-larger\"").unwrap(), OverwritePolicy::IfLarger);
+        assert!(serde_json::from_str::<OverwritePolicy>("\"sometimes\"").is_err());
+    }
+
+    #[test]
+    fn larger_files_replace() {
+        let dir = std::env::temp_dir().join(format!("grawlix-overwrite-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (small, large) = (dir.join("small.cbz"), dir.join("large.cbz"));
+        std::fs::write(&small, [0; 10]).unwrap();
+        std::fs::write(&large, [0; 20]).unwrap();
+        let (small, large) = (small.to_str().unwrap(), large.to_str().unwrap());
+        assert!(OverwritePolicy::IfLarger.should_replace(large, small));
+        assert!(!OverwritePolicy::IfLarger.should_replace(small, large));
+        assert!(OverwritePolicy::Always.should_replace(small, large));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn newer_metadata() {
+        let existing = Metadata { title: Some("Moon Knight #1".to_string()), year: Some(2016), ..Default::default() };
+        let more_fields = Metadata { description: Some("Who is Moon Knight?".to_string()), ..existing.clone() };
+        assert!(!metadata_newer(&existing, &more_fields));
+        let retitled = Metadata { title: Some("Moon Knight (2016) #1".to_string()), ..existing.clone() };
+        assert!(metadata_newer(&existing, &retitled));
+        let fewer_fields = Metadata { year: None, ..retitled.clone() };
+        assert!(!metadata_newer(&existing, &fewer_fields));
+        let older = Metadata { year: Some(2015), ..retitled };
+        assert!(!metadata_newer(&existing, &older));
+    }
+}
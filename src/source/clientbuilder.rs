@@ -1,15 +1,135 @@
-use std::collections::HashMap;
+use crate::error::GrawlixDownloadError;
+use std::{collections::HashMap, sync::RwLock, time::Duration};
+
+/// Connection settings for reqwest clients
+#[derive(Clone, Debug)]
+pub struct ClientOptions {
+    /// Maximum time to establish a connection
+    pub connect_timeout: Option<Duration>,
+    /// Maximum time between receiving two parts of a response
+    pub read_timeout: Option<Duration>,
+    /// Maximum time for a whole request
+    pub timeout: Option<Duration>,
+    /// Maximum number of idle connections kept open per host
+    pub pool_max_idle_per_host: Option<usize>,
+    /// Allow HTTP/2 connections. Only HTTP/1 is used if disabled.
+    pub http2: bool,
+}
+
+impl ClientOptions {
+    const fn new() -> Self {
+        Self {
+            connect_timeout: Some(Duration::from_secs(30)),
+            read_timeout: Some(Duration::from_secs(60)),
+            timeout: Some(Duration::from_secs(300)),
+            pool_max_idle_per_host: None,
+            http2: true,
+        }
+    }
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Options used by all new clients
+static CLIENT_OPTIONS: RwLock<ClientOptions> = RwLock::new(ClientOptions::new());
+
+/// Set options used by all clients created after this call. This includes clients created by
+/// sources when they authenticate.
+pub fn set_client_options(options: ClientOptions) {
+    *CLIENT_OPTIONS.write().unwrap() = options;
+}
+
+/// Options used by new clients
+pub fn client_options() -> ClientOptions {
+    CLIENT_OPTIONS.read().unwrap().clone()
+}
+
+/// Read body of `response`. Fails if no data has been received for longer than the read timeout
+/// in `client_options`.
+pub async fn read_response(mut response: reqwest::Response) -> Result<bytes::Bytes, GrawlixDownloadError> {
+    let read_timeout = client_options().read_timeout;
+    let url = response.url().to_string();
+    let mut body = Vec::new();
+    loop {
+        let chunk = match read_timeout {
+            Some(duration) => tokio::time::timeout(duration, response.chunk()).await
+                .map_err(|_| GrawlixDownloadError::Timeout { url: url.clone() })?,
+            None => response.chunk().await,
+        };
+        match chunk? {
+            Some(chunk) => body.extend_from_slice(&chunk),
+            None => return Ok(body.into()),
+        }
+    }
+}
+
+/// Run request to `url`. The request is cancelled if it takes longer than the timeout in
+/// `client_options`, which also applies to clients not created with `ClientBuilder`.
+pub async fn with_timeout<T, F>(url: &str, request: F) -> Result<T, GrawlixDownloadError>
+where
+    F: std::future::Future<Output = Result<T, GrawlixDownloadError>>,
+{
+    let result = match client_options().timeout {
+        Some(duration) => tokio::time::timeout(duration, request).await
+            .unwrap_or_else(|_| Err(GrawlixDownloadError::Timeout { url: url.to_string() })),
+        None => request.await,
+    };
+    result.map_err(|e| match e {
+        GrawlixDownloadError::RequestError(e) if e.is_timeout() => {
+            GrawlixDownloadError::Timeout { url: url.to_string() }
+        },
+        e => e,
+    })
+}
 
 /// Builder for reqwest client
-#[derive(Default)]
 pub struct ClientBuilder {
     headers: Vec<(String, String)>,
     cookies: Vec<(String, String)>,
+    options: ClientOptions,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            headers: Vec::new(),
+            cookies: Vec::new(),
+            options: client_options(),
+        }
+    }
 }
 
 
 impl ClientBuilder {
 
+    /// Set maximum time to establish a connection
+    pub fn connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.options.connect_timeout = timeout;
+        self
+    }
+
+    /// Set maximum time for a whole request
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.options.timeout = timeout;
+        self
+    }
+
+    /// Set maximum number of idle connections per host
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.options.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// Enable or disable HTTP/2
+    pub fn http2(mut self, enabled: bool) -> Self {
+        self.options.http2 = enabled;
+        self
+    }
+
     pub fn cookie<S: ToString>(mut self, key: S, value: S) -> Self {
         self.add_cookie(key, value);
         self
@@ -29,7 +149,19 @@ impl ClientBuilder {
     }
 
     pub fn to_reqwest_client(&self) -> reqwest::Client {
-        let reqwest_builder = reqwest::Client::builder();
+        let mut reqwest_builder = reqwest::Client::builder();
+        if let Some(timeout) = self.options.connect_timeout {
+            reqwest_builder = reqwest_builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.options.timeout {
+            reqwest_builder = reqwest_builder.timeout(timeout);
+        }
+        if let Some(max) = self.options.pool_max_idle_per_host {
+            reqwest_builder = reqwest_builder.pool_max_idle_per_host(max);
+        }
+        if !self.options.http2 {
+            reqwest_builder = reqwest_builder.http1_only();
+        }
         let mut headers = create_reqwest_headermap(&self.headers);
         headers.insert(
             reqwest::header::COOKIE,
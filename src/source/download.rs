@@ -1,11 +1,16 @@
-use super::{ComicId, Source, Request, SourceResponse, Result, Error, SeriesInfo, ClientBuilder};
+use super::{ComicId, Source, Request, SourceResponse, Result, Error, SeriesInfo, ClientBuilder, CONNECTION_LIMITS, FIXTURES, ID_CACHE, METRICS, read_response, with_timeout};
 use crate::{
     comic::Comic, metadata::{Metadata, Identifier}
 };
 use async_recursion::async_recursion;
-use futures::{StreamExt, TryStreamExt, stream};
+use futures::{Stream, StreamExt, TryStreamExt, stream};
 use reqwest::Client;
 use log::{debug, trace};
+use std::time::Instant;
+use tracing::Instrument;
+
+/// Number of comics downloaded at the same time by default
+pub const DEFAULT_CONCURRENCY: usize = 5;
 
 /// Create new default `reqwest::Client` to use in `Source`
 pub fn create_default_client() -> ClientBuilder {
@@ -19,22 +24,21 @@ pub async fn download_comics_from_url(url: &str) -> Result<Vec<Comic>> {
     let mut client = source.create_client();
     let comicid = source.id_from_url(url)?;
     debug!("Got id from url: {:?}", comicid);
-    let all_ids = get_all_ids(&source, &mut client, comicid).await?;
-    download_comics(all_ids, &client, &source).await
+    let all_ids = get_all_ids(&*source, &mut client, comicid).await?;
+    download_comics(all_ids, &client, &*source).await
 }
 
 /// Downloads `Metadata` from comicid if `Issue` and extracts metadata if `IssueWithMetadata` and
 /// adds identifier for current source
-async fn metadata_from_comicid(source: &Box<dyn Source>, client: &Client, comicid: ComicId) -> Result<Metadata> {
+pub async fn metadata_from_comicid(source: &dyn Source, client: &Client, comicid: ComicId) -> Result<Metadata> {
     let id_str = comicid.inner().clone(); // Needed later
     // Extract or download metadata
     let mut metadata = match comicid {
-        ComicId::Issue(_) => {
-            let metadata_response = source.get_metadata(&client, &comicid)?;
+        ComicId::IssueWithMetadata(_, meta) => meta,
+        _ => {
+            let metadata_response = source.get_metadata(&client, comicid.issue_id()?)?;
             eval_source_response(metadata_response).await?
         },
-        ComicId::IssueWithMetadata(_, meta) => meta,
-        _ => unreachable!()
     };
     // Add identifier for current source
     metadata.identifiers.push(Identifier {
@@ -44,13 +48,52 @@ async fn metadata_from_comicid(source: &Box<dyn Source>, client: &Client, comici
     Ok(metadata)
 }
 
+/// What to download when resolving a comic
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResolveMode {
+    /// Download both metadata and pages
+    Full,
+    /// Only download metadata. The comic will have no pages.
+    MetadataOnly,
+    /// Only download pages. Metadata is only available if the source includes it in the id.
+    PagesOnly,
+}
+
 /// Creates `Comic` from comicid
-pub async fn comic_from_comicid(source: &Box<dyn Source>, client: &Client, comicid: ComicId) -> Result<Comic> {
-    let pages_response = source.get_pages(&client, &comicid)?;
-    log::trace!("Retrieving pages");
-    let pages = eval_source_response(pages_response).await?;
-    log::trace!("Retrieving metadata");
-    let metadata = metadata_from_comicid(source, client, comicid).await?;
+pub async fn comic_from_comicid(source: &dyn Source, client: &Client, comicid: ComicId) -> Result<Comic> {
+    comic_from_comicid_with_mode(source, client, comicid, ResolveMode::Full).await
+}
+
+/// Creates `Comic` from comicid. Metadata and pages are retrieved at the same time.
+pub async fn comic_from_comicid_with_mode(
+    source: &dyn Source,
+    client: &Client,
+    comicid: ComicId,
+    mode: ResolveMode
+) -> Result<Comic> {
+    let pages = async {
+        if mode == ResolveMode::MetadataOnly {
+            return Ok::<_, Error>(Vec::new());
+        }
+        log::trace!("Retrieving pages");
+        let pages_response = source.get_pages(&client, comicid.issue_id()?)?;
+        eval_source_response(pages_response).await
+    };
+    let metadata = async {
+        match (mode, &comicid) {
+            (ResolveMode::PagesOnly, ComicId::IssueWithMetadata(_, meta)) => Ok(meta.clone()),
+            (ResolveMode::PagesOnly, _) => Ok(Metadata::default()),
+            _ => {
+                log::trace!("Retrieving metadata");
+                metadata_from_comicid(source, client, comicid.clone()).await
+            }
+        }
+    };
+    let span = tracing::info_span!("comic", source = %source.name(), id = %comicid.inner());
+    let (pages, metadata) = async { futures::try_join!(pages, metadata) }
+        .instrument(span)
+        .await?;
+    METRICS.record_comic();
     Ok(Comic {
         pages,
         metadata,
@@ -59,39 +102,102 @@ pub async fn comic_from_comicid(source: &Box<dyn Source>, client: &Client, comic
 }
 
 /// Download all comics from ids
-pub async fn download_comics(comic_ids: Vec<ComicId>, client: &Client, source: &Box<dyn Source>) -> Result<Vec<Comic>> {
-    stream::iter(comic_ids)
-        .map(|comicid| {
-            let source = &source;
-            let client = &client;
-            async move {
-                comic_from_comicid(source, client, comicid).await
-            }
-        })
-        .buffered(5)
+pub async fn download_comics(comic_ids: Vec<ComicId>, client: &Client, source: &dyn Source) -> Result<Vec<Comic>> {
+    download_comics_stream(comic_ids, client, source, DEFAULT_CONCURRENCY)
         .try_collect()
         .await
 }
 
-/// Download series metadata
-pub async fn download_series_metadata(client: &Client, source: &Box<dyn Source>, comicid: &ComicId) -> Result<SeriesInfo> {
-    let request = source.get_series_info(client, comicid)?;
-    let series_info = eval_source_response(request).await?;
-    Ok(series_info)
+/// Download comics from ids as a stream. Up to `concurrency` comics are downloaded at the same
+/// time and each comic is returned in order as soon as it is ready.
+pub fn download_comics_stream<'a>(
+    comic_ids: Vec<ComicId>,
+    client: &'a Client,
+    source: &'a dyn Source,
+    concurrency: usize,
+) -> impl Stream<Item = Result<Comic>> + 'a {
+    stream::iter(comic_ids)
+        .map(move |comicid| comic_from_comicid(source, client, comicid))
+        .buffered(concurrency.max(1))
+}
+
+/// Find ids of the `count` most recent comics in series
+pub async fn get_latest_ids(
+    source: &dyn Source,
+    client: &Client,
+    seriesid: &ComicId,
+    count: usize
+) -> Result<Vec<ComicId>> {
+    let latest = eval_source_response(source.get_latest(client, seriesid.series_id()?, count)?).await?;
+    let mut result = Vec::new();
+    for comicid in latest {
+        result.append(&mut get_all_ids(source, client, comicid).await?);
+    }
+    Ok(result)
 }
 
+/// Find ids of all comics the authenticated account has access to
+pub async fn get_owned_ids(source: &dyn Source, client: &Client) -> Result<Vec<ComicId>> {
+    eval_source_response(source.get_owned(client)?).await
+}
+
+/// Download series metadata. Sources without series metadata get it from the first issue of the
+/// series instead.
+pub async fn download_series_metadata(client: &Client, source: &dyn Source, comicid: &ComicId) -> Result<SeriesInfo> {
+    match source.get_series_info(client, comicid.series_id()?) {
+        Ok(request) => eval_source_response(request).await,
+        Err(Error::SeriesInfoNotSupported(_)) => {
+            debug!("Finding series info of {} from first issue", comicid.inner());
+            series_info_from_issues(client, source, comicid).await
+        },
+        Err(e) => Err(e),
+    }
+}
+
+/// Series metadata derived from the metadata of the first issue in series. Sources list issues
+/// either oldest or newest first, so the first and last issues are compared to find the first.
+async fn series_info_from_issues(client: &Client, source: &dyn Source, comicid: &ComicId) -> Result<SeriesInfo> {
+    let mut ids = get_all_ids(source, client, comicid.clone()).await?;
+    let last = if ids.len() > 1 { ids.pop() } else { None };
+    let metadata = match ids.into_iter().next() {
+        Some(id) => metadata_from_comicid(source, client, id).await?,
+        None => Metadata::default(),
+    };
+    let metadata = match last {
+        Some(id) => earliest(metadata, metadata_from_comicid(source, client, id).await?),
+        None => metadata,
+    };
+    Ok(SeriesInfo {
+        name: metadata.series.or(metadata.title).unwrap_or_else(|| comicid.inner().clone()),
+        start_year: metadata.year,
+        publisher: metadata.publisher,
+        ..Default::default()
+    })
+}
+
+/// The issue of `a` and `b` that comes first in the series, by issue number or else release date
+fn earliest(a: Metadata, b: Metadata) -> Metadata {
+    let b_first = match (a.issue_number, b.issue_number) {
+        (Some(x), Some(y)) => y < x,
+        // Issues without a release year are not compared by date
+        _ => match (a.year, b.year) {
+            (Some(_), Some(_)) => (b.year, b.month, b.day) < (a.year, a.month, a.day),
+            _ => false,
+        },
+    };
+    if b_first { b } else { a }
+}
+
+/// Download metadata of all comics in `comicid` without downloading pages
 pub async fn download_comics_metadata(
-    source: &mut Box<dyn Source>,
-    url: &str,
+    source: &dyn Source,
+    client: &Client,
+    comicid: ComicId,
 ) -> Result<Vec<Metadata>> {
-    let mut client = source.create_client();
-    let comicid = source.id_from_url(url)?;
-    let all_ids = get_all_ids(&source, &mut client, comicid).await?;
+    let all_ids = get_all_ids(source, client, comicid).await?;
     let mut metadata = Vec::new();
-    for i in all_ids {
-        let response = source.get_metadata(&client, &i)?;
-        let content = eval_source_response(response).await?;
-        metadata.push(content);
+    for id in all_ids {
+        metadata.push(metadata_from_comicid(source, client, id).await?);
     }
     return Ok(metadata);
 }
@@ -112,55 +218,175 @@ async fn make_request<T>(request: Request<T>) -> Result<T> {
     let mut responses = Vec::new();
     trace!("Making request");
     for request in request.requests {
-        let bytes = request
-            .send()
-            .await?
-            .bytes()
-            .await?;
-        responses.push(bytes);
+        // Building a copy of the request to find its url without consuming it
+        let url = request.try_clone()
+            .and_then(|x| x.build().ok())
+            .map(|x| x.url().to_string())
+            .unwrap_or_default();
+        let span = tracing::debug_span!("request", url = %url);
+        let _permit = CONNECTION_LIMITS.acquire(&url).await;
+        let start = Instant::now();
+        let response = with_timeout(&url, async { read_response(request.send().await?).await })
+            .instrument(span)
+            .await;
+        match response {
+            Ok(bytes) => {
+                METRICS.record_request(bytes.len(), start.elapsed());
+                FIXTURES.record(&url, &bytes);
+                responses.push(bytes);
+            },
+            Err(e) => {
+                METRICS.record_failed_request();
+                return Err(e);
+            }
+        }
     }
     trace!("Transforming response");
     (request.transform)(&responses).ok_or(Error::FailedResponseParse)
 }
 
-#[async_recursion(?Send)]
+/// Resolve `ComicId::Other` with `Source::get_correct_id` or find it in `ID_CACHE`
+async fn correct_id(source: &dyn Source, client: &Client, id: &str) -> Result<ComicId> {
+    let name = source.name();
+    if let Some(new_id) = ID_CACHE.get(&name, id) {
+        trace!("Using previously resolved id for {}", id);
+        return Ok(new_id);
+    }
+    let new_id = eval_source_response(source.get_correct_id(client, id)?).await?;
+    // Metadata in ids can change, so only plain ids are cached
+    if let ComicId::Issue(_) | ComicId::Series(_) = new_id {
+        ID_CACHE.insert(&name, id, new_id.clone());
+    }
+    Ok(new_id)
+}
+
+/// Find ids of all comics in `comicid`. Fails if any id in a series could not be resolved.
 pub async fn get_all_ids(
-    source: &Box<dyn Source>,
+    source: &dyn Source,
     client: &Client,
     comicid: ComicId
 ) -> Result<Vec<ComicId>> {
+    Ok(resolve_ids(source, client, comicid, false).await?.0)
+}
+
+/// Find ids of all comics in `comicid`. Ids in a series that could not be resolved are skipped
+/// and returned with their error instead of failing the whole series.
+pub async fn get_all_ids_partial(
+    source: &dyn Source,
+    client: &Client,
+    comicid: ComicId
+) -> Result<(Vec<ComicId>, Vec<(String, Error)>)> {
+    resolve_ids(source, client, comicid, true).await
+}
+
+/// Find ids of all comics in `comicid`. Errors of ids in a series are collected if `partial` is
+/// enabled.
+#[async_recursion]
+async fn resolve_ids(
+    source: &dyn Source,
+    client: &Client,
+    comicid: ComicId,
+    partial: bool,
+) -> Result<(Vec<ComicId>, Vec<(String, Error)>)> {
     Ok(match comicid {
-        ComicId::Other(_) => {
-            let new_id_request = source.get_correct_id(client, &comicid)?;
-            let new_id = eval_source_response(new_id_request).await?;
-            get_all_ids(source, client, new_id).await?
+        ComicId::Other(id) => {
+            let new_id = correct_id(source, client, &id).await?;
+            resolve_ids(source, client, new_id, partial).await?
         },
         ComicId::OtherWithMetadata(id, meta) => {
-            let new_ids = get_all_ids(source, client, ComicId::Other(id)).await?;
+            let (new_ids, errors) = resolve_ids(source, client, ComicId::Other(id), partial).await?;
             match &new_ids[..] {
-                [ComicId::Issue(x)] => vec![ComicId::IssueWithMetadata(x.to_string(), meta)],
-                _ => new_ids,
+                [ComicId::Issue(x)] => (vec![ComicId::IssueWithMetadata(x.clone(), meta)], errors),
+                _ => (new_ids, errors),
             }
         }
-        ComicId::Series(_) => {
+        ComicId::Series(ref seriesid) => {
             // Ids of each issue in series
-            let new_ids = eval_source_response(source.get_series_ids(client, &comicid)?).await?;
-            // let mut result = Vec::new();
+            let new_ids = eval_source_response(source.get_series_ids(client, seriesid)?).await?;
             let evaluated_ids = stream::iter(new_ids)
                 .map(|new_id| async move {
-                    get_all_ids(source, client, new_id).await
+                    let id = new_id.inner().clone();
+                    resolve_ids(source, client, new_id, partial).await
+                        .map_err(|e| (id, e))
                 })
                 .buffered(5)
-                .collect::<Vec<Result<Vec<ComicId>>>>().await;
+                .collect::<Vec<_>>().await;
             // Evaluating new ids
             let mut result = Vec::new();
+            let mut errors = Vec::new();
             for id in evaluated_ids {
-                result.append(&mut id?);
+                match id {
+                    Ok((mut ids, mut id_errors)) => {
+                        result.append(&mut ids);
+                        errors.append(&mut id_errors);
+                    },
+                    Err((id, e)) if partial => {
+                        debug!("Failed to resolve {}: {}", id, e);
+                        errors.push((id, e));
+                    },
+                    Err((_, e)) => return Err(e),
+                }
             }
             debug!("Finished downloading series ids for {:?}", comicid);
-            result
+            (result, errors)
         },
-        ComicId::Issue(_) => vec![comicid],
-        ComicId::IssueWithMetadata(..) => vec![comicid],
+        ComicId::Issue(_) => (vec![comicid], Vec::new()),
+        ComicId::IssueWithMetadata(..) => (vec![comicid], Vec::new()),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ComicId, ID_CACHE, get_all_ids};
+    use crate::source::{IssueId, source_from_name};
+    use futures::FutureExt;
+
+    /// Source with a single series listed newest first and no series metadata
+    struct NoSeriesInfo;
+
+    impl crate::source::Source for NoSeriesInfo {
+        fn name(&self) -> String {
+            "NoSeriesInfo".to_string()
+        }
+        fn id_from_url(&self, _url: &str) -> crate::source::Result<ComicId> {
+            Ok(ComicId::Series("series".into()))
+        }
+        fn get_series_ids(&self, _client: &reqwest::Client, _seriesid: &crate::source::SeriesId) -> crate::source::Result<crate::source::SourceResponse<Vec<ComicId>>> {
+            let issue = |number: u32, year: u32| ComicId::IssueWithMetadata(number.to_string().into(), crate::metadata::Metadata {
+                title: Some(format!("Moon Knight #{}", number)),
+                series: Some("Moon Knight".to_string()),
+                issue_number: Some(number),
+                year: Some(year),
+                ..Default::default()
+            });
+            Ok(crate::source::SourceResponse::Value(vec![issue(2, 2017), issue(1, 2016)]))
+        }
+        fn get_metadata(&self, _client: &reqwest::Client, issueid: &IssueId) -> crate::source::Result<crate::source::SourceResponse<crate::metadata::Metadata>> {
+            Err(crate::source::Error::NotAnIssue(issueid.to_string()))
+        }
+    }
+
+    #[test]
+    fn series_info_from_first_issue() {
+        let info = super::download_series_metadata(&reqwest::Client::new(), &NoSeriesInfo, &ComicId::Series("series".into()))
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.name, "Moon Knight");
+        assert_eq!(info.start_year, Some(2016));
+    }
+
+    #[test]
+    fn resolved_ids_are_reused() {
+        let source = source_from_name("webtoon").unwrap();
+        let client = source.create_client();
+        let resolved = ComicId::Issue(IssueId::from("123"));
+        ID_CACHE.insert(&source.name(), "cached", resolved.clone());
+        // No request is made since the id has already been resolved
+        let ids = get_all_ids(&*source, &client, ComicId::Other("cached".to_string()))
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        assert_eq!(ids, vec![resolved]);
+    }
+}
@@ -0,0 +1,81 @@
+use std::{
+    io::Write,
+    path::PathBuf,
+    sync::Mutex,
+};
+
+/// Saves responses from sources to disk so they can be used as test data
+pub struct FixtureRecorder {
+    /// Output directory and number of recorded responses
+    state: Mutex<Option<(PathBuf, usize)>>,
+}
+
+/// Recorder used for all requests made by grawlix
+pub static FIXTURES: FixtureRecorder = FixtureRecorder::new();
+
+impl FixtureRecorder {
+    const fn new() -> Self {
+        Self { state: Mutex::new(None) }
+    }
+
+    /// Start writing responses to `dir`
+    pub fn start(&self, dir: impl Into<PathBuf>) -> std::io::Result<()> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        *self.state.lock().unwrap() = Some((dir, 0));
+        Ok(())
+    }
+
+    /// Write response from `url` to recording directory if recording is enabled. Responses are
+    /// named after the site they came from, and the url of each file is added to `index.txt`.
+    pub fn record(&self, url: &str, data: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        if let Some((dir, count)) = state.as_mut() {
+            *count += 1;
+            let filename = fixture_name(url, *count, data);
+            let result = std::fs::write(dir.join(&filename), data)
+                .and_then(|_| {
+                    std::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(dir.join("index.txt"))?
+                        .write_all(format!("{} {}\n", filename, url).as_bytes())
+                });
+            if let Err(e) = result {
+                log::warn!("Failed to record response from {}: {}", url, e);
+            }
+        }
+    }
+}
+
+/// Name of test data file in the same style as the files in `tests/source_data`
+fn fixture_name(url: &str, n: usize, data: &[u8]) -> String {
+    let site = reqwest::Url::parse(url).ok()
+        .and_then(|x| x.host_str().map(site_name))
+        .unwrap_or_else(|| "unknown".to_string());
+    let extension = match data.iter().find(|x| !x.is_ascii_whitespace()) {
+        Some(b'{') | Some(b'[') => ".json",
+        Some(b'<') => ".html",
+        _ => "",
+    };
+    format!("{}_{:03}{}", site, n, extension)
+}
+
+/// Name of site without subdomains and top level domain
+fn site_name(host: &str) -> String {
+    let parts: Vec<&str> = host.split('.').collect();
+    match parts.len() {
+        0 | 1 => host.to_string(),
+        n => parts[n - 2].to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn fixture_names() {
+        assert_eq!(super::fixture_name("https://www.marvel.com/comics/issue/1", 1, b"<html>"), "marvel_001.html");
+        assert_eq!(super::fixture_name("https://api.izneo.com/series", 12, b" {}"), "izneo_012.json");
+        assert_eq!(super::fixture_name("https://jumpg-webapi.tokyo-cdn.com/api", 3, &[8, 1]), "tokyo-cdn_003");
+    }
+}
@@ -0,0 +1,190 @@
+use super::ComicId;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Ids are resolved again after this long by default, in case the source has changed them
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Resolved id and when it was resolved in seconds since the unix epoch
+#[derive(Clone, Deserialize, Serialize)]
+struct Entry {
+    id: ComicId,
+    resolved: u64,
+}
+
+/// Resolved ids by source name and original id
+type Ids = HashMap<String, HashMap<String, Entry>>;
+
+/// Ids resolved with `Source::get_correct_id`. Resolving ids can require an extra request for
+/// each issue, so resolved ids can be saved to disk and reused by later runs.
+pub struct IdCache {
+    state: Mutex<State>,
+}
+
+struct State {
+    /// Resolved ids
+    ids: Option<Ids>,
+    /// File ids are saved to
+    path: Option<PathBuf>,
+    /// Whether new ids have been added since loading
+    changed: bool,
+    /// Ids resolved longer ago than this are not used
+    max_age: Duration,
+}
+
+/// Cache used when resolving ids in `get_all_ids`
+pub static ID_CACHE: IdCache = IdCache::new();
+
+/// Current time in seconds since the unix epoch
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0)
+}
+
+impl State {
+    fn is_fresh(&self, entry: &Entry) -> bool {
+        now().saturating_sub(entry.resolved) <= self.max_age.as_secs()
+    }
+
+    /// Add fresh ids that are not already known
+    fn add_missing(&mut self, ids: Ids) {
+        let fresh: Vec<(String, String, Entry)> = ids.into_iter()
+            .flat_map(|(source, ids)| ids.into_iter().map(move |(id, entry)| (source.clone(), id, entry)))
+            .filter(|(_, _, entry)| self.is_fresh(entry))
+            .collect();
+        let current = self.ids.get_or_insert_with(HashMap::new);
+        for (source, id, entry) in fresh {
+            current.entry(source).or_default().entry(id).or_insert(entry);
+        }
+    }
+}
+
+impl IdCache {
+    const fn new() -> Self {
+        Self {
+            state: Mutex::new(State { ids: None, path: None, changed: false, max_age: DEFAULT_MAX_AGE })
+        }
+    }
+
+    /// Previously resolved id of `id` on `source` if it is newer than the maximum age
+    pub fn get(&self, source: &str, id: &str) -> Option<ComicId> {
+        let state = self.state.lock().unwrap();
+        let entry = state.ids.as_ref()?.get(source)?.get(id)?;
+        state.is_fresh(entry).then(|| entry.id.clone())
+    }
+
+    /// Store that `id` on `source` resolves to `comicid`
+    pub fn insert(&self, source: &str, id: &str, comicid: ComicId) {
+        let mut state = self.state.lock().unwrap();
+        state.ids.get_or_insert_with(HashMap::new)
+            .entry(source.to_string())
+            .or_default()
+            .insert(id.to_string(), Entry { id: comicid, resolved: now() });
+        state.changed = true;
+    }
+
+    /// Add ids stored in `path` that were resolved within `max_age` and save new ids there. A
+    /// missing or invalid file is replaced when saving.
+    pub fn load(&self, path: impl Into<PathBuf>, max_age: Duration) {
+        let path = path.into();
+        let ids = read_ids(&path);
+        let mut state = self.state.lock().unwrap();
+        state.max_age = max_age;
+        if let Some(ids) = ids {
+            state.add_missing(ids);
+        }
+        state.path = Some(path);
+    }
+
+    /// Write ids to the file given to `load` if new ids have been resolved. Ids saved by other
+    /// processes since loading are kept, and the file is replaced in one step so it is never
+    /// left half written.
+    pub fn save(&self) -> std::io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let path = match (&state.path, state.changed) {
+            (Some(path), true) => path.clone(),
+            _ => return Ok(()),
+        };
+        log::debug!("Saving resolved ids to {}", path.display());
+        if let Some(ids) = read_ids(&path) {
+            state.add_missing(ids);
+        }
+        // Expired ids are removed from the file
+        let fresh: Ids = state.ids.iter()
+            .flatten()
+            .map(|(source, ids)| {
+                let ids = ids.iter()
+                    .filter(|(_, entry)| state.is_fresh(entry))
+                    .map(|(id, entry)| (id.clone(), entry.clone()))
+                    .collect();
+                (source.clone(), ids)
+            })
+            .collect();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let partial = path.with_extension("json.part");
+        std::fs::write(&partial, serde_json::to_string(&fresh)?)?;
+        std::fs::rename(&partial, &path)?;
+        state.changed = false;
+        Ok(())
+    }
+}
+
+/// Ids stored in `path`. Files from older versions without resolve times are ignored.
+fn read_ids(path: &Path) -> Option<Ids> {
+    let data = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str::<Ids>(&data) {
+        Ok(ids) => Some(ids),
+        Err(e) => {
+            log::warn!("Ignoring invalid id cache {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source::{ComicId, IssueId};
+
+    #[test]
+    fn cached_ids() {
+        let cache = super::IdCache::new();
+        assert_eq!(cache.get("Marvel", "abc"), None);
+        cache.insert("Marvel", "abc", ComicId::Issue(IssueId::from("123")));
+        assert_eq!(cache.get("Marvel", "abc"), Some(ComicId::Issue(IssueId::from("123"))));
+        // Ids are stored per source
+        assert_eq!(cache.get("Webtoon", "abc"), None);
+        // Nothing is written without a file
+        assert!(cache.save().is_ok());
+    }
+
+    #[test]
+    fn saved_ids() {
+        let path = std::env::temp_dir().join(format!("grawlix-ids-{}.json", std::process::id()));
+        let first = super::IdCache::new();
+        first.load(&path, super::DEFAULT_MAX_AGE);
+        first.insert("Marvel", "abc", ComicId::Issue(IssueId::from("123")));
+        // Ids saved by another process are kept
+        let second = super::IdCache::new();
+        second.load(&path, super::DEFAULT_MAX_AGE);
+        second.insert("Marvel", "def", ComicId::Issue(IssueId::from("456")));
+        second.save().unwrap();
+        first.save().unwrap();
+        let cache = super::IdCache::new();
+        cache.load(&path, super::DEFAULT_MAX_AGE);
+        assert_eq!(cache.get("Marvel", "def"), Some(ComicId::Issue(IssueId::from("456"))));
+        assert_eq!(cache.get("Marvel", "abc"), Some(ComicId::Issue(IssueId::from("123"))));
+        // Ids older than the maximum age are resolved again
+        let mut state = cache.state.lock().unwrap();
+        state.ids.as_mut().unwrap().get_mut("Marvel").unwrap().get_mut("abc").unwrap().resolved -= 60;
+        state.max_age = std::time::Duration::from_secs(30);
+        drop(state);
+        assert_eq!(cache.get("Marvel", "abc"), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Caps on the number of simultaneous connections to hosts. Some CDNs ban clients that fetch
+/// many pages in parallel, independently of how many requests are made in total.
+pub struct ConnectionLimits {
+    /// Host and semaphore with a permit for each allowed connection
+    hosts: Mutex<Vec<(String, Arc<Semaphore>)>>,
+}
+
+/// Connection limits used for all requests made by grawlix
+pub static CONNECTION_LIMITS: ConnectionLimits = ConnectionLimits::new();
+
+impl ConnectionLimits {
+    const fn new() -> Self {
+        Self { hosts: Mutex::new(Vec::new()) }
+    }
+
+    /// Allow at most `max` simultaneous connections to `host` and its subdomains
+    pub fn set_limit(&self, host: &str, max: usize) {
+        let mut hosts = self.hosts.lock().unwrap();
+        hosts.retain(|(x, _)| x != host);
+        hosts.push((host.to_string(), Arc::new(Semaphore::new(max.max(1)))));
+    }
+
+    /// Semaphore for the host of `url` if it is limited
+    fn semaphore(&self, url: &str) -> Option<Arc<Semaphore>> {
+        let url = reqwest::Url::parse(url).ok()?;
+        let host = url.host_str()?;
+        self.hosts.lock().unwrap()
+            .iter()
+            // Most specific host is used if both a domain and its subdomain are limited
+            .filter(|(x, _)| host == x || host.ends_with(&format!(".{}", x)))
+            .max_by_key(|(x, _)| x.len())
+            .map(|(_, semaphore)| Arc::clone(semaphore))
+    }
+
+    /// Wait until a connection to the host of `url` is allowed. The connection is counted until
+    /// the returned permit is dropped. Returns `None` if the host is not limited.
+    pub async fn acquire(&self, url: &str) -> Option<OwnedSemaphorePermit> {
+        let semaphore = self.semaphore(url)?;
+        if semaphore.available_permits() == 0 {
+            log::trace!("Waiting for free connection to {}", url);
+        }
+        semaphore.acquire_owned().await.ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    #[test]
+    fn connection_limits() {
+        let limits = super::ConnectionLimits::new();
+        limits.set_limit("cdn.example.com", 2);
+        let url = "https://pages.cdn.example.com/1.jpg";
+        let first = limits.acquire(url).now_or_never().flatten();
+        let second = limits.acquire(url).now_or_never().flatten();
+        assert!(first.is_some() && second.is_some());
+        // Third connection has to wait for one of the others to finish
+        assert!(limits.acquire(url).now_or_never().is_none());
+        drop(first);
+        assert!(limits.acquire(url).now_or_never().flatten().is_some());
+        // Other hosts are not limited
+        assert!(limits.acquire("https://example.com/1.jpg").now_or_never().unwrap().is_none());
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Counters for requests made to sources
+pub struct Metrics {
+    /// Number of successful requests
+    requests: AtomicU64,
+    /// Number of failed requests
+    failed_requests: AtomicU64,
+    /// Number of bytes downloaded
+    bytes: AtomicU64,
+    /// Total time spent on requests in milliseconds
+    request_time_ms: AtomicU64,
+    /// Number of comics resolved
+    comics: AtomicU64,
+}
+
+/// Metrics for all requests made by grawlix
+pub static METRICS: Metrics = Metrics::new();
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            requests: AtomicU64::new(0),
+            failed_requests: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            request_time_ms: AtomicU64::new(0),
+            comics: AtomicU64::new(0),
+        }
+    }
+
+    /// Record successful request
+    pub fn record_request(&self, bytes: usize, duration: Duration) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.request_time_ms.fetch_add(duration.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Record failed request
+    pub fn record_failed_request(&self) {
+        self.failed_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record resolved comic
+    pub fn record_comic(&self) {
+        self.comics.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Export metrics in Prometheus text format
+    pub fn to_prometheus(&self) -> String {
+        [
+            ("grawlix_requests_total", "counter", "Number of successful requests", &self.requests),
+            ("grawlix_failed_requests_total", "counter", "Number of failed requests", &self.failed_requests),
+            ("grawlix_downloaded_bytes_total", "counter", "Number of downloaded bytes", &self.bytes),
+            ("grawlix_request_duration_milliseconds_total", "counter", "Time spent on requests", &self.request_time_ms),
+            ("grawlix_comics_total", "counter", "Number of resolved comics", &self.comics),
+        ].iter()
+            .map(|(name, metric_type, help, value)| format!(
+                "# HELP {name} {help}\n# TYPE {name} {metric_type}\n{name} {value}\n",
+                name = name,
+                help = help,
+                metric_type = metric_type,
+                value = value.load(Ordering::Relaxed)
+            ))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn prometheus_format() {
+        let metrics = super::Metrics::new();
+        metrics.record_request(100, std::time::Duration::from_millis(20));
+        metrics.record_request(50, std::time::Duration::from_millis(10));
+        let output = metrics.to_prometheus();
+        assert!(output.contains("grawlix_requests_total 2\n"));
+        assert!(output.contains("grawlix_downloaded_bytes_total 150\n"));
+        assert!(output.contains("# TYPE grawlix_comics_total counter\n"));
+    }
+}
@@ -1,6 +1,16 @@
 mod clientbuilder;
 /// Functions for downloading comics
 mod download;
+/// Recording of responses for test data
+mod fixtures;
+/// Cache of resolved ids
+mod idcache;
+/// Caps on simultaneous connections per host
+mod limits;
+/// Request metrics
+mod metrics;
+/// Series of comics resolved from source
+mod series;
 /// Utility functions and macros for implementing `Source`
 mod utils;
 /// Implementations of `Source` for different sites
@@ -8,11 +18,16 @@ mod sites;
 
 pub use clientbuilder::*;
 pub use download::*;
-pub use sites::{source_from_name, source_from_url};
+pub use fixtures::{FixtureRecorder, FIXTURES};
+pub use idcache::{IdCache, ID_CACHE, DEFAULT_MAX_AGE};
+pub use limits::{ConnectionLimits, CONNECTION_LIMITS};
+pub use metrics::{Metrics, METRICS};
+pub use series::*;
+pub use sites::{Gallery, HEALTH_CHECK_URLS, source_from_name, source_from_url};
 
 use crate::{
     error::GrawlixDownloadError as Error,
-    metadata::Metadata,
+    metadata::{Metadata, TitleLanguage},
     comic::Page
 };
 use reqwest::Client;
@@ -20,37 +35,124 @@ use serde::{Deserialize, Serialize};
 
 
 /// Result type with `GrawlixDownloadError`
-type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Id of a single comic on source
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct IssueId(pub String);
+
+/// Id of a series on source
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct SeriesId(pub String);
+
+macro_rules! impl_id {
+    ($($id:ident),+) => ($(
+        impl std::fmt::Display for $id {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl From<String> for $id {
+            fn from(s: String) -> Self {
+                Self(s)
+            }
+        }
+
+        impl From<&str> for $id {
+            fn from(s: &str) -> Self {
+                Self(s.to_string())
+            }
+        }
+
+        impl $id {
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+    )+)
+}
+impl_id!(IssueId, SeriesId);
 
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 /// Id of comic or series on source
 pub enum ComicId {
-    Issue(String),
-    IssueWithMetadata(String, Metadata),
+    Issue(IssueId),
+    IssueWithMetadata(IssueId, Metadata),
+    /// Id that has to be resolved with `Source::get_correct_id` before it can be used
     Other(String),
     OtherWithMetadata(String, Metadata),
-    Series(String),
+    Series(SeriesId),
 }
 
 impl ComicId {
     pub fn inner(&self) -> &String {
         match self {
-            ComicId::Issue(x)
-            | ComicId::IssueWithMetadata(x, _)
+            ComicId::Issue(IssueId(x))
+            | ComicId::IssueWithMetadata(IssueId(x), _)
             | ComicId::Other(x)
             | ComicId::OtherWithMetadata(x, _)
-            | ComicId::Series(x) => x
+            | ComicId::Series(SeriesId(x)) => x
+        }
+    }
+
+    /// Id of issue. Fails if `self` is not an issue.
+    pub fn issue_id(&self) -> Result<&IssueId> {
+        match self {
+            ComicId::Issue(x) | ComicId::IssueWithMetadata(x, _) => Ok(x),
+            _ => Err(Error::NotAnIssue(self.inner().clone())),
+        }
+    }
+
+    /// Id of series. Fails if `self` is not a series.
+    pub fn series_id(&self) -> Result<&SeriesId> {
+        match self {
+            ComicId::Series(x) => Ok(x),
+            _ => Err(Error::NotASeries(self.inner().clone())),
         }
     }
 }
 
 /// Info about comic series
-#[derive(Default)]
+#[derive(Clone, Debug, Default)]
 pub struct SeriesInfo {
     /// Name of series
     pub name: String,
     /// true if the series is ended false if not or unknown
     pub ended: bool,
+    /// Number of issues in series
+    pub issue_count: Option<usize>,
+    /// Year the series started
+    pub start_year: Option<u32>,
+    /// Year the series ended
+    pub end_year: Option<u32>,
+    /// Publisher of series
+    pub publisher: Option<String>,
+    /// Description of series
+    pub description: Option<String>,
+    /// Url of cover image for series
+    pub cover_url: Option<String>,
+}
+
+impl SeriesInfo {
+    /// Years the series has run as "2012-2015". Ongoing series are formatted as "2012-".
+    pub fn years(&self) -> Option<String> {
+        format_years(self.start_year, self.end_year, self.ended)
+    }
+}
+
+/// Format `start` and `end` year of series as a range
+pub fn format_years(start: Option<u32>, end: Option<u32>, ended: bool) -> Option<String> {
+    match (start, end) {
+        (Some(start), Some(end)) if start == end => Some(start.to_string()),
+        (Some(start), Some(end)) => Some(format!("{}-{}", start, end)),
+        (Some(start), None) if ended => Some(start.to_string()),
+        (Some(start), None) => Some(format!("{}-", start)),
+        (None, Some(end)) => Some(format!("-{}", end)),
+        (None, None) => None,
+    }
 }
 
 /// Response from source.
@@ -66,10 +168,11 @@ pub struct Request<T> {
     /// Reqwest request
     requests: Vec<reqwest::RequestBuilder>,
     /// Function to parse response
-    transform: Box<dyn Fn(&[bytes::Bytes]) -> Option<T>>,
+    transform: Box<dyn Fn(&[bytes::Bytes]) -> Option<T> + Send + Sync>,
 }
 
 /// Login credentials for source
+#[derive(Clone)]
 pub enum Credentials {
     UsernamePassword(String, String),
     ApiKey(String),
@@ -80,7 +183,7 @@ pub enum Credentials {
 /// Trait for interacting with comic book source
 /// Trait object can be created with `source_from_url` function
 #[async_trait::async_trait]
-pub trait Source: Send {
+pub trait Source: Send + Sync {
     /// Name of source
     fn name(&self) -> String;
 
@@ -103,24 +206,47 @@ pub trait Source: Send {
     /// This is only meant to be called if the source returns the `ComicId::Other` type in
     /// `id_from_url` or `get_series_ids`.
     #[allow(unused_variables)]
-    fn get_correct_id(&self, client: &Client, otherid: &ComicId) -> Result<SourceResponse<ComicId>> {
-        unreachable!()
+    fn get_correct_id(&self, client: &Client, otherid: &str) -> Result<SourceResponse<ComicId>> {
+        Err(Error::UnresolvedId(otherid.to_string()))
     }
 
     /// Retrieves `ComicId` for all comics in series
-    /// `seriesid` has to be a `ComicId::Series`
-    fn get_series_ids(&self, client: &Client, seriesid: &ComicId) -> Result<SourceResponse<Vec<ComicId>>>;
+    fn get_series_ids(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<Vec<ComicId>>>;
+
+    /// Retrieves `ComicId` of the `count` most recent comics in series, ordered from oldest to
+    /// newest. Used to check for new comics without listing the whole series.
+    #[allow(unused_variables)]
+    fn get_latest(&self, client: &Client, seriesid: &SeriesId, count: usize) -> Result<SourceResponse<Vec<ComicId>>> {
+        Err(Error::LatestNotSupported(self.name()))
+    }
+
+    /// Retrieves `ComicId` of all comics the authenticated account owns, has borrowed, or can
+    /// read through a subscription
+    #[allow(unused_variables)]
+    fn get_owned(&self, client: &Client) -> Result<SourceResponse<Vec<ComicId>>> {
+        Err(Error::OwnedNotSupported(self.name()))
+    }
+
+    /// Url of comic or series on the website of the source. The url can be converted back with
+    /// `id_from_url`.
+    #[allow(unused_variables)]
+    fn url_from_id(&self, comicid: &ComicId) -> Option<String> {
+        None
+    }
 
     /// Creates `SourceREsponse` to download comic metadata
-    fn get_metadata(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<Metadata>>;
+    fn get_metadata(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Metadata>>;
 
-    /// Creates `SourceResponse` to get metadata about series
+    /// Creates `SourceResponse` to get metadata about series. Sources without series metadata
+    /// get it from the first issue in the series with `download_series_metadata`.
     #[allow(unused_variables)]
-    fn get_series_info(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<SeriesInfo>>;
+    fn get_series_info(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<SeriesInfo>> {
+        Err(Error::SeriesInfoNotSupported(self.name()))
+    }
 
     /// Downloads pages
     #[allow(unused_variables)]
-    fn get_pages(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<Vec<Page>>> {
+    fn get_pages(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Vec<Page>>> {
         Err(Error::PagesNotSupported(self.name()))
     }
 
@@ -139,6 +265,21 @@ pub trait Source: Send {
         self.metadata_require_authentication() || self.pages_require_authentication()
     }
 
+    /// Returns `true` if the source can log in with username and password
+    fn supports_login(&self) -> bool {
+        false
+    }
+
+    /// Set preferred language of titles. Only used by sources that return titles in more than
+    /// one language.
+    #[allow(unused_variables)]
+    fn set_title_language(&mut self, language: TitleLanguage) {}
+
+    /// Set locale of comics, like `de_de`. Only used by sources with editions in more than one
+    /// language.
+    #[allow(unused_variables)]
+    fn set_locale(&mut self, locale: &str) {}
+
     /// Authenticate with source using `creds`
     #[allow(unused_variables)]
     async fn authenticate(&mut self, client: &mut Client, creds: &Credentials) -> Result<()> {
@@ -146,3 +287,47 @@ pub trait Source: Send {
     }
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ComicId, IssueId, Source, source_from_name, get_all_ids, comic_from_comicid};
+    use std::sync::Arc;
+
+    #[test]
+    fn comicid_serialization() {
+        // Ids are stored in update files so the format should not change
+        let id = ComicId::Issue(IssueId::from("123"));
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, r#"{"Issue":"123"}"#);
+        assert_eq!(serde_json::from_str::<ComicId>(&json).unwrap(), id);
+    }
+
+    #[test]
+    fn series_years() {
+        assert_eq!(super::format_years(Some(2012), Some(2015), true).as_deref(), Some("2012-2015"));
+        assert_eq!(super::format_years(Some(2012), None, false).as_deref(), Some("2012-"));
+        assert_eq!(super::format_years(Some(2012), Some(2012), true).as_deref(), Some("2012"));
+        assert_eq!(super::format_years(None, None, true), None);
+    }
+
+    #[test]
+    fn typed_ids() {
+        let id = ComicId::Series("abc".into());
+        assert_eq!(id.series_id().unwrap().as_str(), "abc");
+        assert!(id.issue_id().is_err());
+    }
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    #[test]
+    fn sources_are_shareable() {
+        let source: Arc<dyn Source> = source_from_name("webtoon").unwrap().into();
+        let client = source.create_client();
+        let id = ComicId::Issue(IssueId::from("123"));
+        // Futures are only checked and never polled
+        assert_send(&get_all_ids(&*source, &client, id.clone()));
+        assert_send(&comic_from_comicid(&*source, &client, id));
+        let shared = Arc::clone(&source);
+        std::thread::spawn(move || shared.name()).join().unwrap();
+    }
+}
@@ -0,0 +1,61 @@
+use super::{
+    ComicId, Error, Result, SeriesInfo, Source,
+    download_comics, download_comics_stream, download_series_metadata, get_all_ids, source_from_url,
+    DEFAULT_CONCURRENCY,
+};
+use crate::comic::Comic;
+use futures::Stream;
+use reqwest::Client;
+use std::sync::Arc;
+
+/// Comic series resolved from a source. Issues can be inspected and filtered before downloading.
+pub struct Series {
+    /// Info about series
+    pub info: SeriesInfo,
+    /// Ids of issues in series
+    pub issues: Vec<ComicId>,
+    /// Source series is from
+    pub source: Arc<dyn Source>,
+    /// Client used for requests to source
+    pub client: Client,
+}
+
+impl Series {
+    /// Only keep issues where `f` returns `true`
+    pub fn retain<F: FnMut(&ComicId) -> bool>(&mut self, f: F) {
+        self.issues.retain(f);
+    }
+
+    /// Download selected issues from series
+    pub async fn download(&self, issues: &[ComicId]) -> Result<Vec<Comic>> {
+        download_comics(issues.to_vec(), &self.client, &*self.source).await
+    }
+
+    /// Download all issues in series
+    pub async fn download_all(&self) -> Result<Vec<Comic>> {
+        self.download(&self.issues).await
+    }
+
+    /// Download all issues in series as a stream returning each comic when it is ready
+    pub fn stream(&self) -> impl Stream<Item = Result<Comic>> + '_ {
+        download_comics_stream(self.issues.clone(), &self.client, &*self.source, DEFAULT_CONCURRENCY)
+    }
+}
+
+/// Resolve series at `url` with default client of source
+pub async fn resolve_series(url: &str) -> Result<Series> {
+    let source = source_from_url(url)?;
+    let client = source.create_client();
+    let seriesid = source.id_from_url(url)?;
+    resolve_series_with_source(source.into(), client, seriesid).await
+}
+
+/// Resolve series with an already created (and possibly authenticated) source and client
+pub async fn resolve_series_with_source(source: Arc<dyn Source>, client: Client, seriesid: ComicId) -> Result<Series> {
+    if !matches!(seriesid, ComicId::Series(_)) {
+        return Err(Error::NotASeries(seriesid.inner().to_string()));
+    }
+    let info = download_series_metadata(&client, &*source, &seriesid).await?;
+    let issues = get_all_ids(&*source, &client, seriesid).await?;
+    Ok(Series { info, issues, source, client })
+}
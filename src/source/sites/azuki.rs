@@ -0,0 +1,204 @@
+use crate::{
+    comic::Page,
+    metadata::Metadata,
+    source::{
+        self,
+        ComicId, IssueId, SeriesId, Credentials, Error, Result, Source, SourceResponse, SeriesInfo,
+        utils::{self, first_attr, issue_id_match, resp_to_json, simple_response, source_request}
+    }
+};
+use reqwest::Client;
+use scraper::Html;
+
+/// Source for azuki.co
+#[derive(Default)]
+pub struct Azuki {
+    /// Access token retrieved when authenticating
+    access_token: Option<String>,
+}
+
+const API_URL: &str = "https://production.api.azuki.co/api/v0";
+
+#[async_trait::async_trait]
+impl Source for Azuki {
+    fn name(&self) -> String {
+        "Azuki".to_string()
+    }
+
+    fn supports_login(&self) -> bool {
+        true
+    }
+
+    fn client_builder(&self) -> source::ClientBuilder {
+        let mut clientbuilder = source::create_default_client();
+        if let Some(token) = &self.access_token {
+            clientbuilder.add_header("Authorization", &format!("Bearer {}", token));
+        }
+        clientbuilder
+    }
+
+    fn id_from_url(&self, url: &str) -> Result<ComicId> {
+        issue_id_match!(url,
+            r"azuki.co/series/([^/]+/read/[^/?#]+)" => Issue,
+            r"azuki.co/series/([^/?#]+)" => Series
+        )
+    }
+
+    fn get_series_ids(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<Vec<ComicId>>> {
+        simple_response!(
+            id: seriesid,
+            client: client,
+            url: "https://www.azuki.co/series/{}",
+            value: find_series_ids
+        )
+    }
+
+    fn get_series_info(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<SeriesInfo>> {
+        simple_response!(
+            id: seriesid,
+            client: client,
+            url: "https://www.azuki.co/series/{}",
+            value: parse_series_info
+        )
+    }
+
+    fn get_metadata(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Metadata>> {
+        source_request!(
+            requests: [
+                client.get(format!("https://www.azuki.co/series/{}", issueid)),
+                client.get(format!("https://www.azuki.co/series/{}", series_slug(issueid.as_str())))
+            ],
+            transform: parse_metadata
+        )
+    }
+
+    fn get_pages(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Vec<Page>>> {
+        source_request!(
+            requests: client.get(format!("{}/chapters/{}/pages", API_URL, chapter_uuid(issueid.as_str()))),
+            transform: parse_pages
+        )
+    }
+
+    fn metadata_require_authentication(&self) -> bool {
+        false
+    }
+
+    async fn authenticate(&mut self, client: &mut Client, creds: &Credentials) -> Result<()> {
+        if let Credentials::UsernamePassword(username, password) = creds {
+            let response: serde_json::Value = client.post(format!("{}/token", API_URL))
+                .form(&[
+                    ("username", username.as_str()),
+                    ("password", password.as_str()),
+                    ("grant_type", "password"),
+                ])
+                .send().await?
+                .json().await?;
+            let token = response["access_token"].as_str()
+                .ok_or(Error::FailedAuthentication(self.name()))?;
+            self.access_token = Some(token.to_string());
+            *client = self.create_client();
+            Ok(())
+        } else {
+            Err(Error::FailedAuthentication("Azuki requires username and password to login".to_string()))
+        }
+    }
+}
+
+/// Extract uuid of chapter from issue id
+fn chapter_uuid(issueid: &str) -> &str {
+    issueid.rsplit('/').next().unwrap_or(issueid)
+}
+
+/// Extract slug of series from issue id
+fn series_slug(issueid: &str) -> &str {
+    issueid.split('/').next().unwrap_or(issueid)
+}
+
+fn find_series_ids(resp: &[bytes::Bytes]) -> Option<Vec<ComicId>> {
+    let mut ids = Vec::new();
+    for link in utils::find_links(r#"a[href*="/read/"]"#, &resp[0])? {
+        let id = issue_id_match!(&format!("https://www.azuki.co{}", link),
+            r"azuki.co/series/([^/]+/read/[^/?#]+)" => Issue
+        ).ok()?;
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    Some(ids)
+}
+
+fn parse_series_info(resp: &[bytes::Bytes]) -> Option<SeriesInfo> {
+    let doc = Html::parse_document(std::str::from_utf8(&resp[0]).ok()?);
+    Some(SeriesInfo {
+        name: first_attr(&doc, r#"meta[property="og:title"]"#, "content")?,
+        description: first_attr(&doc, r#"meta[property="og:description"]"#, "content"),
+        cover_url: first_attr(&doc, r#"meta[property="og:image"]"#, "content"),
+        ..Default::default()
+    })
+}
+
+/// Parse metadata from the page of the chapter and the page of its series
+fn parse_metadata(resp: &[bytes::Bytes]) -> Option<Metadata> {
+    let doc = Html::parse_document(std::str::from_utf8(&resp[0]).ok()?);
+    let series = parse_series_info(&resp[1..])?;
+    Some(Metadata {
+        title: first_attr(&doc, r#"meta[property="og:title"]"#, "content"),
+        series: Some(series.name),
+        description: first_attr(&doc, r#"meta[property="og:description"]"#, "content"),
+        source: Some("Azuki".to_string()),
+        ..Default::default()
+    })
+}
+
+/// Find the largest jpeg version of each page. Urls are signed with a token and expire shortly
+/// after being retrieved.
+fn parse_pages(resp: &[bytes::Bytes]) -> Option<Vec<Page>> {
+    resp_to_json::<serde_json::Value>(&resp[0])?["data"]["pages"]
+        .as_array()?
+        .iter()
+        .map(|page| {
+            let url = page["image_wp"]["jpg"]
+                .as_array()?
+                .iter()
+                .max_by_key(|image| image["width"].as_u64().unwrap_or(0))?["url"]
+                .as_str()?;
+            Some(Page::from_url(url, "jpg"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source::{ComicId, Source};
+
+    #[test]
+    fn ids() {
+        let source = super::Azuki::default();
+        assert_eq!(
+            source.id_from_url("https://www.azuki.co/series/the-cafe-terrace-and-its-goddesses/read/4f2f5c1a-7f0e-4b9b-9c2a-1d6a3f0c2e11").unwrap(),
+            ComicId::Issue("the-cafe-terrace-and-its-goddesses/read/4f2f5c1a-7f0e-4b9b-9c2a-1d6a3f0c2e11".into())
+        );
+        assert_eq!(
+            source.id_from_url("https://www.azuki.co/series/the-cafe-terrace-and-its-goddesses").unwrap(),
+            ComicId::Series("the-cafe-terrace-and-its-goddesses".into())
+        );
+    }
+
+    #[test]
+    fn chapter_uuid() {
+        assert_eq!(super::chapter_uuid("series-name/read/abc-123"), "abc-123");
+        assert_eq!(super::series_slug("series-name/read/abc-123"), "series-name");
+    }
+
+    #[test]
+    fn series_name() {
+        let chapter = r#"<meta property="og:title" content="Chapter 1"><meta property="og:site_name" content="Azuki">"#;
+        let series = r#"<meta property="og:title" content="The Café Terrace and Its Goddesses">"#;
+        let metadata = super::parse_metadata(&[
+            bytes::Bytes::from_static(chapter.as_bytes()),
+            bytes::Bytes::from_static(series.as_bytes()),
+        ]).unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Chapter 1"));
+        assert_eq!(metadata.series.as_deref(), Some("The Café Terrace and Its Goddesses"));
+    }
+}
@@ -0,0 +1,205 @@
+use crate::{
+    comic::{Page, PageType, PageEncryptionScheme, OnlinePage, comicwalker_key},
+    metadata::{self, Metadata, ReadingDirection, TitleLanguage},
+    source::{
+        ComicId, IssueId, SeriesId, Error, Result, Source, SourceResponse, SeriesInfo,
+        utils::{issue_id_match, resp_to_json, source_request, value_fn, value_to_optstring}
+    }
+};
+use reqwest::Client;
+
+/// Source for comic-walker.com
+#[derive(Default)]
+pub struct ComicWalker {
+    /// Preferred language of titles
+    title_language: TitleLanguage,
+}
+
+const API_URL: &str = "https://comic-walker.com/api/contents";
+
+impl Source for ComicWalker {
+    fn name(&self) -> String {
+        "ComicWalker".to_string()
+    }
+
+    fn id_from_url(&self, url: &str) -> Result<ComicId> {
+        issue_id_match!(url,
+            r"comic-walker.com/detail/(\w+/episodes/\w+)" => Issue,
+            r"comic-walker.com/detail/(\w+)" => Series
+        )
+    }
+
+    fn get_series_ids(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<Vec<ComicId>>> {
+        let work_code = seriesid.0.clone();
+        source_request!(
+            requests: client.get(format!(
+                "{}/details/work-episodes?workCode={}&episodeFrom=1&episodeTo=10000",
+                API_URL, work_code
+            )),
+            transform: |resp: &[bytes::Bytes]| find_series_ids(resp, &work_code)
+        )
+    }
+
+    fn get_series_info(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<SeriesInfo>> {
+        let language = self.title_language;
+        source_request!(
+            requests: client.get(format!("{}/details/work?workCode={}", API_URL, seriesid)),
+            transform: |resp: &[bytes::Bytes]| parse_series_info(resp, language)
+        )
+    }
+
+    fn get_metadata(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Metadata>> {
+        let language = self.title_language;
+        source_request!(
+            requests: client.get(episode_details_url(issueid.as_str())?),
+            transform: |resp: &[bytes::Bytes]| parse_metadata(resp, language)
+        )
+    }
+
+    fn get_pages(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Vec<Page>>> {
+        let id = issueid.as_str();
+        let new_client = client.clone();
+        Ok(SourceResponse::Request(crate::source::Request {
+            requests: vec![client.get(episode_details_url(id)?)],
+            transform: Box::new(move |resp| {
+                // The viewer requires the internal id of the episode
+                let details = resp_to_json::<serde_json::Value>(&resp[0])?;
+                let episode_id = details["episode"]["id"].as_str()?;
+                Some(SourceResponse::Request(crate::source::Request {
+                    requests: vec![
+                        new_client.get(format!(
+                            "{}/viewer?episodeId={}&imageSizeType=width:1284",
+                            API_URL, episode_id
+                        ))
+                    ],
+                    transform: value_fn(&parse_pages)
+                }))
+            })
+        }))
+    }
+
+    fn metadata_require_authentication(&self) -> bool {
+        false
+    }
+
+    fn pages_require_authentication(&self) -> bool {
+        false
+    }
+
+    fn set_title_language(&mut self, language: TitleLanguage) {
+        self.title_language = language;
+    }
+}
+
+/// Create url for episode details from issue id in the format "<work code>/episodes/<episode code>"
+fn episode_details_url(issueid: &str) -> Result<String> {
+    let (work_code, episode_code) = issueid.split_once("/episodes/")
+        .ok_or(Error::FailedResponseParse)?;
+    Ok(format!(
+        "{}/details/episode?workCode={}&episodeCode={}&episodeType=first",
+        API_URL, work_code, episode_code
+    ))
+}
+
+fn find_series_ids(resp: &[bytes::Bytes], work_code: &str) -> Option<Vec<ComicId>> {
+    resp_to_json::<serde_json::Value>(&resp[0])?["episodes"]["result"]
+        .as_array()?
+        .iter()
+        .map(|episode| Some(ComicId::Issue(format!("{}/episodes/{}", work_code, episode["code"].as_str()?).into())))
+        .collect()
+}
+
+/// Title of work in `language`. Works are only titled in japanese, so english titles fall back to
+/// the japanese one.
+fn work_title(work: &serde_json::Value, language: TitleLanguage) -> Option<String> {
+    language.choose(value_to_optstring(&work["title"]), None)
+}
+
+fn parse_series_info(resp: &[bytes::Bytes], language: TitleLanguage) -> Option<SeriesInfo> {
+    let data = resp_to_json::<serde_json::Value>(&resp[0])?;
+    Some(SeriesInfo {
+        name: work_title(&data["work"], language)?,
+        description: value_to_optstring(&data["work"]["summary"]),
+        ..Default::default()
+    })
+}
+
+fn parse_metadata(resp: &[bytes::Bytes], language: TitleLanguage) -> Option<Metadata> {
+    let data = resp_to_json::<serde_json::Value>(&resp[0])?;
+    let work = &data["work"];
+    let episode = &data["episode"];
+    let date = episode["updateDate"].as_str()
+        .and_then(|x| metadata::date_from_str(x.split('T').next()?));
+    Some(Metadata {
+        title: value_to_optstring(&episode["title"]),
+        series: work_title(work, language),
+        description: value_to_optstring(&work["summary"]),
+        year: date.map(|x| x.0),
+        month: date.map(|x| x.1),
+        day: date.map(|x| x.2),
+        genres: work["genre"]["name"].as_str().map(String::from).into_iter().collect(),
+        reading_direction: ReadingDirection::RightToLeft,
+        source: Some("ComicWalker".to_string()),
+        ..Default::default()
+    })
+}
+
+fn parse_pages(resp: &[bytes::Bytes]) -> Option<Vec<Page>> {
+    resp_to_json::<serde_json::Value>(&resp[0])?["manuscripts"]
+        .as_array()?
+        .iter()
+        .map(|page| {
+            let hash = page["drmHash"].as_str()?;
+            if comicwalker_key(hash).is_none() {
+                log::error!("Invalid ComicWalker page key: {}", hash);
+                return None;
+            }
+            Some(Page {
+                file_format: "jpg".to_string(),
+                page_type: PageType::Url(OnlinePage {
+                    url: page["drmImageUrl"].as_str()?.to_string(),
+                    encryption: Some(PageEncryptionScheme::ComicWalker(hash.to_string())),
+                    ..Default::default()
+                })
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        metadata::TitleLanguage,
+        source::{ComicId, Source},
+    };
+
+    #[test]
+    fn ids() {
+        let source = super::ComicWalker::default();
+        assert_eq!(
+            source.id_from_url("https://comic-walker.com/detail/KC_003002_S/episodes/KC_0030020000200011_E?episodeType=first").unwrap(),
+            ComicId::Issue("KC_003002_S/episodes/KC_0030020000200011_E".into())
+        );
+        assert_eq!(
+            source.id_from_url("https://comic-walker.com/detail/KC_003002_S").unwrap(),
+            ComicId::Series("KC_003002_S".into())
+        );
+    }
+
+    #[test]
+    fn episode_details_url() {
+        assert_eq!(
+            super::episode_details_url("KC_003002_S/episodes/KC_0030020000200011_E").unwrap(),
+            "https://comic-walker.com/api/contents/details/episode?workCode=KC_003002_S&episodeCode=KC_0030020000200011_E&episodeType=first"
+        );
+    }
+
+    #[test]
+    fn title_language() {
+        let resp = [bytes::Bytes::from_static("{\"work\":{\"title\":\"ちいかわ\"}}".as_bytes())];
+        let native = super::parse_series_info(&resp, TitleLanguage::Native).unwrap();
+        assert_eq!(native.name, "ちいかわ");
+        let romanized = super::parse_series_info(&resp, TitleLanguage::Romanized).unwrap();
+        assert_eq!(romanized.name, "chiikawa");
+    }
+}
@@ -3,8 +3,8 @@ use crate::{
     metadata::{Metadata, Author, AuthorType},
     source::{
         self,
-        Source, Result, Error, ComicId, SeriesInfo, SourceResponse, Credentials,
-        utils::{issue_id_match, simple_response, resp_to_json, value_fn}
+        Source, Result, Error, ComicId, IssueId, SeriesId, SeriesInfo, SourceResponse, Credentials,
+        utils::{issue_id_match, simple_response, source_request, resp_to_json, value_fn}
     }
 };
 use reqwest::Client;
@@ -42,42 +42,56 @@ impl Source for DCUniverseInfinite {
         )
     }
 
-    fn get_series_ids(&self, client: &Client, seriesid: &ComicId) -> Result<SourceResponse<Vec<ComicId>>> {
+    fn get_series_ids(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<Vec<ComicId>>> {
         simple_response!(
             id: seriesid,
             client: client,
-            id_type: Series,
             url: "https://www.dcuniverseinfinite.com/api/comics/1/series/{}/?trans=en",
             value: find_series_ids
         )
     }
 
-    fn get_series_info(&self, client: &Client, seriesid: &ComicId) -> Result<SourceResponse<SeriesInfo>> {
+    /// Comics saved to the library of the account
+    fn get_owned(&self, client: &Client) -> Result<SourceResponse<Vec<ComicId>>> {
+        source_request!(
+            requests: client.get("https://www.dcuniverseinfinite.com/api/comics/1/my-library/?trans=en"),
+            transform: find_series_ids
+        )
+    }
+
+    fn url_from_id(&self, comicid: &ComicId) -> Option<String> {
+        // The name in urls is only for readability and is not needed to find the comic
+        match comicid {
+            ComicId::Issue(id) | ComicId::IssueWithMetadata(id, _) =>
+                Some(format!("https://www.dcuniverseinfinite.com/comics/book/comic/{}/c/reader", id)),
+            ComicId::Series(id) => Some(format!("https://www.dcuniverseinfinite.com/comics/series/series/{}", id)),
+            _ => None,
+        }
+    }
+
+    fn get_series_info(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<SeriesInfo>> {
         simple_response!(
             id: seriesid,
             client: client,
-            id_type: Series,
             url: "https://www.dcuniverseinfinite.com/api/comics/1/series/{}/?trans=en",
             value: parse_series_info
         )
     }
 
-    fn get_metadata(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<Metadata>> {
+    fn get_metadata(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Metadata>> {
         simple_response!(
-            id: comicid,
+            id: issueid,
             client: client,
-            id_type: Issue,
             url: "https://www.dcuniverseinfinite.com/api/comics/1/book/{}/?trans=en",
             value: parse_metadata
         )
     }
 
-    fn get_pages(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<Vec<Page>>> {
+    fn get_pages(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Vec<Page>>> {
         let new_client = client.clone();
         simple_response!(
-            id: comicid,
+            id: issueid,
             client: client,
-            id_type: Issue,
             url: "https://www.dcuniverseinfinite.com/api/5/1/rights/comic/{}?trans=en",
             request: move |resp| {
                 let auth_jwt = resp_to_json::<serde_json::Value>(&resp[0])?;
@@ -109,7 +123,7 @@ fn find_series_ids(resp: &[bytes::Bytes]) -> Option<Vec<ComicId>> {
     data["book_uuids"]["issue"]
         .as_array()?
         .into_iter()
-        .map(|x| Some(ComicId::Issue(x.as_str()?.to_string())))
+        .map(|x| Some(ComicId::Issue(x.as_str()?.into())))
         .collect()
 }
 
@@ -127,6 +141,7 @@ fn create_pages(resp: &[bytes::Bytes]) -> Option<Vec<Page>> {
                 page_type: PageType::Url(OnlinePage {
                     url: x["signed_url"].as_str()?.to_string(),
                     headers: None,
+                    mirrors: Vec::new(),
                     encryption: Some(PageEncryptionScheme::DCUniverseInfinite(
                         create_decryption_key(uuid, x["page_number"].as_u64()?, job_id, format)
                     ))
@@ -160,6 +175,7 @@ fn parse_metadata(resp: &[bytes::Bytes]) -> Option<Metadata> {
         description: data["description"].as_str().map(String::from),
         publisher: data["publisher"].as_str().map(String::from),
         issue_number: data["issue_number"].as_str().and_then(|x| x.parse::<u32>().ok()),
+        age_rating: data["age_rating"].as_str().and_then(|x| x.parse().ok()),
         authors,
         ..Default::default()
     })
@@ -169,6 +185,9 @@ fn parse_series_info(resp: &[bytes::Bytes]) -> Option<SeriesInfo> {
     let data = resp_to_json::<serde_json::Value>(&resp[0])?;
     Some(SeriesInfo {
         name: data["title"].as_str()?.to_string(),
+        issue_count: data["books_count"].as_u64().map(|x| x as usize),
+        publisher: Some("DC Comics".to_string()),
+        description: data["description"].as_str().map(String::from),
         ..Default::default()
     })
 }
@@ -194,16 +213,25 @@ mod tests {
             source.id_from_url(
                 "https://www.dcuniverseinfinite.com/comics/book/the-sandman-8/761ad52d-b961-49b1-87b6-ca85774fc3a6/c/reader"
             ).unwrap(),
-            ComicId::Issue("761ad52d-b961-49b1-87b6-ca85774fc3a6".to_string())
+            ComicId::Issue("761ad52d-b961-49b1-87b6-ca85774fc3a6".into())
         );
         assert_eq!(
             source.id_from_url(
                 "https://www.dcuniverseinfinite.com/comics/series/the-sandman/fbf5f10f-03ca-4f2b-90a0-66df08806a99"
             ).unwrap(),
-            ComicId::Series("fbf5f10f-03ca-4f2b-90a0-66df08806a99".to_string())
+            ComicId::Series("fbf5f10f-03ca-4f2b-90a0-66df08806a99".into())
         );
     }
 
+    #[test]
+    fn url_from_id() {
+        let source = super::DCUniverseInfinite::default();
+        for id in [ComicId::Issue("761ad52d-b961-49b1-87b6-ca85774fc3a6".into()), ComicId::Series("fbf5f10f-03ca-4f2b-90a0-66df08806a99".into())] {
+            let url = source.url_from_id(&id).unwrap();
+            assert_eq!(source.id_from_url(&url).unwrap(), id);
+        }
+    }
+
     #[test]
     fn decryption_key() {
         let key = super::create_decryption_key(
@@ -230,6 +258,7 @@ mod tests {
                 description: Some("Spend a day with Dream as he catches up with his younger sister, Death, in search of inspiration. When the King of Dreams is depressed, can even a pep talk from Death set him on the right path?".to_string()),
                 publisher: Some("DC Comics".to_string()),
                 issue_number: Some(8),
+                age_rating: Some(crate::metadata::AgeRating::Mature),
                 authors: vec![
                     super::Author { name: "Neil Gaiman".to_string(), author_type: super::AuthorType::Writer },
                     super::Author { name: "Robbie Busch".to_string(), author_type: super::AuthorType::Colorist },
@@ -247,7 +276,7 @@ mod tests {
         let resp = std::fs::read("./tests/source_data/dcuniverseinfinite_series.json").unwrap();
         let issues = super::find_series_ids(&[resp.into()]).unwrap();
         assert_eq!(issues.len(), 8);
-        assert_eq!(issues[2], ComicId::Issue("1958170b-f678-4eeb-a774-ef750b8aa8bc".to_string()));
+        assert_eq!(issues[2], ComicId::Issue("1958170b-f678-4eeb-a774-ef750b8aa8bc".into()));
     }
 
     #[test]
@@ -255,5 +284,7 @@ mod tests {
         let resp = std::fs::read("./tests/source_data/dcuniverseinfinite_series.json").unwrap();
         let info = super::parse_series_info(&[resp.into()]).unwrap();
         assert_eq!(&info.name, "The Sandman");
+        assert_eq!(info.issue_count, Some(8));
+        assert_eq!(info.publisher.as_deref(), Some("DC Comics"));
     }
 }
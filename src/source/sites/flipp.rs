@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use crate::{
     source::{
-        Source, SourceResponse, Result, Error, ComicId, SeriesInfo,
+        Source, SourceResponse, Result, ComicId, Credentials, Error, IssueId, SeriesId, SeriesInfo,
         utils::{self, issue_id_match, resp_to_json, value_to_optstring, source_request}
     },
     comic::Page,
@@ -11,13 +11,23 @@ use crate::{
 use regex::Regex;
 use reqwest::Client;
 
-pub struct Flipp;
+/// Source for flipp.dk
+#[derive(Default)]
+pub struct Flipp {
+    /// Email and password of subscriber sent when signing in
+    credentials: Option<(String, String)>,
+}
 
+#[async_trait::async_trait]
 impl Source for Flipp {
     fn name(&self) -> String {
         "Flipp".to_string()
     }
 
+    fn supports_login(&self) -> bool {
+        true
+    }
+
     fn id_from_url(&self, url: &str) -> Result<ComicId> {
         issue_id_match!(url,
             r"https?://reader.flipp.dk/html5/reader/production/default.aspx\?pubname=&edid=([^/]+)" => Other,
@@ -25,83 +35,74 @@ impl Source for Flipp {
         )
     }
 
-    fn get_correct_id(&self, client: &Client, otherid: &ComicId) -> Result<SourceResponse<ComicId>> {
-        if let ComicId::Other(eid) = otherid {
-            let eid = eid.to_string();
-            let url = format!(
-                "https://reader.flipp.dk/html5/reader/production/default.aspx?pubname=&edid={}",
-                eid
-            );
-            source_request!(
-                requests: client.get(url),
-                transform: |resp: &[bytes::Bytes]| {
-                    let site = std::str::from_utf8(&resp[0]).ok()?;
-                    let pubid_re = Regex::new("(?:publicationguid = \")([^\"]+)").unwrap();
-                    let pubid = pubid_re.captures(site)?.get(1)?.as_str().to_string();
-                    return Some(ComicId::Issue(format!(
-                        "https://reader.flipp.dk/html5/reader/get_page_groups_from_eid.aspx?pubid={}&eid={}",
-                        pubid, eid
-                    )));
-                }
-            )
-        } else { Err(Error::FailedResponseParse) }
+    fn get_correct_id(&self, client: &Client, otherid: &str) -> Result<SourceResponse<ComicId>> {
+        let eid = otherid.to_string();
+        let url = format!(
+            "https://reader.flipp.dk/html5/reader/production/default.aspx?pubname=&edid={}",
+            eid
+        );
+        source_request!(
+            requests: client.get(url),
+            transform: |resp: &[bytes::Bytes]| {
+                let site = std::str::from_utf8(&resp[0]).ok()?;
+                let pubid_re = Regex::new("(?:publicationguid = \")([^\"]+)").unwrap();
+                let pubid = pubid_re.captures(site)?.get(1)?.as_str().to_string();
+                return Some(ComicId::Issue(format!(
+                    "https://reader.flipp.dk/html5/reader/get_page_groups_from_eid.aspx?pubid={}&eid={}",
+                    pubid, eid
+                ).into()));
+            }
+        )
     }
 
-    fn get_series_info(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<SeriesInfo>> {
-        if let ComicId::Series(x) = comicid {
-            let series_id = x.to_string();
-            source_request!(
-                requests: signin_data(client),
-                transform: |resp| {
-                    let series_data = get_series_data(resp, &series_id)?;
-                    Some(SeriesInfo {
-                        name: series_data["name"].as_str()?.to_string(),
-                        ..Default::default()
-                    })
-                }
-            )
-        } else { Err(Error::FailedResponseParse) }
+    fn get_series_info(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<SeriesInfo>> {
+        let series_id = seriesid.to_string();
+        source_request!(
+            requests: self.signin_data(client),
+            transform: |resp| {
+                let series_data = get_series_data(resp, &series_id)?;
+                Some(SeriesInfo {
+                    name: series_data["name"].as_str()?.to_string(),
+                    ..Default::default()
+                })
+            }
+        )
     }
 
-    fn get_metadata(&self, _client: &Client, _comicid: &ComicId) -> Result<SourceResponse<Metadata>> {
+    fn get_metadata(&self, _client: &Client, _issueid: &IssueId) -> Result<SourceResponse<Metadata>> {
         Ok(SourceResponse::Value(Metadata::default()))
     }
 
-    fn get_series_ids(&self, client: &Client, seriesid: &ComicId) -> Result<SourceResponse<Vec<ComicId>>> {
-        match seriesid {
-            ComicId::Series(x) => {
-                let series_id = x.to_string();
-                source_request!(
-                    requests: signin_data(client),
-                    transform: |resp: &[bytes::Bytes]| {
-                        let series_data = get_series_data(resp, &series_id)?;
-                        // Extracting issue data
-                        let series_name = &series_data["name"].as_str()?;
-                        let series_id = series_data["customPublicationCode"].as_str()?;
-                        series_data["issues"]
-                            .as_array()?
-                            .iter()
-                            .map(|issue| {
-                                let issue_id = value_to_optstring(&issue["customIssueCode"])?;
-                                let metadata = Metadata {
-                                    title: Some(format!("{} {}", series_name, &issue["issueName"].as_str()?)),
-                                    series: Some(series_name.to_string()),
-                                    source: Some("Flipp".to_string()),
-                                    ..Default::default()
-                                };
-                                let data_url = format!(
-                                    "https://reader.flipp.dk/html5/reader/get_page_groups_from_eid.aspx?pubid={}&eid={}",
-                                    series_id, issue_id
-                                );
-                                Some(ComicId::IssueWithMetadata(data_url, metadata))
-                            })
-                            .rev()
-                            .collect::<Option<Vec<ComicId>>>()
-                    }
-                )
-            },
-            _ => Err(Error::FailedResponseParse)
-        }
+    fn get_series_ids(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<Vec<ComicId>>> {
+        let series_id = seriesid.to_string();
+        source_request!(
+            requests: self.signin_data(client),
+            transform: |resp: &[bytes::Bytes]| {
+                let series_data = get_series_data(resp, &series_id)?;
+                // Extracting issue data
+                let series_name = &series_data["name"].as_str()?;
+                let series_id = series_data["customPublicationCode"].as_str()?;
+                series_data["issues"]
+                    .as_array()?
+                    .iter()
+                    .map(|issue| {
+                        let issue_id = value_to_optstring(&issue["customIssueCode"])?;
+                        let metadata = Metadata {
+                            title: Some(format!("{} {}", series_name, &issue["issueName"].as_str()?)),
+                            series: Some(series_name.to_string()),
+                            source: Some("Flipp".to_string()),
+                            ..Default::default()
+                        };
+                        let data_url = format!(
+                            "https://reader.flipp.dk/html5/reader/get_page_groups_from_eid.aspx?pubid={}&eid={}",
+                            series_id, issue_id
+                        );
+                        Some(ComicId::IssueWithMetadata(data_url.into(), metadata))
+                    })
+                    .rev()
+                    .collect::<Option<Vec<ComicId>>>()
+            }
+        )
     }
 
     fn metadata_require_authentication(&self) -> bool {
@@ -112,16 +113,57 @@ impl Source for Flipp {
         false
     }
 
-    fn get_pages(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<Vec<Page>>> {
-        if let ComicId::Issue(url) | ComicId::IssueWithMetadata(url, _) = comicid {
-            source_request!(
-                requests: client.get(url),
-                // requests: HttpRequest::get(&url),
-                transform: response_to_pages
-            )
-        } else { Err(Error::FailedDownload(self.name())) }
+    fn get_pages(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Vec<Page>>> {
+        source_request!(
+            requests: client.get(issueid.as_str()),
+            transform: response_to_pages
+        )
     }
 
+    async fn authenticate(&mut self, client: &mut Client, creds: &Credentials) -> Result<()> {
+        if let Credentials::UsernamePassword(email, password) = creds {
+            self.credentials = Some((email.clone(), password.clone()));
+            let response = self.signin_data(client)
+                .send().await?
+                .bytes().await?;
+            // Publications are only listed if the login succeeded
+            let publications = resp_to_json::<serde_json::Value>(&response)
+                .map(|x| x["publications"].is_array())
+                .unwrap_or(false);
+            if !publications {
+                self.credentials = None;
+                return Err(Error::FailedAuthentication(self.name()));
+            }
+            Ok(())
+        } else {
+            Err(Error::FailedAuthentication("Flipp requires email and password to login".to_string()))
+        }
+    }
+
+}
+
+impl Flipp {
+    /// Sign in to list available publications. Subscribers get their paid magazines as well as
+    /// the free ones.
+    fn signin_data(&self, client: &Client) -> reqwest::RequestBuilder {
+        let (email, password) = match &self.credentials {
+            Some((email, password)) => (email.as_str(), password.as_str()),
+            None => ("", ""),
+        };
+        // Required data
+        let data = HashMap::from([
+            ("email", email),
+            ("password", password),
+            ("token", ""),
+            ("languageCulture", "da-DK"),
+            ("appId", ""),
+            ("appVersion", ""),
+            ("uuid", ""),
+            ("os", "")
+        ]);
+        client.post("https://flippapi.egmontservice.com/api/signin")
+            .json(&data)
+    }
 }
 
 fn get_series_data(resp: &[bytes::Bytes], series_id: &str) -> Option<serde_json::Value> {
@@ -135,23 +177,6 @@ fn get_series_data(resp: &[bytes::Bytes], series_id: &str) -> Option<serde_json:
     Some(series_data)
 }
 
-fn signin_data(client: &Client) -> reqwest::RequestBuilder {
-    // Required data
-    let data = HashMap::from([
-        ("email", ""),
-        ("password", ""),
-        ("token", ""),
-        ("languageCulture", "da-DK"),
-        ("appId", ""),
-        ("appVersion", ""),
-        ("uuid", ""),
-        ("os", "")
-    ]);
-    client.post("https://flippapi.egmontservice.com/api/signin")
-        .json(&data)
-}
-
-
 fn response_to_pages(responses: &[bytes::Bytes]) -> Option<Vec<Page>> {
     utils::resp_to_json::<serde_json::Value>(&responses[0])?["pageGroups"]
         .as_array()?
@@ -174,7 +199,7 @@ mod tests {
 
     #[test]
     fn otherid_from_url() {
-        let source = super::Flipp;
+        let source = super::Flipp::default();
         assert_eq!(
             source.id_from_url("https://reader.flipp.dk/html5/reader/production/default.aspx?pubname=&edid=31d29e20-fd60-48ad-96b2-79a3d9d65788").unwrap(),
             ComicId::Other("31d29e20-fd60-48ad-96b2-79a3d9d65788".to_string())
@@ -183,10 +208,10 @@ mod tests {
 
     #[test]
     fn seriesid_from_url() {
-        let source = super::Flipp;
+        let source = super::Flipp::default();
         assert_eq!(
             source.id_from_url("https://magasiner.flipp.dk/flipp/web-app/#/publications/fa7c63ad-0a48-445b-9a17-7d536006902a").unwrap(),
-            ComicId::Series("fa7c63ad-0a48-445b-9a17-7d536006902a".to_string())
+            ComicId::Series("fa7c63ad-0a48-445b-9a17-7d536006902a".into())
         );
     }
 
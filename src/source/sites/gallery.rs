@@ -0,0 +1,181 @@
+use crate::{
+    comic::Page,
+    metadata::Metadata,
+    source::{
+        ComicId, IssueId, SeriesId, Error, Request, Result, Source, SourceResponse, SeriesInfo,
+        utils::{first_attr, first_text}
+    }
+};
+use reqwest::Client;
+use scraper::{Html, Selector};
+
+/// Generic source for simple gallery sites. Pages are found with user supplied css selectors.
+#[derive(Clone, Debug)]
+pub struct Gallery {
+    /// Css selector matching page images
+    pub image_selector: String,
+    /// Attribute of image elements containing the url of the image
+    pub image_attribute: String,
+    /// Css selector matching link to next page of gallery
+    pub next_selector: Option<String>,
+}
+
+/// Maximum number of gallery pages followed
+const MAX_GALLERY_PAGES: usize = 500;
+
+impl Gallery {
+    /// Create new `Gallery` source with css selector for images
+    pub fn new(image_selector: &str) -> Self {
+        Self {
+            image_selector: image_selector.to_string(),
+            image_attribute: "src".to_string(),
+            next_selector: None,
+        }
+    }
+
+    /// Set css selector for link to next page of gallery
+    pub fn next_selector(mut self, selector: &str) -> Self {
+        self.next_selector = Some(selector.to_string());
+        self
+    }
+
+    /// Set attribute containing url of images
+    pub fn image_attribute(mut self, attribute: &str) -> Self {
+        self.image_attribute = attribute.to_string();
+        self
+    }
+}
+
+impl Source for Gallery {
+    fn name(&self) -> String {
+        "Gallery".to_string()
+    }
+
+    fn id_from_url(&self, url: &str) -> Result<ComicId> {
+        Ok(ComicId::Issue(url.into()))
+    }
+
+    /// Galleries are not part of series
+    fn get_series_ids(&self, _client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<Vec<ComicId>>> {
+        Err(Error::NotASeries(seriesid.to_string()))
+    }
+
+    fn get_series_info(&self, _client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<SeriesInfo>> {
+        Err(Error::NotASeries(seriesid.to_string()))
+    }
+
+    fn get_metadata(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Metadata>> {
+        Ok(SourceResponse::Request(Request {
+            requests: vec![client.get(issueid.as_str())],
+            transform: Box::new(|resp| {
+                let value = parse_metadata(resp)?;
+                Some(SourceResponse::Value(value))
+            })
+        }))
+    }
+
+    fn get_pages(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Vec<Page>>> {
+        Ok(gallery_page(client.clone(), self.clone(), issueid.to_string(), Vec::new(), Vec::new()))
+    }
+
+    fn metadata_require_authentication(&self) -> bool {
+        false
+    }
+
+    fn pages_require_authentication(&self) -> bool {
+        false
+    }
+}
+
+/// Create request for gallery page at `url`. Requests for following pages are created until no
+/// link to a new page is found.
+fn gallery_page(client: Client, gallery: Gallery, url: String, visited: Vec<String>, pages: Vec<Page>) -> SourceResponse<Vec<Page>> {
+    SourceResponse::Request(Request {
+        requests: vec![client.get(&url)],
+        transform: Box::new(move |resp| {
+            let (mut new_pages, next) = parse_gallery_page(&resp[0], &gallery, &url)?;
+            let mut pages = pages.clone();
+            pages.append(&mut new_pages);
+            let mut visited = visited.clone();
+            visited.push(url.clone());
+            match next {
+                Some(next) if !visited.contains(&next) && visited.len() < MAX_GALLERY_PAGES => {
+                    Some(gallery_page(client.clone(), gallery.clone(), next, visited, pages))
+                },
+                _ => Some(SourceResponse::Value(pages))
+            }
+        })
+    })
+}
+
+/// Find images and link to next page in gallery page
+fn parse_gallery_page(resp: &[u8], gallery: &Gallery, url: &str) -> Option<(Vec<Page>, Option<String>)> {
+    let base = reqwest::Url::parse(url).ok()?;
+    let doc = Html::parse_document(std::str::from_utf8(resp).ok()?);
+    let image_selector = Selector::parse(&gallery.image_selector).ok()?;
+    let pages = doc.select(&image_selector)
+        .filter_map(|element| {
+            let image_url = base.join(element.value().attr(&gallery.image_attribute)?).ok()?;
+            // The format is detected from the downloaded data when the url has no extension
+            let file_format = image_url.path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .and_then(|name| std::path::Path::new(name).extension()?.to_str())
+                .filter(|ext| ext.len() <= 4 && ext.chars().all(|x| x.is_ascii_alphanumeric()))
+                .unwrap_or("jpg")
+                .to_lowercase();
+            Some(Page::from_url(image_url.as_str(), &file_format))
+        })
+        .collect();
+    let next = match &gallery.next_selector {
+        Some(selector) => first_attr(&doc, selector, "href")
+            .and_then(|link| base.join(&link).ok())
+            .map(|link| link.to_string()),
+        None => None,
+    };
+    Some((pages, next))
+}
+
+fn parse_metadata(resp: &[bytes::Bytes]) -> Option<Metadata> {
+    let doc = Html::parse_document(std::str::from_utf8(&resp[0]).ok()?);
+    Some(Metadata {
+        title: first_attr(&doc, r#"meta[property="og:title"]"#, "content")
+            .or_else(|| first_text(&doc, "title"))
+            .map(|x| x.trim().to_string()),
+        source: Some("Gallery".to_string()),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Gallery;
+    use crate::comic::{Page, PageType};
+
+    fn page_url(page: &Page) -> &str {
+        match &page.page_type {
+            PageType::Url(x) => &x.url,
+            PageType::Container(x) => x,
+        }
+    }
+
+    #[test]
+    fn gallery_page() {
+        let html = br#"<html><body>
+            <div class="page"><img src="/images/1.png"></div>
+            <div class="page"><img src="https://cdn.example.com/2.jpg"></div>
+            <div class="page"><img src="/a.b/c"></div>
+            <a class="next" href="?page=2">Next</a>
+        </body></html>"#;
+        let gallery = Gallery::new(".page img").next_selector("a.next");
+        let (pages, next) = super::parse_gallery_page(html, &gallery, "https://example.com/gallery/1").unwrap();
+        assert_eq!(
+            pages.iter().map(|x| (page_url(x), x.file_format.as_str())).collect::<Vec<_>>(),
+            vec![
+                ("https://example.com/images/1.png", "png"),
+                ("https://cdn.example.com/2.jpg", "jpg"),
+                ("https://example.com/a.b/c", "jpg"),
+            ]
+        );
+        assert_eq!(next, Some("https://example.com/gallery/1?page=2".to_string()));
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{
+    comic::Page,
+    metadata::{self, Author, Metadata},
+    source::{
+        self,
+        ComicId, IssueId, SeriesId, Credentials, Error, Request, Result, Source, SourceResponse, SeriesInfo,
+        utils::{issue_id_match, resp_to_json, simple_response, value_to_optstring}
+    }
+};
+use reqwest::Client;
+
+/// Source for hoopladigital.com. Comics are borrowed through a public library and require a
+/// library card to download.
+#[derive(Default)]
+pub struct Hoopla {
+    /// Token retrieved when authenticating
+    token: Option<String>,
+}
+
+const PATRON_API: &str = "https://patron-api-gateway.hoopladigital.com/core";
+const CIRCULATION_API: &str = "https://circulation-api-gateway.hoopladigital.com/circulation";
+
+#[async_trait::async_trait]
+impl Source for Hoopla {
+    fn name(&self) -> String {
+        "Hoopla".to_string()
+    }
+
+    fn supports_login(&self) -> bool {
+        true
+    }
+
+    fn client_builder(&self) -> source::ClientBuilder {
+        let mut clientbuilder = source::create_default_client();
+        clientbuilder.add_header("app", "ANDROID");
+        if let Some(token) = &self.token {
+            clientbuilder.add_header("Authorization", &format!("Bearer {}", token));
+        }
+        clientbuilder
+    }
+
+    fn id_from_url(&self, url: &str) -> Result<ComicId> {
+        issue_id_match!(url,
+            r"hoopladigital.com/title/(\d+)" => Issue,
+            r"hoopladigital.com/series/(\d+)" => Series
+        )
+    }
+
+    fn get_series_ids(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<Vec<ComicId>>> {
+        simple_response!(
+            id: seriesid,
+            client: client,
+            url: "https://patron-api-gateway.hoopladigital.com/core/series/{}/titles",
+            value: find_series_ids
+        )
+    }
+
+    fn get_series_info(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<SeriesInfo>> {
+        simple_response!(
+            id: seriesid,
+            client: client,
+            url: "https://patron-api-gateway.hoopladigital.com/core/series/{}",
+            value: parse_series_info
+        )
+    }
+
+    /// Titles currently borrowed by the library card
+    fn get_owned(&self, client: &Client) -> Result<SourceResponse<Vec<ComicId>>> {
+        Ok(SourceResponse::Request(Request {
+            requests: vec![client.get(format!("{}/borrowed/current", CIRCULATION_API))],
+            transform: Box::new(|resp| Some(SourceResponse::Value(find_borrowed_ids(resp)?)))
+        }))
+    }
+
+    fn url_from_id(&self, comicid: &ComicId) -> Option<String> {
+        match comicid {
+            ComicId::Issue(id) | ComicId::IssueWithMetadata(id, _) =>
+                Some(format!("https://www.hoopladigital.com/title/{}", id)),
+            ComicId::Series(id) => Some(format!("https://www.hoopladigital.com/series/{}", id)),
+            _ => None,
+        }
+    }
+
+    fn get_metadata(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Metadata>> {
+        simple_response!(
+            id: issueid,
+            client: client,
+            url: "https://patron-api-gateway.hoopladigital.com/core/titles/{}",
+            value: parse_metadata
+        )
+    }
+
+    /// Finds the title among the current borrows, borrowing it only if it isn't already
+    /// borrowed. A token for the borrowed content is then used to download the image manifest.
+    fn get_pages(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Vec<Page>>> {
+        let borrow_client = client.clone();
+        let id = issueid.as_str().to_string();
+        Ok(SourceResponse::Request(Request {
+            requests: vec![client.get(format!("{}/borrowed/current", CIRCULATION_API))],
+            transform: Box::new(move |resp| {
+                if let Some(content) = find_borrowed_content(resp, &id) {
+                    return Some(content_pages(&borrow_client, content));
+                }
+                log::warn!("Borrowing Hoopla title {}. This uses one of the library card's borrows", id);
+                let token_client = borrow_client.clone();
+                Some(SourceResponse::Request(Request {
+                    requests: vec![
+                        borrow_client.post(format!("{}/titles/{}/borrow", CIRCULATION_API, id))
+                    ],
+                    transform: Box::new(move |resp| {
+                        let borrowed = resp_to_json::<serde_json::Value>(&resp[0])?;
+                        let content = parse_content(&borrowed)?;
+                        Some(content_pages(&token_client, content))
+                    })
+                }))
+            })
+        }))
+    }
+
+    async fn authenticate(&mut self, client: &mut Client, creds: &Credentials) -> Result<()> {
+        if let Credentials::UsernamePassword(card_number, pin) = creds {
+            let response: serde_json::Value = client.post(format!("{}/tokens", PATRON_API))
+                .form(&[("username", card_number.as_str()), ("password", pin.as_str())])
+                .send().await?
+                .json().await?;
+            let token = response["token"].as_str()
+                .ok_or(Error::FailedAuthentication(self.name()))?;
+            self.token = Some(token.to_string());
+            *client = self.create_client();
+            Ok(())
+        } else {
+            Err(Error::FailedAuthentication("Hoopla requires a library card number and pin to login".to_string()))
+        }
+    }
+}
+
+fn find_series_ids(resp: &[bytes::Bytes]) -> Option<Vec<ComicId>> {
+    resp_to_json::<serde_json::Value>(&resp[0])?
+        .as_array()?
+        .iter()
+        .map(|title| Some(ComicId::Issue(title["id"].as_u64()?.to_string().into())))
+        .collect()
+}
+
+/// Ids of borrowed comics. Borrowed ebooks, movies, and music are skipped.
+fn find_borrowed_ids(resp: &[bytes::Bytes]) -> Option<Vec<ComicId>> {
+    Some(resp_to_json::<serde_json::Value>(&resp[0])?
+        .as_array()?
+        .iter()
+        .filter(|title| title["kind"]["name"].as_str().map(|x| x == "COMIC").unwrap_or(true))
+        .filter_map(|title| Some(ComicId::Issue(title["id"].as_u64()?.to_string().into())))
+        .collect())
+}
+
+/// Content id and media key of a borrowed title
+fn parse_content(title: &serde_json::Value) -> Option<(u64, String)> {
+    let content = &title["contents"][0];
+    Some((content["id"].as_u64()?, content["mediaKey"].as_str()?.to_string()))
+}
+
+/// Content of `id` if it is among the currently borrowed titles
+fn find_borrowed_content(resp: &[bytes::Bytes], id: &str) -> Option<(u64, String)> {
+    resp_to_json::<serde_json::Value>(&resp[0])?
+        .as_array()?
+        .iter()
+        .find(|title| title["id"].as_u64().map(|x| x.to_string()).as_deref() == Some(id))
+        .and_then(parse_content)
+}
+
+/// Retrieves a token for borrowed content and uses it to download the image manifest
+fn content_pages(client: &Client, (content_id, media_key): (u64, String)) -> SourceResponse<Vec<Page>> {
+    let manifest_client = client.clone();
+    SourceResponse::Request(Request {
+        requests: vec![client.get(format!("{}/contents/{}/token", PATRON_API, content_id))],
+        transform: Box::new(move |resp| {
+            let token = resp_to_json::<serde_json::Value>(&resp[0])?["token"]
+                .as_str()?
+                .to_string();
+            let base_url = format!("https://dash.hoopladigital.com/{}", media_key);
+            Some(SourceResponse::Request(Request {
+                requests: vec![
+                    manifest_client.get(format!("{}/manifest.json", base_url))
+                        .query(&[("token", &token)])
+                ],
+                transform: Box::new(move |resp| {
+                    let pages = parse_manifest(resp, &base_url, &token)?;
+                    Some(SourceResponse::Value(pages))
+                })
+            }))
+        })
+    })
+}
+
+fn parse_series_info(resp: &[bytes::Bytes]) -> Option<SeriesInfo> {
+    let data = resp_to_json::<serde_json::Value>(&resp[0])?;
+    Some(SeriesInfo {
+        name: data["name"].as_str()?.to_string(),
+        publisher: value_to_optstring(&data["publisher"]["name"]),
+        description: value_to_optstring(&data["synopsis"]),
+        ..Default::default()
+    })
+}
+
+fn parse_metadata(resp: &[bytes::Bytes]) -> Option<Metadata> {
+    let data = resp_to_json::<serde_json::Value>(&resp[0])?;
+    let date = data["releaseDate"].as_str()
+        .and_then(|x| metadata::date_from_str(x.split('T').next()?));
+    Some(Metadata {
+        title: value_to_optstring(&data["title"]),
+        series: value_to_optstring(&data["series"]["name"]),
+        publisher: value_to_optstring(&data["publisher"]["name"]),
+        description: value_to_optstring(&data["synopsis"]),
+        issue_number: data["issueNumber"].as_u64().map(|x| x as u32),
+        year: date.map(|x| x.0),
+        month: date.map(|x| x.1),
+        day: date.map(|x| x.2),
+        authors: data["artists"]
+            .as_array()
+            .unwrap_or(&Vec::new())
+            .iter()
+            .filter_map(|artist| Some(Author {
+                name: value_to_optstring(&artist["name"])?,
+                author_type: value_to_optstring(&artist["relationship"])?.into(),
+            }))
+            .collect(),
+        genres: data["genres"]
+            .as_array()
+            .unwrap_or(&Vec::new())
+            .iter()
+            .filter_map(|genre| value_to_optstring(&genre["name"]))
+            .collect(),
+        source: Some("Hoopla".to_string()),
+        ..Default::default()
+    })
+}
+
+/// Create pages from image manifest. Each image has to be requested with the content token.
+fn parse_manifest(resp: &[bytes::Bytes], base_url: &str, token: &str) -> Option<Vec<Page>> {
+    resp_to_json::<serde_json::Value>(&resp[0])?["pages"]
+        .as_array()?
+        .iter()
+        .map(|page| {
+            let url = format!("{}/{}?token={}", base_url, page["url"].as_str()?, token);
+            Some(Page::from_url(&url, "jpg"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source::{ComicId, Source};
+
+    #[test]
+    fn ids() {
+        let source = super::Hoopla::default();
+        assert_eq!(
+            source.id_from_url("https://www.hoopladigital.com/title/11890426").unwrap(),
+            ComicId::Issue("11890426".into())
+        );
+        assert_eq!(
+            source.id_from_url("https://www.hoopladigital.com/series/54321").unwrap(),
+            ComicId::Series("54321".into())
+        );
+    }
+
+    #[test]
+    fn borrowed() {
+        let resp = br#"[{"id":11890426,"kind":{"name":"COMIC"}},{"id":123,"kind":{"name":"AUDIOBOOK"}}]"#;
+        let ids = super::find_borrowed_ids(&[bytes::Bytes::from_static(resp)]).unwrap();
+        assert_eq!(ids, vec![ComicId::Issue("11890426".into())]);
+        let source = super::Hoopla::default();
+        let url = source.url_from_id(&ids[0]).unwrap();
+        assert_eq!(source.id_from_url(&url).unwrap(), ids[0]);
+    }
+
+    #[test]
+    fn borrowed_content() {
+        let resp = br#"[{"id":11890426,"contents":[{"id":42,"mediaKey":"abc"}]}]"#;
+        let resp = [bytes::Bytes::from_static(resp)];
+        assert_eq!(super::find_borrowed_content(&resp, "11890426"), Some((42, "abc".to_string())));
+        assert_eq!(super::find_borrowed_content(&resp, "123"), None);
+    }
+
+    #[test]
+    fn manifest() {
+        let resp = br#"{"pages":[{"url":"page-001.jpg"},{"url":"page-002.jpg"}]}"#;
+        let pages = super::parse_manifest(
+            &[bytes::Bytes::from_static(resp)],
+            "https://dash.hoopladigital.com/abc",
+            "token"
+        ).unwrap();
+        assert_eq!(pages.len(), 2);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{
+    comic::Page,
+    metadata::{Author, AuthorType, Metadata},
+    source::{
+        ComicId, IssueId, SeriesId, Error, Result, Source, SourceResponse, SeriesInfo,
+        utils::{issue_id_match, resp_to_json, source_request}
+    }
+};
+use reqwest::Client;
+
+/// Source for public domain comics on archive.org
+pub struct InternetArchive;
+
+impl Source for InternetArchive {
+    fn name(&self) -> String {
+        "Internet Archive".to_string()
+    }
+
+    fn id_from_url(&self, url: &str) -> Result<ComicId> {
+        issue_id_match!(url,
+            r"archive.org/details/([^/?#]+)" => Issue
+        )
+    }
+
+    /// Items on archive.org are not part of series
+    fn get_series_ids(&self, _client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<Vec<ComicId>>> {
+        Err(Error::NotASeries(seriesid.to_string()))
+    }
+
+    fn get_series_info(&self, _client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<SeriesInfo>> {
+        Err(Error::NotASeries(seriesid.to_string()))
+    }
+
+    fn get_metadata(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Metadata>> {
+        source_request!(
+            requests: client.get(format!("https://archive.org/metadata/{}", issueid.as_str())),
+            transform: parse_metadata
+        )
+    }
+
+    fn get_pages(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Vec<Page>>> {
+        let id = issueid.0.clone();
+        source_request!(
+            requests: client.get(format!("https://archive.org/metadata/{}", id)),
+            transform: |resp: &[bytes::Bytes]| parse_pages(resp, &id)
+        )
+    }
+
+    fn metadata_require_authentication(&self) -> bool {
+        false
+    }
+
+    fn pages_require_authentication(&self) -> bool {
+        false
+    }
+}
+
+/// Converts a value that is either a string or a list of strings to a list of strings
+fn string_or_list(value: &serde_json::Value) -> Vec<String> {
+    match value {
+        serde_json::Value::String(x) => vec![x.clone()],
+        serde_json::Value::Array(x) => x.iter()
+            .filter_map(|y| y.as_str().map(String::from))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn parse_metadata(resp: &[bytes::Bytes]) -> Option<Metadata> {
+    let data = resp_to_json::<serde_json::Value>(&resp[0])?;
+    let meta = &data["metadata"];
+    // Dates are either "year" or "year-month-day"
+    let date: Vec<u32> = meta["date"].as_str()
+        .unwrap_or("")
+        .split('-')
+        .filter_map(|x| x.parse().ok())
+        .collect();
+    Some(Metadata {
+        title: string_or_list(&meta["title"]).into_iter().next(),
+        series: string_or_list(&meta["series"]).into_iter().next(),
+        publisher: string_or_list(&meta["publisher"]).into_iter().next(),
+        description: string_or_list(&meta["description"]).into_iter().next(),
+        year: date.first().copied(),
+        month: date.get(1).copied(),
+        day: date.get(2).copied(),
+        authors: string_or_list(&meta["creator"])
+            .into_iter()
+            .map(|name| Author { name, author_type: AuthorType::Other })
+            .collect(),
+        genres: string_or_list(&meta["subject"]),
+        source: Some("Internet Archive".to_string()),
+        ..Default::default()
+    })
+}
+
+/// Find page scans in item. Original jpeg files are used if they exist, otherwise jpeg versions
+/// of the processed jp2 scans are used.
+fn parse_pages(resp: &[bytes::Bytes], id: &str) -> Option<Vec<Page>> {
+    let data = resp_to_json::<serde_json::Value>(&resp[0])?;
+    let files = data["files"].as_array()?;
+    let download_url = format!("https://archive.org/download/{}", id);
+    let mut jpegs: Vec<&str> = files.iter()
+        .filter(|file| file["format"].as_str() == Some("JPEG") && file["source"].as_str() == Some("original"))
+        .filter_map(|file| file["name"].as_str())
+        .collect();
+    if !jpegs.is_empty() {
+        jpegs.sort();
+        return Some(jpegs.iter()
+            .map(|name| Page::from_url(&format!("{}/{}", download_url, name), "jpg"))
+            .collect());
+    }
+    // Converting jp2 scans inside zip file to jpeg
+    let zip_name = files.iter()
+        .filter(|file| file["format"].as_str() == Some("Single Page Processed JP2 ZIP"))
+        .find_map(|file| file["name"].as_str())?;
+    let stem = zip_name.strip_suffix(".zip")?;
+    let image_count: usize = match &data["metadata"]["imagecount"] {
+        serde_json::Value::String(x) => x.parse().ok()?,
+        x => x.as_u64()? as usize,
+    };
+    Some((0..image_count)
+        .map(|n| {
+            let url = format!("{}/{}/{}%2F{}_{:04}.jp2&ext=jpg", download_url, zip_name, stem, id, n);
+            Page::from_url(&url, "jpg")
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source::{ComicId, Source};
+
+    #[test]
+    fn issueid_from_url() {
+        let source = super::InternetArchive;
+        assert_eq!(
+            source.id_from_url("https://archive.org/details/Action_Comics_001_1938").unwrap(),
+            ComicId::Issue("Action_Comics_001_1938".into())
+        );
+    }
+
+    #[test]
+    fn prefer_original_jpegs() {
+        let resp = br#"{"files":[
+            {"name":"scan_jp2.zip","format":"Single Page Processed JP2 ZIP","source":"derivative"},
+            {"name":"002.jpg","format":"JPEG","source":"original"},
+            {"name":"001.jpg","format":"JPEG","source":"original"}
+        ],"metadata":{"imagecount":"2"}}"#;
+        let pages = super::parse_pages(&[bytes::Bytes::from_static(resp)], "scan").unwrap();
+        assert_eq!(pages.len(), 2);
+    }
+
+    #[test]
+    fn jp2_derivatives() {
+        let resp = br#"{"files":[
+            {"name":"scan_jp2.zip","format":"Single Page Processed JP2 ZIP","source":"derivative"}
+        ],"metadata":{"imagecount":"3"}}"#;
+        let pages = super::parse_pages(&[bytes::Bytes::from_static(resp)], "scan").unwrap();
+        assert_eq!(pages.len(), 3);
+    }
+}
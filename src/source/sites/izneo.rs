@@ -2,10 +2,10 @@ use reqwest::Client;
 
 use crate::{
     comic::{Page, OnlinePage, PageEncryptionScheme, PageType},
-    metadata::Metadata,
+    metadata::{Metadata, PageLayout, ReadingDirection},
     source::{
-        ComicId, Result, Source, SourceResponse, SeriesInfo,
-        utils::{self, issue_id_match, simple_response, value_to_optstring}
+        ComicId, IssueId, SeriesId, Result, Source, SourceResponse, SeriesInfo,
+        utils::{self, issue_id_match, simple_response, source_request, value_to_optstring}
     }
 };
 
@@ -21,41 +21,54 @@ impl Source for Izneo {
         id_from_url(url)
     }
 
-    fn get_series_ids(&self, client: &Client, seriesid: &ComicId) -> Result<SourceResponse<Vec<ComicId>>>  {
+    fn get_series_ids(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<Vec<ComicId>>>  {
         simple_response!(
             id: seriesid,
             client: client,
-            id_type: Series,
             url: "https://izneo.com/en/api/android/serie/{}/volumes/old/0/10000",
             value: find_series_ids
         )
     }
 
-    fn get_series_info(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<SeriesInfo>>  {
+    /// Albums in the library of the account, which are purchased or included in a subscription
+    fn get_owned(&self, client: &Client) -> Result<SourceResponse<Vec<ComicId>>> {
+        source_request!(
+            requests: client.get("https://izneo.com/en/api/android/library/albums/0/10000"),
+            transform: find_series_ids
+        )
+    }
+
+    fn url_from_id(&self, comicid: &ComicId) -> Option<String> {
+        // Only the id at the end of the name is needed to find the album
+        match comicid {
+            ComicId::Issue(id) | ComicId::IssueWithMetadata(id, _) =>
+                Some(format!("https://www.izneo.com/en/comics/all/series/album-{}/read", id)),
+            _ => None,
+        }
+    }
+
+    fn get_series_info(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<SeriesInfo>>  {
         simple_response!(
-            id: comicid,
+            id: seriesid,
             client: client,
-            id_type: Series,
             url: "https://izneo.com/en/api/android/serie/{}",
             value: find_series_info
         )
     }
 
-    fn get_metadata(&self,client: &Client, comicid: &ComicId) -> Result<SourceResponse<Metadata>>  {
+    fn get_metadata(&self,client: &Client, comicid: &IssueId) -> Result<SourceResponse<Metadata>>  {
         simple_response!(
             id: comicid,
             client: client,
-            id_type: Issue,
             url: "https://www.izneo.com/book/{}",
             value: parse_metadata
         )
     }
 
-    fn get_pages(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<Vec<Page>>> {
+    fn get_pages(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Vec<Page>>> {
         simple_response!(
-            id: comicid,
+            id: issueid,
             client: client,
-            id_type: Issue,
             url: "https://www.izneo.com/book/{}",
             value: get_pages
         )
@@ -85,7 +98,7 @@ fn find_series_ids(resp: &[bytes::Bytes]) -> Option<Vec<ComicId>> {
         .iter()
         .map(|x| {
             let id = x["id"].as_str()?.to_string();
-            Some(ComicId::Issue(id))
+            Some(ComicId::Issue(id.into()))
         })
         .collect()
 }
@@ -114,6 +127,7 @@ fn get_pages(resp: &[bytes::Bytes]) -> Option<Vec<Page>> {
                         image_type = image_type
                     ),
                     headers: None,
+                    mirrors: Vec::new(),
                     encryption: Some(PageEncryptionScheme::AES {
                         key: f(&x["key"])?,
                         iv: f(&x["iv"])?,
@@ -125,14 +139,34 @@ fn get_pages(resp: &[bytes::Bytes]) -> Option<Vec<Page>> {
     Some(pages)
 }
 
+/// Reading direction and page layout of album. Webtoons are marked either with the format of the
+/// album or with a vertical reading direction.
+fn layout(data: &serde_json::Value) -> Option<(ReadingDirection, PageLayout)> {
+    let direction = data["readDirection"].as_str()?;
+    let format = data["format"].as_str().unwrap_or_default();
+    let vertical = ["vertical", "webtoon", "ttb"].iter()
+        .any(|x| direction.eq_ignore_ascii_case(x) || format.eq_ignore_ascii_case(x));
+    if vertical {
+        Some((ReadingDirection::LeftToRight, PageLayout::Vertical))
+    } else {
+        Some((direction.try_into().ok()?, PageLayout::Paged))
+    }
+}
+
 fn parse_metadata(resp: &[bytes::Bytes]) -> Option<Metadata> {
     let root: serde_json::Value = utils::resp_to_json(&resp[0])?;
     let data = &root["data"];
-    // let info = &data["endingPageRules"]["ctaAlbum"];
+    let info = &data["endingPageRules"]["ctaAlbum"];
+    let (reading_direction, page_layout) = layout(data)?;
     Some(Metadata {
         title: value_to_optstring(&data["subtitle"]),
         series: value_to_optstring(&data["title"]),
-        reading_direction: data["readDirection"].as_str()?.try_into().ok()?,
+        language: value_to_optstring(&info["language"]),
+        volume: data["volume"].as_str().and_then(|x| x.parse().ok()),
+        reading_direction,
+        page_layout,
+        // Pages of books the account does not own are limited to a preview
+        preview: data["state"].as_str() == Some("preview"),
         // authors: info["authors"]
         //     .as_array()?
         //     .iter()
@@ -147,7 +181,7 @@ fn parse_metadata(resp: &[bytes::Bytes]) -> Option<Metadata> {
 
 #[cfg(test)]
 mod tests {
-    use crate::metadata::ReadingDirection;
+    use crate::metadata::{PageLayout, ReadingDirection};
     use crate::source::ComicId;
     use crate::source::utils::tests as test_utils;
 
@@ -156,15 +190,23 @@ mod tests {
     fn issueid_from_url() {
         assert_eq!(
             super::id_from_url("https://www.izneo.com/en/us-comics/fantasy/jim-butcher-s-the-dresden-files-20229/jim-butcher-s-the-dresden-files-down-town-46333/read/1?exiturl=https://www.izneo.com/en/us-comics/fantasy/jim-butcher-s-the-dresden-files-20229").unwrap(),
-            ComicId::Issue("46333".to_string())
+            ComicId::Issue("46333".into())
         )
     }
 
+    #[test]
+    fn url_from_id() {
+        use crate::source::Source;
+        let id = ComicId::Issue("46333".into());
+        let url = super::Izneo.url_from_id(&id).unwrap();
+        assert_eq!(super::id_from_url(&url).unwrap(), id);
+    }
+
     #[test]
     fn seriesid_from_url() {
         assert_eq!(
             super::id_from_url("https://www.izneo.com/en/us-comics/fantasy/jim-butcher-s-the-dresden-files-20229").unwrap(),
-            ComicId::Series("20229".to_string())
+            ComicId::Series("20229".into())
         )
     }
 
@@ -190,7 +232,10 @@ mod tests {
             crate::metadata::Metadata {
                 title: Some("Jim Butcher's The Dresden Files: Down Town".to_string()),
                 series: Some("Jim Butcher's The Dresden Files".to_string()),
+                language: Some("en".to_string()),
+                volume: Some(1),
                 reading_direction: ReadingDirection::LeftToRight,
+                preview: true,
                 // authors: vec![
                 //     Author { name: "Jim Butcher".to_string(), author_type: AuthorType::Other },
                 //     Author { name: "Mark Powers".to_string(), author_type: AuthorType::Other },
@@ -200,4 +245,22 @@ mod tests {
         )
     }
 
+    #[test]
+    fn page_layout() {
+        let layout = |x| super::layout(&serde_json::from_str(x).unwrap());
+        assert_eq!(
+            layout(r#"{"readDirection": "rtl"}"#),
+            Some((ReadingDirection::RightToLeft, PageLayout::Paged))
+        );
+        assert_eq!(
+            layout(r#"{"readDirection": "ltr", "format": "webtoon"}"#),
+            Some((ReadingDirection::LeftToRight, PageLayout::Vertical))
+        );
+        assert_eq!(
+            layout(r#"{"readDirection": "vertical"}"#),
+            Some((ReadingDirection::LeftToRight, PageLayout::Vertical))
+        );
+        assert_eq!(layout(r#"{"readDirection": "diagonal"}"#), None);
+    }
+
 }
@@ -0,0 +1,224 @@
+use crate::{
+    comic::{OnlinePage, Page, PageEncryptionScheme, PageType},
+    metadata::{self, Author, AuthorType, Metadata},
+    source::{
+        self,
+        ComicId, IssueId, SeriesId, Credentials, Error, Result, Source, SourceResponse, SeriesInfo,
+        utils::{issue_id_match, resp_to_json, source_request, value_to_optstring}
+    }
+};
+use crypto::{
+    digest::Digest,
+    sha2::{Sha256, Sha512},
+};
+use reqwest::Client;
+
+/// Source for kmanga.kodansha.com
+#[derive(Default)]
+pub struct KManga {
+    /// Session cookie retrieved when authenticating
+    session: Option<String>,
+}
+
+const API_URL: &str = "https://api.kmanga.kodansha.com";
+
+#[async_trait::async_trait]
+impl Source for KManga {
+    fn name(&self) -> String {
+        "K Manga".to_string()
+    }
+
+    fn supports_login(&self) -> bool {
+        true
+    }
+
+    fn client_builder(&self) -> source::ClientBuilder {
+        let mut clientbuilder = source::create_default_client();
+        if let Some(session) = &self.session {
+            clientbuilder.add_cookie("glsc", session);
+        }
+        clientbuilder
+    }
+
+    fn id_from_url(&self, url: &str) -> Result<ComicId> {
+        issue_id_match!(url,
+            r"kmanga.kodansha.com/title/\d+/episode/(\d+)" => Issue,
+            r"kmanga.kodansha.com/title/(\d+)" => Series
+        )
+    }
+
+    fn get_series_ids(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<Vec<ComicId>>> {
+        source_request!(
+            requests: signed_request(client, "/web/title/detail", &[("title_id", seriesid.as_str())]),
+            transform: find_series_ids
+        )
+    }
+
+    fn get_series_info(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<SeriesInfo>> {
+        source_request!(
+            requests: signed_request(client, "/web/title/detail", &[("title_id", seriesid.as_str())]),
+            transform: parse_series_info
+        )
+    }
+
+    fn get_metadata(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Metadata>> {
+        source_request!(
+            requests: signed_request(client, "/episode/list", &[("episode_id_list", issueid.as_str())]),
+            transform: parse_metadata
+        )
+    }
+
+    fn get_pages(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Vec<Page>>> {
+        source_request!(
+            requests: signed_request(client, "/web/episode/viewer", &[("episode_id", issueid.as_str())]),
+            transform: parse_pages
+        )
+    }
+
+    fn metadata_require_authentication(&self) -> bool {
+        false
+    }
+
+    async fn authenticate(&mut self, client: &mut Client, creds: &Credentials) -> Result<()> {
+        if let Credentials::UsernamePassword(email, password) = creds {
+            let response = client.post(format!("{}/web/user/login", API_URL))
+                .form(&[("email", email.as_str()), ("password", password.as_str())])
+                .send().await?;
+            let session = response.cookies()
+                .find(|cookie| cookie.name() == "glsc")
+                .map(|cookie| cookie.value().to_string())
+                .ok_or(Error::FailedAuthentication(self.name()))?;
+            self.session = Some(session);
+            *client = self.create_client();
+            Ok(())
+        } else {
+            Err(Error::FailedAuthentication("K Manga requires email and password to login".to_string()))
+        }
+    }
+}
+
+/// Create GET request to api signed with `x-kmanga-hash` header
+fn signed_request(client: &Client, path: &str, params: &[(&str, &str)]) -> reqwest::RequestBuilder {
+    client.get(format!("{}{}", API_URL, path))
+        .query(params)
+        .header("x-kmanga-platform", "3")
+        .header("x-kmanga-hash", create_hash(params))
+}
+
+/// Create hash of request parameters required by the api
+fn create_hash(params: &[(&str, &str)]) -> String {
+    let mut sorted = params.to_vec();
+    sorted.sort();
+    let param_hashes = sorted.iter()
+        .map(|(key, value)| {
+            let mut key_hasher = Sha256::new();
+            key_hasher.input_str(key);
+            let mut value_hasher = Sha512::new();
+            value_hasher.input_str(value);
+            format!("{}_{}", key_hasher.result_str(), value_hasher.result_str())
+        })
+        .collect::<Vec<String>>()
+        .join(",");
+    let mut hasher = Sha256::new();
+    hasher.input_str(&param_hashes);
+    hasher.result_str()
+}
+
+fn find_series_ids(resp: &[bytes::Bytes]) -> Option<Vec<ComicId>> {
+    resp_to_json::<serde_json::Value>(&resp[0])?["web_title"]["episode_id_list"]
+        .as_array()?
+        .iter()
+        .map(|id| Some(ComicId::Issue(id.as_u64()?.to_string().into())))
+        .collect()
+}
+
+fn parse_series_info(resp: &[bytes::Bytes]) -> Option<SeriesInfo> {
+    let data = resp_to_json::<serde_json::Value>(&resp[0])?;
+    Some(SeriesInfo {
+        name: data["web_title"]["title_name"].as_str()?.to_string(),
+        issue_count: data["web_title"]["episode_id_list"].as_array().map(|x| x.len()),
+        publisher: Some("Kodansha".to_string()),
+        ..Default::default()
+    })
+}
+
+fn parse_metadata(resp: &[bytes::Bytes]) -> Option<Metadata> {
+    let data = resp_to_json::<serde_json::Value>(&resp[0])?;
+    let episode = &data["episode_list"][0];
+    let date = episode["start_time"].as_str()
+        .and_then(|x| metadata::date_from_str(x.split(' ').next()?));
+    Some(Metadata {
+        title: value_to_optstring(&episode["episode_name"]),
+        series: value_to_optstring(&episode["title_name"]),
+        publisher: Some("Kodansha".to_string()),
+        year: date.map(|x| x.0),
+        month: date.map(|x| x.1),
+        day: date.map(|x| x.2),
+        authors: value_to_optstring(&episode["author_text"])
+            .map(|name| vec![Author { name, author_type: AuthorType::Writer }])
+            .unwrap_or_default(),
+        reading_direction: metadata::ReadingDirection::RightToLeft,
+        source: Some("K Manga".to_string()),
+        ..Default::default()
+    })
+}
+
+/// Page urls are signed and only valid for a short time. Pages of episodes with a non-zero
+/// `scramble_seed` are shuffled in tiles, which are moved back in place after downloading.
+fn parse_pages(resp: &[bytes::Bytes]) -> Option<Vec<Page>> {
+    let data = resp_to_json::<serde_json::Value>(&resp[0])?;
+    let seed = u32::try_from(data["scramble_seed"].as_u64().unwrap_or(0)).ok()?;
+    data["page_list"]
+        .as_array()?
+        .iter()
+        .map(|url| Some(Page {
+            file_format: "jpg".to_string(),
+            page_type: PageType::Url(OnlinePage {
+                url: url.as_str()?.to_string(),
+                encryption: (seed != 0).then_some(PageEncryptionScheme::KManga(seed)),
+                ..Default::default()
+            })
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        comic::{PageEncryptionScheme, PageType},
+        source::{ComicId, Source},
+    };
+
+    #[test]
+    fn ids() {
+        let source = super::KManga::default();
+        assert_eq!(
+            source.id_from_url("https://kmanga.kodansha.com/title/10044/episode/317350").unwrap(),
+            ComicId::Issue("317350".into())
+        );
+        assert_eq!(
+            source.id_from_url("https://kmanga.kodansha.com/title/10044").unwrap(),
+            ComicId::Series("10044".into())
+        );
+    }
+
+    #[test]
+    fn scrambled_pages() {
+        let pages = |x: &'static str| super::parse_pages(&[bytes::Bytes::from_static(x.as_bytes())]);
+        let unscrambled = pages(r#"{"page_list": ["https://example.com/1.jpg"], "scramble_seed": 0}"#);
+        assert_eq!(unscrambled.map(|x| x.len()), Some(1));
+        let scrambled = pages(r#"{"page_list": ["https://example.com/1.jpg"], "scramble_seed": 42}"#).unwrap();
+        match &scrambled[0].page_type {
+            PageType::Url(page) => assert!(matches!(page.encryption, Some(PageEncryptionScheme::KManga(42)))),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn hash_independent_of_parameter_order() {
+        assert_eq!(
+            super::create_hash(&[("a", "1"), ("b", "2")]),
+            super::create_hash(&[("b", "2"), ("a", "1")])
+        );
+    }
+}
@@ -1,21 +1,37 @@
 use crate::{
     source::{
-        Source, ComicId, Result, SourceResponse, Error, SeriesInfo,
-        utils::{issue_id_match, source_request, simple_response, resp_to_json}
+        Source, ComicId, IssueId, SeriesId, Result, SourceResponse, SeriesInfo,
+        utils::{issue_id_match, source_request, resp_to_json}
     },
     comic::Page,
     metadata::{Metadata, Author, AuthorType},
 };
 use reqwest::Client;
 
+/// Locale used if none is set
+const DEFAULT_LOCALE: &str = "en_us";
 
-pub struct LeagueOfLegends;
+/// Source for universe.leagueoflegends.com
+pub struct LeagueOfLegends {
+    /// Locale of comics like `en_us`
+    locale: String,
+}
+
+impl Default for LeagueOfLegends {
+    fn default() -> Self {
+        Self { locale: DEFAULT_LOCALE.to_string() }
+    }
+}
 
 impl Source for LeagueOfLegends {
     fn name(&self) -> String {
         "League of Legends".to_string()
     }
 
+    fn set_locale(&mut self, locale: &str) {
+        self.locale = locale.to_lowercase().replace('-', "_");
+    }
+
     fn id_from_url(&self, url: &str) -> Result<ComicId> {
         issue_id_match!(url,
             r"/comic/([^/]+/[^/]+)/" => Issue,
@@ -23,32 +39,27 @@ impl Source for LeagueOfLegends {
         )
     }
 
-    fn get_series_ids(&self, client: &Client, seriesid: &ComicId) -> Result<SourceResponse<Vec<ComicId>>> {
-        if let ComicId::Series(id) = seriesid {
-            let sid = id.clone();
-            source_request!(
-                requests: client.get(info_url(id)),
-                transform: |responses: &[bytes::Bytes]| {
-                    resp_to_json::<serde_json::Value>(&responses[0])?
-                        .get("issues")?
-                        .as_array()?
-                        .iter()
-                        .map(|issue| {
-                            Some(ComicId::Issue(format!("{}/{}", sid, issue["id"].as_str()?)))
-                        })
-                        .collect::<Option<Vec<ComicId>>>()
-                }
-            )
-        } else { Err(Error::FailedResponseParse) }
-    }
-
-    fn get_series_info(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<SeriesInfo>> {
-        simple_response!(
-            id: comicid,
-            client: client,
-            id_type: Issue,
-            url: "https://universe-meeps.leagueoflegends.com/v1/en_us/comics/{}/index.json",
-            value: response_series_info
+    fn get_series_ids(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<Vec<ComicId>>> {
+        let sid = seriesid.to_string();
+        source_request!(
+            requests: client.get(info_url(&self.locale, seriesid.as_str())),
+            transform: |responses: &[bytes::Bytes]| {
+                resp_to_json::<serde_json::Value>(&responses[0])?
+                    .get("issues")?
+                    .as_array()?
+                    .iter()
+                    .map(|issue| {
+                        Some(ComicId::Issue(format!("{}/{}", sid, issue["id"].as_str()?).into()))
+                    })
+                    .collect::<Option<Vec<ComicId>>>()
+            }
+        )
+    }
+
+    fn get_series_info(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<SeriesInfo>> {
+        source_request!(
+            requests: client.get(info_url(&self.locale, seriesid.as_str())),
+            transform: response_series_info
         )
     }
 
@@ -60,46 +71,49 @@ impl Source for LeagueOfLegends {
         false
     }
 
-    fn get_metadata(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<Metadata>> {
-        simple_response!(
-            id: comicid,
-            client: client,
-            id_type: Issue,
-            url: "https://universe-meeps.leagueoflegends.com/v1/en_us/comics/{}/index.json",
-            value: response_to_metadata
+    fn get_metadata(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Metadata>> {
+        let language = language(&self.locale);
+        source_request!(
+            requests: client.get(info_url(&self.locale, issueid.as_str())),
+            transform: |responses: &[bytes::Bytes]| response_to_metadata(responses, &language)
         )
     }
 
 
-    fn get_pages(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<Vec<Page>>> {
-        if let ComicId::Issue(issueid) = comicid {
-            source_request!(
-                requests: client.get(
-                    format!(
-                        "https://universe-comics.leagueoflegends.com/comics/en_us/{}/index.json",
-                        issueid
-                    )
-                ),
-                transform: response_to_pages
-            )
-        } else { Err(Error::FailedResponseParse) }
+    fn get_pages(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Vec<Page>>> {
+        source_request!(
+            requests: client.get(
+                format!(
+                    "https://universe-comics.leagueoflegends.com/comics/{}/{}/index.json",
+                    self.locale, issueid.as_str()
+                )
+            ),
+            transform: response_to_pages
+        )
     }
 
 }
 
-fn info_url(id: &str) -> String {
+fn info_url(locale: &str, id: &str) -> String {
     format!(
-        "https://universe-meeps.leagueoflegends.com/v1/en_us/comics/{}/index.json",
-        id
+        "https://universe-meeps.leagueoflegends.com/v1/{}/comics/{}/index.json",
+        locale, id
     )
 }
 
+/// ISO 639-1 code of language in `locale`
+fn language(locale: &str) -> String {
+    locale.split('_').next().unwrap_or(locale).to_string()
+}
+
 fn response_series_info(responses: &[bytes::Bytes]) -> Option<SeriesInfo> {
+    let data = resp_to_json::<serde_json::Value>(&responses[0])?;
     Some(SeriesInfo {
-        name: resp_to_json::<serde_json::Value>(&responses[0])?
-            .get("name")?
-            .as_str()?
-            .to_string(),
+        name: data.get("name")?.as_str()?.to_string(),
+        issue_count: data["issues"].as_array().map(|x| x.len()),
+        publisher: Some("Riot Games".to_string()),
+        description: data["description"].as_str().map(String::from),
+        cover_url: data["header-image"]["uri"].as_str().map(String::from),
         ..Default::default()
     })
 }
@@ -127,7 +141,7 @@ fn response_to_pages(responses: &[bytes::Bytes]) -> Option<Vec<Page>> {
     Some(pages)
 }
 
-fn response_to_metadata(responses: &[bytes::Bytes]) -> Option<Metadata> {
+fn response_to_metadata(responses: &[bytes::Bytes], language: &str) -> Option<Metadata> {
     let resp = resp_to_json::<serde_json::Value>(&responses[0])?;
     let info = resp.get("comic-info")?;
     let title = info.get("title")?.as_str()?;
@@ -153,37 +167,38 @@ fn response_to_metadata(responses: &[bytes::Bytes]) -> Option<Metadata> {
             .filter(|author| author.author_type != AuthorType::Other)
             .collect(),
         source: Some("League of Legends".to_string()),
+        language: Some(language.to_string()),
         ..Default::default()
     })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::source::{Source, ComicId, utils::tests::{response_from_testfile, transform_from_source_response}};
+    use crate::source::{Source, ComicId, SeriesId, utils::tests::{response_from_testfile, transform_from_source_response}};
     use crate::metadata::{Author, AuthorType};
 
     #[test]
     fn issueid_from_url() {
-        let source = super::LeagueOfLegends;
+        let source = super::LeagueOfLegends::default();
         assert_eq!(
             source.id_from_url("https://universe.leagueoflegends.com/en_us/comic/star-guardian/issue-1/0/").unwrap(),
-            ComicId::Issue("star-guardian/issue-1".to_string())
+            ComicId::Issue("star-guardian/issue-1".into())
         );
     }
 
     #[test]
     fn seriesid_from_url() {
-        let source = super::LeagueOfLegends;
+        let source = super::LeagueOfLegends::default();
         assert_eq!(
             source.id_from_url("https://universe.leagueoflegends.com/en_us/comic/star-guardian").unwrap(),
-            ComicId::Series("star-guardian".to_string())
+            ComicId::Series("star-guardian".into())
         );
     }
 
     #[test]
     fn metadata() {
         let responses = response_from_testfile("leagueoflegends_issue_metadata.json");
-        let metadata = super::response_to_metadata(&responses).unwrap();
+        let metadata = super::response_to_metadata(&responses, "en").unwrap();
         assert_eq!(
             metadata,
             crate::metadata::Metadata {
@@ -197,11 +212,24 @@ mod tests {
                     Author { name: "Molly Mahan".to_string(), author_type: AuthorType::Editor },
                 ],
                 source: Some("League of Legends".to_string()),
+                language: Some("en".to_string()),
                 ..Default::default()
             }
         )
     }
 
+    #[test]
+    fn localized_urls() {
+        let mut source = super::LeagueOfLegends::default();
+        source.set_locale("de-DE");
+        assert_eq!(source.locale, "de_de");
+        assert_eq!(
+            super::info_url(&source.locale, "star-guardian"),
+            "https://universe-meeps.leagueoflegends.com/v1/de_de/comics/star-guardian/index.json"
+        );
+        assert_eq!(super::language(&source.locale), "de");
+    }
+
     #[test]
     fn number_of_pages() {
         let meta_resp = std::fs::read("./tests/source_data/leagueoflegends_issue_metadata.json").unwrap();
@@ -213,8 +241,8 @@ mod tests {
     #[test]
     fn series() {
         // Setup
-        let source = super::LeagueOfLegends;
-        let seriesid = ComicId::Series("sentinelsoflight".to_string());
+        let source = super::LeagueOfLegends::default();
+        let seriesid = SeriesId::from("sentinelsoflight");
         let client = reqwest::Client::new();
         let responses = response_from_testfile("leagueoflegends_series.json");
         // Series issues
@@ -224,10 +252,11 @@ mod tests {
         let issues = transform(&responses);
         assert_eq!(issues.len(), 6);
         if let super::ComicId::Issue(issueid) = &issues[3] {
-            assert_eq!("sentinelsoflight/issue-4", issueid);
+            assert_eq!("sentinelsoflight/issue-4", issueid.as_str());
         } else { panic!("Returned id was not an issue") }
         // Series info
         let series_info = super::response_series_info(&responses).unwrap();
         assert_eq!(series_info.name, "Steadfast Heart".to_string());
+        assert_eq!(series_info.issue_count, Some(6));
     }
 }
@@ -5,13 +5,20 @@ use crate::{
     comic::Page,
     metadata::{Metadata, ReadingDirection},
     source::{
-        Source, ComicId, Result, SourceResponse, SeriesInfo,
+        Source, ComicId, IssueId, SeriesId, Request, Result, SourceResponse, SeriesInfo,
         utils::{issue_id_match, first_capture_bin, simple_response}
     }
 };
 
+/// Url of title details with chapter list
+const TITLE_URL: &str = "https://jumpg-api.tokyo-cdn.com/api/title_detailV2?os=android&os_ver=32&app_ver=40&secret=2afb69fbb05f57a1856cf75e1c4b6ee6&lang=eng&title_id=";
 
-pub struct MangaPlus;
+#[derive(Default)]
+pub struct MangaPlus {
+    /// Language of translation to download (ISO 639-1). Series in other languages are replaced
+    /// with their translation in this language if it exists.
+    language: Option<String>,
+}
 
 impl Source for MangaPlus {
     fn name(&self) -> String {
@@ -25,45 +32,173 @@ impl Source for MangaPlus {
         )
     }
 
-    fn get_series_ids(&self, client: &Client, seriesid: &ComicId) -> Result<SourceResponse<Vec<ComicId>>> {
-        simple_response!(
-            id: seriesid,
-            client: client,
-            id_type: Series,
-            url: "https://jumpg-api.tokyo-cdn.com/api/title_detailV2?title_id={}&lang=eng&os=android&os_ver=32&app_ver=40&secret=2afb69fbb05f57a1856cf75e1c4b6ee6",
-            value: find_series_ids
-        )
+    fn get_series_ids(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<Vec<ComicId>>> {
+        let language = self.language.clone();
+        let translation_client = client.clone();
+        Ok(SourceResponse::Request(Request {
+            requests: vec![client.get(format!("{}{}", TITLE_URL, seriesid))],
+            transform: Box::new(move |resp| {
+                // Translations are separate titles
+                let translation = language.as_deref()
+                    .filter(|x| series_language(&resp[0]) != Some(*x))
+                    .and_then(|x| translations(&resp[0]).into_iter().find(|(_, language)| *language == x));
+                match translation {
+                    Some((title_id, _)) => Some(SourceResponse::Request(Request {
+                        requests: vec![translation_client.get(format!("{}{}", TITLE_URL, title_id))],
+                        transform: Box::new(|resp| Some(SourceResponse::Value(find_series_ids(resp)?))),
+                    })),
+                    None => Some(SourceResponse::Value(find_series_ids(resp)?)),
+                }
+            })
+        }))
     }
 
-    fn get_series_info(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<SeriesInfo>> {
+    fn get_series_info(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<SeriesInfo>> {
         simple_response!(
-            id: comicid,
+            id: seriesid,
             client: client,
-            id_type: Series,
             url: "https://jumpg-webapi.tokyo-cdn.com/api/title_detailV2?title_id={}",
             value: response_series_info
         )
     }
 
-    fn get_metadata(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<Metadata>> {
-        simple_response!(
-            id: comicid,
-            client: client,
-            id_type: Issue,
-            url: "https://jumpg-webapi.tokyo-cdn.com/api/manga_viewer?chapter_id={}&split=yes&img_quality=super_high",
-            value: response_to_metadata
-        )
+    /// Language is found from the details of the title the chapter is from
+    fn get_metadata(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Metadata>> {
+        let title_client = client.clone();
+        Ok(SourceResponse::Request(Request {
+            requests: vec![client.get(format!("https://jumpg-webapi.tokyo-cdn.com/api/manga_viewer?chapter_id={}&split=yes&img_quality=super_high", issueid))],
+            transform: Box::new(move |resp| {
+                let metadata = response_to_metadata(resp)?;
+                let title_id = proto_int(message(&resp[0], &[1, 10])?, 9)?;
+                Some(SourceResponse::Request(Request {
+                    requests: vec![title_client.get(format!("{}{}", TITLE_URL, title_id))],
+                    transform: Box::new(move |resp| Some(SourceResponse::Value(Metadata {
+                        language: series_language(&resp[0]).map(String::from),
+                        ..metadata.clone()
+                    }))),
+                }))
+            })
+        }))
     }
 
-    fn get_pages(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<Vec<Page>>> {
+    fn get_pages(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Vec<Page>>> {
         simple_response!(
-            id: comicid,
+            id: issueid,
             client: client,
-            id_type: Issue,
             url: "https://jumpg-webapi.tokyo-cdn.com/api/manga_viewer?chapter_id={}&split=yes&img_quality=super_high",
             value: response_to_pages
         )
     }
+
+    fn set_locale(&mut self, locale: &str) {
+        let language = locale.split(['_', '-']).next().unwrap_or(locale);
+        self.language = Some(language.to_lowercase());
+    }
+}
+
+/// Value of a field in a protobuf message
+enum Field<'a> {
+    Int(u64),
+    Bytes(&'a [u8]),
+}
+
+/// Read varint at the start of `data` and move `data` past it
+fn read_varint(data: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for (n, byte) in data.iter().enumerate().take(10) {
+        value |= ((byte & 0x7f) as u64) << (7 * n);
+        if byte & 0x80 == 0 {
+            *data = &data[n + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Numbers and values of all fields in protobuf message. Manga Plus responses are protobuf
+/// messages without a published schema, so only the fields that are used are known.
+fn proto_fields(mut data: &[u8]) -> Option<Vec<(u64, Field<'_>)>> {
+    let mut fields = Vec::new();
+    while !data.is_empty() {
+        let key = read_varint(&mut data)?;
+        let value = match key & 7 {
+            0 => Field::Int(read_varint(&mut data)?),
+            1 | 5 => {
+                let len = if key & 7 == 1 { 8 } else { 4 };
+                let (value, rest) = (data.get(..len)?, data.get(len..)?);
+                data = rest;
+                Field::Bytes(value)
+            },
+            2 => {
+                let len = read_varint(&mut data)? as usize;
+                let (value, rest) = (data.get(..len)?, data.get(len..)?);
+                data = rest;
+                Field::Bytes(value)
+            },
+            _ => return None,
+        };
+        fields.push((key >> 3, value));
+    }
+    Some(fields)
+}
+
+/// Embedded message found by following field numbers in `path`
+fn message<'a>(data: &'a [u8], path: &[u64]) -> Option<&'a [u8]> {
+    path.iter().try_fold(data, |data, number| {
+        proto_fields(data)?.into_iter().find_map(|(n, value)| match value {
+            Field::Bytes(x) if n == *number => Some(x),
+            _ => None,
+        })
+    })
+}
+
+/// Integer field `number` of message. Missing fields are 0 in protobuf.
+fn proto_int(data: &[u8], number: u64) -> Option<u64> {
+    let fields = proto_fields(data)?;
+    Some(fields.into_iter()
+        .find_map(|(n, value)| match value {
+            Field::Int(x) if n == number => Some(x),
+            _ => None,
+        })
+        .unwrap_or(0))
+}
+
+/// ISO 639-1 code of language number used by Manga Plus
+fn language_code(language: u64) -> Option<&'static str> {
+    Some(match language {
+        0 => "en",
+        1 => "es",
+        2 => "fr",
+        3 => "id",
+        4 => "pt",
+        5 => "ru",
+        6 => "th",
+        7 => "de",
+        9 => "vi",
+        _ => return None,
+    })
+}
+
+/// Language of title in title details response
+fn series_language(resp: &bytes::Bytes) -> Option<&'static str> {
+    language_code(proto_int(message(resp, &[1, 8, 1])?, 7)?)
+}
+
+/// Ids and languages of all translations of title in title details response
+fn translations(resp: &bytes::Bytes) -> Vec<(u64, &'static str)> {
+    let details = match message(resp, &[1, 8]).and_then(proto_fields) {
+        Some(fields) => fields,
+        None => return Vec::new(),
+    };
+    details.into_iter()
+        .filter_map(|(n, value)| match value {
+            Field::Bytes(translation) if n == 27 => {
+                let title_id = proto_int(translation, 1)?;
+                Some((title_id, language_code(proto_int(translation, 2)?)?))
+            },
+            _ => None,
+        })
+        .collect()
 }
 
 fn find_series_ids(resp: &[bytes::Bytes]) -> Option<Vec<ComicId>> {
@@ -71,7 +206,7 @@ fn find_series_ids(resp: &[bytes::Bytes]) -> Option<Vec<ComicId>> {
     url_re.captures_iter(&resp[0])
         .map(|cap| {
             let id = std::str::from_utf8(&cap["id"]).ok()?.to_string();
-            Some(ComicId::Issue(id))
+            Some(ComicId::Issue(id.into()))
         })
         .collect()
 }
@@ -133,19 +268,19 @@ mod tests {
 
     #[test]
     fn issueid_from_url() {
-        let source = super::MangaPlus;
+        let source = super::MangaPlus::default();
         assert_eq!(
             source.id_from_url("https://mangaplus.shueisha.co.jp/viewer/1000486").unwrap(),
-            ComicId::Issue("1000486".to_string())
+            ComicId::Issue("1000486".into())
         );
     }
 
     #[test]
     fn seriesid_from_url() {
-        let source = super::MangaPlus;
+        let source = super::MangaPlus::default();
         assert_eq!(
             source.id_from_url("https://mangaplus.shueisha.co.jp/titles/100020").unwrap(),
-            ComicId::Series("100020".to_string())
+            ComicId::Series("100020".into())
         );
     }
 
@@ -185,6 +320,19 @@ mod tests {
         assert_eq!(issues.len(), 1051);
     }
 
+    #[test]
+    fn languages() {
+        let series = response_from_testfile("mangaplus_series");
+        assert_eq!(super::series_language(&series[0]), Some("en"));
+        let translations = super::translations(&series[0]);
+        assert_eq!(translations.len(), 6);
+        assert!(translations.contains(&(200016, "es")));
+        assert!(translations.contains(&(700005, "fr")));
+        let issue = response_from_testfile("mangaplus_issue");
+        let viewer = super::message(&issue[0], &[1, 10]).unwrap();
+        assert_eq!(super::proto_int(viewer, 9), Some(100020));
+    }
+
     #[test]
     fn get_series_info() {
         let responses = response_from_testfile("mangaplus_series");
@@ -1,6 +1,6 @@
 use crate::{
     source::{
-        Source, ComicId, Result, SourceResponse, SeriesInfo,
+        Source, ComicId, IssueId, SeriesId, Result, SourceResponse, SeriesInfo,
         utils::{
             first_capture, value_to_optstring, resp_to_json, simple_response, issue_id_match
         },
@@ -33,60 +33,54 @@ impl Source for Marvel {
         )
     }
 
-    fn get_correct_id(&self, client: &Client, otherid: &ComicId) -> Result<SourceResponse<ComicId>> {
+    fn get_correct_id(&self, client: &Client, otherid: &str) -> Result<SourceResponse<ComicId>> {
         simple_response!(
             id: otherid,
             client: client,
-            id_type: Other,
             url: "https://www.marvel.com/comics/issue/{}",
             value: find_correct_id
         )
     }
 
-    fn get_series_ids(&self, client: &Client, seriesid: &ComicId) -> Result<SourceResponse<Vec<ComicId>>> {
+    fn get_series_ids(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<Vec<ComicId>>> {
         simple_response!(
             id: seriesid,
             client: client,
-            id_type: Series,
             url: "https://api.marvel.com/browse/comics?byType=comic_series&isDigital=1&limit=10000&byId={}",
             value: find_series_ids
         )
     }
 
-    fn get_series_info(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<SeriesInfo>> {
-        if let crate::source::ComicId::Series(seriesid) = comicid {
-            Ok(SourceResponse::Request(
-                crate::source::Request {
-                    requests: vec![
-                        client.get(format!(
-                            "https://gateway.marvel.com:443/v1/public/series/{}?apikey={}",
-                            seriesid, API_KEY)
-                        ).header("Referer", "https://developer.marvel.com/")
-                    ],
-                    transform: Box::new(|resp| {
-                        let value = find_series_info(resp)?;
-                        Some(SourceResponse::Value(value))
-                    })
-                }
-            ))
-        } else { unreachable!() }
+    fn get_series_info(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<SeriesInfo>> {
+        Ok(SourceResponse::Request(
+            crate::source::Request {
+                requests: vec![
+                    client.get(format!(
+                        "https://gateway.marvel.com:443/v1/public/series/{}?apikey={}",
+                        seriesid, API_KEY)
+                    ).header("Referer", "https://developer.marvel.com/")
+                ],
+                transform: Box::new(|resp| {
+                    let value = find_series_info(resp)?;
+                    Some(SourceResponse::Value(value))
+                })
+            }
+        ))
     }
 
-    fn get_pages(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<Vec<Page>>> {
+    fn get_pages(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Vec<Page>>> {
         simple_response!(
-            id: comicid,
+            id: issueid,
             client: client,
-            id_type: Issue,
             url: "https://bifrost.marvel.com/v1/catalog/digital-comics/web/assets/{}",
             value: find_pages
         )
     }
 
-    fn get_metadata(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<Metadata>> {
+    fn get_metadata(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Metadata>> {
         simple_response!(
-            id: comicid,
+            id: issueid,
             client: client,
-            id_type: Issue,
             url: "https://bifrost.marvel.com/v1/catalog/digital-comics/metadata/{}",
             value: parse_metadata
         )
@@ -97,7 +91,7 @@ impl Source for Marvel {
 fn find_correct_id(resp: &[bytes::Bytes]) -> Option<ComicId> {
     let data = std::str::from_utf8(&resp[0]).ok()?;
     let re = Regex::new(r#"digital_comic_id: "(\d+)""#).unwrap();
-    Some(ComicId::Issue(first_capture(&re, data)?))
+    Some(ComicId::Issue(first_capture(&re, data)?.into()))
 }
 
 fn find_series_ids(resp: &[bytes::Bytes]) -> Option<Vec<ComicId>> {
@@ -105,7 +99,7 @@ fn find_series_ids(resp: &[bytes::Bytes]) -> Option<Vec<ComicId>> {
         .as_array()?
         .iter()
         .filter_map(|x| {
-            Some(ComicId::Issue(value_to_optstring(&x["digital_id"])?))
+            Some(ComicId::Issue(value_to_optstring(&x["digital_id"])?.into()))
         })
         .collect()
     )
@@ -113,11 +107,22 @@ fn find_series_ids(resp: &[bytes::Bytes]) -> Option<Vec<ComicId>> {
 
 fn find_series_info(resp: &[bytes::Bytes]) -> Option<SeriesInfo> {
     let results = get_results(&resp[0])?;
-    let title = results[0]["title"].as_str()?.to_string();
-    let ended = results[0]["endYear"].as_u64()? != 2099; // endYear is 2099 if not finished
+    let series = &results[0];
+    let title = series["title"].as_str()?.to_string();
+    let end_year = series["endYear"].as_u64()?;
+    let ended = end_year != 2099; // endYear is 2099 if not finished
+    let thumbnail = &series["thumbnail"];
     Some(SeriesInfo {
         name: title,
         ended,
+        issue_count: series["comics"]["available"].as_u64().map(|x| x as usize),
+        start_year: series["startYear"].as_u64().map(|x| x as u32),
+        end_year: Some(end_year as u32).filter(|_| ended),
+        publisher: Some("Marvel".to_string()),
+        description: value_to_optstring(&series["description"]),
+        cover_url: thumbnail["path"].as_str()
+            .zip(thumbnail["extension"].as_str())
+            .map(|(path, extension)| format!("{}.{}", path, extension)),
     })
 }
 
@@ -200,7 +205,7 @@ mod tests {
         let source = super::Marvel;
         assert_eq!(
             source.id_from_url("https://www.marvel.com/comics/series/16309/hawkeye_2012_-_2015").unwrap(),
-            ComicId::Series("16309".to_string())
+            ComicId::Series("16309".into())
         );
     }
 
@@ -209,7 +214,7 @@ mod tests {
         let source = super::Marvel;
         assert_eq!(
             source.id_from_url("https://read.marvel.com/#/book/3257").unwrap(),
-            ComicId::Issue("3257".to_string())
+            ComicId::Issue("3257".into())
         );
     }
 
@@ -219,7 +224,7 @@ mod tests {
         let responses = response_from_testfile("marvel_issue.html");
         assert_eq!(
             super::find_correct_id(&responses),
-            Some(ComicId::Issue("3257".to_string()))
+            Some(ComicId::Issue("3257".into()))
         );
     }
 
@@ -1,16 +1,30 @@
+mod azuki;
+mod comicwalker;
 mod dcuniverseinfinite;
 mod flipp;
+mod gallery;
+mod hoopla;
+mod internetarchive;
 mod izneo;
+mod kmanga;
 mod leagueoflegends;
 mod mangaplus;
 mod marvel;
+mod pocketcomics;
 mod webtoon;
 
+pub use azuki::Azuki;
+pub use comicwalker::ComicWalker;
 pub use dcuniverseinfinite::DCUniverseInfinite;
 pub use flipp::Flipp;
+pub use gallery::Gallery;
+pub use hoopla::Hoopla;
+pub use internetarchive::InternetArchive;
+pub use kmanga::KManga;
 pub use leagueoflegends::LeagueOfLegends;
 pub use mangaplus::MangaPlus;
 pub use marvel::Marvel;
+pub use pocketcomics::PocketComics;
 pub use webtoon::Webtoon;
 
 use crate::{
@@ -33,28 +47,80 @@ macro_rules! match_re {
 /// Create a corresponding `Source` trait object from url
 pub fn source_from_url(url: &str) -> Result<Box<dyn Source>> {
     match_re!(url,
+        "azuki.co" => azuki::Azuki::default(),
+        "comic-walker.com" => comicwalker::ComicWalker::default(),
         "dcuniverseinfinite.com" => dcuniverseinfinite::DCUniverseInfinite::default(),
-        "flipp.dk" => flipp::Flipp,
+        "flipp.dk" => flipp::Flipp::default(),
+        "hoopladigital.com" => hoopla::Hoopla::default(),
+        "archive.org" => internetarchive::InternetArchive,
         "izneo.com" => izneo::Izneo,
-        "universe.leagueoflegends.com" => leagueoflegends::LeagueOfLegends,
-        "mangaplus.shueisha.co.jp" => mangaplus::MangaPlus,
+        "kmanga.kodansha.com" => kmanga::KManga::default(),
+        "universe.leagueoflegends.com" => leagueoflegends::LeagueOfLegends::default(),
+        "mangaplus.shueisha.co.jp" => mangaplus::MangaPlus::default(),
         "marvel.com" => marvel::Marvel,
+        "pocketcomics.com" => pocketcomics::PocketComics::default(),
         "webtoons.com" => webtoon::Webtoon
     );
     Err(Error::UrlNotSupported(url.to_string()))
 }
 
+/// Links to freely available comics or series on each source. Used for checking if sources still
+/// work.
+pub const HEALTH_CHECK_URLS: [&str; 13] = [
+    "https://www.azuki.co/series/the-cafe-terrace-and-its-goddesses",
+    "https://comic-walker.com/detail/KC_003002_S/episodes/KC_0030020000200011_E",
+    "https://www.dcuniverseinfinite.com/comics/book/the-sandman-8/761ad52d-b961-49b1-87b6-ca85774fc3a6/c/reader",
+    "https://reader.flipp.dk/html5/reader/production/default.aspx?pubname=&edid=31d29e20-fd60-48ad-96b2-79a3d9d65788",
+    "https://www.hoopladigital.com/title/11890426",
+    "https://archive.org/details/Action_Comics_001_1938",
+    "https://www.izneo.com/en/us-comics/fantasy/jim-butcher-s-the-dresden-files-20229",
+    "https://kmanga.kodansha.com/title/10044/episode/317350",
+    "https://universe.leagueoflegends.com/en_us/comic/star-guardian/issue-1/0/",
+    "https://mangaplus.shueisha.co.jp/viewer/1000486",
+    "https://www.marvel.com/comics/issue/42768/hawkeye_2012_1",
+    "https://www.pocketcomics.com/viewer/51873",
+    "https://www.webtoons.com/en/challenge/the-weekly-roll/ch-116-grimdahls-folly/viewer?title_no=358889&episode_no=118",
+];
+
 /// Create source object from name
 pub fn source_from_name(name: &str) -> Result<Box<dyn Source>> {
     let lower = name.to_lowercase();
     Ok(match lower.as_str() {
-        "dc" | "dcuniverseinfinite" => Box::new(dcuniverseinfinite::DCUniverseInfinite::default()),
-        "flipp" => Box::new(flipp::Flipp),
+        "azuki" => Box::new(azuki::Azuki::default()),
+        "comicwalker" => Box::new(comicwalker::ComicWalker::default()),
+        "dc" | "dc universe infinite" | "dcuniverseinfinite" => Box::new(dcuniverseinfinite::DCUniverseInfinite::default()),
+        "flipp" => Box::new(flipp::Flipp::default()),
+        "hoopla" => Box::new(hoopla::Hoopla::default()),
+        "internet archive" | "archive.org" => Box::new(internetarchive::InternetArchive),
         "izneo" => Box::new(izneo::Izneo),
-        "league of legends" => Box::new(leagueoflegends::LeagueOfLegends),
-        "manga plus" => Box::new(mangaplus::MangaPlus),
+        "k manga" | "kmanga" => Box::new(kmanga::KManga::default()),
+        "league of legends" => Box::new(leagueoflegends::LeagueOfLegends::default()),
+        "manga plus" => Box::new(mangaplus::MangaPlus::default()),
         "marvel" => Box::new(marvel::Marvel),
+        "pocket comics" | "pocketcomics" => Box::new(pocketcomics::PocketComics::default()),
         "webtoon" => Box::new(webtoon::Webtoon),
         _ => return Err(Error::InvalidSourceName(name.to_string()))
     })
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn health_check_urls() {
+        let mut names = Vec::new();
+        for url in super::HEALTH_CHECK_URLS {
+            let source = super::source_from_url(url).unwrap();
+            assert!(source.id_from_url(url).is_ok(), "{}", url);
+            assert!(!names.contains(&source.name()), "{}", url);
+            names.push(source.name());
+        }
+    }
+
+    #[test]
+    fn source_from_own_name() {
+        for url in super::HEALTH_CHECK_URLS {
+            let name = super::source_from_url(url).unwrap().name();
+            assert_eq!(super::source_from_name(&name).unwrap().name(), name);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{
+    comic::Page,
+    metadata::{self, Author, Metadata},
+    source::{
+        self,
+        ComicId, IssueId, SeriesId, Credentials, Error, Request, Result, Source, SourceResponse, SeriesInfo,
+        utils::{issue_id_match, resp_to_json, simple_response, value_to_optstring}
+    }
+};
+use reqwest::Client;
+
+/// Source for pocketcomics.com
+#[derive(Default)]
+pub struct PocketComics {
+    /// Session cookie from a logged in browser
+    session: Option<String>,
+}
+
+const API_URL: &str = "https://api.pocketcomics.com/api/v1";
+
+/// Name of cookie containing the session of a logged in user
+const SESSION_COOKIE: &str = "session";
+
+/// Number of episodes in each page of episode list
+const EPISODES_PER_PAGE: usize = 100;
+
+#[async_trait::async_trait]
+impl Source for PocketComics {
+    fn name(&self) -> String {
+        "Pocket Comics".to_string()
+    }
+
+    fn client_builder(&self) -> source::ClientBuilder {
+        let mut clientbuilder = source::create_default_client();
+        if let Some(session) = &self.session {
+            clientbuilder.add_cookie(SESSION_COOKIE, session);
+        }
+        clientbuilder
+    }
+
+    fn id_from_url(&self, url: &str) -> Result<ComicId> {
+        issue_id_match!(url,
+            r"pocketcomics.com/viewer/(\d+)" => Issue,
+            r"pocketcomics.com/comic/(\d+)" => Series
+        )
+    }
+
+    fn get_series_ids(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<Vec<ComicId>>> {
+        Ok(episode_list_page(client.clone(), seriesid.to_string(), 0, Vec::new()))
+    }
+
+    fn get_series_info(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<SeriesInfo>> {
+        simple_response!(
+            id: seriesid,
+            client: client,
+            url: "https://api.pocketcomics.com/api/v1/comics/{}",
+            value: parse_series_info
+        )
+    }
+
+    fn get_metadata(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Metadata>> {
+        simple_response!(
+            id: issueid,
+            client: client,
+            url: "https://api.pocketcomics.com/api/v1/episodes/{}",
+            value: parse_metadata
+        )
+    }
+
+    fn get_pages(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Vec<Page>>> {
+        simple_response!(
+            id: issueid,
+            client: client,
+            url: "https://api.pocketcomics.com/api/v1/episodes/{}/viewer",
+            value: parse_pages
+        )
+    }
+
+    fn metadata_require_authentication(&self) -> bool {
+        false
+    }
+
+    async fn authenticate(&mut self, client: &mut Client, creds: &Credentials) -> Result<()> {
+        if let Credentials::ApiKey(session) = creds {
+            self.session = Some(session.clone());
+            *client = self.create_client();
+            Ok(())
+        } else {
+            Err(Error::FailedAuthentication("Pocket Comics requires a session cookie as api key to login".to_string()))
+        }
+    }
+}
+
+/// Create request for page `page` of the episode list. Requests for following pages are created
+/// until the whole list has been retrieved.
+fn episode_list_page(client: Client, seriesid: String, page: usize, ids: Vec<ComicId>) -> SourceResponse<Vec<ComicId>> {
+    let request = client.get(format!(
+        "{}/comics/{}/episodes?page={}&size={}",
+        API_URL, seriesid, page, EPISODES_PER_PAGE
+    ));
+    SourceResponse::Request(Request {
+        requests: vec![request],
+        transform: Box::new(move |resp| {
+            let (mut new_ids, has_next) = parse_episode_list(resp)?;
+            let mut ids = ids.clone();
+            ids.append(&mut new_ids);
+            if has_next {
+                Some(episode_list_page(client.clone(), seriesid.clone(), page + 1, ids))
+            } else {
+                Some(SourceResponse::Value(ids))
+            }
+        })
+    })
+}
+
+/// Find readable episodes in a page of the episode list. Free, daily free, and purchased episodes
+/// are readable.
+fn parse_episode_list(resp: &[bytes::Bytes]) -> Option<(Vec<ComicId>, bool)> {
+    let data = &resp_to_json::<serde_json::Value>(&resp[0])?["data"];
+    let ids = data["episodes"]
+        .as_array()?
+        .iter()
+        .filter(|episode| {
+            let readable = episode["readable"].as_bool().unwrap_or(false);
+            if !readable {
+                log::debug!("Skipping locked episode {}", episode["id"]);
+            }
+            readable
+        })
+        .map(|episode| Some(ComicId::Issue(episode["id"].as_u64()?.to_string().into())))
+        .collect::<Option<Vec<ComicId>>>()?;
+    Some((ids, data["hasNext"].as_bool().unwrap_or(false)))
+}
+
+fn parse_series_info(resp: &[bytes::Bytes]) -> Option<SeriesInfo> {
+    let data = &resp_to_json::<serde_json::Value>(&resp[0])?["data"];
+    Some(SeriesInfo {
+        name: data["title"].as_str()?.to_string(),
+        ended: data["status"].as_str() == Some("COMPLETED"),
+        description: value_to_optstring(&data["description"]),
+        ..Default::default()
+    })
+}
+
+fn parse_metadata(resp: &[bytes::Bytes]) -> Option<Metadata> {
+    let data = &resp_to_json::<serde_json::Value>(&resp[0])?["data"];
+    let comic = &data["comic"];
+    let date = data["publishedAt"].as_str()
+        .and_then(|x| metadata::date_from_str(x.split('T').next()?));
+    Some(Metadata {
+        title: value_to_optstring(&data["name"]),
+        series: value_to_optstring(&comic["title"]),
+        description: value_to_optstring(&comic["description"]),
+        year: date.map(|x| x.0),
+        month: date.map(|x| x.1),
+        day: date.map(|x| x.2),
+        authors: comic["authors"]
+            .as_array()
+            .unwrap_or(&Vec::new())
+            .iter()
+            .filter_map(|author| Some(Author {
+                name: value_to_optstring(&author["name"])?,
+                author_type: value_to_optstring(&author["role"])?.into(),
+            }))
+            .collect(),
+        source: Some("Pocket Comics".to_string()),
+        ..Default::default()
+    })
+}
+
+fn parse_pages(resp: &[bytes::Bytes]) -> Option<Vec<Page>> {
+    resp_to_json::<serde_json::Value>(&resp[0])?["data"]["images"]
+        .as_array()?
+        .iter()
+        .map(|image| Some(Page::from_url(image["url"].as_str()?, "jpg")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source::{ComicId, Source};
+
+    #[test]
+    fn ids() {
+        let source = super::PocketComics::default();
+        assert_eq!(
+            source.id_from_url("https://www.pocketcomics.com/viewer/51873").unwrap(),
+            ComicId::Issue("51873".into())
+        );
+        assert_eq!(
+            source.id_from_url("https://www.pocketcomics.com/comic/1234").unwrap(),
+            ComicId::Series("1234".into())
+        );
+    }
+
+    #[test]
+    fn locked_episodes_skipped() {
+        let resp = br#"{"data":{"episodes":[{"id":1,"readable":true},{"id":2,"readable":false},{"id":3,"readable":true}],"hasNext":true}}"#;
+        let (ids, has_next) = super::parse_episode_list(&[bytes::Bytes::from_static(resp)]).unwrap();
+        assert_eq!(ids, vec![ComicId::Issue("1".into()), ComicId::Issue("3".into())]);
+        assert!(has_next);
+    }
+
+    #[test]
+    fn session_cookie() {
+        let mut source = super::PocketComics::default();
+        let mut client = source.create_client();
+        let creds = crate::source::Credentials::ApiKey("abc".to_string());
+        futures::executor::block_on(source.authenticate(&mut client, &creds)).unwrap();
+        assert_eq!(source.session.as_deref(), Some("abc"));
+    }
+}
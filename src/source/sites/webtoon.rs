@@ -1,10 +1,10 @@
 use std::collections::HashMap;
 
 use crate::{
-    comic::Page, metadata::{Author, AuthorType, Metadata},
+    comic::Page, metadata::{Author, AuthorType, Metadata, PageLayout},
     source::{
         self,
-        ComicId, Error, Result, Source, SourceResponse, SeriesInfo,
+        ComicId, IssueId, SeriesId, Result, Source, SourceResponse, SeriesInfo,
         utils::{
             self, first_text, first_attr, issue_id_match, simple_response, source_request, ANDROID_USER_AGENT
         }
@@ -37,59 +37,80 @@ impl Source for Webtoon {
         id_from_url(url)
     }
 
-    fn get_series_ids(&self, client: &Client, seriesid: &ComicId) -> Result<SourceResponse<Vec<ComicId>>> {
-        if let ComicId::Series(x) = seriesid {
-            source_request!(
-                requests:
-                    client.get(format!("https://m.webtoons.com/en/{}", x))
-                        .header("User-Agent", ANDROID_USER_AGENT),
-                transform: |resp: &[bytes::Bytes]| {
-                    utils::find_links("ul#_episodeList li a", &resp[0])?
-                        .iter()
-                        .map(|link| id_from_url(link).ok())
-                        .collect::<Option<Vec<ComicId>>>()
-                }
-            )
-        } else { Err(Error::FailedResponseParse) }
+    fn get_series_ids(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<Vec<ComicId>>> {
+        source_request!(
+            requests:
+                client.get(format!("https://m.webtoons.com/en/{}", seriesid))
+                    .header("User-Agent", ANDROID_USER_AGENT),
+            transform: find_episode_ids
+        )
     }
 
-    fn get_series_info(&self, client: &Client, seriesid: &ComicId) -> Result<SourceResponse<SeriesInfo>> {
-        if let ComicId::Series(x) = seriesid {
-            source_request!(
-                requests:
-                    client.get(format!("https://m.webtoons.com/en/{}", x))
-                        .header("User-Agent", ANDROID_USER_AGENT),
-                transform: response_series_info
-            )
-        } else { Err(Error::FailedResponseParse) }
+    fn get_latest(&self, client: &Client, seriesid: &SeriesId, count: usize) -> Result<SourceResponse<Vec<ComicId>>> {
+        // The desktop site only lists the 10 newest episodes on the first page, while the mobile
+        // site lists all of them
+        source_request!(
+            requests: client.get(format!("https://www.webtoons.com/en/{}&page=1", seriesid)),
+            transform: move |resp: &[bytes::Bytes]| {
+                // Episodes are listed from newest to oldest
+                let mut ids = find_listed_episode_ids(resp)?;
+                ids.truncate(count);
+                ids.reverse();
+                Some(ids)
+            }
+        )
     }
 
-    fn get_metadata(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<Metadata>> {
+    fn get_series_info(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<SeriesInfo>> {
+        source_request!(
+            requests:
+                client.get(format!("https://m.webtoons.com/en/{}", seriesid))
+                    .header("User-Agent", ANDROID_USER_AGENT),
+            transform: response_series_info
+        )
+    }
+
+    fn get_metadata(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Metadata>> {
         simple_response!(
-            id: comicid,
+            id: issueid,
             client: client,
-            id_type: Issue,
             url: "https://www.webtoons.com/en/{}",
             value: parse_metadata
         )
     }
 
-    fn get_pages(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<Vec<Page>>> {
+    fn get_pages(&self, client: &Client, issueid: &IssueId) -> Result<SourceResponse<Vec<Page>>> {
         simple_response!(
-            id: comicid,
+            id: issueid,
             client: client,
-            id_type: Issue,
             url: "https://www.webtoons.com/en/{}",
             value: response_to_pages
         )
     }
 }
 
+fn find_episode_ids(resp: &[bytes::Bytes]) -> Option<Vec<ComicId>> {
+    utils::find_links("ul#_episodeList li a", &resp[0])?
+        .iter()
+        .map(|link| id_from_url(link).ok())
+        .collect()
+}
+
+/// Episode ids on a page of the desktop episode list
+fn find_listed_episode_ids(resp: &[bytes::Bytes]) -> Option<Vec<ComicId>> {
+    utils::find_links("ul#_listUl li a", &resp[0])?
+        .iter()
+        .map(|link| id_from_url(link).ok())
+        .collect()
+}
+
 fn response_series_info(resp: &[bytes::Bytes]) -> Option<SeriesInfo> {
     let html = std::str::from_utf8(&resp[0]).ok()?;
     let doc = Html::parse_document(html);
     Some(SeriesInfo{
         name: first_attr(&doc, r#"meta[property="og:title"]"#, "content")?,
+        description: first_attr(&doc, r#"meta[property="og:description"]"#, "content"),
+        cover_url: first_attr(&doc, r#"meta[property="og:image"]"#, "content"),
         ..Default::default()
     })
 }
@@ -103,6 +124,7 @@ fn parse_metadata(resp: &[bytes::Bytes]) -> Option<Metadata> {
         authors: vec![find_author(&doc)?],
         description: first_attr(&doc, r#"meta[property="og:description"]"#, "content"),
         source: Some("Webtoon".to_string()),
+        page_layout: PageLayout::Vertical,
         ..Default::default()
     })
 }
@@ -146,7 +168,7 @@ mod tests {
         let source = super::Webtoon;
         assert_eq!(
             source.id_from_url("https://www.webtoons.com/en/challenge/the-weekly-roll/ch-116-grimdahls-folly/viewer?title_no=358889&episode_no=118").unwrap(),
-            ComicId::Issue("challenge/the-weekly-roll/ch-116-grimdahls-folly/viewer?title_no=358889&episode_no=118".to_string())
+            ComicId::Issue("challenge/the-weekly-roll/ch-116-grimdahls-folly/viewer?title_no=358889&episode_no=118".into())
         );
     }
 
@@ -155,7 +177,7 @@ mod tests {
         let source = super::Webtoon;
         assert_eq!(
             source.id_from_url("https://www.webtoons.com/en/challenge/the-weekly-roll/list?title_no=358889").unwrap(),
-            ComicId::Series("challenge/the-weekly-roll/list?title_no=358889".to_string())
+            ComicId::Series("challenge/the-weekly-roll/list?title_no=358889".into())
         );
     }
 
@@ -164,9 +186,10 @@ mod tests {
         let source = super::Webtoon;
         let series_id = source.id_from_url("https://www.webtoons.com/en/challenge/the-weekly-roll/list?title_no=358889")
             .unwrap();
+        let series_id = series_id.series_id().unwrap();
         let client = source.create_client();
         let parser = transform_from_source_response(
-            source.get_series_ids(&client, &series_id)
+            source.get_series_ids(&client, series_id)
         );
         let responses = response_from_testfile("webtoon_series.html");
         let issues = parser(&responses);
@@ -175,6 +198,26 @@ mod tests {
         assert_eq!(info.name, "The Weekly Roll".to_string());
     }
 
+    #[test]
+    fn latest() {
+        let source = super::Webtoon;
+        let series_id = "challenge/the-weekly-roll/list?title_no=358889".into();
+        let page = (1..=3).rev()
+            .map(|n| format!(r#"<li><a href="https://www.webtoons.com/en/challenge/the-weekly-roll/ch-{n}/viewer?title_no=358889&episode_no={n}">{n}</a></li>"#))
+            .collect::<String>();
+        let responses = [format!(r#"<html><body><ul id="_listUl">{}</ul></body></html>"#, page).into()];
+        let latest = transform_from_source_response(
+            source.get_latest(&source.create_client(), &series_id, 2)
+        )(&responses);
+        assert_eq!(
+            latest,
+            vec![
+                ComicId::Issue("challenge/the-weekly-roll/ch-2/viewer?title_no=358889&episode_no=2".into()),
+                ComicId::Issue("challenge/the-weekly-roll/ch-3/viewer?title_no=358889&episode_no=3".into()),
+            ]
+        );
+    }
+
     #[test]
     fn get_correct_number_of_pages() {
         let responses = response_from_testfile("webtoon_issue.html");
@@ -196,6 +239,7 @@ mod tests {
                 ],
                 description: Some("A weekly four-panel comic strip that follows the exploits of a party of adventurers as they walk the fine line between being the good guys and homeless psychopaths for hire. \n\nUpdates every Weekend".to_string()),
                 source: Some("Webtoon".to_string()),
+                page_layout: crate::metadata::PageLayout::Vertical,
                 ..Default::default()
             }
         );
@@ -1,8 +1,8 @@
 use crate::{
+    error::GrawlixDownloadError as Error,
     metadata::Metadata,
-    comic::Page,
     source::{
-        Source, ComicId, Result, SourceResponse, SeriesInfo,
+        Source, ComicId, IssueId, SeriesId, Result, SourceResponse,
         utils::{issue_id_match, source_request}
     },
 };
@@ -56,22 +56,12 @@ impl Source for StandardSource {
         )
     }
 
-    fn get_series_ids(&self, client: &Client, seriesid: &ComicId) -> Result<SourceResponse<Vec<ComicId>>> {
-        if let ComicId::Series(id) = seriesid {
-            apply_retrieval_method(&self.series_id_retrieval_method, client, &id)
-        } else { unreachable!() }
+    fn get_series_ids(&self, client: &Client, seriesid: &SeriesId) -> Result<SourceResponse<Vec<ComicId>>> {
+        apply_retrieval_method(&self.series_id_retrieval_method, client, seriesid.as_str())
     }
 
-    fn get_series_info(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<SeriesInfo>> {
-        todo!()
-    }
-
-    fn get_metadata(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<Metadata>> {
-        todo!()
-    }
-
-    fn get_pages(&self, client: &Client, comicid: &ComicId) -> Result<SourceResponse<Vec<Page>>> {
-        todo!()
+    fn get_metadata(&self, _client: &Client, _issueid: &IssueId) -> Result<SourceResponse<Metadata>> {
+        Err(Error::MetadataNotSupported(self.name()))
     }
 
 }
@@ -22,7 +22,7 @@ pub const ANDROID_USER_AGENT: &str = "Mozilla/5.0 (Linux; Android 9; ASUS_X00TD;
 macro_rules! issue_id_match {
     ($url:expr, $($pattern:expr => $idtype:ident),+) => {
         crate::source::utils::issue_id_match_internal($url, &[$(
-            ($pattern, Box::new(ComicId::$idtype)),
+            ($pattern, Box::new(|x: String| ComicId::$idtype(x.into()))),
         )*])
     }
 }
@@ -81,35 +81,30 @@ macro_rules! source_request {
 pub(super) use source_request;
 
 
-/// Simply create SourceResponse
+/// Simply create SourceResponse from a single request to an url containing `id`
 macro_rules! simple_response {
-    (id: $id:expr, client: $client:expr, id_type: $idtype:ident, url: $url:expr, value: $transform:expr) => {
-        if let crate::source::ComicId::$idtype(x) = $id {
-            Ok::<_, crate::error::GrawlixDownloadError>(
-                crate::source::SourceResponse::Request(
-                    crate::source::Request{
-                        requests: vec![$client.get(format!($url, x))],
-                        // requests: vec![crate::source::HttpRequest::get(format!($url, x))],
-                        transform: Box::new(|resp| {
-                            let value = $transform(resp)?;
-                            Some(SourceResponse::Value(value))
-                        })
-                    }
-                )
+    (id: $id:expr, client: $client:expr, url: $url:expr, value: $transform:expr) => {
+        Ok::<_, crate::error::GrawlixDownloadError>(
+            crate::source::SourceResponse::Request(
+                crate::source::Request{
+                    requests: vec![$client.get(format!($url, $id))],
+                    transform: Box::new(|resp| {
+                        let value = $transform(resp)?;
+                        Some(SourceResponse::Value(value))
+                    })
+                }
             )
-        } else { Err(crate::source::Error::FailedResponseParse) }
+        )
     };
-    (id: $id:expr, client: $client:expr, id_type: $idtype:ident, url: $url:expr, request: $transform:expr) => {
-        if let crate::source::ComicId::$idtype(x) = $id {
-            Ok::<_, crate::error::GrawlixDownloadError>(
-                crate::source::SourceResponse::Request(
-                    crate::source::Request{
-                        requests: vec![$client.get(format!($url, x))],
-                        transform: Box::new($transform)
-                    }
-                )
+    (id: $id:expr, client: $client:expr, url: $url:expr, request: $transform:expr) => {
+        Ok::<_, crate::error::GrawlixDownloadError>(
+            crate::source::SourceResponse::Request(
+                crate::source::Request{
+                    requests: vec![$client.get(format!($url, $id))],
+                    transform: Box::new($transform)
+                }
             )
-        } else { Err(crate::source::Error::FailedResponseParse) }
+        )
     }
 }
 pub(super) use simple_response;
@@ -154,7 +149,7 @@ pub fn first_capture_bin(re: &regex::bytes::Regex, input: &[u8]) -> Option<Strin
     Some(value.to_string())
 }
 
-pub fn value_fn<T>(f: &'static dyn Fn(&[bytes::Bytes]) -> Option<T>) -> Box<dyn Fn(&[bytes::Bytes]) -> Option<SourceResponse<T>>> {
+pub fn value_fn<T>(f: &'static (dyn Fn(&[bytes::Bytes]) -> Option<T> + Sync)) -> Box<dyn Fn(&[bytes::Bytes]) -> Option<SourceResponse<T>> + Send + Sync> {
     Box::new(|resp| {
         let value = f(resp)?;
         Some(SourceResponse::Value(value))